@@ -0,0 +1,99 @@
+use crate::inputs::expand_all;
+use crate::Error;
+use crate::{map_file, Input};
+use std::io::stdout;
+use std::path::PathBuf;
+use v8_cpuprofile::aggregate::{duration_weights, ProfileSet};
+use v8_cpuprofile::anonymous::AnonymousGroups;
+use v8_cpuprofile::{Profile, Weighting};
+
+/// How the times of each run are scaled before aggregating.
+pub enum Weights {
+    Equal,
+    /// Scales each run to the mean capture duration.
+    Duration,
+    /// A weight per profile in the order given.
+    Explicit(Vec<f64>),
+}
+
+pub fn run(
+    inputs: &[PathBuf],
+    weights: Weights,
+    weighting: Weighting,
+    error_bars: bool,
+    groups: Option<&AnonymousGroups>,
+) -> Result<(), Error> {
+    let cpu_profiles = expand_all(inputs)?;
+    let mmaps = map_files(&cpu_profiles)?;
+    let mut profiles = parse_profiles(&mmaps)?;
+    for profile in &mut profiles {
+        profile.set_weighting(weighting);
+        if let Some(groups) = groups {
+            profile.group_anonymous(groups)?;
+        }
+    }
+    let weights = match weights {
+        Weights::Equal => vec![1.0; profiles.len()],
+        Weights::Duration => duration_weights(&profiles),
+        Weights::Explicit(weights) => {
+            if weights.len() != profiles.len() {
+                return Err(format!(
+                    "{} weights given for {} profiles",
+                    weights.len(),
+                    profiles.len()
+                )
+                .into());
+            }
+            weights
+        }
+    };
+    let mut set = ProfileSet::aggregate_weighted(&profiles, &weights)?;
+    if error_bars {
+        set.add_error_bars();
+    }
+    serde_json::to_writer_pretty(stdout().lock(), &set)?;
+    println!();
+    Ok(())
+}
+
+pub fn outliers(inputs: &[PathBuf], top: usize, weighting: Weighting) -> Result<(), Error> {
+    let cpu_profiles = expand_all(inputs)?;
+    let mmaps = map_files(&cpu_profiles)?;
+    let mut profiles = parse_profiles(&mmaps)?;
+    for profile in &mut profiles {
+        profile.set_weighting(weighting);
+    }
+    let set = ProfileSet::aggregate(&profiles)?;
+    for score in set.outliers() {
+        println!(
+            "{:>6.1}%  {:?}",
+            score.score * 100.0,
+            cpu_profiles[score.run]
+        );
+        for deviation in score.frames.iter().take(top) {
+            let sign = if deviation.self_time > deviation.median {
+                '+'
+            } else {
+                '-'
+            };
+            println!(
+                "        {}{:<10.1?} {}",
+                sign,
+                deviation.difference(),
+                set.frames[deviation.frame].frame
+            );
+        }
+    }
+    Ok(())
+}
+
+pub fn map_files(paths: &[PathBuf]) -> Result<Vec<Input>, Error> {
+    paths.iter().map(|path| map_file(path)).collect()
+}
+
+pub fn parse_profiles(mmaps: &[Input]) -> Result<Vec<Profile<'_>>, Error> {
+    Ok(mmaps
+        .iter()
+        .map(|mmap| serde_json::from_slice(mmap))
+        .collect::<Result<_, _>>()?)
+}
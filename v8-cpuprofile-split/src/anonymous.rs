@@ -0,0 +1,33 @@
+use structopt::StructOpt;
+use v8_cpuprofile::anonymous::AnonymousGroups;
+
+/// Options for merging the frames of eval'd and anonymous scripts.
+#[derive(Debug, StructOpt)]
+pub struct GroupOpts {
+    /// Merges the frames of scripts without a url into one (eval) function
+    /// and those of node's vm module into one (vm) function
+    #[structopt(long)]
+    group_anonymous: bool,
+    /// Merges the frames of scripts whose url starts with a prefix into one
+    /// function, like webpack-internal:=(webpack), repeated for more
+    #[structopt(long = "group-url", number_of_values = 1, parse(try_from_str = crate::convert::label))]
+    group_urls: Vec<(String, String)>,
+}
+
+impl GroupOpts {
+    pub fn groups(&self) -> Option<AnonymousGroups> {
+        if !self.group_anonymous && self.group_urls.is_empty() {
+            return None;
+        }
+        let mut groups = AnonymousGroups::default();
+        if !self.group_anonymous {
+            groups.unnamed = None;
+            groups.prefixes.clear();
+        }
+        // before the defaults, so a longer prefix of a default one wins
+        groups
+            .prefixes
+            .splice(0..0, self.group_urls.iter().cloned());
+        Some(groups)
+    }
+}
@@ -0,0 +1,67 @@
+use crate::format::percent;
+use crate::map_file;
+use crate::Error;
+use std::path::Path;
+use v8_cpuprofile::links::SourceLinks;
+use v8_cpuprofile::{FrameKey, Profile};
+
+pub fn run(old: &Path, new: &Path, top: usize, links: Option<&SourceLinks>) -> Result<(), Error> {
+    let old_mmap = map_file(old)?;
+    let old: Profile = serde_json::from_slice(&old_mmap)?;
+    let new_mmap = map_file(new)?;
+    let new: Profile = serde_json::from_slice(&new_mmap)?;
+    let bisect = new.bisect(&old)?;
+
+    let culprit = match bisect.culprit {
+        Some(culprit) => culprit,
+        None => {
+            println!(
+                "no regression, {:.1?} -> {:.1?}",
+                bisect.baseline, bisect.current
+            );
+            return Ok(());
+        }
+    };
+    let grown = culprit.growth.as_secs_f64();
+    println!(
+        "{:.1?} -> {:.1?}, {:.1}% of the growth is under",
+        bisect.baseline,
+        bisect.current,
+        percent(
+            grown,
+            bisect.current.saturating_sub(bisect.baseline).as_secs_f64()
+        )
+    );
+    for (depth, frame) in culprit.stack.iter().enumerate() {
+        print!("{:width$}", "", width = 4 + depth.min(20) * 2);
+        print_frame(frame, links);
+    }
+
+    if !bisect.children.is_empty() {
+        println!();
+        println!("grew below it:");
+    }
+    for child in bisect.children.iter().take(top) {
+        print!(
+            "  +{:<10.1?} {:>5.1}%  ",
+            child.growth,
+            percent(child.growth.as_secs_f64(), grown)
+        );
+        print_frame(&child.stack[child.stack.len() - 1], links);
+    }
+
+    println!();
+    println!("functions by self time growth:");
+    for (frame, growth) in bisect.frames.iter().take(top) {
+        print!("  +{:<10.1?} ", growth);
+        print_frame(frame, links);
+    }
+    Ok(())
+}
+
+fn print_frame(frame: &FrameKey, links: Option<&SourceLinks>) {
+    match links.and_then(|links| links.link(&frame.url, frame.line_number, frame.column_number)) {
+        Some(link) => println!("{}  {}", frame, link),
+        None => println!("{}", frame),
+    }
+}
@@ -0,0 +1,38 @@
+use crate::{map_file, parse_any, Error};
+use std::path::Path;
+use v8_cpuprofile::bundle::ChunkMap;
+use v8_cpuprofile::format::decompress;
+
+/// Prints the self time of each route and the self and total time of each
+/// chunk of a bundle, the most self time first.
+pub fn run(cpu_profile: &Path, stats: &Path) -> Result<(), Error> {
+    let stats_mmap = map_file(stats)?;
+    let map: ChunkMap = serde_json::from_slice(&stats_mmap)?;
+    let mmap = map_file(cpu_profile)?;
+    let input = decompress(&mmap)?;
+    let profile = parse_any(&input)?;
+    let report = profile.bundle_report(&map)?;
+
+    println!("{:>10}  route", "self");
+    for route in &report.routes {
+        println!(
+            "{:>10.1?}  {} ({})",
+            route.self_time,
+            route.name,
+            route.chunks.join(", ")
+        );
+    }
+    println!();
+    println!("{:>10} {:>10}  chunk", "self", "total");
+    for chunk in &report.chunks {
+        println!(
+            "{:>10.1?} {:>10.1?}  {}",
+            chunk.self_time, chunk.total_time, chunk.name
+        );
+        for url in &chunk.urls {
+            println!("{:>22}{}", "", url);
+        }
+    }
+    println!("{:>10.1?} {:>10}  (unattributed)", report.unattributed, "");
+    Ok(())
+}
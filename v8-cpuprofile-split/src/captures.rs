@@ -0,0 +1,33 @@
+use crate::inputs::{by_process, expand_all};
+use crate::map_file;
+use crate::Error;
+use std::path::PathBuf;
+use v8_cpuprofile::Profile;
+
+pub fn run(inputs: &[PathBuf]) -> Result<(), Error> {
+    let (processes, other) = by_process(expand_all(inputs)?);
+    for (pid, threads) in &processes {
+        println!("pid {}:", pid);
+        for (name, cpu_profile) in threads {
+            let mmap = map_file(cpu_profile)?;
+            let profile: Profile = serde_json::from_slice(&mmap)?;
+            let thread = name.thread_name();
+            println!(
+                "  {:<10} {:>10.1?} {:>8} samples  written {} {:06}  {:?}",
+                thread,
+                profile.duration(),
+                profile.samples.len(),
+                name.date,
+                name.time,
+                cpu_profile
+            );
+        }
+    }
+    if !other.is_empty() {
+        println!("not named by --cpu-prof:");
+        for cpu_profile in &other {
+            println!("  {:?}", cpu_profile);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,195 @@
+use crate::inputs::{expand, is_batch};
+use crate::map_file;
+use crate::Error;
+use rayon::prelude::*;
+use std::fmt::Write as _;
+use std::fs::read_to_string;
+use std::path::Path;
+use std::str::FromStr;
+use v8_cpuprofile::budget::{Budgets, Metric, Violation};
+use v8_cpuprofile::links::repo_path;
+use v8_cpuprofile::{FrameKey, FrameTimes, Profile};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CheckFormat {
+    Text,
+    /// GitHub Actions `::warning` commands, which show each violation on
+    /// the function's source in a pull request.
+    Github,
+}
+
+impl FromStr for CheckFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, String> {
+        match format {
+            "text" => Ok(CheckFormat::Text),
+            "github" => Ok(CheckFormat::Github),
+            _ => Err(format!("unknown check format {}", format)),
+        }
+    }
+}
+
+pub struct Output<'a> {
+    pub format: CheckFormat,
+    /// Stripped from script urls to get paths in the repository.
+    pub source_root: &'a str,
+}
+
+impl Default for Output<'_> {
+    fn default() -> Self {
+        Output {
+            format: CheckFormat::Text,
+            source_root: "",
+        }
+    }
+}
+
+/// Returns whether all budgets were met by every profile.
+pub fn run(
+    input: &Path,
+    budgets: &Path,
+    stitch_async: bool,
+    attribute_gc: bool,
+    output: &Output<'_>,
+) -> Result<bool, Error> {
+    let budgets: Budgets = toml::from_str(&read_to_string(budgets)?)?;
+    if !is_batch(input) {
+        return check(input, &budgets, stitch_async, attribute_gc, output);
+    }
+    let paths = expand(input)?;
+    // checked in parallel, reported in the order of the paths
+    let reports: Vec<_> = paths
+        .par_iter()
+        .map(|cpu_profile| {
+            let mut report = format!("{:?}:\n", cpu_profile);
+            let result = report_into(
+                &mut report,
+                cpu_profile,
+                &budgets,
+                stitch_async,
+                attribute_gc,
+                output,
+            );
+            (report, result)
+        })
+        .collect();
+    let mut over_budget = 0;
+    for (report, result) in reports {
+        print!("{}", report);
+        if !result? {
+            over_budget += 1;
+        }
+    }
+    println!("{} of {} profiles over budget", over_budget, paths.len());
+    Ok(over_budget == 0)
+}
+
+/// With `stitch_async` time after an await counts toward the async
+/// function's caller, with `attribute_gc` garbage collection counts toward
+/// the function that ran before it.
+pub fn check(
+    cpu_profile: &Path,
+    budgets: &Budgets,
+    stitch_async: bool,
+    attribute_gc: bool,
+    output: &Output<'_>,
+) -> Result<bool, Error> {
+    let mut report = String::new();
+    let result = report_into(
+        &mut report,
+        cpu_profile,
+        budgets,
+        stitch_async,
+        attribute_gc,
+        output,
+    );
+    print!("{}", report);
+    result
+}
+
+/// Checks like [`check`], writing what it would print to `report`.
+fn report_into(
+    report: &mut String,
+    cpu_profile: &Path,
+    budgets: &Budgets,
+    stitch_async: bool,
+    attribute_gc: bool,
+    output: &Output<'_>,
+) -> Result<bool, Error> {
+    let mmap = map_file(cpu_profile)?;
+    let mut profile: Profile = serde_json::from_slice(&mmap)?;
+    if attribute_gc {
+        profile.attribute_gc()?;
+    }
+    let frame_times = if stitch_async {
+        profile.stitched_frame_times()?
+    } else {
+        profile.frame_times()?
+    };
+    let violations = profile.check_budgets_in(&frame_times, &budgets.budget);
+
+    for violation in &violations {
+        let (what, unit) = match violation.metric {
+            Metric::SelfTime => ("self time", "ms"),
+            Metric::TotalTime => ("total time", "ms"),
+            Metric::SelfPercent => ("self time", "%"),
+            Metric::TotalPercent => ("total time", "%"),
+        };
+        let function = match &violation.budget.url {
+            Some(url) => format!("{} ({})", violation.budget.function, url),
+            None => violation.budget.function.clone(),
+        };
+        let message = format!(
+            "over budget: {} of {} is {:.1}{} (limit {:.1}{})",
+            what, function, violation.actual, unit, violation.limit, unit
+        );
+        match output.format {
+            CheckFormat::Text => writeln!(report, "{}", message)?,
+            CheckFormat::Github => {
+                let location = hottest(violation, &frame_times).and_then(|frame| {
+                    let path = repo_path(&frame.url, output.source_root)?;
+                    let line = i64::from(frame.line_number.max(0)) + 1;
+                    Some(format!(" file={},line={},", escape_property(path), line))
+                });
+                writeln!(
+                    report,
+                    "::warning{}title=Over budget::{}",
+                    location.as_deref().unwrap_or(" "),
+                    escape_data(&message)
+                )?;
+            }
+        }
+    }
+    if violations.is_empty() {
+        writeln!(report, "all {} budgets met", budgets.budget.len())?;
+    }
+    Ok(violations.is_empty())
+}
+
+/// The function a budget applies to with the most self time, where its
+/// violation is annotated.
+fn hottest<'f, 'raw: 'f>(
+    violation: &Violation<'_>,
+    frame_times: impl IntoIterator<Item = (&'f FrameKey<'raw>, &'f FrameTimes)>,
+) -> Option<&'f FrameKey<'raw>> {
+    frame_times
+        .into_iter()
+        .filter(|(frame, _)| violation.budget.matches(frame))
+        .max_by_key(|(_, times)| times.self_time)
+        .map(|(frame, _)| frame)
+}
+
+/// Escapes the message of a workflow command.
+fn escape_data(data: &str) -> String {
+    data.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escapes a property of a workflow command.
+fn escape_property(property: &str) -> String {
+    escape_data(property)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
@@ -0,0 +1,96 @@
+use crate::{create_file, map_file, Error};
+use std::fs::read_to_string;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::Duration;
+use v8_cpuprofile::format::{decompress, Csv, Folded, Pprof, Registry};
+use v8_cpuprofile::links::SourceLinks;
+use v8_cpuprofile::script::ScriptTable;
+
+/// How the input is read.
+pub struct Source<'a> {
+    /// The format, detected when `None`.
+    pub from: Option<&'a str>,
+    /// How far apart the samples counted in folded stacks are, for those of
+    /// DTrace or bpftrace.
+    pub sample_interval: Option<Duration>,
+}
+
+/// Changes made to the profile before it is written.
+pub struct Edits<'a> {
+    pub strip_idle: bool,
+    /// A file of `Debugger.scriptParsed` events to rename scripts by id.
+    pub scripts: Option<&'a Path>,
+}
+
+pub fn run(
+    input: &Path,
+    source: &Source<'_>,
+    to: &str,
+    out: &Path,
+    edits: &Edits<'_>,
+    labels: Vec<(String, String)>,
+    links: Option<SourceLinks>,
+) -> Result<(), Error> {
+    let mut registry = Registry::default();
+    if links.is_some() {
+        registry.register(Box::new(Csv { links }));
+    }
+    if !labels.is_empty() {
+        if to != "pprof" {
+            return Err("--label only applies to pprof profiles".into());
+        }
+        registry.register(Box::new(Pprof { labels }));
+    }
+    let from = source.from;
+    if let Some(interval) = source.sample_interval {
+        if from != Some("folded") {
+            return Err("--sample-interval-us only applies with --from folded".into());
+        }
+        registry.register(Box::new(Folded {
+            interval: Some(interval),
+        }));
+    }
+    let bytes = map_file(input)?;
+    let bytes = decompress(&bytes)?;
+    let reader = registry.reader(&bytes, input, from).map_err(|error| {
+        if from.is_none() && error.kind() == io::ErrorKind::InvalidData {
+            format!("{}, pass --from", error).into()
+        } else {
+            Error::from(error)
+        }
+    })?;
+    let writer = registry.writer(to)?;
+    let scripts = match edits.scripts {
+        Some(path) => Some(ScriptTable::parse(&read_to_string(path)?)?),
+        None => None,
+    };
+
+    let mut profile = reader.read(&bytes)?;
+    if edits.strip_idle {
+        let removed = profile.strip_idle()?;
+        eprintln!("removed {:.1?} of idle time", removed);
+    }
+    if let Some(scripts) = &scripts {
+        let renamed = profile.remap_scripts(scripts)?;
+        eprintln!("renamed the scripts of {} nodes", renamed);
+    }
+    eprintln!(
+        "converting {} from {} to {}",
+        profile.summary(),
+        reader.name(),
+        writer.name()
+    );
+    let mut out = create_file(out)?;
+    writer.write(&profile, &mut out)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// A `key=value` label.
+pub fn label(label: &str) -> Result<(String, String), String> {
+    match label.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected key=value, got {:?}", label)),
+    }
+}
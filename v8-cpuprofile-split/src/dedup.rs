@@ -0,0 +1,57 @@
+use crate::inputs::expand_all;
+use crate::{map_file, parse_any, Error};
+use rayon::prelude::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+use v8_cpuprofile::fingerprint::duplicates;
+use v8_cpuprofile::format::decompress;
+use v8_cpuprofile::sidecar::sidecar_path;
+
+/// Lists profiles that are copies of an earlier one by fingerprint, in path
+/// order, and deletes them and their sidecars with `remove`.
+pub fn run(inputs: &[PathBuf], remove: bool) -> Result<(), Error> {
+    let cpu_profiles = expand_all(inputs)?;
+    let fingerprints = cpu_profiles
+        .par_iter()
+        .map(|cpu_profile| {
+            fingerprint(cpu_profile).map_err(|error| format!("{:?}: {}", cpu_profile, error).into())
+        })
+        .collect::<Result<Vec<u64>, Error>>()?;
+
+    let groups = duplicates(&fingerprints);
+    for group in &groups {
+        println!("{:016x}", fingerprints[group[0]]);
+        println!("  keep      {:?}", cpu_profiles[group[0]]);
+        for &index in &group[1..] {
+            let duplicate = &cpu_profiles[index];
+            if remove {
+                fs::remove_file(duplicate)?;
+                let sidecar = sidecar_path(duplicate);
+                if sidecar.exists() {
+                    fs::remove_file(sidecar)?;
+                }
+                println!("  removed   {:?}", duplicate);
+            } else {
+                println!("  duplicate {:?}", duplicate);
+            }
+        }
+    }
+    let duplicates: usize = groups.iter().map(|group| group.len() - 1).sum();
+    println!(
+        "{} profiles, {} duplicates{}",
+        cpu_profiles.len(),
+        duplicates,
+        if remove || duplicates == 0 {
+            ""
+        } else {
+            ", pass --remove to delete them"
+        }
+    );
+    Ok(())
+}
+
+fn fingerprint(cpu_profile: &Path) -> Result<u64, Error> {
+    let bytes = map_file(cpu_profile)?;
+    let bytes = decompress(&bytes)?;
+    Ok(parse_any(&bytes)?.fingerprint()?)
+}
@@ -0,0 +1,33 @@
+use crate::format::percent;
+use crate::map_file;
+use crate::Error;
+use std::path::Path;
+use v8_cpuprofile::Profile;
+
+pub fn run(cpu_profile: &Path, top: usize) -> Result<(), Error> {
+    let mmap = map_file(cpu_profile)?;
+    let profile: Profile = serde_json::from_slice(&mmap)?;
+    let report = profile.deopts()?;
+
+    let duration = profile.duration();
+    println!(
+        "{:.1?} of {:.1?} sampled in deoptimized functions ({:.1}%)",
+        report.total,
+        duration,
+        percent(report.total.as_secs_f64(), duration.as_secs_f64())
+    );
+
+    println!("\nby reason:");
+    for reason in &report.reasons {
+        println!(
+            "{:>10.1?} {:>5} nodes  {}",
+            reason.self_time, reason.nodes, reason.reason
+        );
+    }
+
+    println!("\ntop call sites:");
+    for site in report.sites.iter().take(top) {
+        println!("{:>10.1?}  {}  {}", site.self_time, site.frame, site.reason);
+    }
+    Ok(())
+}
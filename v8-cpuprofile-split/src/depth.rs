@@ -0,0 +1,18 @@
+use crate::map_file;
+use crate::Error;
+use std::path::Path;
+use v8_cpuprofile::Profile;
+
+pub fn run(cpu_profile: &Path, top: usize) -> Result<(), Error> {
+    let mmap = map_file(cpu_profile)?;
+    let profile: Profile = serde_json::from_slice(&mmap)?;
+    let stats = profile.depth_stats();
+
+    println!("max depth: {}", stats.max);
+    println!("mean depth: {:.1}", stats.mean);
+    println!("\ndeepest stacks:");
+    for &(node_id, depth) in stats.deepest.iter().take(top) {
+        println!("{:>6}  {}", depth, profile[node_id].frame()?);
+    }
+    Ok(())
+}
@@ -0,0 +1,171 @@
+use crate::aggregate::{map_files, parse_profiles};
+use crate::inputs::expand_all;
+use crate::{create_file, Error};
+use std::collections::BTreeMap;
+use std::fs::{create_dir_all, read};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use v8_cpuprofile::aggregate::ProfileSet;
+use v8_cpuprofile::Profile;
+
+/// When a function counts as a regression.
+pub struct Thresholds {
+    /// Growth of the mean self time relative to the baseline, in percent.
+    pub percent: f64,
+    /// Growth below this is noise however large it is relative to the
+    /// baseline.
+    pub min: Duration,
+}
+
+/// A `--min-ms` of zero or more milliseconds.
+pub fn milliseconds(text: &str) -> Result<Duration, String> {
+    let ms = text.parse::<f64>().map_err(|error| error.to_string())?;
+    if ms.is_nan() || ms < 0.0 {
+        return Err(format!("expected zero or more milliseconds, got {}", text));
+    }
+    Duration::try_from_secs_f64(ms / 1000.0)
+        .map_err(|_| format!("{} milliseconds is too long", text))
+}
+
+/// Aggregates the profiles of each workload and compares them with the
+/// aggregate stored in `baseline_dir`, or replaces it when accepting.
+/// Returns whether no workload regressed.
+pub fn run(
+    inputs: &[PathBuf],
+    baseline_dir: &Path,
+    workload: Option<&str>,
+    accept: bool,
+    thresholds: &Thresholds,
+    top: usize,
+    error_bars: bool,
+) -> Result<bool, Error> {
+    let cpu_profiles = expand_all(inputs)?;
+    let mmaps = map_files(&cpu_profiles)?;
+    let profiles = parse_profiles(&mmaps)?;
+
+    let mut workloads: BTreeMap<String, Vec<Profile>> = BTreeMap::new();
+    for (path, profile) in cpu_profiles.iter().zip(profiles) {
+        let label = match label(&profile)?.as_deref().or(workload) {
+            Some(label) => label.to_string(),
+            None => {
+                return Err(format!(
+                    "{:?} has no workload in its metadata, pass --workload",
+                    path
+                )
+                .into())
+            }
+        };
+        if !is_file_name(&label) {
+            return Err(format!(
+                "{:?} has workload {:?}, which can't name a baseline file",
+                path, label
+            )
+            .into());
+        }
+        workloads.entry(label).or_default().push(profile);
+    }
+
+    if accept {
+        create_dir_all(baseline_dir)?;
+    }
+    let mut passed = true;
+    for (label, profiles) in &workloads {
+        let set = ProfileSet::aggregate(profiles)?;
+        let baseline_path = baseline_dir.join(format!("{}.json", label));
+        if accept {
+            let mut out = create_file(&baseline_path)?;
+            serde_json::to_writer_pretty(&mut out, &set)?;
+            writeln!(out)?;
+            out.flush()?;
+            println!("{}: accepted {} runs as the baseline", label, set.runs);
+            continue;
+        }
+        if !baseline_path.exists() {
+            println!("{}: no baseline, run with --accept to store one", label);
+            passed = false;
+            continue;
+        }
+        let json = read(&baseline_path)?;
+        let baseline: ProfileSet = serde_json::from_slice(&json)?;
+        passed &= compare(label, &set, &baseline, thresholds, top, error_bars);
+    }
+    Ok(passed)
+}
+
+/// Prints how a workload changed, returns whether nothing regressed.
+fn compare(
+    label: &str,
+    set: &ProfileSet,
+    baseline: &ProfileSet,
+    thresholds: &Thresholds,
+    top: usize,
+    error_bars: bool,
+) -> bool {
+    let changes = set.compare(baseline);
+    let regressions = changes
+        .iter()
+        .filter(|change| {
+            change.is_regression()
+                && change.difference() >= thresholds.min
+                && change.relative() * 100.0 > thresholds.percent
+        })
+        .count();
+    let total = |set: &ProfileSet| -> Duration {
+        set.frames.iter().map(|frame| frame.self_time.mean).sum()
+    };
+    println!(
+        "{}: {:.1?} -> {:.1?} over {} runs, {} functions regressed",
+        label,
+        total(baseline),
+        total(set),
+        set.runs,
+        regressions
+    );
+    for change in changes.iter().take(top) {
+        let sign = if change.is_regression() { '+' } else { '-' };
+        let margin = if error_bars {
+            // margins of independent times add in quadrature
+            let squared =
+                |margin: Option<Duration>| margin.map_or(0.0, |m| m.as_secs_f64().powi(2));
+            let margin = (squared(set.margin(change.current))
+                + squared(baseline.margin(change.baseline)))
+            .sqrt();
+            format!(" ±{:<8.1?}", Duration::from_secs_f64(margin))
+        } else {
+            String::new()
+        };
+        println!(
+            "    {}{:<10.1?}{} {:>7.1}%  {}",
+            sign,
+            change.difference(),
+            margin,
+            change.relative() * 100.0,
+            change.frame
+        );
+    }
+    regressions == 0
+}
+
+/// Whether a workload names a file inside the baseline directory, the
+/// label comes from the profile so it must not reach outside of it.
+fn is_file_name(label: &str) -> bool {
+    !label.is_empty()
+        && label != "."
+        && label != ".."
+        && !label.contains(|c| c == '/' || c == '\\' || c == ':' || c == '\0')
+        && !Path::new(label).is_absolute()
+}
+
+/// The `workload` string of a profile's metadata.
+fn label(profile: &Profile) -> Result<Option<String>, Error> {
+    let metadata = match &profile.metadata {
+        Some(metadata) => metadata,
+        None => return Ok(None),
+    };
+    let metadata: serde_json::Value = serde_json::from_str(metadata.get())?;
+    Ok(metadata
+        .get("workload")
+        .and_then(serde_json::Value::as_str)
+        .map(String::from))
+}
@@ -0,0 +1,44 @@
+use crate::{create_file, map_file, parse_any, Error};
+use std::io::Write;
+use std::path::Path;
+use v8_cpuprofile::etw::EtwCapture;
+use v8_cpuprofile::format::decompress;
+use v8_cpuprofile::trace::{Trace, Track};
+
+/// Writes a Chrome trace with the profile as the JavaScript thread of `pid`
+/// and a track per thread sampled by ETW, shifted by `offset_us`.
+pub fn run(
+    cpu_profile: &Path,
+    etw: &Path,
+    out: &Path,
+    pid: u32,
+    offset_us: i64,
+) -> Result<(), Error> {
+    let mmap = map_file(cpu_profile)?;
+    let input = decompress(&mmap)?;
+    let profile = parse_any(&input)?;
+    let capture: EtwCapture = serde_json::from_slice(&map_file(etw)?)?;
+    let threads = capture.threads(offset_us)?;
+
+    let mut tracks = vec![Track {
+        pid,
+        tid: 0,
+        name: "JavaScript".into(),
+        profile: &profile,
+    }];
+    tracks.extend(threads.iter().map(|thread| Track {
+        pid: thread.pid,
+        tid: thread.tid,
+        name: thread.name.clone(),
+        profile: &thread.profile,
+    }));
+    if !threads.iter().any(|thread| thread.pid == pid) {
+        eprintln!("no etw samples of process {}", pid);
+    }
+    let trace = Trace { tracks };
+    eprintln!("writing {} tracks to {:?}", trace.tracks.len(), out);
+    let mut writer = create_file(out)?;
+    serde_json::to_writer(&mut writer, &trace)?;
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use crate::Error;
+use serde_json::error::Category;
+use std::fmt;
+use std::io;
+use std::process;
+
+/// A run that completed but found a problem, like an exceeded budget.
+#[derive(Debug)]
+pub struct Failed(pub String);
+
+impl fmt::Display for Failed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Failed {}
+
+/// What went wrong, each kind exits with its own code so scripts can tell
+/// them apart.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Kind {
+    Other = 1,
+    Io = 2,
+    Parse = 3,
+    Failed = 4,
+}
+
+impl Kind {
+    pub fn of(error: &(dyn std::error::Error + 'static)) -> Self {
+        if error.is::<Failed>() {
+            Kind::Failed
+        } else if let Some(error) = error.downcast_ref::<io::Error>() {
+            // how formats other than json report a malformed profile
+            if error.kind() == io::ErrorKind::InvalidData {
+                Kind::Parse
+            } else {
+                Kind::Io
+            }
+        } else if error.is::<glob::GlobError>() {
+            Kind::Io
+        } else if let Some(error) = error.downcast_ref::<serde_json::Error>() {
+            match error.classify() {
+                Category::Io => Kind::Io,
+                Category::Syntax | Category::Data | Category::Eof => Kind::Parse,
+            }
+        } else if error.is::<toml::de::Error>() {
+            Kind::Parse
+        } else {
+            Kind::Other
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Other => "other",
+            Kind::Io => "io",
+            Kind::Parse => "parse",
+            Kind::Failed => "failed",
+        }
+    }
+}
+
+/// Prints the error to stderr, as a json object with `json`, and exits with
+/// the code of its kind.
+pub fn report(error: &Error, json: bool) -> ! {
+    let kind = Kind::of(error.as_ref());
+    if json {
+        let error = serde_json::json!({
+            "kind": kind.name(),
+            "message": error.to_string(),
+            "exitCode": kind as i32,
+        });
+        eprintln!("{}", error);
+    } else {
+        eprintln!("error: {}", error);
+    }
+    process::exit(kind as i32)
+}
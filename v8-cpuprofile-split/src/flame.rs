@@ -0,0 +1,28 @@
+use crate::{create_file, map_file, parse_any, Error};
+use std::io::Write;
+use std::path::Path;
+use v8_cpuprofile::flame::FlameOptions;
+use v8_cpuprofile::format::decompress;
+
+pub fn run(
+    cpu_profile: &Path,
+    out: &Path,
+    attribute_gc: bool,
+    strip_idle: bool,
+    options: &FlameOptions,
+) -> Result<(), Error> {
+    let mmap = map_file(cpu_profile)?;
+    let input = decompress(&mmap)?;
+    let mut profile = parse_any(&input)?;
+    if attribute_gc {
+        profile.attribute_gc()?;
+    }
+    if strip_idle {
+        profile.strip_idle()?;
+    }
+    eprintln!("drawing {} to {:?}", profile.summary(), out);
+    let mut writer = create_file(out)?;
+    profile.write_flamegraph(&mut writer, options)?;
+    writer.flush()?;
+    Ok(())
+}
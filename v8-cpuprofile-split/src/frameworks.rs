@@ -0,0 +1,42 @@
+use crate::Error;
+use std::fs::read_to_string;
+use std::path::PathBuf;
+use structopt::StructOpt;
+use v8_cpuprofile::framework::Rules;
+
+/// Options for the time by framework subsystem.
+#[derive(Debug, StructOpt)]
+pub struct FrameworkOpts {
+    /// Adds the time by subsystem of a framework: react, vue, ember or
+    /// express, repeated for more
+    #[structopt(long = "framework", value_name = "framework", number_of_values = 1, parse(try_from_str = preset))]
+    presets: Vec<Rules>,
+    /// A TOML file of [[rule]] tables, with category, url, functions and
+    /// inherit, and presets, a list of framework names
+    #[structopt(long, parse(from_os_str))]
+    rules: Option<PathBuf>,
+}
+
+fn preset(name: &str) -> Result<Rules, String> {
+    Rules::preset(name).ok_or_else(|| {
+        format!(
+            "unknown framework {}, expected one of {}",
+            name,
+            Rules::PRESETS.join(", ")
+        )
+    })
+}
+
+impl FrameworkOpts {
+    pub fn rules(&self) -> Result<Option<Rules>, Error> {
+        let mut rules = match &self.rules {
+            Some(path) => toml::from_str(&read_to_string(path)?)?,
+            None if self.presets.is_empty() => return Ok(None),
+            None => Rules::default(),
+        };
+        for preset in &self.presets {
+            rules.rule.extend(preset.rule.iter().cloned());
+        }
+        Ok(Some(rules))
+    }
+}
@@ -0,0 +1,34 @@
+use crate::map_file;
+use crate::Error;
+use regex::Regex;
+use std::path::Path;
+use v8_cpuprofile::Profile;
+
+/// Prints the nodes whose function name or url matches `pattern` with the
+/// path from the root to each, the `top` with the most total time.
+pub fn run(cpu_profile: &Path, pattern: &str, top: usize) -> Result<(), Error> {
+    let pattern = Regex::new(pattern)?;
+    let mmap = map_file(cpu_profile)?;
+    let profile: Profile = serde_json::from_slice(&mmap)?;
+
+    let matches = profile.search(&pattern)?;
+    if matches.is_empty() {
+        return Err(format!("no function matches {:?}", pattern.as_str()).into());
+    }
+    for (index, found) in matches.iter().take(top).enumerate() {
+        if index > 0 {
+            println!();
+        }
+        println!(
+            "node {}  self {:.1?}  total {:.1?}",
+            found.node_id, found.self_time, found.total_time
+        );
+        for (depth, frame) in found.path.iter().enumerate() {
+            println!("  {:indent$}{}", "", frame, indent = depth * 2);
+        }
+    }
+    if matches.len() > top {
+        println!("\n{} more matches", matches.len() - top);
+    }
+    Ok(())
+}
@@ -0,0 +1,20 @@
+use crate::Error;
+use crate::{create_file, map_file};
+use std::io::Write;
+use std::path::Path;
+use v8_cpuprofile::heap::AllocationTrace;
+
+pub fn run(heap_snapshot: &Path, out: &Path) -> Result<(), Error> {
+    eprintln!("parsing heap snapshot from {:?}", heap_snapshot);
+    let mmap = map_file(heap_snapshot)?;
+    let trace: AllocationTrace = serde_json::from_slice(&mmap)?;
+    eprintln!(
+        "writing allocation trace of {} functions to {:?}",
+        trace.functions.len(),
+        out
+    );
+    let mut writer = create_file(out)?;
+    serde_json::to_writer(&mut writer, &trace)?;
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use crate::Error;
+use crate::{create_file, map_file};
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+use v8_cpuprofile::indexed::{open_indexed, write_indexed};
+use v8_cpuprofile::{Profile, ProfileChunk};
+
+pub fn run(cpu_profile: &Path, out: &Path, bucket: Duration) -> Result<(), Error> {
+    eprintln!("parsing cpuprofile from {:?}", cpu_profile);
+    let mmap = map_file(cpu_profile)?;
+    let profile: Profile = serde_json::from_slice(&mmap)?;
+    eprintln!(
+        "writing {} samples in {:?} buckets to {:?}",
+        profile.samples.len(),
+        bucket,
+        out
+    );
+    write_indexed(&profile, bucket, create_file(out)?)?;
+    Ok(())
+}
+
+pub fn slice(indexed: &Path, out: &Path, start: Duration, end: Duration) -> Result<(), Error> {
+    let indexed = open_indexed(indexed)?;
+    let profile = indexed.slice(start, end)?;
+    eprintln!(
+        "writing {} samples from {:?} to {:?} to {:?}",
+        profile.samples.len(),
+        start,
+        end,
+        out
+    );
+    // only the nodes sampled in the slice
+    let chunk = ProfileChunk::new(&profile, &profile.samples);
+    let mut writer = create_file(out)?;
+    serde_json::to_writer(&mut writer, &chunk)?;
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use crate::{remote, Error};
+use std::collections::BTreeMap;
+use std::fs::read_dir;
+use std::path::{Path, PathBuf};
+use v8_cpuprofile::capture::CaptureName;
+
+/// Expands a cpuprofile argument: directories are searched recursively for
+/// `*.cpuprofile` files, glob patterns are matched and anything else, like
+/// an `s3://` url, is used as is.
+pub fn expand(input: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut paths = Vec::new();
+    if input.is_dir() {
+        walk(input, &mut paths)?;
+    } else if is_glob(input) && !remote::is_remote(input) {
+        let pattern = input.to_str().ok_or("glob pattern is not valid utf-8")?;
+        for path in glob::glob(pattern)? {
+            paths.push(path?);
+        }
+    } else {
+        paths.push(input.to_path_buf());
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Expands each argument, see [`expand`].
+pub fn expand_all(inputs: &[PathBuf]) -> Result<Vec<PathBuf>, Error> {
+    let mut paths = Vec::new();
+    for input in inputs {
+        paths.extend(expand(input)?);
+    }
+    Ok(paths)
+}
+
+/// Whether the argument named more than one file.
+pub fn is_batch(input: &Path) -> bool {
+    input.is_dir() || is_glob(input)
+}
+
+fn is_glob(input: &Path) -> bool {
+    match input.to_str() {
+        Some(s) => s.contains(&['*', '?', '['][..]),
+        None => false,
+    }
+}
+
+fn walk(dir: &Path, paths: &mut Vec<PathBuf>) -> Result<(), Error> {
+    for entry in read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            walk(&path, paths)?;
+        } else if path.extension() == Some("cpuprofile".as_ref()) {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// The `--cpu-prof` profiles of each thread of one process.
+pub type Threads = Vec<(CaptureName, PathBuf)>;
+
+/// Profiles written by `node --cpu-prof` grouped by pid, each group sorted
+/// by thread, and the paths whose file names aren't in that format.
+pub fn by_process(paths: Vec<PathBuf>) -> (BTreeMap<u32, Threads>, Vec<PathBuf>) {
+    let mut processes: BTreeMap<u32, Threads> = BTreeMap::new();
+    let mut other = Vec::new();
+    for path in paths {
+        match path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(CaptureName::parse)
+        {
+            Some(name) => processes.entry(name.pid).or_default().push((name, path)),
+            None => other.push(path),
+        }
+    }
+    for threads in processes.values_mut() {
+        threads.sort_by_key(|&(name, _)| (name.tid, name.seq));
+    }
+    (processes, other)
+}
+
+/// Prints how many inputs failed and returns an error if any did.
+pub fn summarize(results: Vec<(PathBuf, Result<(), Error>)>) -> Result<(), Error> {
+    let total = results.len();
+    let failed: Vec<_> = results
+        .into_iter()
+        .filter_map(|(path, result)| result.err().map(|error| (path, error)))
+        .collect();
+    println!("{} of {} profiles succeeded", total - failed.len(), total);
+    for (path, error) in &failed {
+        println!("  {:?}: {}", path, error);
+    }
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("{} profiles failed", failed.len()).into())
+    }
+}
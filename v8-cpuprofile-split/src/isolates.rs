@@ -0,0 +1,80 @@
+use crate::aggregate::{map_files, parse_profiles};
+use crate::inputs::{by_process, expand_all};
+use crate::Error;
+use std::path::PathBuf;
+use std::time::Duration;
+use v8_cpuprofile::isolate::IsolateSet;
+
+/// Prints the hottest functions across the isolates of a run, with the time
+/// in each, and then the hottest of each isolate.
+pub fn run(inputs: &[PathBuf], top: usize) -> Result<(), Error> {
+    let (processes, other) = by_process(expand_all(inputs)?);
+    let mut labels = Vec::new();
+    let mut cpu_profiles = Vec::new();
+    for (pid, threads) in processes {
+        for (name, cpu_profile) in threads {
+            labels.push(format!("{} {}", pid, name.thread_name()));
+            cpu_profiles.push(cpu_profile);
+        }
+    }
+    // profiles not named by --cpu-prof are labeled by file name
+    for cpu_profile in other {
+        labels.push(
+            cpu_profile
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned(),
+        );
+        cpu_profiles.push(cpu_profile);
+    }
+    let mmaps = map_files(&cpu_profiles)?;
+    let profiles = parse_profiles(&mmaps)?;
+    let set = IsolateSet::new(&profiles, labels)?;
+
+    let total = set.duration();
+    let percent = |time: Duration| {
+        if total > Duration::default() {
+            time.as_secs_f64() / total.as_secs_f64() * 100.0
+        } else {
+            0.0
+        }
+    };
+    println!("isolates:");
+    for (index, (label, duration)) in set.labels.iter().zip(&set.durations).enumerate() {
+        println!(
+            "  [{}] {:<20} {:>10.1?} {:>5.1}%  {:?}",
+            index,
+            label,
+            duration,
+            percent(*duration),
+            cpu_profiles[index]
+        );
+    }
+
+    println!("\nhottest functions across isolates by self time:");
+    print!("{:>10} ", "all");
+    for index in 0..set.labels.len() {
+        print!("{:>10} ", format!("[{}]", index));
+    }
+    println!(" function");
+    for frame in set.frames.iter().take(top) {
+        print!("{:>10.1?} ", frame.combined.self_time);
+        for times in &frame.isolates {
+            print!("{:>10.1?} ", times.self_time);
+        }
+        println!(" {}", frame.frame);
+    }
+
+    for (index, label) in set.labels.iter().enumerate() {
+        println!("\n[{}] {}:", index, label);
+        for frame in set.isolate_frames(index).into_iter().take(top) {
+            let times = frame.isolates[index];
+            println!(
+                "{:>10.1?} {:>10.1?}  {}",
+                times.self_time, times.total_time, frame.frame
+            );
+        }
+    }
+    Ok(())
+}
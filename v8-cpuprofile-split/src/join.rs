@@ -0,0 +1,125 @@
+use crate::exit::Failed;
+use crate::inputs::expand_all;
+use crate::manifest::{self, sha256};
+use crate::provenance;
+use crate::Error;
+use crate::{create_file, map_file, Input};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use v8_cpuprofile::Profile;
+
+/// Joins split parts back into one profile. The inputs are either a
+/// manifest, or a directory with one, or the part files in any order.
+/// With `provenance` the parts are written as the joined profile's
+/// `metadata`.
+pub fn run(inputs: &[PathBuf], out: &Path, provenance: bool) -> Result<(), Error> {
+    match inputs {
+        [manifest] if manifest.extension() == Some("json".as_ref()) => {
+            join_manifest(manifest, out, provenance)
+        }
+        [dir] if dir.join(manifest::FILE_NAME).is_file() => {
+            join_manifest(&dir.join(manifest::FILE_NAME), out, provenance)
+        }
+        _ => {
+            let paths = expand_all(inputs)?;
+            let mut mmaps = Vec::with_capacity(paths.len());
+            for path in &paths {
+                mmaps.push(map_file(path)?);
+            }
+            let parts = parse(&mmaps)?;
+            let mut profile = Profile::join(&parts)?;
+            if provenance {
+                let files: Vec<_> = paths.iter().map(|path| path.to_string_lossy()).collect();
+                let hashes: Vec<_> = mmaps.iter().map(|mmap| sha256(mmap)).collect();
+                let sources: Vec<_> = files
+                    .iter()
+                    .map(AsRef::as_ref)
+                    .zip(hashes.iter().map(AsRef::as_ref))
+                    .collect();
+                set_provenance(&mut profile, &sources)?;
+            }
+            eprintln!("writing {} parts to {:?}", parts.len(), out);
+            write(&profile, out)
+        }
+    }
+}
+
+/// Reassembles a split profile from the parts listed in its manifest, and
+/// the node table they share if they were split with shared nodes, after
+/// checking their hashes. The manifest's sample ranges tell which samples
+/// parts share if they overlap.
+fn join_manifest(manifest_path: &Path, out: &Path, provenance: bool) -> Result<(), Error> {
+    let manifest = manifest::read(manifest_path)?;
+    let dir = manifest_path.parent().unwrap_or_else(|| Path::new(""));
+    // (file, sha256), the node table first
+    let files: Vec<(&str, &str)> = manifest
+        .shared_nodes
+        .iter()
+        .map(|shared_nodes| (shared_nodes.file.as_str(), shared_nodes.sha256.as_str()))
+        .chain(
+            manifest
+                .parts
+                .iter()
+                .map(|part| (part.file.as_str(), part.sha256.as_str())),
+        )
+        .collect();
+    let mut mmaps: Vec<Input> = Vec::with_capacity(files.len());
+    for &(file, hash) in &files {
+        let mmap = map_file(&dir.join(file))?;
+        if sha256(&mmap) != hash {
+            let message = format!("{} doesn't match its sha256 in the manifest", file);
+            return Err(Failed(message).into());
+        }
+        mmaps.push(mmap);
+    }
+    let parts = parse(&mmaps)?;
+    // the node table has no samples
+    let ranges: Vec<_> = manifest
+        .shared_nodes
+        .iter()
+        .map(|_| 0..0)
+        .chain(manifest.parts.iter().map(|part| part.samples.clone()))
+        .collect();
+    let mut profile = Profile::join_ranges(&parts, &ranges)?;
+    if provenance {
+        let sources = files;
+        set_provenance(&mut profile, &sources)?;
+    }
+    if profile.samples.len() != manifest.samples {
+        let message = format!(
+            "joined {} samples but {} had {}",
+            profile.samples.len(),
+            manifest.source,
+            manifest.samples
+        );
+        return Err(Failed(message).into());
+    }
+    eprintln!(
+        "writing {} from {} parts to {:?}",
+        manifest.source,
+        manifest.parts.len(),
+        out
+    );
+    write(&profile, out)
+}
+
+fn parse(mmaps: &[Input]) -> Result<Vec<Profile<'_>>, Error> {
+    let mut parts = Vec::with_capacity(mmaps.len());
+    for mmap in mmaps {
+        parts.push(serde_json::from_slice(mmap)?);
+    }
+    Ok(parts)
+}
+
+fn set_provenance(profile: &mut Profile, sources: &[(&str, &str)]) -> Result<(), Error> {
+    let metadata = provenance::metadata(sources, "join")?;
+    profile.metadata = Some(metadata.into());
+    Ok(())
+}
+
+fn write(profile: &Profile, out: &Path) -> Result<(), Error> {
+    let mut writer = create_file(out)?;
+    serde_json::to_writer(&mut writer, profile)?;
+    writer.flush()?;
+    Ok(())
+}
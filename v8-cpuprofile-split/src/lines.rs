@@ -0,0 +1,130 @@
+use crate::map_file;
+use crate::Error;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fs::read_to_string;
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+use v8_cpuprofile::links::SourceLinks;
+use v8_cpuprofile::Profile;
+
+pub struct Annotate<'a> {
+    pub source: &'a Path,
+    pub url: &'a str,
+    pub html: Option<&'a Path>,
+}
+
+pub fn run(
+    cpu_profile: &Path,
+    top: usize,
+    links: Option<&SourceLinks>,
+    annotate: Option<Annotate<'_>>,
+) -> Result<(), Error> {
+    let mmap = map_file(cpu_profile)?;
+    let profile: Profile = serde_json::from_slice(&mmap)?;
+
+    if let Some(annotate) = annotate {
+        let times = profile.line_times(annotate.url)?;
+        let source = read_to_string(annotate.source)?;
+        if let Some(html) = annotate.html {
+            let mut out = BufWriter::new(File::create(html)?);
+            write_html(&mut out, annotate.url, &source, &times, links)?;
+            out.flush()?;
+        } else {
+            for (index, line) in source.lines().enumerate() {
+                println!("{:>10} | {}", margin(&times, index), line);
+            }
+        }
+        return Ok(());
+    }
+
+    for hotspot in profile.line_hotspots()?.iter().take(top) {
+        let name = if hotspot.function_name.is_empty() {
+            "(anonymous)"
+        } else {
+            &hotspot.function_name
+        };
+        print!(
+            "{:>6}  {}:{}  {}",
+            hotspot.ticks, hotspot.url, hotspot.line, name
+        );
+        let line_number = i32::try_from(hotspot.line).map_or(-1, |line| line - 1);
+        match links.and_then(|links| links.link(&hotspot.url, line_number, -1)) {
+            Some(link) => println!("  {}", link),
+            None => println!(),
+        }
+    }
+    Ok(())
+}
+
+/// The sampled time of a 0-based line index, blank if it wasn't sampled.
+fn margin(times: &BTreeMap<u32, Duration>, index: usize) -> String {
+    line_time(times, index).map_or_else(String::new, |time| format!("{:.1?}", time))
+}
+
+fn line_time(times: &BTreeMap<u32, Duration>, index: usize) -> Option<Duration> {
+    let line = u32::try_from(index + 1).ok()?;
+    times
+        .get(&line)
+        .copied()
+        .filter(|time| *time > Duration::default())
+}
+
+fn write_html(
+    out: &mut impl Write,
+    url: &str,
+    source: &str,
+    times: &BTreeMap<u32, Duration>,
+    links: Option<&SourceLinks>,
+) -> Result<(), Error> {
+    let max = times.values().max().copied().unwrap_or_default();
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html><head><meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>{}</title>", escape(url))?;
+    writeln!(
+        out,
+        "<style>td{{font-family:monospace;white-space:pre;padding:0 .5em}}td.t{{text-align:right;color:#666}}</style>"
+    )?;
+    writeln!(out, "</head><body><table>")?;
+    for (index, line) in source.lines().enumerate() {
+        let time = line_time(times, index);
+        let heat = match time {
+            Some(time) if max > Duration::default() => time.as_secs_f64() / max.as_secs_f64(),
+            _ => 0.0,
+        };
+        // the line number links to the source if there is a link
+        let number = match links
+            .and_then(|links| links.link(url, i32::try_from(index).unwrap_or(i32::MAX), -1))
+        {
+            Some(link) => format!("<a href=\"{}\">{}</a>", escape(&link), index + 1),
+            None => (index + 1).to_string(),
+        };
+        writeln!(
+            out,
+            "<tr style=\"background:rgba(255,0,0,{:.2})\"><td class=\"t\">{}</td><td class=\"t\">{}</td><td>{}</td></tr>",
+            heat * 0.6,
+            margin(times, index),
+            number,
+            escape(line)
+        )?;
+    }
+    writeln!(out, "</table></body></html>")?;
+    Ok(())
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,35 @@
+use structopt::StructOpt;
+use v8_cpuprofile::links::SourceLinks;
+use v8_cpuprofile::template::TemplateError;
+
+/// Options for linking frames in a report to their source.
+#[derive(Debug, StructOpt)]
+pub struct LinkOpts {
+    /// Links each function to its source with this url template, where
+    /// {rev}, {path}, {line} and {column} are replaced, like
+    /// https://github.com/org/repo/blob/{rev}/{path}#L{line}
+    #[structopt(long, parse(try_from_str = template))]
+    source_links: Option<String>,
+    /// Replaces {rev} in the link template, HEAD by default
+    #[structopt(long, requires = "source-links")]
+    rev: Option<String>,
+    /// Where the repository is in script urls, like file:///home/ci/app/,
+    /// stripped to get {path}. Scripts elsewhere get no link
+    #[structopt(long, requires = "source-links")]
+    source_root: Option<String>,
+}
+
+fn template(template: &str) -> Result<String, TemplateError> {
+    SourceLinks::check_template(template)?;
+    Ok(template.to_string())
+}
+
+impl LinkOpts {
+    pub fn links(&self) -> Option<SourceLinks> {
+        Some(SourceLinks {
+            template: self.source_links.clone()?,
+            rev: self.rev.clone().unwrap_or_else(|| "HEAD".into()),
+            root: self.source_root.clone().unwrap_or_default(),
+        })
+    }
+}
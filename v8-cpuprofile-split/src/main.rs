@@ -1,74 +1,1056 @@
 #![deny(clippy::all, clippy::pedantic)]
-#![feature(once_cell)]
+
+mod aggregate;
+mod anonymous;
+mod bisect;
+mod bundles;
+mod captures;
+mod check;
+mod convert;
+mod dedup;
+mod deopts;
+mod depth;
+mod diff;
+mod etw;
+mod exit;
+mod flame;
+mod format;
+mod frameworks;
+mod grep;
+mod heap_trace;
+mod index;
+mod inputs;
+mod isolates;
+mod join;
+mod lines;
+mod links;
+mod manifest;
+mod meta;
+mod path;
+mod peek;
+mod pipeline;
+mod progress;
+mod provenance;
+#[cfg(feature = "push")]
+mod push;
+mod remote;
+mod report;
+mod requests;
+mod scripts;
+mod series;
+mod spans;
+mod split;
+mod stats;
+mod subtract;
+mod synth;
+mod timing;
+mod tracks;
+mod tree;
+mod watch;
 
 use memmap::Mmap;
-use std::fs::create_dir_all;
 use std::fs::File;
-use std::io::BufWriter;
-use std::lazy::OnceCell;
+use std::io::{self, BufWriter, Read, Write};
+use std::ops::Deref;
 use std::path::Path;
 use std::path::PathBuf;
-use std::sync::Arc;
-use std::sync::Mutex;
+use std::time::Duration;
 use structopt::StructOpt;
+use v8_cpuprofile::filter::Filter;
+use v8_cpuprofile::flame::{FlameOptions, Palette};
+use v8_cpuprofile::format::Registry;
+use v8_cpuprofile::synth::{Hotspots, SynthOptions};
 use v8_cpuprofile::Profile;
-use v8_cpuprofile::ProfileChunk;
 
 #[derive(Debug, StructOpt)]
-#[structopt(name = "cpuprofile-split")]
-struct Opt {
-    #[structopt(parse(from_os_str))]
-    cpu_profile: PathBuf,
-    #[structopt(parse(from_os_str))]
-    out_dir: PathBuf,
-    chunk_num: usize,
+#[structopt(name = "cpuprofile")]
+struct Args {
+    /// Prints errors to stderr as json objects with the kind of error, the
+    /// message and the exit code
+    #[structopt(long, global = true)]
+    json_errors: bool,
+    #[structopt(subcommand)]
+    command: Opt,
+}
+
+#[derive(Debug, StructOpt)]
+enum Opt {
+    /// Splits a cpuprofile into chunks small enough to load in dev tools
+    Split {
+        /// A cpuprofile, or a directory or glob pattern to split every
+        /// matching cpuprofile into its own directory
+        #[structopt(parse(from_os_str))]
+        cpu_profile: PathBuf,
+        #[structopt(parse(from_os_str))]
+        out_dir: PathBuf,
+        chunk_num: usize,
+        /// Indents the written chunks
+        #[structopt(long)]
+        pretty: bool,
+        /// File name of each chunk with the placeholders `{stem}`, `{num}`,
+        /// `{start_ms}` and `{end_ms}`, which can be zero padded like `{num:03}`,
+        /// and `{{` and `}}` for braces
+        #[structopt(long, default_value = split::DEFAULT_NAME_TEMPLATE)]
+        name_template: String,
+        /// Writes the tool version, the source file's sha256, the part and
+        /// the command line into each chunk as `metadata`
+        #[structopt(long)]
+        provenance: bool,
+        /// Lists the chunks that would be written, with their sample counts,
+        /// time ranges and sizes, without writing them
+        #[structopt(long)]
+        dry_run: bool,
+        /// Writes every node once to nodes.cpuprofile, which the chunks
+        /// leave out, instead of the nodes of its samples into each chunk
+        #[structopt(long)]
+        shared_nodes: bool,
+    },
+    /// Reassembles a split profile from the manifest.json a split writes
+    /// next to its parts, or from the parts themselves
+    Join {
+        /// The manifest, a directory with one, or the parts in any order as
+        /// files, directories or glob patterns
+        #[structopt(parse(from_os_str), required = true)]
+        inputs: Vec<PathBuf>,
+        #[structopt(long, parse(from_os_str))]
+        out: PathBuf,
+        /// Writes the tool version, the parts with their sha256 and the
+        /// command line into the joined profile as `metadata`
+        #[structopt(long)]
+        provenance: bool,
+    },
+    /// Reports time spent in deoptimized functions by reason and call site
+    Deopts {
+        #[structopt(parse(from_os_str))]
+        cpu_profile: PathBuf,
+        /// Number of call sites to list
+        #[structopt(long, default_value = "20")]
+        top: usize,
+    },
+    /// Lists the hottest source lines from the position ticks of each node
+    Lines {
+        #[structopt(parse(from_os_str))]
+        cpu_profile: PathBuf,
+        /// Number of lines to list
+        #[structopt(long, default_value = "20")]
+        top: usize,
+        /// Prints this source file with the sampled time of each line in
+        /// the margin instead
+        #[structopt(long, parse(from_os_str), requires = "url")]
+        annotate: Option<PathBuf>,
+        /// The script url of the annotated source file in the profile
+        #[structopt(long)]
+        url: Option<String>,
+        /// Writes the annotated source as html to this file
+        #[structopt(long, parse(from_os_str), requires = "annotate")]
+        html: Option<PathBuf>,
+        #[structopt(flatten)]
+        links: links::LinkOpts,
+    },
+    /// Prints the path from the root that always follows the heaviest child
+    Path {
+        #[structopt(parse(from_os_str))]
+        cpu_profile: PathBuf,
+    },
+    /// Finds the nodes whose function name or script url matches a regex and
+    /// prints the path from the root to each
+    Grep {
+        #[structopt(parse(from_os_str))]
+        cpu_profile: PathBuf,
+        pattern: String,
+        /// Number of matches to show, the most total time first
+        #[structopt(long, default_value = "20")]
+        top: usize,
+    },
+    /// Prints the callers of the functions matching a regex with the time
+    /// spent in them through each, and the callees they spend it in
+    Peek {
+        #[structopt(parse(from_os_str))]
+        cpu_profile: PathBuf,
+        /// Matched against function names
+        pattern: String,
+        /// Number of matching functions to show, the most total time first
+        #[structopt(long, default_value = "5")]
+        top: usize,
+    },
+    /// Prints the call tree pruned to the paths with a share of the time,
+    /// what was pruned under a node summed into an (other) child
+    Tree {
+        #[structopt(parse(from_os_str))]
+        cpu_profile: PathBuf,
+        /// Percent of the total time a path needs to be shown
+        #[structopt(long, default_value = "5")]
+        threshold: f64,
+    },
+    /// Prints node and sample counts and the estimated memory needed to
+    /// process the profile, which may be in any format convert reads
+    Stats {
+        #[structopt(parse(from_os_str))]
+        cpu_profile: PathBuf,
+    },
+    /// Lists the scripts of a profile with their nodes, samples and time,
+    /// the most self time first
+    Scripts {
+        #[structopt(parse(from_os_str))]
+        cpu_profile: PathBuf,
+    },
+    /// Attributes the time of each script to its bundle chunk and the time
+    /// of each chunk to the routes that load it, with a webpack stats.json
+    Bundles {
+        #[structopt(parse(from_os_str))]
+        cpu_profile: PathBuf,
+        #[structopt(parse(from_os_str))]
+        stats: PathBuf,
+    },
+    /// Lists the calls reconstructed from runs of samples with them on the
+    /// stack, in time order
+    Spans {
+        #[structopt(parse(from_os_str))]
+        cpu_profile: PathBuf,
+        /// Shortest call to list, in milliseconds
+        #[structopt(long, default_value = "1")]
+        min_ms: f64,
+        /// Lists only the calls of the function with this name
+        #[structopt(long)]
+        function: Option<String>,
+    },
+    /// Cuts the profile into the spans of requests or other labeled work
+    /// and prints the hottest functions of each label by mean self time
+    Requests {
+        #[structopt(parse(from_os_str))]
+        cpu_profile: PathBuf,
+        /// A line per request of its start and end in milliseconds and its
+        /// label, like `1700000000123.5 1700000000180 GET /users`
+        #[structopt(parse(from_os_str))]
+        intervals: PathBuf,
+        /// The time the profile started at in the clock of the intervals,
+        /// in milliseconds, which are relative to the start without it
+        #[structopt(long, default_value = "0")]
+        capture_start: f64,
+        /// Writes the profile of each request to <n>.cpuprofile here, with
+        /// its label as the workload, instead
+        #[structopt(long, parse(from_os_str))]
+        out_dir: Option<PathBuf>,
+        /// Number of functions to list per label
+        #[structopt(long, default_value = "10")]
+        top: usize,
+    },
+    /// Summarizes the hottest functions and the time by category, as text
+    /// or as markdown for a pull request comment
+    Report {
+        #[structopt(parse(from_os_str))]
+        cpu_profile: PathBuf,
+        /// text or markdown
+        #[structopt(long, default_value = "text")]
+        format: report::ReportFormat,
+        /// Number of functions to list
+        #[structopt(long, default_value = "10")]
+        top: usize,
+        /// Adds a mermaid pie chart of the categories to markdown
+        #[structopt(long)]
+        mermaid: bool,
+        #[structopt(flatten)]
+        weighting: timing::WeightingOpts,
+        #[structopt(flatten)]
+        gc: timing::GcOpts,
+        /// Lists only the functions matching an expression, like
+        /// 'url ~= "node_modules" && self_ms > 5'
+        #[structopt(long = "where")]
+        filter: Option<Filter>,
+        #[structopt(flatten)]
+        groups: anonymous::GroupOpts,
+        #[structopt(flatten)]
+        frameworks: frameworks::FrameworkOpts,
+        #[structopt(flatten)]
+        links: links::LinkOpts,
+    },
+    /// Reports max and mean stack depth and the deepest sampled stacks
+    Depth {
+        #[structopt(parse(from_os_str))]
+        cpu_profile: PathBuf,
+        /// Number of stacks to list
+        #[structopt(long, default_value = "10")]
+        top: usize,
+    },
+    /// Prints per function statistics across runs of the same workload as json
+    Aggregate {
+        #[structopt(parse(from_os_str), required = true)]
+        cpu_profiles: Vec<PathBuf>,
+        /// Scales each run to the mean capture duration so long captures
+        /// don't outweigh short ones
+        #[structopt(long, conflicts_with = "weights")]
+        normalize: bool,
+        /// Comma separated weight of each profile in the order given
+        #[structopt(long, use_delimiter = true)]
+        weights: Vec<f64>,
+        #[structopt(flatten)]
+        weighting: timing::WeightingOpts,
+        /// Adds the 95% confidence margin of each mean time due to sampling
+        #[structopt(long)]
+        error_bars: bool,
+        #[structopt(flatten)]
+        groups: anonymous::GroupOpts,
+    },
+    /// Ranks runs of the same workload by how much they deviate from the
+    /// median run
+    Outliers {
+        #[structopt(parse(from_os_str), required = true)]
+        cpu_profiles: Vec<PathBuf>,
+        /// Number of functions to list per run
+        #[structopt(long, default_value = "5")]
+        top: usize,
+        #[structopt(flatten)]
+        weighting: timing::WeightingOpts,
+    },
+    /// Writes the self and total time of functions in each of an ordered set
+    /// of profiles, such as nightly captures, to plot trends
+    Series {
+        /// cpuprofiles, directories or glob patterns in the order of the
+        /// series, directories are sorted by file name
+        #[structopt(parse(from_os_str), required = true)]
+        cpu_profiles: Vec<PathBuf>,
+        /// A function name to include, may be repeated
+        #[structopt(long = "function")]
+        functions: Vec<String>,
+        /// Number of the hottest functions to include when none are named
+        #[structopt(long, default_value = "10")]
+        top: usize,
+        /// csv or json
+        #[structopt(long, default_value = "csv")]
+        format: series::SeriesFormat,
+        #[structopt(long, parse(from_os_str), default_value = "-")]
+        out: PathBuf,
+    },
+    /// Lists the stacks that got slower than in a baseline profile, to
+    /// isolate the cost of a change by comparing captures with and without it
+    Subtract {
+        #[structopt(parse(from_os_str))]
+        cpu_profile: PathBuf,
+        #[structopt(parse(from_os_str))]
+        baseline: PathBuf,
+        /// Number of stacks to list
+        #[structopt(long, default_value = "10")]
+        top: usize,
+        #[structopt(flatten)]
+        links: links::LinkOpts,
+    },
+    /// Points at the subtree of calls most responsible for a regression
+    /// between two profiles, with the functions that grew under it
+    BisectReport {
+        #[structopt(parse(from_os_str))]
+        old: PathBuf,
+        #[structopt(parse(from_os_str))]
+        new: PathBuf,
+        /// Number of subtrees and functions to list
+        #[structopt(long, default_value = "10")]
+        top: usize,
+        #[structopt(flatten)]
+        links: links::LinkOpts,
+    },
+    /// Compares profiles with the baseline stored for their workload, the
+    /// `workload` of their metadata, and exits with an error if a function
+    /// got slower
+    Diff {
+        /// cpuprofiles, directories or glob patterns, several runs of a
+        /// workload are aggregated
+        #[structopt(parse(from_os_str), required = true)]
+        cpu_profiles: Vec<PathBuf>,
+        /// Where the aggregate of each workload is stored as <workload>.json
+        #[structopt(long, parse(from_os_str))]
+        baseline_dir: PathBuf,
+        /// The workload of profiles without one in their metadata
+        #[structopt(long)]
+        workload: Option<String>,
+        /// Stores the profiles as the new baselines instead of comparing
+        #[structopt(long)]
+        accept: bool,
+        /// Growth of a function's mean self time, in percent, that counts as
+        /// a regression
+        #[structopt(long, default_value = "10")]
+        threshold: f64,
+        /// Growth in milliseconds below which a function isn't a regression
+        #[structopt(long, default_value = "1", parse(try_from_str = diff::milliseconds))]
+        min_ms: Duration,
+        /// Number of changed functions to list per workload
+        #[structopt(long, default_value = "10")]
+        top: usize,
+        /// Shows the 95% confidence margin of each change due to sampling
+        #[structopt(long)]
+        error_bars: bool,
+    },
+    /// Checks a cpuprofile against performance budgets from a TOML file and
+    /// exits with an error if any are exceeded
+    Check {
+        /// A cpuprofile, or a directory or glob pattern to check every
+        /// matching cpuprofile
+        #[structopt(parse(from_os_str))]
+        cpu_profile: PathBuf,
+        #[structopt(parse(from_os_str))]
+        budgets: PathBuf,
+        /// Counts time in async continuations toward the caller of the async
+        /// function instead of the microtask queue
+        #[structopt(long)]
+        stitch_async: bool,
+        #[structopt(flatten)]
+        gc: timing::GcOpts,
+        /// text, or github to print GitHub Actions annotations on the source
+        /// of each function over budget
+        #[structopt(long, default_value = "text")]
+        format: check::CheckFormat,
+        /// Where the repository is in script urls, like file:///home/ci/app/,
+        /// stripped to get the annotated file
+        #[structopt(long, default_value = "")]
+        source_root: String,
+    },
+    /// Runs the passes of a TOML pipeline over a cpuprofile, like stripping
+    /// idle time, pruning and exporting
+    Run {
+        #[structopt(parse(from_os_str))]
+        pipeline: PathBuf,
+        /// A cpuprofile, `-` for stdin
+        #[structopt(parse(from_os_str))]
+        cpu_profile: PathBuf,
+    },
+    /// Lists profiles written by node's --cpu-prof grouped by process, with
+    /// the main thread and each worker thread
+    Captures {
+        #[structopt(parse(from_os_str), required = true)]
+        cpu_profiles: Vec<PathBuf>,
+    },
+    /// Finds profiles that are copies of another by a structural hash of
+    /// their sampled stacks and times, to drop them before aggregating a
+    /// corpus
+    Dedup {
+        #[structopt(parse(from_os_str), required = true)]
+        cpu_profiles: Vec<PathBuf>,
+        /// Deletes the copies and their sidecars, keeping the first of each
+        /// by path
+        #[structopt(long)]
+        remove: bool,
+    },
+    /// Reports the hottest functions across the isolates of a run, like the
+    /// main thread and workers from node's --cpu-prof or the processes of a
+    /// cluster, with the time in each isolate and the hottest of each
+    Isolates {
+        #[structopt(parse(from_os_str), required = true)]
+        cpu_profiles: Vec<PathBuf>,
+        /// Number of functions to list across isolates and per isolate
+        #[structopt(long, default_value = "10")]
+        top: usize,
+    },
+    /// Lists profiles grouped by the environment in their
+    /// `*.cpuprofile.meta.json` sidecars, or writes the sidecars with the
+    /// environment of this machine
+    Meta {
+        #[structopt(parse(from_os_str), required = true)]
+        cpu_profiles: Vec<PathBuf>,
+        /// Writes a sidecar next to each profile, right after capturing them
+        #[structopt(long)]
+        write: bool,
+        /// The node binary that captured the profiles, for its versions
+        #[structopt(long, default_value = "node")]
+        node: String,
+    },
+    /// Merges the profiles of each thread into one Chrome trace with a track
+    /// per thread, for the Chrome performance panel or the Firefox profiler
+    Tracks {
+        #[structopt(parse(from_os_str), required = true)]
+        cpu_profiles: Vec<PathBuf>,
+        #[structopt(long, parse(from_os_str))]
+        out: PathBuf,
+    },
+    /// Lines the profile up with the native cpu samples of a Windows ETW
+    /// trace exported to json, in one Chrome trace with a track per thread
+    Etw {
+        #[structopt(parse(from_os_str))]
+        cpu_profile: PathBuf,
+        /// The samples, with `timestamp` in microseconds, `pid`, `tid` and
+        /// `stack` as `module!function` frames, outermost first
+        #[structopt(parse(from_os_str))]
+        etw: PathBuf,
+        #[structopt(long, parse(from_os_str))]
+        out: PathBuf,
+        /// The process id of node, for the JavaScript track
+        #[structopt(long)]
+        pid: u32,
+        /// Microseconds to add to the ETW timestamps to reach the clock of
+        /// the profile
+        #[structopt(long, default_value = "0")]
+        offset_us: i64,
+    },
+    /// Writes a cpuprofile in an indexed format where a time range can be
+    /// read without loading every sample
+    Index {
+        #[structopt(parse(from_os_str))]
+        cpu_profile: PathBuf,
+        #[structopt(parse(from_os_str))]
+        out: PathBuf,
+        /// Milliseconds of samples per bucket
+        #[structopt(long, default_value = "1000")]
+        bucket_ms: u64,
+    },
+    /// Extracts a time range of an indexed profile into a cpuprofile
+    Slice {
+        #[structopt(parse(from_os_str))]
+        indexed: PathBuf,
+        #[structopt(parse(from_os_str))]
+        out: PathBuf,
+        /// Start in milliseconds since the start of the profile
+        #[structopt(long)]
+        from_ms: u64,
+        /// End in milliseconds since the start of the profile
+        #[structopt(long)]
+        to_ms: u64,
+    },
+    /// Watches a directory for new cpuprofiles, for example from node's
+    /// --cpu-prof, and runs the given steps on each
+    Watch {
+        #[structopt(parse(from_os_str))]
+        dir: PathBuf,
+        /// Splits each new profile into this many chunks under --out-dir
+        #[structopt(long, requires = "out-dir")]
+        split: Option<usize>,
+        #[structopt(long, parse(from_os_str))]
+        out_dir: Option<PathBuf>,
+        /// Checks each new profile against budgets from this TOML file
+        #[structopt(long, parse(from_os_str))]
+        check: Option<PathBuf>,
+        /// Prints the dominant path of each new profile
+        #[structopt(long)]
+        path: bool,
+        /// Seconds between scans of the directory
+        #[structopt(long, default_value = "1")]
+        interval: u64,
+    },
+    /// Extracts the allocation stack tree of a heap snapshot or allocation
+    /// timeline into a cpuprofile
+    HeapTrace {
+        #[structopt(parse(from_os_str))]
+        heap_snapshot: PathBuf,
+        #[structopt(parse(from_os_str))]
+        out: PathBuf,
+    },
+    /// Generates a random profile of a given shape, for benchmarks and large
+    /// fixtures
+    Synth {
+        #[structopt(parse(from_os_str))]
+        out: PathBuf,
+        /// Number of nodes including the root
+        #[structopt(long, default_value = "100")]
+        nodes: usize,
+        /// Deepest a node may be below the root
+        #[structopt(long, default_value = "16")]
+        max_depth: usize,
+        #[structopt(long, default_value = "1000")]
+        samples: usize,
+        /// Microseconds between samples
+        #[structopt(long, default_value = "1000")]
+        interval_us: u64,
+        /// Concentrates samples in a few hot nodes, sampling the nth hottest
+        /// in proportion to 1 / n^exponent, instead of spreading them evenly
+        #[structopt(long)]
+        zipf: Option<f64>,
+        /// The same seed and shape generate the same profile
+        #[structopt(long, default_value = "0")]
+        seed: u64,
+        /// Indents the written profile
+        #[structopt(long)]
+        pretty: bool,
+    },
+    /// Converts a profile between cpuprofile, speedscope, pprof and devtools
+    /// trace files, or writes it as folded stacks, csv, d3-flame-graph json or
+    /// a trace for the Firefox profiler. Gzip compressed inputs are decompressed
+    Convert {
+        #[structopt(parse(from_os_str))]
+        input: PathBuf,
+        /// cpuprofile, speedscope, pprof, folded, csv, d3 or firefox
+        #[structopt(long)]
+        to: String,
+        /// The input's format, by default detected from its contents or
+        /// extension
+        #[structopt(long)]
+        from: Option<String>,
+        /// Reads the counts of folded stacks as samples this many
+        /// microseconds apart, as DTrace and bpftrace count them, instead
+        /// of microseconds
+        #[structopt(long)]
+        sample_interval_us: Option<f64>,
+        /// Where to write the converted profile, stdout by default
+        #[structopt(long, parse(from_os_str), default_value = "-")]
+        out: PathBuf,
+        /// Removes (idle) and (program) samples so the timeline is cpu
+        /// time instead of wall time
+        #[structopt(long)]
+        strip_idle: bool,
+        /// Renames scripts by id with the Debugger.scriptParsed events in a
+        /// json file, a json array or one event per line. Scripts without a
+        /// url are named by the hash of their source
+        #[structopt(long, parse(from_os_str))]
+        scripts: Option<PathBuf>,
+        /// A label for every sample of a pprof profile, like service=api,
+        /// repeated for more. Replaces the label with the same key in the
+        /// profile's metadata
+        #[structopt(long = "label", number_of_values = 1, parse(try_from_str = convert::label))]
+        labels: Vec<(String, String)>,
+        #[structopt(flatten)]
+        links: links::LinkOpts,
+    },
+    /// Uploads a profile in any format convert reads to a Pyroscope or Parca
+    /// server as pprof, labeled by its metadata, its sidecar and --label
+    #[cfg(feature = "push")]
+    Push {
+        #[structopt(parse(from_os_str))]
+        input: PathBuf,
+        /// pyroscope or parca
+        #[structopt(long)]
+        to: push::Backend,
+        /// The server's url, by default on port 4040 for Pyroscope and 7070
+        /// for Parca
+        #[structopt(long)]
+        url: String,
+        /// The application or profile name to index it by
+        #[structopt(long)]
+        name: String,
+        /// A label like service=api, repeated for more
+        #[structopt(long = "label", number_of_values = 1, parse(try_from_str = convert::label))]
+        labels: Vec<(String, String)>,
+    },
+    /// Draws a flamegraph svg of a profile in any format convert reads
+    Flame {
+        #[structopt(parse(from_os_str))]
+        cpu_profile: PathBuf,
+        #[structopt(parse(from_os_str))]
+        out: PathBuf,
+        /// Draws an icicle chart, with the root at the top
+        #[structopt(long)]
+        icicle: bool,
+        /// Merges stacks from the sampled function up, so callers are drawn
+        /// above where the time was spent
+        #[structopt(long)]
+        reverse: bool,
+        /// One of inferno's palettes, like js, hot, aqua or rust
+        #[structopt(long, default_value = "js")]
+        palette: Palette,
+        #[structopt(long, default_value = "Flame Graph")]
+        title: String,
+        #[structopt(flatten)]
+        gc: timing::GcOpts,
+        /// Removes (idle) and (program) samples so the timeline is cpu
+        /// time instead of wall time
+        #[structopt(long)]
+        strip_idle: bool,
+    },
 }
 
 type Error = Box<dyn std::error::Error + Send + Sync>;
 
-// since we serialize out in multiple threads and each chunk
-// borrows from the profile and the profile borrows from mmap
-// we just want to use a static to make it simple to move the
-// chunk into the thread.
-fn parse_cpuprofile(path: &Path) -> Result<&'static Profile<'static>, Error> {
-    static mut MMAP: OnceCell<Mmap> = OnceCell::new();
-    static mut PROFILE: OnceCell<Profile> = OnceCell::new();
+/// The bytes of an input, `-` reads stdin.
+enum Input {
+    Mapped(Mmap),
+    /// Read from stdin or downloaded.
+    Owned(Vec<u8>),
+}
 
-    let file = File::open(path)?;
-    let mmap = unsafe { MMAP.get_or_try_init(|| Mmap::map(&file))? };
-    Ok(unsafe { PROFILE.get_or_try_init(|| serde_json::from_slice(mmap))? })
+impl Deref for Input {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Input::Mapped(mmap) => mmap,
+            Input::Owned(bytes) => bytes,
+        }
+    }
 }
 
-fn main() -> Result<(), Error> {
-    let opt = Opt::from_args();
-    println!("parsing cpuprofile from {:?}", &opt.cpu_profile);
-    let profile = parse_cpuprofile(&opt.cpu_profile)?;
-    create_dir_all(&opt.out_dir)?;
+/// Maps a file, or reads stdin to the end for `-` since a pipe can't be
+/// mapped. `s3://` and `gs://` urls are downloaded.
+fn map_file(path: &Path) -> Result<Input, Error> {
+    if path == Path::new("-") {
+        let mut bytes = Vec::new();
+        io::stdin().lock().read_to_end(&mut bytes)?;
+        return Ok(Input::Owned(bytes));
+    }
+    if remote::is_remote(path) {
+        return Ok(Input::Owned(remote::read(path)?));
+    }
+    let file = File::open(path)?;
+    Ok(Input::Mapped(unsafe { Mmap::map(&file)? }))
+}
 
-    let results = Arc::new(Mutex::new(Vec::with_capacity(opt.chunk_num)));
+/// Parses a decompressed profile in any format the registry detects, a
+/// cpuprofile borrows from the input.
+fn parse_any(input: &[u8]) -> Result<Profile<'_>, Error> {
+    match Registry::default().detect(input) {
+        Some(format) if format.name() != "cpuprofile" => Ok(format.read(input)?),
+        _ => Ok(serde_json::from_slice(input)?),
+    }
+}
 
-    rayon::scope(|s| {
-        for (index, chunk) in profile.chunks(opt.chunk_num).enumerate() {
-            let results = results.clone();
-            let mut path = opt.out_dir.clone();
-            let num = index + 1;
-            path.push(format!("part{}.cpuprofile", num));
-            s.spawn(move |_| {
-                let result = serialize_chunk(&chunk, &path, num);
-                results.lock().unwrap().push(result);
-            })
-        }
-    });
+/// Creates an output file, `-` writes to stdout and `s3://` and `gs://`
+/// urls are uploaded on flush.
+fn create_file(path: &Path) -> Result<Box<dyn Write>, Error> {
+    if path == Path::new("-") {
+        return Ok(Box::new(BufWriter::new(io::stdout())));
+    }
+    if remote::is_remote(path) {
+        return remote::writer(path);
+    }
+    Ok(Box::new(BufWriter::new(File::create(path)?)))
+}
 
-    for result in results.lock().unwrap().drain(..) {
-        result?;
+fn main() {
+    let args = Args::from_args();
+    if let Err(error) = run(args.command) {
+        exit::report(&error, args.json_errors);
     }
-    Ok(())
 }
 
-fn serialize_chunk(chunk: &ProfileChunk<'_, '_>, path: &Path, num: usize) -> Result<(), Error> {
-    println!("writing chunk {} to {:?}", num, path);
-    serde_json::to_writer(BufWriter::new(File::create(path)?), chunk)?;
-    println!("chunk {} done", num);
-    Ok(())
+#[allow(clippy::too_many_lines)]
+fn run(command: Opt) -> Result<(), Error> {
+    match command {
+        Opt::Split {
+            cpu_profile,
+            out_dir,
+            chunk_num,
+            pretty,
+            name_template,
+            provenance,
+            dry_run,
+            shared_nodes,
+        } => split::run(
+            &cpu_profile,
+            &out_dir,
+            chunk_num,
+            &split::Output {
+                pretty,
+                name_template: &name_template,
+                provenance,
+                shared_nodes,
+            },
+            dry_run,
+        ),
+        Opt::Join {
+            inputs,
+            out,
+            provenance,
+        } => join::run(&inputs, &out, provenance),
+        Opt::Deopts { cpu_profile, top } => deopts::run(&cpu_profile, top),
+        Opt::Lines {
+            cpu_profile,
+            top,
+            annotate,
+            url,
+            html,
+            links,
+        } => lines::run(
+            &cpu_profile,
+            top,
+            links.links().as_ref(),
+            annotate
+                .as_deref()
+                .zip(url.as_deref())
+                .map(|(source, url)| lines::Annotate {
+                    source,
+                    url,
+                    html: html.as_deref(),
+                }),
+        ),
+        Opt::Path { cpu_profile } => path::run(&cpu_profile),
+        Opt::Grep {
+            cpu_profile,
+            pattern,
+            top,
+        } => grep::run(&cpu_profile, &pattern, top),
+        Opt::Peek {
+            cpu_profile,
+            pattern,
+            top,
+        } => peek::run(&cpu_profile, &pattern, top),
+        Opt::Tree {
+            cpu_profile,
+            threshold,
+        } => tree::run(&cpu_profile, threshold),
+        Opt::Stats { cpu_profile } => stats::run(&cpu_profile),
+        Opt::Scripts { cpu_profile } => scripts::run(&cpu_profile),
+        Opt::Spans {
+            cpu_profile,
+            min_ms,
+            function,
+        } => spans::run(&cpu_profile, min_ms, function.as_deref()),
+        Opt::Requests {
+            cpu_profile,
+            intervals,
+            capture_start,
+            out_dir,
+            top,
+        } => requests::run(
+            &cpu_profile,
+            &intervals,
+            &requests::Requests {
+                capture_start,
+                out_dir: out_dir.as_deref(),
+                top,
+            },
+        ),
+        Opt::Bundles { cpu_profile, stats } => bundles::run(&cpu_profile, &stats),
+        Opt::Report {
+            cpu_profile,
+            format,
+            top,
+            mermaid,
+            weighting,
+            gc,
+            filter,
+            groups,
+            frameworks,
+            links,
+        } => report::run(
+            &cpu_profile,
+            &report::Report {
+                format,
+                top,
+                mermaid,
+                weighting: weighting.weighting(),
+                attribute_gc: gc.attribute_gc(),
+                links: links.links().as_ref(),
+                filter: filter.as_ref(),
+                groups: groups.groups(),
+                frameworks: frameworks.rules()?.as_ref(),
+            },
+        ),
+        Opt::Depth { cpu_profile, top } => depth::run(&cpu_profile, top),
+        Opt::Aggregate {
+            cpu_profiles,
+            normalize,
+            weights,
+            weighting,
+            error_bars,
+            groups,
+        } => {
+            let weights = if normalize {
+                aggregate::Weights::Duration
+            } else if weights.is_empty() {
+                aggregate::Weights::Equal
+            } else {
+                aggregate::Weights::Explicit(weights)
+            };
+            aggregate::run(
+                &cpu_profiles,
+                weights,
+                weighting.weighting(),
+                error_bars,
+                groups.groups().as_ref(),
+            )
+        }
+        Opt::Outliers {
+            cpu_profiles,
+            top,
+            weighting,
+        } => aggregate::outliers(&cpu_profiles, top, weighting.weighting()),
+        Opt::Series {
+            cpu_profiles,
+            functions,
+            top,
+            format,
+            out,
+        } => series::run(&cpu_profiles, &functions, top, format, &out),
+        Opt::Subtract {
+            cpu_profile,
+            baseline,
+            top,
+            links,
+        } => subtract::run(&cpu_profile, &baseline, top, links.links().as_ref()),
+        Opt::BisectReport {
+            old,
+            new,
+            top,
+            links,
+        } => bisect::run(&old, &new, top, links.links().as_ref()),
+        Opt::Diff {
+            cpu_profiles,
+            baseline_dir,
+            workload,
+            accept,
+            threshold,
+            min_ms,
+            top,
+            error_bars,
+        } => {
+            let thresholds = diff::Thresholds {
+                percent: threshold,
+                min: min_ms,
+            };
+            let passed = diff::run(
+                &cpu_profiles,
+                &baseline_dir,
+                workload.as_deref(),
+                accept,
+                &thresholds,
+                top,
+                error_bars,
+            )?;
+            if passed {
+                Ok(())
+            } else {
+                Err(exit::Failed("slower than the baseline".into()).into())
+            }
+        }
+        Opt::Check {
+            cpu_profile,
+            budgets,
+            stitch_async,
+            gc,
+            format,
+            source_root,
+        } => {
+            let output = check::Output {
+                format,
+                source_root: &source_root,
+            };
+            let attribute_gc = gc.attribute_gc();
+            if check::run(&cpu_profile, &budgets, stitch_async, attribute_gc, &output)? {
+                Ok(())
+            } else {
+                Err(exit::Failed("over budget".into()).into())
+            }
+        }
+        Opt::Run {
+            pipeline,
+            cpu_profile,
+        } => pipeline::run(&pipeline, &cpu_profile),
+        Opt::Captures { cpu_profiles } => captures::run(&cpu_profiles),
+        Opt::Dedup {
+            cpu_profiles,
+            remove,
+        } => dedup::run(&cpu_profiles, remove),
+        Opt::Isolates { cpu_profiles, top } => isolates::run(&cpu_profiles, top),
+        Opt::Meta {
+            cpu_profiles,
+            write,
+            node,
+        } => {
+            if write {
+                meta::write(&cpu_profiles, &node)
+            } else {
+                meta::run(&cpu_profiles)
+            }
+        }
+        Opt::Tracks { cpu_profiles, out } => tracks::run(&cpu_profiles, &out),
+        Opt::Etw {
+            cpu_profile,
+            etw,
+            out,
+            pid,
+            offset_us,
+        } => etw::run(&cpu_profile, &etw, &out, pid, offset_us),
+        Opt::Index {
+            cpu_profile,
+            out,
+            bucket_ms,
+        } => index::run(&cpu_profile, &out, Duration::from_millis(bucket_ms)),
+        Opt::Slice {
+            indexed,
+            out,
+            from_ms,
+            to_ms,
+        } => index::slice(
+            &indexed,
+            &out,
+            Duration::from_millis(from_ms),
+            Duration::from_millis(to_ms),
+        ),
+        Opt::Watch {
+            dir,
+            split,
+            out_dir,
+            check,
+            path,
+            interval,
+        } => watch::run(
+            &dir,
+            &watch::Pipeline {
+                split: split.zip(out_dir.as_deref()),
+                budgets: check.as_deref(),
+                path,
+            },
+            Duration::from_secs(interval),
+        ),
+        Opt::HeapTrace { heap_snapshot, out } => heap_trace::run(&heap_snapshot, &out),
+        Opt::Synth {
+            out,
+            nodes,
+            max_depth,
+            samples,
+            interval_us,
+            zipf,
+            seed,
+            pretty,
+        } => synth::run(
+            &out,
+            &SynthOptions {
+                nodes,
+                max_depth,
+                samples,
+                interval: Duration::from_micros(interval_us),
+                hotspots: zipf.map_or(Hotspots::Uniform, Hotspots::Zipf),
+                seed,
+            },
+            pretty,
+        ),
+        #[cfg(feature = "push")]
+        Opt::Push {
+            input,
+            to,
+            url,
+            name,
+            labels,
+        } => push::run(&input, to, &url, &name, labels),
+        Opt::Convert {
+            input,
+            to,
+            from,
+            sample_interval_us,
+            out,
+            strip_idle,
+            scripts,
+            labels,
+            links,
+        } => convert::run(
+            &input,
+            &convert::Source {
+                from: from.as_deref(),
+                sample_interval: sample_interval_us
+                    .map(|micros| Duration::from_secs_f64(micros.max(0.0) / 1_000_000.0)),
+            },
+            &to,
+            &out,
+            &convert::Edits {
+                strip_idle,
+                scripts: scripts.as_deref(),
+            },
+            labels,
+            links.links(),
+        ),
+        Opt::Flame {
+            cpu_profile,
+            out,
+            icicle,
+            reverse,
+            palette,
+            title,
+            gc,
+            strip_idle,
+        } => flame::run(
+            &cpu_profile,
+            &out,
+            gc.attribute_gc(),
+            strip_idle,
+            &FlameOptions {
+                title,
+                icicle,
+                reverse,
+                palette,
+            },
+        ),
+    }
 }
@@ -0,0 +1,163 @@
+use crate::Error;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::convert::TryFrom;
+use std::fs::{read_to_string, File};
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
+use std::path::Path;
+
+use v8_cpuprofile::{Micros, Profile};
+
+/// Written next to the chunks of a split as `manifest.json`.
+pub const FILE_NAME: &str = "manifest.json";
+
+/// The node table of a split with shared nodes.
+pub const NODES_FILE_NAME: &str = "nodes.cpuprofile";
+
+/// One chunk of a split.
+#[derive(Debug)]
+pub struct Part {
+    /// Relative to the manifest.
+    pub file: String,
+    /// Indices of the chunk's samples in the split profile.
+    pub samples: Range<usize>,
+    pub time_range: Range<Micros>,
+    pub nodes: usize,
+    pub sha256: String,
+}
+
+/// The node table the chunks of a split with shared nodes leave out.
+#[derive(Debug)]
+pub struct SharedNodes {
+    /// Relative to the manifest.
+    pub file: String,
+    pub nodes: usize,
+    pub sha256: String,
+}
+
+#[derive(Debug)]
+pub struct Manifest {
+    pub source: String,
+    pub samples: usize,
+    pub parts: Vec<Part>,
+    pub shared_nodes: Option<SharedNodes>,
+}
+
+/// Writes the manifest of a split, `parts` in sample order.
+pub fn write(
+    out_dir: &Path,
+    source: &str,
+    profile: &Profile,
+    parts: &[Part],
+    shared_nodes: Option<&SharedNodes>,
+) -> Result<(), Error> {
+    let parts: Vec<Value> = parts
+        .iter()
+        .map(|part| {
+            json!({
+                "file": part.file,
+                "samples": [part.samples.start, part.samples.end],
+                "timeRange": [
+                    part.time_range.start.0,
+                    part.time_range.end.0,
+                ],
+                "nodes": part.nodes,
+                "sha256": part.sha256,
+            })
+        })
+        .collect();
+    let mut manifest = json!({
+        "source": source,
+        "startTime": profile.start_time.0,
+        "endTime": profile.end_time.0,
+        "samples": profile.samples.len(),
+        "parts": parts,
+    });
+    if let Some(shared_nodes) = shared_nodes {
+        manifest["sharedNodes"] = json!({
+            "file": shared_nodes.file,
+            "nodes": shared_nodes.nodes,
+            "sha256": shared_nodes.sha256,
+        });
+    }
+    let writer = BufWriter::new(File::create(out_dir.join(FILE_NAME))?);
+    serde_json::to_writer_pretty(writer, &manifest)?;
+    Ok(())
+}
+
+pub fn read(path: &Path) -> Result<Manifest, Error> {
+    let manifest: Value = serde_json::from_str(&read_to_string(path)?)?;
+    let invalid = || format!("{:?} is not a split manifest", path);
+    let string = |value: &Value| value.as_str().map(str::to_string).ok_or_else(invalid);
+    let number = |value: &Value| value.as_u64().ok_or_else(invalid);
+    let index = |value: &Value| {
+        value
+            .as_u64()
+            .and_then(|n| usize::try_from(n).ok())
+            .ok_or_else(invalid)
+    };
+
+    let mut parts = Vec::new();
+    for part in manifest["parts"].as_array().ok_or_else(invalid)? {
+        parts.push(Part {
+            file: string(&part["file"])?,
+            samples: index(&part["samples"][0])?..index(&part["samples"][1])?,
+            time_range: Micros(number(&part["timeRange"][0])?)
+                ..Micros(number(&part["timeRange"][1])?),
+            nodes: index(&part["nodes"])?,
+            sha256: string(&part["sha256"])?,
+        });
+    }
+    let shared_nodes = match manifest.get("sharedNodes") {
+        Some(shared_nodes) => Some(SharedNodes {
+            file: string(&shared_nodes["file"])?,
+            nodes: index(&shared_nodes["nodes"])?,
+            sha256: string(&shared_nodes["sha256"])?,
+        }),
+        None => None,
+    };
+    Ok(Manifest {
+        source: string(&manifest["source"])?,
+        samples: index(&manifest["samples"])?,
+        parts,
+        shared_nodes,
+    })
+}
+
+pub fn sha256(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Hashes what is written through it.
+pub struct HashWriter<W> {
+    inner: W,
+    hasher: Sha256,
+}
+
+impl<W: Write> HashWriter<W> {
+    pub fn new(inner: W) -> Self {
+        HashWriter {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+
+    /// Flushes and returns the hex sha256 of everything written.
+    pub fn finish(mut self) -> io::Result<String> {
+        self.inner.flush()?;
+        Ok(format!("{:x}", self.hasher.finalize()))
+    }
+}
+
+impl<W: Write> Write for HashWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,84 @@
+use crate::inputs::expand_all;
+use crate::Error;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use v8_cpuprofile::sidecar::{sidecar_path, Environment};
+
+/// Writes a sidecar next to each profile with the environment of this
+/// machine, `node` being the binary that captured them.
+pub fn write(inputs: &[PathBuf], node: &str) -> Result<(), Error> {
+    let environment = detect(node);
+    for cpu_profile in expand_all(inputs)? {
+        environment.write(&cpu_profile)?;
+        println!("wrote {:?}", sidecar_path(&cpu_profile));
+    }
+    Ok(())
+}
+
+/// Prints the profiles grouped by the environment they were captured in.
+pub fn run(inputs: &[PathBuf]) -> Result<(), Error> {
+    let mut groups: BTreeMap<Option<Environment>, Vec<PathBuf>> = BTreeMap::new();
+    for cpu_profile in expand_all(inputs)? {
+        let environment = Environment::read(&cpu_profile)?;
+        groups.entry(environment).or_default().push(cpu_profile);
+    }
+    for (environment, cpu_profiles) in groups {
+        match environment {
+            Some(environment) => {
+                println!("{}:", environment.label());
+                if let Some(v8_version) = &environment.v8_version {
+                    println!("  v8 {}", v8_version);
+                }
+            }
+            None => println!("no sidecar:"),
+        }
+        for cpu_profile in cpu_profiles {
+            println!("    {:?}", cpu_profile);
+        }
+    }
+    Ok(())
+}
+
+fn detect(node: &str) -> Environment {
+    Environment {
+        hostname: fs::read_to_string("/etc/hostname")
+            .ok()
+            .map(|hostname| hostname.trim().to_string())
+            .filter(|hostname| !hostname.is_empty())
+            .or_else(|| output("hostname", &[])),
+        node_version: output(node, &["--version"]),
+        v8_version: output(node, &["-p", "process.versions.v8"]),
+        cpu_model: cpu_model(Path::new("/proc/cpuinfo"))
+            .or_else(|| output("sysctl", &["-n", "machdep.cpu.brand_string"])),
+        git_revision: output("git", &["rev-parse", "HEAD"]),
+    }
+}
+
+/// The first line a command prints, `None` if it can't be run or fails.
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    let line = stdout.lines().next()?.trim();
+    if line.is_empty() {
+        None
+    } else {
+        Some(line.to_string())
+    }
+}
+
+fn cpu_model(cpuinfo: &Path) -> Option<String> {
+    let cpuinfo = fs::read_to_string(cpuinfo).ok()?;
+    cpuinfo.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        if key.trim() == "model name" {
+            Some(value.trim().to_string())
+        } else {
+            None
+        }
+    })
+}
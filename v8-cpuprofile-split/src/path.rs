@@ -0,0 +1,20 @@
+use crate::map_file;
+use crate::Error;
+use std::path::Path;
+use v8_cpuprofile::Profile;
+
+pub fn run(cpu_profile: &Path) -> Result<(), Error> {
+    let mmap = map_file(cpu_profile)?;
+    let profile: Profile = serde_json::from_slice(&mmap)?;
+
+    for step in profile.dominant_path() {
+        let frame = profile[step.node_id].frame()?;
+        println!(
+            "{:>6.1}% {:>10.1?}  {}",
+            step.fraction * 100.0,
+            step.total_time,
+            frame
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use crate::map_file;
+use crate::Error;
+use regex::Regex;
+use std::path::Path;
+use std::time::Duration;
+use v8_cpuprofile::{FrameKey, Profile};
+
+/// Prints the callers and callees of the functions whose name matches
+/// `pattern`, the `top` with the most total time.
+pub fn run(cpu_profile: &Path, pattern: &str, top: usize) -> Result<(), Error> {
+    let pattern = Regex::new(pattern)?;
+    let mmap = map_file(cpu_profile)?;
+    let profile: Profile = serde_json::from_slice(&mmap)?;
+
+    let mut frames: Vec<_> = profile
+        .frame_times()?
+        .into_iter()
+        .filter(|(frame, _)| pattern.is_match(&frame.function_name))
+        .collect();
+    if frames.is_empty() {
+        return Err(format!("no function matches {:?}", pattern.as_str()).into());
+    }
+    frames.sort_by(|a, b| b.1.total_time.cmp(&a.1.total_time).then(a.0.cmp(&b.0)));
+
+    for (index, (frame, _)) in frames.iter().take(top).enumerate() {
+        let detail = profile.function_detail(frame)?;
+        if index > 0 {
+            println!();
+        }
+        println!(
+            "{}  self {:.1?}  total {:.1?}",
+            frame, detail.self_time, detail.total_time
+        );
+        print_times("callers", &detail.callers, detail.total_time);
+        print_times("callees", &detail.callees, detail.total_time);
+    }
+    Ok(())
+}
+
+fn print_times(heading: &str, times: &[(FrameKey, Duration)], total: Duration) {
+    if times.is_empty() {
+        return;
+    }
+    println!("  {}", heading);
+    for (frame, time) in times {
+        println!(
+            "    {:>10.1?} {:>6.1}%  {}",
+            time,
+            time.as_secs_f64() / total.as_secs_f64() * 100.0,
+            frame
+        );
+    }
+}
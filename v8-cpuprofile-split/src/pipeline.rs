@@ -0,0 +1,20 @@
+use crate::{map_file, parse_any, Error};
+use std::fs::read_to_string;
+use std::path::Path;
+use v8_cpuprofile::format::{decompress, Registry};
+use v8_cpuprofile::pipeline::{Pipeline, PipelineConfig};
+
+/// Runs the passes of a TOML pipeline over a profile, printing their
+/// reports to stderr so an export to `-` can be piped.
+pub fn run(config: &Path, input: &Path) -> Result<(), Error> {
+    let config: PipelineConfig = toml::from_str(&read_to_string(config)?)?;
+    let registry = Registry::default();
+    let pipeline = Pipeline::from_config(&config, &registry)?;
+    let bytes = map_file(input)?;
+    let bytes = decompress(&bytes)?;
+    let mut profile = parse_any(&bytes)?;
+    for (pass, report) in pipeline.run(&mut profile)? {
+        eprintln!("{}: {}", pass, report);
+    }
+    Ok(())
+}
@@ -0,0 +1,30 @@
+use indicatif::{ProgressBar, ProgressStyle};
+
+/// A spinner with the elapsed time for phases without a known length, like
+/// parsing, so long runs on large captures don't look hung. Like the other
+/// bars it is only drawn when stderr is a terminal.
+pub fn spinner(message: &str) -> ProgressBar {
+    let spinner = ProgressBar::new_spinner()
+        .with_style(ProgressStyle::default_spinner().template("{spinner} {msg} {elapsed}"));
+    spinner.set_message(message);
+    spinner.enable_steady_tick(100);
+    spinner
+}
+
+pub fn samples(len: usize) -> ProgressBar {
+    ProgressBar::new(len as u64).with_style(
+        ProgressStyle::default_bar()
+            .template("{msg} [{bar:40}] {pos}/{len} samples ({eta})")
+            .progress_chars("=> "),
+    )
+}
+
+/// Prints above the bar, or plainly when it is hidden, as it is for batches
+/// where several run at once.
+pub fn println(bar: &ProgressBar, message: String) {
+    if bar.is_hidden() {
+        println!("{}", message);
+    } else {
+        bar.println(message);
+    }
+}
@@ -0,0 +1,24 @@
+use crate::Error;
+use serde_json::json;
+use serde_json::value::{to_raw_value, RawValue};
+use std::env;
+
+/// The `metadata` written with `--provenance`, which version of the tool
+/// made a profile from which inputs and how, so the output describes itself.
+/// `sources` are the file names of the inputs with their sha256.
+pub fn metadata(sources: &[(&str, &str)], transform: &str) -> Result<Box<RawValue>, Error> {
+    let sources: Vec<_> = sources
+        .iter()
+        .map(|(file, sha256)| json!({ "file": file, "sha256": sha256 }))
+        .collect();
+    let metadata = json!({
+        "provenance": {
+            "tool": env!("CARGO_PKG_NAME"),
+            "version": env!("CARGO_PKG_VERSION"),
+            "sources": sources,
+            "transform": transform,
+            "commandLine": env::args().collect::<Vec<_>>(),
+        }
+    });
+    Ok(to_raw_value(&metadata)?)
+}
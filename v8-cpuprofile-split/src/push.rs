@@ -0,0 +1,150 @@
+use crate::{map_file, parse_any, Error};
+use base64::Engine;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use v8_cpuprofile::format::{decompress, Format, Pprof};
+use v8_cpuprofile::sidecar::Environment;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Backend {
+    /// `POST /ingest` with the pprof as the body and the labels in the
+    /// application name.
+    Pyroscope,
+    /// `POST /profiles/writeraw`, the http form of Parca's `WriteRaw`.
+    Parca,
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(backend: &str) -> Result<Self, String> {
+        match backend {
+            "pyroscope" => Ok(Backend::Pyroscope),
+            "parca" => Ok(Backend::Parca),
+            _ => Err(format!("unknown backend {}", backend)),
+        }
+    }
+}
+
+/// Converts a profile in any format to pprof and uploads it with the labels
+/// of its metadata, its sidecar and `labels`, the later replacing the
+/// earlier.
+pub fn run(
+    input: &Path,
+    backend: Backend,
+    url: &str,
+    name: &str,
+    labels: Vec<(String, String)>,
+) -> Result<(), Error> {
+    let bytes = map_file(input)?;
+    let bytes = decompress(&bytes)?;
+    let profile = parse_any(&bytes)?;
+
+    let mut all = sidecar_labels(input)?;
+    all.extend(labels);
+    let pprof = Pprof { labels: all };
+    let labels = pprof.labels(&profile)?;
+    let mut body = Vec::new();
+    pprof.write(&profile, &mut body)?;
+    let pairs: Vec<String> = labels
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect();
+
+    let url = url.trim_end_matches('/');
+    match backend {
+        Backend::Pyroscope => {
+            // the profile's own times are from a monotonic clock
+            let until = captured_at(input);
+            let from = until.checked_sub(profile.duration()).unwrap_or(until);
+            let seconds = |time: SystemTime| {
+                time.duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs()
+                    .to_string()
+            };
+            let name = if pairs.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}{{{}}}", name, pairs.join(","))
+            };
+            let request = ureq::post(&format!("{}/ingest", url))
+                .query("name", &name)
+                .query("from", &seconds(from))
+                .query("until", &seconds(until))
+                .query("format", "pprof")
+                .query("spyName", "nodespy");
+            send(request, "application/octet-stream", &body)?;
+        }
+        Backend::Parca => {
+            let mut series_labels = vec![serde_json::json!({ "name": "__name__", "value": name })];
+            series_labels.extend(
+                labels
+                    .iter()
+                    .map(|(key, value)| serde_json::json!({ "name": key, "value": value })),
+            );
+            let request = serde_json::json!({
+                "series": [{
+                    "labels": { "labels": series_labels },
+                    "samples": [{
+                        "rawProfile": base64::engine::general_purpose::STANDARD.encode(&body),
+                    }],
+                }],
+            });
+            let request_body = serde_json::to_vec(&request)?;
+            send(
+                ureq::post(&format!("{}/profiles/writeraw", url)),
+                "application/json",
+                &request_body,
+            )?;
+        }
+    }
+    eprintln!(
+        "pushed {} to {} as {} {}",
+        profile.summary(),
+        url,
+        name,
+        pairs.join(" ")
+    );
+    Ok(())
+}
+
+/// The environment of the sidecar as labels.
+fn sidecar_labels(input: &Path) -> Result<Vec<(String, String)>, Error> {
+    let Some(environment) = Environment::read(input)? else {
+        return Ok(Vec::new());
+    };
+    let fields = vec![
+        ("hostname", environment.hostname),
+        ("node_version", environment.node_version),
+        ("v8_version", environment.v8_version),
+        ("cpu_model", environment.cpu_model),
+        ("git_revision", environment.git_revision),
+    ];
+    Ok(fields
+        .into_iter()
+        .filter_map(|(key, value)| Some((key.to_string(), value?)))
+        .collect())
+}
+
+/// When the profile was written, which is right after capturing it, or now
+/// for stdin.
+fn captured_at(input: &Path) -> SystemTime {
+    fs::metadata(input)
+        .and_then(|metadata| metadata.modified())
+        .unwrap_or_else(|_| SystemTime::now())
+}
+
+fn send(request: ureq::Request, content_type: &str, body: &[u8]) -> Result<(), Error> {
+    let url = request.url().to_string();
+    match request.set("Content-Type", content_type).send_bytes(body) {
+        Ok(_) => Ok(()),
+        Err(ureq::Error::Status(status, response)) => {
+            let answer = response.into_string().unwrap_or_default();
+            Err(format!("{} answered {}: {}", url, status, answer.trim()).into())
+        }
+        Err(error) => Err(error.into()),
+    }
+}
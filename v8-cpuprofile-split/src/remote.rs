@@ -0,0 +1,168 @@
+//! Urls as inputs and outputs: `http://` and `https://` to read, with the
+//! `http` feature, and `s3://` and `gs://` to read and write, with the
+//! `remote` feature. Object storage credentials come from the environment
+//! like `AWS_ACCESS_KEY_ID` or `GOOGLE_APPLICATION_CREDENTIALS`.
+
+use crate::Error;
+#[cfg(feature = "remote")]
+use std::io;
+use std::io::Write;
+use std::path::Path;
+
+/// Whether the argument is a url rather than a file.
+pub fn is_remote(path: &Path) -> bool {
+    path.to_str().map_or(false, |url| {
+        ["s3://", "gs://", "http://", "https://"]
+            .iter()
+            .any(|scheme| url.starts_with(scheme))
+    })
+}
+
+fn is_http(path: &Path) -> bool {
+    path.to_str().map_or(false, |url| {
+        url.starts_with("http://") || url.starts_with("https://")
+    })
+}
+
+/// Downloads the whole file, gzip compression undone for http.
+pub fn read(path: &Path) -> Result<Vec<u8>, Error> {
+    if is_http(path) {
+        download(path)
+    } else {
+        read_object(path)
+    }
+}
+
+pub fn writer(path: &Path) -> Result<Box<dyn Write>, Error> {
+    if is_http(path) {
+        Err(format!("{:?} can only be read", path).into())
+    } else {
+        write_object(path)
+    }
+}
+
+#[cfg(feature = "http")]
+fn download(path: &Path) -> Result<Vec<u8>, Error> {
+    let url = path.to_str().ok_or("urls must be utf-8")?;
+    Ok(v8_cpuprofile::http::download(url)?)
+}
+
+#[cfg(not(feature = "http"))]
+fn download(path: &Path) -> Result<Vec<u8>, Error> {
+    Err(unsupported(path, "http"))
+}
+
+#[cfg(not(feature = "remote"))]
+fn read_object(path: &Path) -> Result<Vec<u8>, Error> {
+    Err(unsupported(path, "remote"))
+}
+
+#[cfg(not(feature = "remote"))]
+fn write_object(path: &Path) -> Result<Box<dyn Write>, Error> {
+    Err(unsupported(path, "remote"))
+}
+
+#[cfg(not(all(feature = "http", feature = "remote")))]
+fn unsupported(path: &Path, feature: &str) -> Error {
+    format!("{:?} needs a build with the {} feature", path, feature).into()
+}
+
+#[cfg(feature = "remote")]
+fn read_object(path: &Path) -> Result<Vec<u8>, Error> {
+    let (store, key) = store(path)?;
+    let bytes = runtime()?.block_on(async { store.get(&key).await?.bytes().await })?;
+    Ok(bytes.to_vec())
+}
+
+/// Buffers everything written and uploads it on flush.
+#[cfg(feature = "remote")]
+fn write_object(path: &Path) -> Result<Box<dyn Write>, Error> {
+    let (store, key) = store(path)?;
+    Ok(Box::new(Upload {
+        store,
+        key,
+        bytes: Vec::new(),
+        uploaded: true,
+    }))
+}
+
+#[cfg(feature = "remote")]
+struct Upload {
+    store: Box<dyn object_store::ObjectStore>,
+    key: object_store::path::Path,
+    bytes: Vec<u8>,
+    /// Whether the object has all that was written.
+    uploaded: bool,
+}
+
+#[cfg(feature = "remote")]
+impl Write for Upload {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.bytes.extend_from_slice(buf);
+        self.uploaded = false;
+        Ok(buf.len())
+    }
+
+    /// Puts the whole object again, so each flush replaces the last.
+    fn flush(&mut self) -> io::Result<()> {
+        if self.uploaded {
+            return Ok(());
+        }
+        // a failed upload is reported once, not again on drop
+        self.uploaded = true;
+        let payload = object_store::PutPayload::from(self.bytes.clone());
+        runtime()
+            .and_then(|runtime| {
+                runtime
+                    .block_on(self.store.put(&self.key, payload))
+                    .map_err(Error::from)
+            })
+            .map_err(io::Error::other)?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "remote")]
+impl Drop for Upload {
+    fn drop(&mut self) {
+        if let Err(error) = self.flush() {
+            eprintln!("failed to upload {}: {}", self.key, error);
+        }
+    }
+}
+
+/// The bucket's store and the object's key in it.
+#[cfg(feature = "remote")]
+fn store(
+    path: &Path,
+) -> Result<(Box<dyn object_store::ObjectStore>, object_store::path::Path), Error> {
+    use object_store::aws::AmazonS3Builder;
+    use object_store::gcp::GoogleCloudStorageBuilder;
+
+    let url = path.to_str().ok_or("object storage urls must be utf-8")?;
+    let (scheme, rest) = url.split_once("://").ok_or("expected s3:// or gs://")?;
+    let (bucket, key) = rest
+        .split_once('/')
+        .filter(|(_, key)| !key.is_empty())
+        .ok_or_else(|| format!("{} has no object key", url))?;
+    let store: Box<dyn object_store::ObjectStore> = match scheme {
+        "s3" => Box::new(
+            AmazonS3Builder::from_env()
+                .with_bucket_name(bucket)
+                .build()?,
+        ),
+        _ => Box::new(
+            GoogleCloudStorageBuilder::from_env()
+                .with_bucket_name(bucket)
+                .build()?,
+        ),
+    };
+    Ok((store, object_store::path::Path::from(key)))
+}
+
+#[cfg(feature = "remote")]
+fn runtime() -> Result<tokio::runtime::Runtime, Error> {
+    Ok(tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?)
+}
@@ -0,0 +1,254 @@
+use crate::format::percent;
+use crate::{map_file, parse_any, Error};
+use std::fmt::Write;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+use v8_cpuprofile::anonymous::AnonymousGroups;
+use v8_cpuprofile::category::Category;
+use v8_cpuprofile::filter::Filter;
+use v8_cpuprofile::format::decompress;
+use v8_cpuprofile::framework::Rules;
+use v8_cpuprofile::links::SourceLinks;
+use v8_cpuprofile::{FrameKey, FrameTimes, Weighting};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ReportFormat {
+    Text,
+    /// For a GitHub comment, such as from a bot on a pull request.
+    Markdown,
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, String> {
+        match format {
+            "text" => Ok(ReportFormat::Text),
+            "markdown" => Ok(ReportFormat::Markdown),
+            _ => Err(format!("unknown report format {}", format)),
+        }
+    }
+}
+
+pub struct Report<'a> {
+    pub format: ReportFormat,
+    pub top: usize,
+    /// Adds a mermaid pie chart of the categories to markdown.
+    pub mermaid: bool,
+    pub weighting: Weighting,
+    /// Charges garbage collection to the function that ran before it.
+    pub attribute_gc: bool,
+    pub links: Option<&'a SourceLinks>,
+    /// Lists only the functions it matches.
+    pub filter: Option<&'a Filter>,
+    /// Merges the frames of eval'd and anonymous scripts.
+    pub groups: Option<AnonymousGroups>,
+    /// Adds the time by framework subsystem.
+    pub frameworks: Option<&'a Rules>,
+}
+
+pub fn run(cpu_profile: &Path, report: &Report<'_>) -> Result<(), Error> {
+    let mmap = map_file(cpu_profile)?;
+    let input = decompress(&mmap)?;
+    let mut profile = parse_any(&input)?;
+    profile.set_weighting(report.weighting);
+    if report.attribute_gc {
+        profile.attribute_gc()?;
+    }
+    if let Some(groups) = &report.groups {
+        profile.group_anonymous(groups)?;
+    }
+
+    let duration = profile.duration();
+    let mut frames: Vec<_> = profile
+        .frame_times()?
+        .into_iter()
+        .filter(|(frame, times)| {
+            report
+                .filter
+                .map_or(true, |filter| filter.matches(frame, times, duration))
+        })
+        .collect();
+    frames.sort_unstable_by(|(a_frame, a), (b_frame, b)| {
+        b.self_time.cmp(&a.self_time).then(a_frame.cmp(b_frame))
+    });
+    frames.truncate(report.top);
+    let categories = profile.category_times()?;
+    let subsystems = match report.frameworks {
+        Some(rules) => profile.framework_times(rules)?,
+        None => Vec::new(),
+    };
+    let truncation = profile.truncation()?;
+    let summary = Summary {
+        line: profile.summary(),
+        truncated: Some(truncation.fraction()).filter(|&fraction| fraction >= TRUNCATED),
+        duration,
+        frames: &frames,
+        categories: &categories,
+        subsystems: &subsystems,
+    };
+    let out = match report.format {
+        ReportFormat::Text => text(&summary),
+        ReportFormat::Markdown => markdown(&summary, report),
+    }?;
+    print!("{}", out);
+    Ok(())
+}
+
+/// The subsystem of frames no framework rule matches.
+const OTHER: &str = "(other)";
+
+/// Share of samples with truncated looking stacks that gets a warning.
+const TRUNCATED: f64 = 0.05;
+
+struct Summary<'s, 'raw> {
+    line: String,
+    /// The share of samples with truncated looking stacks, if enough to warn.
+    truncated: Option<f64>,
+    duration: Duration,
+    frames: &'s [(FrameKey<'raw>, FrameTimes)],
+    categories: &'s [(Category, Duration)],
+    /// Empty without framework rules.
+    subsystems: &'s [(Option<&'s str>, Duration)],
+}
+
+impl Summary<'_, '_> {
+    fn percent(&self, time: Duration) -> f64 {
+        percent(time.as_secs_f64(), self.duration.as_secs_f64())
+    }
+}
+
+fn text(summary: &Summary<'_, '_>) -> Result<String, Error> {
+    let mut out = String::new();
+    writeln!(out, "{}", summary.line)?;
+    if let Some(fraction) = summary.truncated {
+        writeln!(
+            out,
+            "warning: {:.1}% of samples have stacks that look truncated, bottom-up times may be unreliable",
+            fraction * 100.0
+        )?;
+    }
+    writeln!(out, "\nhottest functions by self time:")?;
+    for (frame, times) in summary.frames {
+        writeln!(
+            out,
+            "{:>10.1?} {:>5.1}% {:>10.1?}  {}",
+            times.self_time,
+            summary.percent(times.self_time),
+            times.total_time,
+            frame
+        )?;
+    }
+    writeln!(out, "\nself time by category:")?;
+    for &(category, time) in summary.categories {
+        writeln!(
+            out,
+            "{:>10.1?} {:>5.1}%  {}",
+            time,
+            summary.percent(time),
+            category
+        )?;
+    }
+    if !summary.subsystems.is_empty() {
+        writeln!(out, "\nself time by subsystem:")?;
+        for &(subsystem, time) in summary.subsystems {
+            writeln!(
+                out,
+                "{:>10.1?} {:>5.1}%  {}",
+                time,
+                summary.percent(time),
+                subsystem.unwrap_or(OTHER)
+            )?;
+        }
+    }
+    Ok(out)
+}
+
+fn markdown(summary: &Summary<'_, '_>, report: &Report<'_>) -> Result<String, Error> {
+    let mut out = String::new();
+    writeln!(out, "### CPU profile\n")?;
+    writeln!(out, "{}\n", summary.line)?;
+    if let Some(fraction) = summary.truncated {
+        writeln!(
+            out,
+            "> **Warning:** {:.1}% of samples have stacks that look truncated, bottom-up times may be unreliable.\n",
+            fraction * 100.0
+        )?;
+    }
+    writeln!(out, "| Self | Self % | Total | Function |")?;
+    writeln!(out, "| ---: | ---: | ---: | --- |")?;
+    for (frame, times) in summary.frames {
+        let name = if frame.function_name.is_empty() {
+            "(anonymous)"
+        } else {
+            &frame.function_name
+        };
+        let mut function = format!("`{}`", cell(name));
+        if let Some(link) = report
+            .links
+            .and_then(|links| links.link(&frame.url, frame.line_number, frame.column_number))
+        {
+            function = format!("[{}]({})", function, link);
+        }
+        if !frame.url.is_empty() {
+            write!(function, " {}", cell(&frame.url))?;
+            if frame.line_number >= 0 {
+                write!(function, ":{}", i64::from(frame.line_number) + 1)?;
+            }
+        }
+        writeln!(
+            out,
+            "| {:.1?} | {:.1}% | {:.1?} | {} |",
+            times.self_time,
+            summary.percent(times.self_time),
+            times.total_time,
+            function
+        )?;
+    }
+
+    writeln!(out, "\n#### Self time by category\n")?;
+    writeln!(out, "| Category | Self | Self % |")?;
+    writeln!(out, "| --- | ---: | ---: |")?;
+    for &(category, time) in summary.categories {
+        writeln!(
+            out,
+            "| {} | {:.1?} | {:.1}% |",
+            category,
+            time,
+            summary.percent(time)
+        )?;
+    }
+    if report.mermaid && !summary.categories.is_empty() {
+        writeln!(out, "\n```mermaid\npie title Self time by category")?;
+        for &(category, time) in summary.categories {
+            writeln!(
+                out,
+                "    \"{}\" : {:.1}",
+                category,
+                time.as_secs_f64() * 1000.0
+            )?;
+        }
+        writeln!(out, "```")?;
+    }
+    if !summary.subsystems.is_empty() {
+        writeln!(out, "\n#### Self time by subsystem\n")?;
+        writeln!(out, "| Subsystem | Self | Self % |")?;
+        writeln!(out, "| --- | ---: | ---: |")?;
+        for &(subsystem, time) in summary.subsystems {
+            writeln!(
+                out,
+                "| {} | {:.1?} | {:.1}% |",
+                cell(subsystem.unwrap_or(OTHER)),
+                time,
+                summary.percent(time)
+            )?;
+        }
+    }
+    Ok(out)
+}
+
+/// Text safe to put in a table cell.
+fn cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('`', "'")
+}
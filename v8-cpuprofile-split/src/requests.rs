@@ -0,0 +1,93 @@
+use crate::{create_file, map_file, parse_any, Error};
+use std::fs::read_to_string;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+use v8_cpuprofile::format::decompress;
+use v8_cpuprofile::request::Interval;
+use v8_cpuprofile::{Profile, RawJson};
+
+pub struct Requests<'a> {
+    /// The time of the profile's start in the clock of the intervals, in
+    /// milliseconds.
+    pub capture_start: f64,
+    /// Writes a profile per interval instead of printing the aggregates.
+    pub out_dir: Option<&'a Path>,
+    pub top: usize,
+}
+
+/// Prints the hottest functions of each label by mean self time over its
+/// intervals, or writes the profile of each interval.
+pub fn run(cpu_profile: &Path, intervals: &Path, requests: &Requests<'_>) -> Result<(), Error> {
+    let intervals = parse_intervals(&read_to_string(intervals)?, requests.capture_start)?;
+    let mmap = map_file(cpu_profile)?;
+    let input = decompress(&mmap)?;
+    let profile = parse_any(&input)?;
+
+    if let Some(out_dir) = requests.out_dir {
+        std::fs::create_dir_all(out_dir)?;
+        let profiles = profile.request_profiles(&intervals);
+        for (index, (interval, mut profile)) in intervals.iter().zip(profiles).enumerate() {
+            set_workload(&mut profile, &interval.label)?;
+            let mut writer = create_file(&out_dir.join(format!("{}.cpuprofile", index + 1)))?;
+            serde_json::to_writer(&mut writer, &profile)?;
+            writer.flush()?;
+        }
+        return Ok(());
+    }
+
+    for (label, set) in profile.request_aggregates(&intervals)? {
+        let mean: Duration = set.frames.iter().map(|frame| frame.self_time.mean).sum();
+        println!("{}: {} requests, {:.1?} mean", label, set.runs, mean);
+        for frame in set.frames.iter().take(requests.top) {
+            println!(
+                "{:>10.1?} {:>10.1?}  {}",
+                frame.self_time.mean, frame.self_time.p90, frame.frame
+            );
+        }
+    }
+    Ok(())
+}
+
+/// A line per interval of its start and end in milliseconds and its label,
+/// separated by whitespace. Blank lines and lines starting with `#` are
+/// skipped.
+fn parse_intervals(text: &str, capture_start: f64) -> Result<Vec<Interval>, Error> {
+    let mut intervals = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.splitn(3, char::is_whitespace);
+        let mut time = || -> Result<Duration, Error> {
+            let ms: f64 = fields.next().unwrap_or_default().trim().parse()?;
+            Ok(Duration::from_secs_f64(
+                (ms - capture_start).max(0.0) / 1000.0,
+            ))
+        };
+        let (start, end) = match (time(), time()) {
+            (Ok(start), Ok(end)) => (start, end),
+            _ => {
+                return Err(
+                    format!("line {}: expected <start-ms> <end-ms> <label>", number + 1).into(),
+                )
+            }
+        };
+        let label = fields.next().unwrap_or_default().trim().to_string();
+        intervals.push(Interval { label, start, end });
+    }
+    Ok(intervals)
+}
+
+/// Sets the `workload` of the profile's metadata to the label, so `diff`
+/// groups the profiles of a label.
+fn set_workload(profile: &mut Profile, label: &str) -> Result<(), Error> {
+    let mut metadata = match &profile.metadata {
+        Some(metadata) => serde_json::from_str(metadata.get())?,
+        None => serde_json::Map::new(),
+    };
+    metadata.insert("workload".into(), label.into());
+    profile.metadata = Some(RawJson::from(serde_json::value::to_raw_value(&metadata)?));
+    Ok(())
+}
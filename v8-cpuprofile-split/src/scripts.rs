@@ -0,0 +1,28 @@
+use crate::{map_file, parse_any, Error};
+use std::path::Path;
+use v8_cpuprofile::format::decompress;
+
+/// Prints each script of a profile with its nodes, samples and times, the
+/// most self time first.
+pub fn run(cpu_profile: &Path) -> Result<(), Error> {
+    let mmap = map_file(cpu_profile)?;
+    let input = decompress(&mmap)?;
+    let profile = parse_any(&input)?;
+
+    println!(
+        "{:>10} {:>10} {:>7} {:>7} {:>6}  url",
+        "self", "total", "samples", "nodes", "id"
+    );
+    for script in profile.scripts()? {
+        println!(
+            "{:>10.1?} {:>10.1?} {:>7} {:>7} {:>6}  {}",
+            script.self_time,
+            script.total_time,
+            script.samples,
+            script.nodes,
+            script.script_id,
+            script.url
+        );
+    }
+    Ok(())
+}
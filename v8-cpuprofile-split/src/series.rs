@@ -0,0 +1,73 @@
+use crate::aggregate::{map_files, parse_profiles};
+use crate::inputs::expand_all;
+use crate::{create_file, Error};
+use serde_json::json;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use v8_cpuprofile::series::TimeSeries;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SeriesFormat {
+    /// A row per run and function, for spreadsheets and plotting libraries.
+    Csv,
+    Json,
+}
+
+impl FromStr for SeriesFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, String> {
+        match format {
+            "csv" => Ok(SeriesFormat::Csv),
+            "json" => Ok(SeriesFormat::Json),
+            _ => Err(format!("unknown series format {}", format)),
+        }
+    }
+}
+
+/// Writes the times of the named functions, or of the `top` hottest when
+/// none are named, in each profile in the order given. Runs are labeled by
+/// their file name.
+pub fn run(
+    inputs: &[PathBuf],
+    functions: &[String],
+    top: usize,
+    format: SeriesFormat,
+    out: &Path,
+) -> Result<(), Error> {
+    let cpu_profiles = expand_all(inputs)?;
+    let mmaps = map_files(&cpu_profiles)?;
+    let profiles = parse_profiles(&mmaps)?;
+    let mut series = TimeSeries::new(&profiles, |frame| {
+        functions.is_empty()
+            || functions
+                .iter()
+                .any(|function| *function == frame.function_name)
+    })?;
+    if functions.is_empty() {
+        series.functions.truncate(top);
+    }
+    let labels: Vec<String> = cpu_profiles
+        .iter()
+        .map(|path| match path.file_name() {
+            Some(name) => name.to_string_lossy().into_owned(),
+            None => path.display().to_string(),
+        })
+        .collect();
+
+    let mut out = create_file(out)?;
+    match format {
+        SeriesFormat::Csv => {
+            let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+            series.write_csv(&labels, &mut out)?;
+        }
+        SeriesFormat::Json => {
+            let json = json!({ "runs": labels, "functions": series.functions });
+            serde_json::to_writer_pretty(&mut out, &json)?;
+            writeln!(out)?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use crate::{map_file, parse_any, Error};
+use std::path::Path;
+use std::time::Duration;
+use v8_cpuprofile::format::decompress;
+
+/// Prints the calls reconstructed from the samples lasting at least
+/// `min_ms`, in time order and indented by depth, or only the calls of
+/// `function` without indentation.
+pub fn run(cpu_profile: &Path, min_ms: f64, function: Option<&str>) -> Result<(), Error> {
+    let mmap = map_file(cpu_profile)?;
+    let input = decompress(&mmap)?;
+    let profile = parse_any(&input)?;
+    let min_duration = Duration::from_secs_f64(min_ms.max(0.0) / 1000.0);
+
+    println!("{:>10} {:>10}  function", "start", "duration");
+    for span in profile.reconstruct_spans(min_duration)? {
+        let depth = match function {
+            Some(function) if span.frame.function_name != function => continue,
+            Some(_) => 0,
+            None => span.depth,
+        };
+        println!(
+            "{:>10.1?} {:>10.1?}  {:indent$}{}",
+            span.start,
+            span.duration(),
+            "",
+            span.frame,
+            indent = depth * 2
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,419 @@
+use crate::inputs::{expand, is_batch, summarize};
+use crate::manifest::{self, HashWriter, Part, SharedNodes};
+use crate::map_file;
+use crate::progress;
+use crate::provenance;
+use crate::Error;
+use indicatif::ProgressBar;
+use rayon::prelude::*;
+use serde_json::value::{to_raw_value, RawValue};
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::fs::create_dir_all;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Mutex;
+
+use v8_cpuprofile::template;
+use v8_cpuprofile::{Micros, Profile, SerializeOptions};
+use v8_cpuprofile::{ProfileChunk, ProfileChunks};
+
+/// Chunk file names without `--name-template`.
+pub const DEFAULT_NAME_TEMPLATE: &str = "part{num}.cpuprofile";
+
+/// How chunks are written.
+#[derive(Debug)]
+pub struct Output<'a> {
+    /// Indents the chunks.
+    pub pretty: bool,
+    /// See [`chunk_name`].
+    pub name_template: &'a str,
+    /// Writes where each chunk came from as its `metadata`.
+    pub provenance: bool,
+    /// Writes every node once to [`manifest::NODES_FILE_NAME`] instead of
+    /// the nodes of each chunk's samples into the chunk.
+    pub shared_nodes: bool,
+}
+
+impl Output<'_> {
+    fn serialize_options(&self) -> SerializeOptions {
+        SerializeOptions {
+            pretty: self.pretty,
+            ..SerializeOptions::default()
+        }
+    }
+}
+
+impl Default for Output<'_> {
+    fn default() -> Self {
+        Output {
+            pretty: false,
+            name_template: DEFAULT_NAME_TEMPLATE,
+            provenance: false,
+            shared_nodes: false,
+        }
+    }
+}
+
+pub fn run(
+    input: &Path,
+    out_dir: &Path,
+    chunk_num: usize,
+    output: &Output,
+    dry_run: bool,
+) -> Result<(), Error> {
+    if dry_run {
+        for cpu_profile in expand(input)? {
+            plan(&cpu_profile, chunk_num, output)?;
+        }
+        return Ok(());
+    }
+    if !is_batch(input) {
+        return split(input, out_dir, chunk_num, output, true);
+    }
+    // each profile gets its own directory named after it
+    let results = expand(input)?
+        .into_par_iter()
+        .map(|cpu_profile| {
+            let stem = cpu_profile.file_stem().unwrap_or_default();
+            let result = split(&cpu_profile, &out_dir.join(stem), chunk_num, output, false);
+            (cpu_profile, result)
+        })
+        .collect();
+    summarize(results)
+}
+
+/// Shows progress bars unless `progress` is false, for batches where several
+/// profiles are split at once.
+pub fn split(
+    cpu_profile: &Path,
+    out_dir: &Path,
+    chunk_num: usize,
+    output: &Output,
+    progress: bool,
+) -> Result<(), Error> {
+    let stem = cpu_profile
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy();
+    // a bad template fails before parsing
+    chunk_name(
+        output.name_template,
+        &stem,
+        1,
+        &(Micros::ZERO..Micros::ZERO),
+    )?;
+    let mmap = map_file(cpu_profile)?;
+    let message = format!(
+        "parsing cpuprofile from {:?} ({} bytes)",
+        cpu_profile,
+        mmap.len()
+    );
+    let profile: Profile = if progress {
+        let spinner = progress::spinner(&message);
+        if spinner.is_hidden() {
+            println!("{}", message);
+        }
+        let profile = serde_json::from_slice(&mmap);
+        spinner.finish();
+        profile?
+    } else {
+        println!("{}", message);
+        serde_json::from_slice(&mmap)?
+    };
+    // every name is known before anything is written, so chunks can't
+    // overwrite each other
+    let chunks: Vec<_> = chunks(&profile, chunk_num, output).collect();
+    let names = chunk_names(output.name_template, &stem, &chunks)?;
+    create_dir_all(out_dir)?;
+    let source = cpu_profile
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let source_sha256 = if output.provenance {
+        manifest::sha256(&mmap)
+    } else {
+        String::new()
+    };
+
+    let bar = if progress {
+        progress::samples(profile.samples.len())
+    } else {
+        ProgressBar::hidden()
+    };
+    let shared_nodes = if output.shared_nodes {
+        let mut table = ProfileChunk::node_table(&profile);
+        if output.provenance {
+            let metadata = provenance::metadata(&[(&source, &source_sha256)], "split node table")?;
+            table.set_metadata(Some(metadata.into()));
+        }
+        let file = manifest::NODES_FILE_NAME.to_string();
+        let options = output.serialize_options();
+        let part = serialize_chunk(&table, out_dir, file, 0..0, options, &bar)?;
+        Some(SharedNodes {
+            file: part.file,
+            nodes: part.nodes,
+            sha256: part.sha256,
+        })
+    } else {
+        None
+    };
+
+    // the chunks borrow from the profile which borrows from the mmap,
+    // a rayon scope lets the spawned serializers borrow them directly
+    let results = Mutex::new(Vec::with_capacity(chunk_num));
+
+    rayon::scope(|s| {
+        let mut first_sample = 0;
+        for (index, (mut chunk, name)) in chunks.into_iter().zip(names).enumerate() {
+            let samples = first_sample..first_sample + chunk.samples().len();
+            first_sample = samples.end;
+            let results = &results;
+            let bar = &bar;
+            let num = index + 1;
+            bar.set_message(&format!("building chunk {}", num));
+            let metadata = match chunk_metadata(output, &source, &source_sha256, num) {
+                Ok(metadata) => metadata,
+                Err(error) => {
+                    results.lock().unwrap().push(Err(error));
+                    continue;
+                }
+            };
+            if let Some(metadata) = metadata {
+                chunk.set_metadata(Some(metadata.into()));
+            }
+            chunk.set_shared_nodes(output.shared_nodes);
+            let options = output.serialize_options();
+            s.spawn(move |_| {
+                let result = serialize_chunk(&chunk, out_dir, name, samples, options, bar);
+                results.lock().unwrap().push(result);
+            });
+        }
+        bar.set_message("writing chunks");
+    });
+    bar.finish();
+
+    let mut parts = Vec::with_capacity(chunk_num);
+    for result in results.into_inner().unwrap() {
+        parts.push(result?);
+    }
+    parts.sort_by_key(|part| part.samples.start);
+    manifest::write(out_dir, &source, &profile, &parts, shared_nodes.as_ref())
+}
+
+/// Chunks without their nodes are about as large as their samples, others
+/// are balanced by the size of their nodes too.
+fn chunks<'profile, 'raw>(
+    profile: &'profile Profile<'raw>,
+    chunk_num: usize,
+    output: &Output,
+) -> ProfileChunks<'profile, 'raw> {
+    if output.shared_nodes {
+        profile.chunks_by_samples(chunk_num)
+    } else {
+        profile.chunks(chunk_num)
+    }
+}
+
+/// The provenance of a chunk with `--provenance` and the file of the nodes
+/// it shares with `--shared-nodes`.
+fn chunk_metadata(
+    output: &Output,
+    source: &str,
+    source_sha256: &str,
+    num: usize,
+) -> Result<Option<Box<RawValue>>, Error> {
+    let mut metadata = Map::new();
+    if output.provenance {
+        let transform = format!("split part {}", num);
+        let provenance = provenance::metadata(&[(source, source_sha256)], &transform)?;
+        metadata = serde_json::from_str(provenance.get())?;
+    }
+    if output.shared_nodes {
+        metadata.insert(
+            "sharedNodes".to_string(),
+            Value::from(manifest::NODES_FILE_NAME),
+        );
+    }
+    if metadata.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(to_raw_value(&metadata)?))
+}
+
+/// Prints the chunks a split would write, with their exact sizes, without
+/// writing anything.
+fn plan(cpu_profile: &Path, chunk_num: usize, output: &Output) -> Result<(), Error> {
+    let stem = cpu_profile
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy();
+    println!("parsing cpuprofile from {:?}", cpu_profile);
+    let mmap = map_file(cpu_profile)?;
+    let profile: Profile = serde_json::from_slice(&mmap)?;
+    let mut chunks: Vec<_> = chunks(&profile, chunk_num, output).collect();
+    for chunk in &mut chunks {
+        chunk.set_shared_nodes(output.shared_nodes);
+    }
+    let size = |chunk: &ProfileChunk<'_, '_>| {
+        let mut counter = Counter(0);
+        chunk.write_json(&mut counter, output.serialize_options())?;
+        Ok(counter.0)
+    };
+    let table = if output.shared_nodes {
+        let table = ProfileChunk::node_table(&profile);
+        Some((size(&table)?, table.nodes().into_iter().count()))
+    } else {
+        None
+    };
+    let names = chunk_names(output.name_template, &stem, &chunks)?;
+    let sizes = chunks
+        .par_iter()
+        .map(size)
+        .collect::<Result<Vec<usize>, serde_json::Error>>()?;
+
+    println!(
+        "{} chunks of {} samples",
+        chunks.len(),
+        profile.samples.len()
+    );
+    for ((chunk, size), name) in chunks.iter().zip(&sizes).zip(&names) {
+        let time_range = chunk.time_range();
+        println!(
+            "{:>12} bytes {:>10} samples {:>10.1}ms - {:>10.1}ms {:>8} nodes  {}",
+            size,
+            chunk.samples().len(),
+            time_range.start.as_duration().as_secs_f64() * 1000.0,
+            time_range.end.as_duration().as_secs_f64() * 1000.0,
+            chunk.nodes().into_iter().count(),
+            name
+        );
+    }
+    if let Some((size, nodes)) = table {
+        println!(
+            "{:>12} bytes {:>56} nodes  {}",
+            size,
+            nodes,
+            manifest::NODES_FILE_NAME
+        );
+    }
+    let table_size = table.map_or(0, |(size, _)| size);
+    println!(
+        "{:>12} bytes total",
+        sizes.iter().sum::<usize>() + table_size
+    );
+    Ok(())
+}
+
+/// Counts the bytes written to it.
+struct Counter(usize);
+
+impl Write for Counter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// `samples` are the indices of the chunk's samples in the profile.
+fn serialize_chunk(
+    chunk: &ProfileChunk<'_, '_>,
+    out_dir: &Path,
+    file: String,
+    samples: Range<usize>,
+    options: SerializeOptions,
+    bar: &ProgressBar,
+) -> Result<Part, Error> {
+    let path = out_dir.join(&file);
+    progress::println(bar, format!("writing {:?}", path));
+    let mut writer = HashWriter::new(BufWriter::new(File::create(&path)?));
+    chunk.write_json(&mut writer, options)?;
+    let sha256 = writer.finish()?;
+    bar.inc(chunk.samples().len() as u64);
+    progress::println(bar, format!("{:?} done", path));
+    Ok(Part {
+        file,
+        samples,
+        time_range: chunk.time_range(),
+        nodes: chunk.nodes().into_iter().count(),
+        sha256,
+    })
+}
+
+/// Fills in the `{stem}`, `{num}`, `{start_ms}` and `{end_ms}` placeholders
+/// of a chunk file name, see [`template`].
+fn chunk_name(
+    template: &str,
+    stem: &str,
+    num: usize,
+    time_range: &Range<Micros>,
+) -> Result<String, Error> {
+    let name = template::render(template, |field, value| {
+        let _ = match field {
+            "stem" => value.write_str(stem),
+            "num" => write!(value, "{}", num),
+            "start_ms" => write!(value, "{}", time_range.start.0 / 1000),
+            "end_ms" => write!(value, "{}", time_range.end.0 / 1000),
+            _ => return false,
+        };
+        true
+    })
+    .map_err(|error| format!("{} in {:?}", error, template))?;
+    Ok(name)
+}
+
+/// The names of all `chunks`, which must differ from each other and from the
+/// files of the manifest and the shared nodes.
+fn chunk_names(
+    template: &str,
+    stem: &str,
+    chunks: &[ProfileChunk<'_, '_>],
+) -> Result<Vec<String>, Error> {
+    let mut names = Vec::with_capacity(chunks.len());
+    let mut seen = HashSet::with_capacity(chunks.len());
+    for (index, chunk) in chunks.iter().enumerate() {
+        let name = chunk_name(template, stem, index + 1, &chunk.time_range())?;
+        if name == manifest::FILE_NAME || name == manifest::NODES_FILE_NAME {
+            return Err(format!("a chunk can't be named {}", name).into());
+        }
+        if !seen.insert(name.clone()) {
+            return Err(format!(
+                "{:?} names several chunks {}, add {{num}} to it",
+                template, name
+            )
+            .into());
+        }
+        names.push(name);
+    }
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{chunk_names, DEFAULT_NAME_TEMPLATE};
+    use v8_cpuprofile::Profile;
+
+    const PROFILE: &str = include_str!("../../v8-cpuprofile/tests/fixture.cpuprofile");
+
+    #[test]
+    fn unique_chunk_names() {
+        let profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        let chunks: Vec<_> = profile.chunks(3).collect();
+        let names = chunk_names(DEFAULT_NAME_TEMPLATE, "fixture", &chunks).unwrap();
+        assert_eq!(
+            names,
+            ["part1.cpuprofile", "part2.cpuprofile", "part3.cpuprofile"]
+        );
+        let error = chunk_names("{stem}.cpuprofile", "fixture", &chunks).unwrap_err();
+        assert!(error.to_string().contains("add {num}"), "{}", error);
+        assert!(chunk_names("manifest.json", "fixture", &chunks[..1]).is_err());
+        assert!(chunk_names("nodes.cpuprofile", "fixture", &chunks[..1]).is_err());
+    }
+}
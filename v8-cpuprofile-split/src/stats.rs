@@ -0,0 +1,38 @@
+use crate::{map_file, parse_any, Error};
+use std::path::Path;
+use v8_cpuprofile::format::decompress;
+
+pub fn run(cpu_profile: &Path) -> Result<(), Error> {
+    let mmap = map_file(cpu_profile)?;
+    let input = decompress(&mmap)?;
+    let profile = parse_any(&input)?;
+    let footprint = profile.memory_footprint();
+    let truncation = profile.truncation()?;
+
+    println!("nodes: {}", profile.nodes.len());
+    println!("samples: {}", profile.samples.len());
+    println!(
+        "duration: {:.1}ms",
+        profile.duration().as_secs_f64() * 1000.0
+    );
+    println!("\nstacks that look truncated:");
+    for (count, kind) in &[
+        (truncation.root_leaves, "sampled right below the root"),
+        (truncation.shallow, "much shallower than the median"),
+        (truncation.at_depth_limit, "at v8's depth limit"),
+    ] {
+        println!(
+            "{:>12}  {:>5.1}%  {}",
+            count,
+            truncation.share(*count) * 100.0,
+            kind
+        );
+    }
+    println!("\nmemory:");
+    println!("{:>12}  input", input.len());
+    println!("{:>12}  nodes", footprint.nodes);
+    println!("{:>12}  samples", footprint.samples);
+    println!("{:>12}  index", footprint.index);
+    println!("{:>12}  total", input.len() + footprint.total());
+    Ok(())
+}
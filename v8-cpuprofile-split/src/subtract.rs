@@ -0,0 +1,40 @@
+use crate::map_file;
+use crate::Error;
+use std::path::Path;
+use std::time::Duration;
+use v8_cpuprofile::links::SourceLinks;
+use v8_cpuprofile::Profile;
+
+pub fn run(
+    cpu_profile: &Path,
+    baseline: &Path,
+    top: usize,
+    links: Option<&SourceLinks>,
+) -> Result<(), Error> {
+    let mmap = map_file(cpu_profile)?;
+    let profile: Profile = serde_json::from_slice(&mmap)?;
+    let baseline_mmap = map_file(baseline)?;
+    let baseline: Profile = serde_json::from_slice(&baseline_mmap)?;
+    let stacks = profile.subtract(&baseline)?;
+
+    let total: Duration = stacks.iter().map(|stack| stack.self_time).sum();
+    println!(
+        "{:.1?} more than the baseline in {} stacks",
+        total,
+        stacks.len()
+    );
+    for stack in stacks.iter().take(top) {
+        println!();
+        println!("{:>10.1?}", stack.self_time);
+        // innermost frame first like a stack trace
+        for frame in stack.stack.iter().rev() {
+            match links
+                .and_then(|links| links.link(&frame.url, frame.line_number, frame.column_number))
+            {
+                Some(link) => println!("    {}  {}", frame, link),
+                None => println!("    {}", frame),
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,24 @@
+use crate::create_file;
+use crate::Error;
+use std::io::Write;
+use std::path::Path;
+use v8_cpuprofile::synth::SynthOptions;
+use v8_cpuprofile::Profile;
+
+pub fn run(out: &Path, options: &SynthOptions, pretty: bool) -> Result<(), Error> {
+    let profile = Profile::synthesize(options);
+    eprintln!(
+        "writing {} nodes and {} samples to {:?}",
+        profile.nodes.len(),
+        profile.samples.len(),
+        out
+    );
+    let mut writer = create_file(out)?;
+    if pretty {
+        serde_json::to_writer_pretty(&mut writer, &profile)?;
+    } else {
+        serde_json::to_writer(&mut writer, &profile)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,31 @@
+use structopt::StructOpt;
+use v8_cpuprofile::Weighting;
+
+/// Options for how samples become time.
+#[derive(Debug, StructOpt)]
+pub struct WeightingOpts {
+    /// How samples become time: delta-to-next, midpoint or hit-count
+    #[structopt(long, default_value = "delta-to-next")]
+    weighting: Weighting,
+}
+
+impl WeightingOpts {
+    pub fn weighting(&self) -> Weighting {
+        self.weighting
+    }
+}
+
+/// Options for where garbage collection time goes.
+#[derive(Debug, StructOpt)]
+pub struct GcOpts {
+    /// Charges garbage collector samples to the function that ran
+    /// before the collection, like the DevTools performance panel
+    #[structopt(long)]
+    attribute_gc: bool,
+}
+
+impl GcOpts {
+    pub fn attribute_gc(&self) -> bool {
+        self.attribute_gc
+    }
+}
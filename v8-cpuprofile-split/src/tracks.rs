@@ -0,0 +1,57 @@
+use crate::inputs::{by_process, expand_all};
+use crate::Error;
+use crate::{create_file, map_file, Input};
+use std::convert::TryFrom;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use v8_cpuprofile::trace::{Trace, Track};
+use v8_cpuprofile::Profile;
+
+pub fn run(inputs: &[PathBuf], out: &Path) -> Result<(), Error> {
+    let (processes, other) = by_process(expand_all(inputs)?);
+
+    // (pid, tid, name, path), profiles not named by --cpu-prof get a thread
+    // of their own in pid 0
+    let mut threads = Vec::new();
+    for (pid, captures) in processes {
+        for (name, cpu_profile) in captures {
+            let thread = name.thread_name();
+            threads.push((pid, name.tid, thread, cpu_profile));
+        }
+    }
+    for (tid, cpu_profile) in other.into_iter().enumerate() {
+        let thread = cpu_profile
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        threads.push((0, u32::try_from(tid)?, thread, cpu_profile));
+    }
+
+    let mmaps = threads
+        .iter()
+        .map(|(_, _, _, cpu_profile)| map_file(cpu_profile))
+        .collect::<Result<Vec<Input>, _>>()?;
+    let profiles = mmaps
+        .iter()
+        .map(|mmap| serde_json::from_slice(mmap))
+        .collect::<Result<Vec<Profile>, _>>()?;
+
+    let trace = Trace {
+        tracks: threads
+            .into_iter()
+            .zip(&profiles)
+            .map(|((pid, tid, name, _), profile)| Track {
+                pid,
+                tid,
+                name,
+                profile,
+            })
+            .collect(),
+    };
+    eprintln!("writing {} tracks to {:?}", trace.tracks.len(), out);
+    let mut writer = create_file(out)?;
+    serde_json::to_writer(&mut writer, &trace)?;
+    writer.flush()?;
+    Ok(())
+}
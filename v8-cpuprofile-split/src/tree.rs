@@ -0,0 +1,41 @@
+use crate::map_file;
+use crate::Error;
+use std::path::Path;
+use v8_cpuprofile::Profile;
+
+/// Prints the call tree pruned to paths with at least `threshold` percent
+/// of the time, indented by depth.
+pub fn run(cpu_profile: &Path, threshold: f64) -> Result<(), Error> {
+    let mmap = map_file(cpu_profile)?;
+    let profile: Profile = serde_json::from_slice(&mmap)?;
+    let pruned = profile.drill_down(threshold / 100.0);
+    let root = match pruned.root() {
+        Some(root) => root.id,
+        None => return Ok(()),
+    };
+
+    let rollups = pruned.with_rollups();
+    let total = rollups.total_time(root).as_secs_f64();
+    let mut stack = vec![(root, 0)];
+    while let Some((node_id, depth)) = stack.pop() {
+        let time = rollups.total_time(node_id);
+        let percent = if total > 0.0 {
+            time.as_secs_f64() / total * 100.0
+        } else {
+            0.0
+        };
+        println!(
+            "{:>6.1}% {:>10.1?}  {:indent$}{}",
+            percent,
+            time,
+            "",
+            pruned[node_id].frame()?,
+            indent = depth * 2
+        );
+        let mut children: Vec<u64> = pruned[node_id].children.iter().flatten().copied().collect();
+        // heaviest child printed first
+        children.sort_by_key(|&child_id| rollups.total_time(child_id));
+        stack.extend(children.into_iter().map(|child_id| (child_id, depth + 1)));
+    }
+    Ok(())
+}
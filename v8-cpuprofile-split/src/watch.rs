@@ -0,0 +1,88 @@
+use crate::inputs::expand;
+use crate::Error;
+use crate::{check, path, split};
+use std::collections::{HashMap, HashSet};
+use std::fs::{metadata, read_to_string};
+use std::path::{Path, PathBuf};
+use std::thread::sleep;
+use std::time::Duration;
+use v8_cpuprofile::budget::Budgets;
+
+/// What to run on each new profile.
+pub struct Pipeline<'a> {
+    /// Chunk count and the directory each profile's chunks go under
+    pub split: Option<(usize, &'a Path)>,
+    pub budgets: Option<&'a Path>,
+    pub path: bool,
+}
+
+/// Polls `dir` for new cpuprofiles and runs the pipeline on each, forever.
+///
+/// Profiles already in the directory are skipped. A new file is only picked
+/// up once its size is unchanged between two scans, node writes the profile
+/// as the process exits and it may still be growing when first seen.
+pub fn run(dir: &Path, pipeline: &Pipeline, interval: Duration) -> Result<(), Error> {
+    if !dir.is_dir() {
+        return Err(format!("{:?} is not a directory", dir).into());
+    }
+    let budgets: Option<Budgets> = match pipeline.budgets {
+        Some(budgets) => Some(toml::from_str(&read_to_string(budgets)?)?),
+        None => None,
+    };
+    let mut seen: HashSet<PathBuf> = expand(dir)?.into_iter().collect();
+    let mut pending: HashMap<PathBuf, u64> = HashMap::new();
+    println!("watching {:?}", dir);
+
+    loop {
+        sleep(interval);
+        for cpu_profile in expand(dir)? {
+            if seen.contains(&cpu_profile) {
+                continue;
+            }
+            let len = match metadata(&cpu_profile) {
+                Ok(metadata) => metadata.len(),
+                // removed again before it settled
+                Err(_) => continue,
+            };
+            if len == 0 || pending.insert(cpu_profile.clone(), len) != Some(len) {
+                continue;
+            }
+            pending.remove(&cpu_profile);
+            if let Err(error) = process(&cpu_profile, pipeline, budgets.as_ref()) {
+                println!("{:?} failed: {}", cpu_profile, error);
+            }
+            seen.insert(cpu_profile);
+        }
+    }
+}
+
+fn process(
+    cpu_profile: &Path,
+    pipeline: &Pipeline,
+    budgets: Option<&Budgets>,
+) -> Result<(), Error> {
+    println!("new profile {:?}", cpu_profile);
+    if let Some((chunk_num, out_dir)) = pipeline.split {
+        let stem = cpu_profile.file_stem().unwrap_or_default();
+        split::split(
+            cpu_profile,
+            &out_dir.join(stem),
+            chunk_num,
+            &split::Output::default(),
+            false,
+        )?;
+    }
+    if let Some(budgets) = budgets {
+        check::check(
+            cpu_profile,
+            budgets,
+            false,
+            false,
+            &check::Output::default(),
+        )?;
+    }
+    if pipeline.path {
+        path::run(cpu_profile)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,459 @@
+use crate::{FrameKey, Profile};
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::cmp::Reverse;
+use core::time::Duration;
+use hashbrown::HashMap;
+
+/// Per function statistics across many runs of the same workload, see
+/// [`ProfileSet::aggregate`].
+#[derive(Debug)]
+pub struct ProfileSet<'raw> {
+    pub runs: usize,
+    /// Mean sampling interval of the runs scaled by their weights, `None`
+    /// if no run has samples to tell.
+    pub sampling_interval: Option<Duration>,
+    /// By mean self time, heaviest first.
+    pub frames: Vec<FrameStats<'raw>>,
+}
+
+#[derive(Debug)]
+pub struct FrameStats<'raw> {
+    pub frame: FrameKey<'raw>,
+    pub self_time: Stats,
+    pub total_time: Stats,
+    /// Self time of each run in the order the profiles were given.
+    pub self_times: Vec<Duration>,
+}
+
+/// How far a run is from the corpus median, see [`ProfileSet::outliers`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunScore {
+    /// Index of the profile given to [`ProfileSet::aggregate`].
+    pub run: usize,
+    /// Summed absolute difference from the median self time of every
+    /// function, relative to the summed median self time.
+    pub score: f64,
+    /// Functions that differ from their median, most different first.
+    pub frames: Vec<FrameDeviation>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FrameDeviation {
+    /// Index into [`ProfileSet::frames`].
+    pub frame: usize,
+    pub self_time: Duration,
+    pub median: Duration,
+}
+
+impl FrameDeviation {
+    #[must_use]
+    pub fn difference(&self) -> Duration {
+        self.self_time
+            .saturating_sub(self.median)
+            .max(self.median.saturating_sub(self.self_time))
+    }
+}
+
+/// How the mean self time of a function moved from a baseline, see
+/// [`ProfileSet::compare`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FrameChange<'a> {
+    pub frame: &'a FrameKey<'a>,
+    /// Zero if the function is new.
+    pub baseline: Duration,
+    /// Zero if the function is gone.
+    pub current: Duration,
+}
+
+impl FrameChange<'_> {
+    /// Whether the function got slower.
+    #[must_use]
+    pub fn is_regression(&self) -> bool {
+        self.current > self.baseline
+    }
+
+    #[must_use]
+    pub fn difference(&self) -> Duration {
+        self.current
+            .saturating_sub(self.baseline)
+            .max(self.baseline.saturating_sub(self.current))
+    }
+
+    /// The difference relative to the baseline, infinite for a new function.
+    #[must_use]
+    pub fn relative(&self) -> f64 {
+        self.difference().as_secs_f64() / self.baseline.as_secs_f64()
+    }
+}
+
+/// Distribution of a time over runs, a run without the function counts as
+/// zero.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Stats {
+    pub mean: Duration,
+    pub stddev: Duration,
+    pub min: Duration,
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+    /// Half the width of the 95% confidence interval of the mean due to
+    /// sampling alone, see [`ProfileSet::add_error_bars`].
+    pub margin: Option<Duration>,
+}
+
+impl Stats {
+    /// # Panics
+    ///
+    /// If `values` is empty.
+    #[must_use]
+    pub fn from_values(values: &mut [Duration]) -> Self {
+        assert!(!values.is_empty(), "no values");
+        values.sort_unstable();
+        #[allow(clippy::cast_precision_loss)]
+        let len = values.len() as f64;
+        let mean = values.iter().map(Duration::as_secs_f64).sum::<f64>() / len;
+        let variance = values
+            .iter()
+            .map(|value| {
+                let delta = value.as_secs_f64() - mean;
+                delta * delta
+            })
+            .sum::<f64>()
+            / len;
+        Stats {
+            mean: Duration::from_secs_f64(mean),
+            stddev: Duration::from_secs_f64(libm::sqrt(variance)),
+            min: values[0],
+            p50: percentile(values, 50),
+            p90: percentile(values, 90),
+            p99: percentile(values, 99),
+            max: values[values.len() - 1],
+            margin: None,
+        }
+    }
+}
+
+/// Weights that scale each profile to the mean capture duration, a profile
+/// without samples keeps its times as is.
+#[must_use]
+pub fn duration_weights(profiles: &[Profile]) -> Vec<f64> {
+    let durations: Vec<f64> = profiles
+        .iter()
+        .map(|profile| profile.duration().as_secs_f64())
+        .collect();
+    #[allow(clippy::cast_precision_loss)]
+    let mean = durations.iter().sum::<f64>() / durations.len().max(1) as f64;
+    durations
+        .iter()
+        .map(|&duration| if duration > 0.0 { mean / duration } else { 1.0 })
+        .collect()
+}
+
+fn margin(interval: Duration, runs: usize, mean: Duration) -> Duration {
+    #[allow(clippy::cast_precision_loss)]
+    let runs = runs.max(1) as f64;
+    // a time of n samples varies by sqrt(n) samples
+    let variance = interval.as_secs_f64() * mean.as_secs_f64() / runs;
+    Duration::from_secs_f64(1.96 * libm::sqrt(variance))
+}
+
+/// Nearest rank percentile of sorted values.
+fn percentile(sorted: &[Duration], percent: usize) -> Duration {
+    let rank = crate::div_ceil(percent * sorted.len(), 100);
+    sorted[rank.max(1) - 1]
+}
+
+impl<'raw> ProfileSet<'raw> {
+    /// Aggregates the self and total time of each function over the runs.
+    ///
+    /// # Errors
+    ///
+    /// If a node of any profile has an invalid call frame.
+    pub fn aggregate(profiles: &[Profile<'raw>]) -> Result<Self, serde_json::Error> {
+        Self::aggregate_weighted(profiles, &alloc::vec![1.0; profiles.len()])
+    }
+
+    /// Aggregates like [`ProfileSet::aggregate`] with the times of each run
+    /// scaled by its weight, so one long capture doesn't drown out several
+    /// short ones, see [`duration_weights`].
+    ///
+    /// # Errors
+    ///
+    /// If a node of any profile has an invalid call frame.
+    ///
+    /// # Panics
+    ///
+    /// If there isn't a weight for each profile.
+    pub fn aggregate_weighted(
+        profiles: &[Profile<'raw>],
+        weights: &[f64],
+    ) -> Result<Self, serde_json::Error> {
+        assert_eq!(profiles.len(), weights.len(), "a weight per profile");
+        let runs = profiles.len();
+        let intervals: Vec<f64> = profiles
+            .iter()
+            .zip(weights)
+            .filter_map(|(profile, weight)| {
+                let interval = profile.nominal_sampling_interval()?;
+                Some(interval.as_secs_f64() * weight)
+            })
+            .collect();
+        #[allow(clippy::cast_precision_loss)]
+        let sampling_interval = if intervals.is_empty() {
+            None
+        } else {
+            let mean = intervals.iter().sum::<f64>() / intervals.len() as f64;
+            Some(Duration::from_secs_f64(mean.max(0.0)))
+        };
+        let mut values: HashMap<FrameKey<'raw>, (Vec<Duration>, Vec<Duration>)> = HashMap::new();
+        for (run, (profile, &weight)) in profiles.iter().zip(weights).enumerate() {
+            for (frame, times) in profile.frame_times()? {
+                let (self_times, total_times) = values.entry(frame).or_insert_with(|| {
+                    (
+                        alloc::vec![Duration::default(); runs],
+                        alloc::vec![Duration::default(); runs],
+                    )
+                });
+                self_times[run] = times.self_time.mul_f64(weight);
+                total_times[run] = times.total_time.mul_f64(weight);
+            }
+        }
+
+        let mut frames: Vec<_> = values
+            .into_iter()
+            .map(|(frame, (self_times, mut total_times))| FrameStats {
+                frame,
+                self_time: Stats::from_values(&mut self_times.clone()),
+                total_time: Stats::from_values(&mut total_times),
+                self_times,
+            })
+            .collect();
+        frames.sort_by(|a, b| {
+            Reverse(a.self_time.mean)
+                .cmp(&Reverse(b.self_time.mean))
+                .then_with(|| a.frame.cmp(&b.frame))
+        });
+        Ok(ProfileSet {
+            runs,
+            sampling_interval,
+            frames,
+        })
+    }
+
+    /// Half the width of the 95% confidence interval of a mean time over
+    /// the runs, treating the number of samples behind it as Poisson
+    /// distributed. `None` without a sampling interval.
+    #[must_use]
+    pub fn margin(&self, mean: Duration) -> Option<Duration> {
+        let interval = self.sampling_interval?;
+        Some(margin(interval, self.runs, mean))
+    }
+
+    /// Sets the [`Stats::margin`] of every function's self and total time.
+    pub fn add_error_bars(&mut self) {
+        let (interval, runs) = match self.sampling_interval {
+            Some(interval) => (interval, self.runs),
+            None => return,
+        };
+        for frame in &mut self.frames {
+            for stats in [&mut frame.self_time, &mut frame.total_time] {
+                stats.margin = Some(margin(interval, runs, stats.mean));
+            }
+        }
+    }
+
+    /// Scores each run by how much it deviates from the median of all runs,
+    /// the most anomalous run first.
+    #[must_use]
+    pub fn outliers(&self) -> Vec<RunScore> {
+        let median_total: Duration = self.frames.iter().map(|frame| frame.self_time.p50).sum();
+        let mut scores: Vec<_> = (0..self.runs)
+            .map(|run| {
+                let mut frames: Vec<_> = self
+                    .frames
+                    .iter()
+                    .enumerate()
+                    .map(|(index, stats)| FrameDeviation {
+                        frame: index,
+                        self_time: stats.self_times[run],
+                        median: stats.self_time.p50,
+                    })
+                    .filter(|deviation| deviation.self_time != deviation.median)
+                    .collect();
+                frames.sort_by_key(|deviation| Reverse(deviation.difference()));
+                let difference: Duration = frames.iter().map(FrameDeviation::difference).sum();
+                let score = if median_total > Duration::default() {
+                    difference.as_secs_f64() / median_total.as_secs_f64()
+                } else {
+                    0.0
+                };
+                RunScore { run, score, frames }
+            })
+            .collect();
+        scores.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal));
+        scores
+    }
+
+    /// The functions whose mean self time differs from `baseline`, such as
+    /// a stored aggregate of an earlier build, the largest change first.
+    #[must_use]
+    pub fn compare<'a>(&'a self, baseline: &'a ProfileSet<'_>) -> Vec<FrameChange<'a>> {
+        let mut baseline_times: HashMap<&FrameKey, Duration> = baseline
+            .frames
+            .iter()
+            .map(|stats| (&stats.frame, stats.self_time.mean))
+            .collect();
+        let mut changes: Vec<_> = self
+            .frames
+            .iter()
+            .map(|stats| FrameChange {
+                frame: &stats.frame,
+                baseline: baseline_times.remove(&stats.frame).unwrap_or_default(),
+                current: stats.self_time.mean,
+            })
+            .collect();
+        changes.extend(
+            baseline
+                .frames
+                .iter()
+                .filter(|stats| baseline_times.contains_key(&stats.frame))
+                .map(|stats| FrameChange {
+                    frame: &stats.frame,
+                    baseline: stats.self_time.mean,
+                    current: Duration::default(),
+                }),
+        );
+        changes.retain(|change| change.current != change.baseline);
+        changes.sort_by(|a, b| {
+            Reverse(a.difference())
+                .cmp(&Reverse(b.difference()))
+                .then_with(|| a.frame.cmp(b.frame))
+        });
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{duration_weights, FrameChange, ProfileSet, Stats};
+    use crate::ser::tests::PROFILE;
+    use core::time::Duration;
+
+    #[test]
+    fn stats() {
+        let mut values: alloc::vec::Vec<_> = [4, 2, 8, 6]
+            .iter()
+            .map(|&ms| Duration::from_millis(ms))
+            .collect();
+        let stats = Stats::from_values(&mut values);
+
+        assert_eq!(stats.mean, Duration::from_millis(5));
+        assert_eq!(stats.min, Duration::from_millis(2));
+        assert_eq!(stats.p50, Duration::from_millis(4));
+        assert_eq!(stats.p90, Duration::from_millis(8));
+        assert_eq!(stats.max, Duration::from_millis(8));
+        assert_eq!(stats.stddev.as_micros(), 2236);
+    }
+
+    #[test]
+    fn aggregate() {
+        let profiles = [
+            serde_json::from_str(PROFILE).unwrap(),
+            serde_json::from_str(PROFILE).unwrap(),
+        ];
+        let set = ProfileSet::aggregate(&profiles).unwrap();
+
+        assert_eq!(set.runs, 2);
+        assert_eq!(set.frames[0].frame.function_name, "WriteStream");
+        assert_eq!(set.frames[0].self_time.stddev, Duration::default());
+        assert_eq!(set.frames[0].self_time.min, set.frames[0].self_time.max);
+        assert!(set.outliers().iter().all(|run| run.frames.is_empty()));
+
+        assert_eq!(duration_weights(&profiles), [1.0, 1.0]);
+        let weighted = ProfileSet::aggregate_weighted(&profiles, &[1.0, 2.0]).unwrap();
+        let stats = &weighted.frames[0].self_time;
+        assert_eq!(stats.max, stats.min * 2);
+    }
+
+    #[test]
+    fn outliers() {
+        let mut profiles: alloc::vec::Vec<crate::Profile> = (0..4)
+            .map(|_| serde_json::from_str(PROFILE).unwrap())
+            .collect();
+        // the third run spends its first samples in another function
+        let other = profiles[2].nodes.last().unwrap().id;
+        for sample in profiles[2].samples.iter_mut().take(10) {
+            sample.node_id = other;
+        }
+        let set = ProfileSet::aggregate(&profiles).unwrap();
+        let outliers = set.outliers();
+        assert_eq!(outliers[0].run, 2);
+        assert!(outliers[0].score > 0.0);
+        assert!(!outliers[0].frames.is_empty());
+        assert!(outliers[1..].iter().all(|run| run.score == 0.0));
+    }
+
+    #[test]
+    fn error_bars() {
+        let profiles: alloc::vec::Vec<crate::Profile> = (0..4)
+            .map(|_| serde_json::from_str(PROFILE).unwrap())
+            .collect();
+        let mut set = ProfileSet::aggregate(&profiles).unwrap();
+        let interval = profiles[0].nominal_sampling_interval();
+        assert_eq!(set.sampling_interval, interval);
+        assert_eq!(set.frames[0].self_time.margin, None);
+
+        // 100 samples vary by 10 samples, the mean of 4 runs by 5
+        let interval = interval.unwrap();
+        let margin = set.margin(interval * 100).unwrap();
+        assert!((margin.as_secs_f64() / interval.as_secs_f64() - 1.96 * 5.0).abs() < 0.01);
+
+        set.add_error_bars();
+        let stats = set.frames[0].self_time;
+        assert_eq!(stats.margin, set.margin(stats.mean));
+        assert!(stats.margin.unwrap() < stats.mean);
+        let json = serde_json::to_string(&set).unwrap();
+        let read: ProfileSet = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            read.frames[0]
+                .self_time
+                .margin
+                .map(|margin| margin.as_micros()),
+            stats.margin.map(|margin| margin.as_micros())
+        );
+    }
+
+    #[test]
+    fn compare() {
+        let profiles = [serde_json::from_str(PROFILE).unwrap()];
+        let set = ProfileSet::aggregate(&profiles).unwrap();
+        let json = serde_json::to_string(&set).unwrap();
+        let baseline: ProfileSet = serde_json::from_str(&json).unwrap();
+        assert_eq!(baseline.runs, 1);
+        assert_eq!(baseline.frames.len(), set.frames.len());
+        assert_eq!(baseline.frames[0].self_times, set.frames[0].self_times);
+        assert_eq!(baseline.sampling_interval, set.sampling_interval);
+        assert!(set.compare(&baseline).is_empty());
+
+        let slower = ProfileSet::aggregate_weighted(&profiles, &[1.5]).unwrap();
+        let changes = slower.compare(&baseline);
+        let sampled = set
+            .frames
+            .iter()
+            .filter(|stats| stats.self_time.mean > core::time::Duration::default())
+            .count();
+        assert_eq!(changes.len(), sampled);
+        assert_eq!(changes[0].frame.function_name, "WriteStream");
+        assert!(changes.iter().all(FrameChange::is_regression));
+        assert!((changes[0].relative() - 0.5).abs() < 0.01);
+
+        let mut fewer = ProfileSet::aggregate(&profiles).unwrap();
+        fewer.frames.remove(0);
+        let changes = fewer.compare(&baseline);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].current, core::time::Duration::default());
+        assert!(!changes[0].is_regression());
+    }
+}
@@ -0,0 +1,174 @@
+//! Frames of eval'd and anonymous scripts merged into a few named buckets,
+//! see [`Profile::group_anonymous`].
+
+use crate::{CallFrame, Profile, RawJson};
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Which frames [`Profile::group_anonymous`] merges and under what names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnonymousGroups {
+    /// The name of frames of scripts without a url, like `eval` and
+    /// `new Function` code, `None` leaves them as they are.
+    pub unnamed: Option<String>,
+    /// Url prefixes and the name the frames of matching scripts get, the
+    /// first match wins.
+    pub prefixes: Vec<(String, String)>,
+}
+
+/// Code without a url as `(eval)`, and scripts of node's `vm` module without
+/// a file name as `(vm)`.
+impl Default for AnonymousGroups {
+    fn default() -> Self {
+        AnonymousGroups {
+            unnamed: Some(String::from("(eval)")),
+            prefixes: alloc::vec![(
+                String::from("evalmachine.<anonymous>"),
+                String::from("(vm)")
+            )],
+        }
+    }
+}
+
+impl AnonymousGroups {
+    /// The bucket of a frame, if any. Pseudo frames like `(root)` have no
+    /// script and are never grouped.
+    fn bucket(&self, frame: &CallFrame<'_>) -> Option<&str> {
+        if frame.script_id == "0" {
+            return None;
+        }
+        if frame.url.is_empty() {
+            return self.unnamed.as_deref();
+        }
+        self.prefixes
+            .iter()
+            .find(|(prefix, _)| frame.url.starts_with(prefix.as_str()))
+            .map(|(_, name)| name.as_str())
+    }
+}
+
+impl Profile<'_> {
+    /// Replaces the frames of eval'd and anonymous scripts with one frame
+    /// per bucket of `groups`, so they add up to one function instead of
+    /// one per function and script. Returns the number of nodes changed.
+    ///
+    /// A grouped frame has the bucket as its function name and no script,
+    /// url or position.
+    ///
+    /// # Errors
+    ///
+    /// If a node has an invalid call frame.
+    pub fn group_anonymous(
+        &mut self,
+        groups: &AnonymousGroups,
+    ) -> Result<usize, serde_json::Error> {
+        let mut changed = 0;
+        for node in &mut self.nodes {
+            let frame = node.frame()?;
+            let Some(bucket) = groups.bucket(&frame) else {
+                continue;
+            };
+            let grouped = CallFrame {
+                function_name: Cow::Borrowed(bucket),
+                script_id: Cow::Borrowed("0"),
+                url: Cow::Borrowed(""),
+                line_number: -1,
+                column_number: -1,
+            };
+            node.call_frame = RawJson::from(serde_json::value::to_raw_value(&grouped)?);
+            changed += 1;
+        }
+        Ok(changed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AnonymousGroups;
+    use crate::Profile;
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
+    use serde_json::json;
+
+    fn node(
+        id: u64,
+        function_name: &str,
+        script_id: &str,
+        url: &str,
+        children: &[u64],
+    ) -> serde_json::Value {
+        json!({
+            "id": id,
+            "callFrame": {
+                "functionName": function_name,
+                "scriptId": script_id,
+                "url": url,
+                "lineNumber": id,
+                "columnNumber": 0,
+            },
+            "hitCount": 0,
+            "children": children,
+        })
+    }
+
+    #[test]
+    fn group_anonymous() {
+        let json = json!({
+            "nodes": [
+                node(1, "(root)", "0", "", &[2, 3, 4, 5, 6]),
+                node(2, "a", "11", "", &[]),
+                node(3, "b", "12", "", &[]),
+                node(4, "c", "13", "evalmachine.<anonymous>", &[]),
+                node(5, "main", "14", "file:///app.js", &[]),
+                node(6, "d", "15", "webpack-internal:///./src/d.js", &[]),
+            ],
+            "startTime": 0,
+            "endTime": 6,
+            "samples": [2, 3, 4, 5, 6, 2],
+            "timeDeltas": [1, 1, 1, 1, 1, 1],
+        })
+        .to_string();
+        let mut profile: Profile = serde_json::from_str(&json).unwrap();
+        let functions = |profile: &Profile| {
+            let mut names: Vec<String> = profile
+                .frame_times()
+                .unwrap()
+                .into_iter()
+                .map(|(frame, _)| frame.function_name.into_owned())
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(functions(&profile).len(), 6);
+
+        assert_eq!(
+            profile
+                .group_anonymous(&AnonymousGroups::default())
+                .unwrap(),
+            3
+        );
+        profile.roundtrip_check().unwrap();
+        assert_eq!(
+            functions(&profile),
+            ["(eval)", "(root)", "(vm)", "d", "main"]
+        );
+        assert_eq!(
+            profile
+                .group_anonymous(&AnonymousGroups::default())
+                .unwrap(),
+            0
+        );
+
+        let mut profile: Profile = serde_json::from_str(&json).unwrap();
+        let groups = AnonymousGroups {
+            unnamed: None,
+            prefixes: alloc::vec![(String::from("webpack-internal:"), String::from("(webpack)"))],
+        };
+        assert_eq!(profile.group_anonymous(&groups).unwrap(), 1);
+        assert_eq!(
+            functions(&profile),
+            ["(root)", "(webpack)", "a", "b", "c", "main"]
+        );
+    }
+}
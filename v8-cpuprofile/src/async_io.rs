@@ -0,0 +1,115 @@
+//! Async entry points for services that already run on tokio, so reading and
+//! writing large profiles doesn't block a worker thread on file I/O. Parsing
+//! and serializing still run on the calling task.
+//!
+//! serde serializes synchronously, so writing first serializes the whole
+//! profile into memory and then writes the bytes, which takes about as much
+//! memory again as the json. To stream a large profile instead, run
+//! [`Profile::write_json`] on a blocking thread, like with
+//! `tokio::task::spawn_blocking`.
+
+use crate::{Profile, ProfileChunk, SerializeOptions};
+use alloc::vec::Vec;
+use serde::Serialize;
+use std::io;
+use std::path::Path;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+impl Profile<'static> {
+    /// Reads and parses a profile that owns its raw json, see
+    /// [`Profile::deserialize_owned`].
+    ///
+    /// # Errors
+    ///
+    /// If reading fails or the file is not a valid profile.
+    pub async fn read_async(path: impl AsRef<Path>) -> io::Result<Self> {
+        let bytes = tokio::fs::read(path).await?;
+        event!("read profile", bytes = bytes.len());
+        let mut deserializer = serde_json::Deserializer::from_slice(&bytes);
+        Ok(Self::deserialize_owned(&mut deserializer)?)
+    }
+}
+
+impl Profile<'_> {
+    /// Like [`Profile::write_json`] but writes with tokio, after serializing
+    /// the whole profile into memory.
+    ///
+    /// # Errors
+    ///
+    /// If writing fails or a node's raw json is invalid.
+    pub async fn write_json_async<W>(
+        &self,
+        writer: &mut W,
+        options: SerializeOptions,
+    ) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        write_all(writer, self.to_json(options)?.as_bytes()).await
+    }
+}
+
+impl ProfileChunk<'_, '_> {
+    /// Writes the chunk as a cpuprofile with tokio, after serializing it into
+    /// memory.
+    ///
+    /// # Errors
+    ///
+    /// If writing fails or a node's raw json is invalid.
+    pub async fn write_json_async<W>(&self, writer: &mut W, pretty: bool) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        write_all(writer, &to_vec(self, pretty)?).await
+    }
+}
+
+fn to_vec<T: Serialize>(value: &T, pretty: bool) -> serde_json::Result<Vec<u8>> {
+    if pretty {
+        serde_json::to_vec_pretty(value)
+    } else {
+        serde_json::to_vec(value)
+    }
+}
+
+async fn write_all<W>(writer: &mut W, bytes: &[u8]) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let _span = span!("write_async", bytes = bytes.len());
+    writer.write_all(bytes).await?;
+    writer.flush().await
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ser::tests::PROFILE;
+    use crate::{Profile, ProfileChunk, SerializeOptions};
+
+    #[tokio::test]
+    async fn round_trip() {
+        let profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        let path = std::env::temp_dir().join(alloc::format!(
+            "v8-cpuprofile-async-{}.cpuprofile",
+            std::process::id()
+        ));
+        let mut file = tokio::fs::File::create(&path).await.unwrap();
+        profile
+            .write_json_async(&mut file, SerializeOptions::default())
+            .await
+            .unwrap();
+
+        let read = Profile::read_async(&path).await.unwrap();
+        assert_eq!(read.samples, profile.samples);
+        assert_eq!(read.nodes.len(), profile.nodes.len());
+
+        let mut out = alloc::vec::Vec::new();
+        ProfileChunk::new(&profile, &profile.samples)
+            .write_json_async(&mut out, false)
+            .await
+            .unwrap();
+        let chunk: Profile = serde_json::from_slice(&out).unwrap();
+        assert_eq!(chunk.samples, profile.samples);
+        std::fs::remove_file(path).unwrap();
+    }
+}
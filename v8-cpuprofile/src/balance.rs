@@ -0,0 +1,199 @@
+//! Splits the samples of a profile into chunks of about the same
+//! serialized size. Each chunk repeats the nodes its samples are on, so a
+//! chunk of samples spread over many stacks is larger than one of the same
+//! number of samples in a few.
+
+use crate::{Node, Profile, Sample};
+use alloc::vec::Vec;
+use hashbrown::HashSet;
+
+/// The json of a node without its call frame or other raw fields, like
+/// `{"id":,"callFrame":,"hitCount":,"children":[]},`.
+const NODE_BYTES: usize = 47;
+
+/// At most `chunk_num` runs of samples in order, with the largest
+/// estimated size as small as it can be.
+pub(crate) fn chunks<'profile>(
+    profile: &'profile Profile,
+    chunk_num: usize,
+) -> Vec<&'profile [Sample]> {
+    let chunk_num = chunk_num.max(1);
+    let mut splitter = Splitter {
+        profile,
+        included: HashSet::new(),
+    };
+    // the smallest cap on the size of a chunk that needs no more chunks
+    let total = splitter.total();
+    let mut low = total / chunk_num;
+    let mut high = total;
+    while low < high {
+        let cap = low + (high - low) / 2;
+        if splitter.split(cap, chunk_num).is_some() {
+            high = cap;
+        } else {
+            low = cap + 1;
+        }
+    }
+    let ends = splitter.split(high, chunk_num).unwrap_or_default();
+    let mut start = 0;
+    ends.into_iter()
+        .map(|end| {
+            let chunk = &profile.samples[start..end];
+            start = end;
+            chunk
+        })
+        .collect()
+}
+
+struct Splitter<'profile, 'raw> {
+    profile: &'profile Profile<'raw>,
+    /// Nodes of the current chunk.
+    included: HashSet<u64>,
+}
+
+impl Splitter<'_, '_> {
+    fn total(&mut self) -> usize {
+        self.included.clear();
+        (0..self.profile.samples.len())
+            .map(|index| self.sample_bytes(index))
+            .sum()
+    }
+
+    /// The end of each chunk when chunks are cut before the sample that
+    /// would make them larger than `cap`, `None` if that is more than
+    /// `chunk_num` chunks.
+    fn split(&mut self, cap: usize, chunk_num: usize) -> Option<Vec<usize>> {
+        self.included.clear();
+        let mut ends = Vec::with_capacity(chunk_num);
+        let mut size = 0;
+        for index in 0..self.profile.samples.len() {
+            let mut bytes = self.sample_bytes(index);
+            if size > 0 && size + bytes > cap {
+                if ends.len() + 1 == chunk_num {
+                    return None;
+                }
+                ends.push(index);
+                self.included.clear();
+                size = 0;
+                bytes = self.sample_bytes(index);
+            }
+            size += bytes;
+        }
+        if size > 0 {
+            ends.push(self.profile.samples.len());
+        }
+        Some(ends)
+    }
+
+    /// The bytes the sample at `index` adds to the current chunk, its id
+    /// and time delta and the nodes on its stack that aren't in the chunk
+    /// yet, which it adds.
+    fn sample_bytes(&mut self, index: usize) -> usize {
+        let samples = &self.profile.samples;
+        let sample = samples[index];
+        let delta = match index.checked_sub(1) {
+            Some(previous) => sample.ts.saturating_sub(samples[previous].ts).0,
+            None => sample.ts.0,
+        };
+        let mut bytes = digits(sample.node_id) + digits(delta) + 2;
+        let stack =
+            core::iter::once(sample.node_id).chain(self.profile.parent_ids_iter(sample.node_id));
+        for node_id in stack {
+            if !self.included.insert(node_id) {
+                break;
+            }
+            bytes += node_bytes(&self.profile[node_id]);
+        }
+        bytes
+    }
+}
+
+fn node_bytes(node: &Node) -> usize {
+    let raw = [&node.deopt_reason, &node.position_ticks]
+        .iter()
+        .filter_map(|raw| raw.as_ref())
+        .map(|raw| raw.get().len() + 16)
+        .sum::<usize>();
+    let extra = node
+        .extra_fields
+        .iter()
+        .map(|(key, value)| key.len() + value.get().len() + 4)
+        .sum::<usize>();
+    // the id is in the parent's children too
+    NODE_BYTES
+        + 2 * digits(node.id)
+        + digits(u64::from(node.hit_count))
+        + node.call_frame.get().len()
+        + raw
+        + extra
+}
+
+fn digits(mut n: u64) -> usize {
+    let mut digits = 1;
+    while n >= 10 {
+        n /= 10;
+        digits += 1;
+    }
+    digits
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Profile;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use core::fmt::Write;
+
+    #[test]
+    fn balanced_by_size() {
+        // 200 samples of one node, then a sample of each of 200 others
+        let mut nodes = String::from(
+            r#"{"id":1,"callFrame":{"functionName":"(root)","scriptId":"0","url":"","lineNumber":-1,"columnNumber":-1},"hitCount":0,"children":[2"#,
+        );
+        for id in 3..203 {
+            write!(nodes, ",{}", id).unwrap();
+        }
+        nodes.push_str("]}");
+        for id in 2..203 {
+            write!(
+                nodes,
+                r#",{{"id":{},"callFrame":{{"functionName":"handler{}","scriptId":"1","url":"file:///app/routes/handlers.js","lineNumber":{},"columnNumber":4}},"hitCount":1}}"#,
+                id, id, id
+            )
+            .unwrap();
+        }
+        let samples: Vec<_> = core::iter::repeat(2).take(200).chain(3..203).collect();
+        let json = alloc::format!(
+            r#"{{"nodes":[{}],"startTime":0,"endTime":400,"samples":{:?},"timeDeltas":{:?}}}"#,
+            nodes,
+            samples,
+            alloc::vec![1; samples.len()]
+        );
+        let profile: Profile = serde_json::from_str(&json).unwrap();
+
+        let sizes: Vec<_> = profile
+            .chunks(2)
+            .map(|chunk| serde_json::to_string(&chunk).unwrap().len())
+            .collect();
+        assert_eq!(sizes.len(), 2);
+        let (small, large) = (sizes[0].min(sizes[1]), sizes[0].max(sizes[1]));
+        assert!(large * 10 < small * 11, "{:?}", sizes);
+        let first = profile.chunks(2).next().unwrap();
+        assert!(first.samples().len() > 250);
+
+        let by_samples: Vec<_> = profile
+            .chunks_by_samples(2)
+            .map(|chunk| chunk.samples().len())
+            .collect();
+        assert_eq!(by_samples, [200, 200]);
+
+        // every sample once, in order
+        for chunk_num in 1..6 {
+            let chunks: Vec<_> = profile.chunks(chunk_num).collect();
+            assert!(chunks.len() <= chunk_num);
+            let total: usize = chunks.iter().map(|chunk| chunk.samples().len()).sum();
+            assert_eq!(total, profile.samples.len());
+        }
+        assert_eq!(profile.chunks(1000).count(), profile.samples.len());
+    }
+}
@@ -0,0 +1,219 @@
+//! Narrows a regression between two profiles down to the subtree of calls
+//! most responsible for it, see [`Profile::bisect`].
+
+use crate::{FrameKey, Profile};
+use alloc::vec::Vec;
+use core::time::Duration;
+use hashbrown::HashMap;
+
+/// Where the time a profile gained over its baseline went.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bisect<'raw> {
+    /// Sampled time of the baseline.
+    pub baseline: Duration,
+    /// Sampled time of the profile.
+    pub current: Duration,
+    /// The deepest subtree with most of the growth, the common ancestor of
+    /// the stacks that got slower. `None` if nothing got slower overall.
+    pub culprit: Option<Growth<'raw>>,
+    /// The subtrees right below the culprit that grew, most first.
+    pub children: Vec<Growth<'raw>>,
+    /// Functions by how much their self time grew, most first.
+    pub frames: Vec<(FrameKey<'raw>, Duration)>,
+}
+
+/// How much the time in a subtree grew, net of what shrank in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Growth<'raw> {
+    /// Frames from the root down to the top of the subtree.
+    pub stack: Vec<FrameKey<'raw>>,
+    pub growth: Duration,
+}
+
+impl Growth<'_> {
+    /// The share of `total`, 0 for no total.
+    #[must_use]
+    pub fn fraction(&self, total: Duration) -> f64 {
+        if total > Duration::default() {
+            self.growth.as_secs_f64() / total.as_secs_f64()
+        } else {
+            0.0
+        }
+    }
+}
+
+/// A subtree is narrowed to one of its children while that child has at
+/// least this share of its growth.
+const DOMINANT: f64 = 0.75;
+
+#[derive(Default)]
+struct Subtree<'raw> {
+    frame: Option<FrameKey<'raw>>,
+    parent: usize,
+    children: HashMap<FrameKey<'raw>, usize>,
+    baseline: Duration,
+    current: Duration,
+}
+
+impl Subtree<'_> {
+    fn growth(&self) -> Duration {
+        self.current.saturating_sub(self.baseline)
+    }
+}
+
+impl<'raw> Profile<'raw> {
+    /// Compares the stacks of the profile with those of `baseline` and
+    /// descends from the root into whichever subtree has most of the
+    /// growth, so a regression points at one call path instead of a flat
+    /// list of stacks.
+    ///
+    /// # Errors
+    ///
+    /// If a node of either profile has an invalid call frame.
+    pub fn bisect<'b>(&self, baseline: &Profile<'b>) -> Result<Bisect<'raw>, serde_json::Error>
+    where
+        'b: 'raw,
+    {
+        let mut tree: Vec<Subtree<'raw>> = alloc::vec![Subtree::default()];
+        for (stack, time) in baseline.stack_times()? {
+            let index = insert(&mut tree, stack);
+            add(&mut tree, index, |subtree| subtree.baseline += time);
+        }
+        for (stack, time) in self.stack_times()? {
+            let index = insert(&mut tree, stack);
+            add(&mut tree, index, |subtree| subtree.current += time);
+        }
+
+        let culprit = if tree[0].growth() > Duration::default() {
+            let mut index = 0;
+            loop {
+                let growth = tree[index].growth().as_secs_f64();
+                let dominant = tree[index]
+                    .children
+                    .values()
+                    .copied()
+                    .find(|&child| tree[child].growth().as_secs_f64() >= growth * DOMINANT);
+                match dominant {
+                    Some(child) => index = child,
+                    None => break,
+                }
+            }
+            Some(index)
+        } else {
+            None
+        };
+        let mut children: Vec<_> = culprit
+            .into_iter()
+            .flat_map(|index| tree[index].children.values().copied())
+            .filter(|&child| tree[child].growth() > Duration::default())
+            .map(|child| growth(&tree, child))
+            .collect();
+        children.sort_by(|a, b| b.growth.cmp(&a.growth).then_with(|| a.stack.cmp(&b.stack)));
+
+        let baseline_frames = baseline.frame_times()?;
+        let mut frames: Vec<_> = self
+            .frame_times()?
+            .into_iter()
+            .filter_map(|(frame, times)| {
+                let before = baseline_frames
+                    .get(&frame)
+                    .map_or(Duration::default(), |times| times.self_time);
+                let growth = times.self_time.saturating_sub(before);
+                if growth > Duration::default() {
+                    Some((frame, growth))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        frames
+            .sort_by(|(a, a_growth), (b, b_growth)| b_growth.cmp(a_growth).then_with(|| a.cmp(b)));
+
+        Ok(Bisect {
+            baseline: tree[0].baseline,
+            current: tree[0].current,
+            culprit: culprit.map(|index| growth(&tree, index)),
+            children,
+            frames,
+        })
+    }
+}
+
+/// The index of the subtree at the end of `stack`, adding any missing.
+fn insert<'raw>(tree: &mut Vec<Subtree<'raw>>, stack: Vec<FrameKey<'raw>>) -> usize {
+    let mut index = 0;
+    for frame in stack {
+        index = if let Some(&child) = tree[index].children.get(&frame) {
+            child
+        } else {
+            let child = tree.len();
+            tree[index].children.insert(frame.clone(), child);
+            tree.push(Subtree {
+                frame: Some(frame),
+                parent: index,
+                ..Subtree::default()
+            });
+            child
+        };
+    }
+    index
+}
+
+/// Applies `f` to a subtree and each of its ancestors.
+fn add<F: Fn(&mut Subtree)>(tree: &mut [Subtree], mut index: usize, f: F) {
+    loop {
+        f(&mut tree[index]);
+        if index == 0 {
+            break;
+        }
+        index = tree[index].parent;
+    }
+}
+
+fn growth<'raw>(tree: &[Subtree<'raw>], index: usize) -> Growth<'raw> {
+    let growth = tree[index].growth();
+    let mut stack = Vec::new();
+    let mut index = index;
+    while let Some(ref frame) = tree[index].frame {
+        stack.push(frame.clone());
+        index = tree[index].parent;
+    }
+    stack.reverse();
+    Growth { stack, growth }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ser::tests::PROFILE;
+    use crate::Profile;
+    use core::time::Duration;
+
+    #[test]
+    fn bisect() {
+        let profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        let same = profile.bisect(&profile).unwrap();
+        assert_eq!(same.culprit, None);
+        assert!(same.children.is_empty());
+        assert!(same.frames.is_empty());
+
+        // everything grew compared to an empty baseline, so the culprit is
+        // where the stacks that have most of the time split
+        let empty: Profile = serde_json::from_str(
+            r#"{"nodes":[],"startTime":0,"endTime":0,"samples":[],"timeDeltas":[]}"#,
+        )
+        .unwrap();
+        let bisect = profile.bisect(&empty).unwrap();
+        assert_eq!(bisect.baseline, Duration::default());
+        assert_eq!(bisect.current, profile.self_times().values().sum());
+        let culprit = bisect.culprit.unwrap();
+        assert_eq!(culprit.stack[0].function_name, "(root)");
+        assert!(culprit.growth.as_secs_f64() >= bisect.current.as_secs_f64() * 0.75);
+        let children: Duration = bisect.children.iter().map(|child| child.growth).sum();
+        assert!(children <= culprit.growth);
+        assert!(bisect
+            .children
+            .iter()
+            .all(|child| child.stack.len() == culprit.stack.len() + 1));
+        assert_eq!(bisect.frames[0].0.function_name, "WriteStream");
+    }
+}
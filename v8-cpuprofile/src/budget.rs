@@ -0,0 +1,171 @@
+use crate::{FrameKey, FrameTimes, Profile};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::time::Duration;
+use hashbrown::HashMap;
+
+/// Limits on the time of one function, matched by name and optionally url.
+///
+/// Deserializes from `function`, `url`, `max_self_ms`, `max_total_ms`,
+/// `max_self_percent` and `max_total_percent`, for example a TOML file with
+/// a `[[budget]]` table per function.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Budget {
+    pub function: String,
+    pub url: Option<String>,
+    pub max_self_time: Option<Duration>,
+    pub max_total_time: Option<Duration>,
+    /// Percent of the capture window.
+    pub max_self_percent: Option<f64>,
+    /// Percent of the capture window.
+    pub max_total_percent: Option<f64>,
+}
+
+/// A list of budgets under a `budget` key.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Budgets {
+    pub budget: Vec<Budget>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Metric {
+    SelfTime,
+    TotalTime,
+    SelfPercent,
+    TotalPercent,
+}
+
+/// A budget limit that was exceeded, see [`Profile::check_budgets`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation<'b> {
+    pub budget: &'b Budget,
+    pub metric: Metric,
+    /// Milliseconds for times, percent for percentages.
+    pub limit: f64,
+    pub actual: f64,
+}
+
+impl Budget {
+    /// Whether the budget applies to a function.
+    #[must_use]
+    pub fn matches(&self, frame: &FrameKey<'_>) -> bool {
+        if frame.function_name != self.function.as_str() {
+            return false;
+        }
+        match &self.url {
+            Some(url) => frame.url == url.as_str(),
+            None => true,
+        }
+    }
+}
+
+impl<'raw> Profile<'raw> {
+    /// Evaluates budgets against the summed time of all matching functions.
+    ///
+    /// # Errors
+    ///
+    /// If a node has an invalid call frame.
+    pub fn check_budgets<'b>(
+        &self,
+        budgets: &'b [Budget],
+    ) -> Result<Vec<Violation<'b>>, serde_json::Error> {
+        Ok(self.check_budgets_in(&self.frame_times()?, budgets))
+    }
+
+    /// Evaluates budgets against already computed frame times, such as
+    /// [`Profile::stitched_frame_times`].
+    #[must_use]
+    pub fn check_budgets_in<'b>(
+        &self,
+        frame_times: &HashMap<FrameKey<'raw>, FrameTimes>,
+        budgets: &'b [Budget],
+    ) -> Vec<Violation<'b>> {
+        let window = self.duration().as_secs_f64();
+        let percent = |time: Duration| {
+            if window > 0.0 {
+                time.as_secs_f64() / window * 100.0
+            } else {
+                0.0
+            }
+        };
+        let millis = |time: Duration| time.as_secs_f64() * 1000.0;
+
+        let mut violations = Vec::new();
+        for budget in budgets {
+            let mut self_time = Duration::default();
+            let mut total_time = Duration::default();
+            for (frame, times) in frame_times {
+                if budget.matches(frame) {
+                    self_time += times.self_time;
+                    total_time += times.total_time;
+                }
+            }
+
+            let checks = [
+                (
+                    Metric::SelfTime,
+                    budget.max_self_time.map(millis),
+                    millis(self_time),
+                ),
+                (
+                    Metric::TotalTime,
+                    budget.max_total_time.map(millis),
+                    millis(total_time),
+                ),
+                (
+                    Metric::SelfPercent,
+                    budget.max_self_percent,
+                    percent(self_time),
+                ),
+                (
+                    Metric::TotalPercent,
+                    budget.max_total_percent,
+                    percent(total_time),
+                ),
+            ];
+            for &(metric, limit, actual) in &checks {
+                if let Some(limit) = limit {
+                    if actual > limit {
+                        violations.push(Violation {
+                            budget,
+                            metric,
+                            limit,
+                            actual,
+                        });
+                    }
+                }
+            }
+        }
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Budget, Metric};
+    use crate::ser::tests::PROFILE;
+    use core::time::Duration;
+
+    #[test]
+    fn check_budgets() {
+        let profile: crate::Profile<'_> = serde_json::from_str(PROFILE).unwrap();
+        let budgets = [
+            Budget {
+                function: "WriteStream".into(),
+                max_self_time: Some(Duration::from_millis(5)),
+                max_total_percent: Some(50.0),
+                ..Budget::default()
+            },
+            Budget {
+                function: "(garbage collector)".into(),
+                max_self_percent: Some(5.0),
+                ..Budget::default()
+            },
+        ];
+        let violations = profile.check_budgets(&budgets).unwrap();
+
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].metric, Metric::SelfTime);
+        assert!(violations[0].actual > 9.0);
+    }
+}
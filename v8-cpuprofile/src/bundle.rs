@@ -0,0 +1,307 @@
+//! Time by bundle chunk and by route, for code splitting decisions, see
+//! [`Profile::bundle_report`].
+
+use crate::Profile;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::time::Duration;
+use hashbrown::HashMap;
+
+/// The files of each chunk of a bundle and the chunks each route loads.
+///
+/// It deserializes from the `stats.json` of webpack, where a chunk is named
+/// by its first name or else its id, and the routes are its named chunk
+/// groups and entrypoints.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ChunkMap {
+    chunks: Vec<String>,
+    /// File paths as the bundler wrote them, and their chunk.
+    files: Vec<(String, usize)>,
+    /// Route names and their chunks.
+    routes: Vec<(String, Vec<usize>)>,
+}
+
+impl ChunkMap {
+    #[must_use]
+    pub fn new() -> Self {
+        ChunkMap::default()
+    }
+
+    /// Adds a chunk and the files it was written to, paths relative to where
+    /// the bundle is served from.
+    pub fn add_chunk(&mut self, name: String, files: impl IntoIterator<Item = String>) {
+        let index = self.chunk_index(name);
+        self.files
+            .extend(files.into_iter().map(|file| (file, index)));
+    }
+
+    /// Adds a route that loads the named chunks, chunks not added yet are
+    /// added without files.
+    pub fn add_route(&mut self, name: String, chunks: impl IntoIterator<Item = String>) {
+        let chunks = chunks
+            .into_iter()
+            .map(|chunk| self.chunk_index(chunk))
+            .collect();
+        self.routes.push((name, chunks));
+    }
+
+    fn chunk_index(&mut self, name: String) -> usize {
+        if let Some(index) = self.chunks.iter().position(|chunk| *chunk == name) {
+            return index;
+        }
+        self.chunks.push(name);
+        self.chunks.len() - 1
+    }
+
+    /// The chunk of a script url, matched by the file its path ends with.
+    /// The query and fragment are ignored.
+    #[must_use]
+    pub fn chunk_of(&self, url: &str) -> Option<&str> {
+        let path = url.split(&['?', '#'][..]).next().unwrap_or(url);
+        self.files
+            .iter()
+            .filter(|(file, _)| {
+                let file = file.trim_start_matches("./");
+                path == file
+                    || path
+                        .strip_suffix(file)
+                        .is_some_and(|prefix| prefix.ends_with('/'))
+            })
+            // the longest path, in case a file name is in several directories
+            .max_by_key(|(file, _)| file.len())
+            .map(|&(_, index)| self.chunks[index].as_str())
+    }
+}
+
+/// Time spent in the code of each chunk and route of a bundle.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct BundleReport {
+    /// The most self time first, chunks without samples are left out.
+    pub chunks: Vec<ChunkCost>,
+    /// The most self time first.
+    pub routes: Vec<RouteCost>,
+    /// Self time of frames in no chunk, like node's and the browser's own
+    /// code, `(program)` and `(garbage collector)`.
+    pub unattributed: Duration,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ChunkCost {
+    pub name: String,
+    pub self_time: Duration,
+    /// Time with code of the chunk anywhere on the stack, counted once per
+    /// sample.
+    pub total_time: Duration,
+    /// The script urls matched to the chunk.
+    pub urls: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RouteCost {
+    pub name: String,
+    /// Self time of the code of its chunks. Chunks shared between routes
+    /// count toward each.
+    pub self_time: Duration,
+    pub chunks: Vec<String>,
+}
+
+impl Profile<'_> {
+    /// Attributes the time of each script to the chunk of `map` it was
+    /// loaded from, and the time of each chunk to the routes that load it.
+    ///
+    /// # Errors
+    ///
+    /// If a node has an invalid call frame.
+    pub fn bundle_report(&self, map: &ChunkMap) -> Result<BundleReport, serde_json::Error> {
+        let chunk_index: HashMap<&str, usize> = map
+            .chunks
+            .iter()
+            .enumerate()
+            .map(|(index, name)| (name.as_str(), index))
+            .collect();
+        let mut chunks: Vec<ChunkCost> = map
+            .chunks
+            .iter()
+            .map(|name| ChunkCost {
+                name: name.clone(),
+                ..ChunkCost::default()
+            })
+            .collect();
+
+        // chunk of each node by the node's index, looked up once per url
+        let mut by_url: HashMap<String, Option<usize>> = HashMap::new();
+        let mut node_chunks = Vec::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            let frame = node.frame()?;
+            let chunk = if let Some(&chunk) = by_url.get(&*frame.url) {
+                chunk
+            } else {
+                let chunk = map.chunk_of(&frame.url).map(|name| chunk_index[name]);
+                if let Some(index) = chunk {
+                    chunks[index].urls.push(String::from(&*frame.url));
+                }
+                by_url.insert(String::from(&*frame.url), chunk);
+                chunk
+            };
+            node_chunks.push(chunk);
+        }
+        let chunk = |node_id: u64| node_chunks[self.node_index[&node_id]];
+
+        let mut report = BundleReport::default();
+        let mut on_stack = Vec::new();
+        for (node_id, time) in self.self_times() {
+            match chunk(node_id) {
+                Some(index) => chunks[index].self_time += time,
+                None => report.unattributed += time,
+            }
+            on_stack.clear();
+            on_stack.extend(
+                core::iter::once(node_id)
+                    .chain(self.parent_ids_iter(node_id))
+                    .filter_map(chunk),
+            );
+            on_stack.sort_unstable();
+            on_stack.dedup();
+            for &index in &on_stack {
+                chunks[index].total_time += time;
+            }
+        }
+
+        report.routes = map
+            .routes
+            .iter()
+            .map(|(name, route_chunks)| RouteCost {
+                name: name.clone(),
+                self_time: route_chunks
+                    .iter()
+                    .map(|&index| chunks[index].self_time)
+                    .sum(),
+                chunks: route_chunks
+                    .iter()
+                    .map(|&index| chunks[index].name.clone())
+                    .collect(),
+            })
+            .collect();
+        report.routes.sort_by(|a, b| {
+            b.self_time
+                .cmp(&a.self_time)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        chunks.retain(|chunk| chunk.total_time > Duration::default());
+        chunks.sort_by(|a, b| {
+            b.self_time
+                .cmp(&a.self_time)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        for chunk in &mut chunks {
+            chunk.urls.sort();
+        }
+        report.chunks = chunks;
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ChunkMap;
+    use crate::Profile;
+    use alloc::string::ToString;
+    use core::time::Duration;
+    use serde_json::json;
+
+    const STATS: &str = r#"{
+        "version": "5.88.0",
+        "assets": [{"name": "main.1a2b.js", "chunks": [179]}],
+        "chunks": [
+            {"id": 179, "names": ["main"], "files": ["main.1a2b.js"], "size": 1000},
+            {"id": "src_pages_home_js", "names": [], "files": ["static/home.3c4d.js", "static/home.3c4d.css"]},
+            {"id": 42, "names": ["vendor"], "files": ["static/vendor.5e6f.js"]}
+        ],
+        "namedChunkGroups": {
+            "home": {"name": "home", "chunks": [179, "src_pages_home_js", 42], "assets": []},
+            "settings": {"name": "settings", "chunks": [179, 42]}
+        },
+        "entrypoints": {
+            "main": {"name": "main", "chunks": [179]}
+        },
+        "modules": [{"id": 1, "name": "./src/index.js"}]
+    }"#;
+
+    fn node(id: u64, url: &str, children: &[u64]) -> serde_json::Value {
+        json!({
+            "id": id,
+            "callFrame": {
+                "functionName": "f",
+                "scriptId": if url.is_empty() { "0" } else { "1" },
+                "url": url,
+                "lineNumber": id,
+                "columnNumber": 0,
+            },
+            "hitCount": 0,
+            "children": children,
+        })
+    }
+
+    #[test]
+    fn chunk_of() {
+        let map: ChunkMap = serde_json::from_str(STATS).unwrap();
+        assert_eq!(
+            map.chunk_of("https://cdn.example.com/main.1a2b.js"),
+            Some("main")
+        );
+        assert_eq!(
+            map.chunk_of("https://cdn.example.com/static/home.3c4d.js?v=1#x"),
+            Some("src_pages_home_js")
+        );
+        assert_eq!(map.chunk_of("static/vendor.5e6f.js"), Some("vendor"));
+        assert_eq!(map.chunk_of("https://cdn.example.com/xmain.1a2b.js"), None);
+        assert_eq!(map.chunk_of(""), None);
+    }
+
+    #[test]
+    fn bundle_report() {
+        let map: ChunkMap = serde_json::from_str(STATS).unwrap();
+        let json = json!({
+            "nodes": [
+                node(1, "", &[2]),
+                node(2, "https://app.example.com/main.1a2b.js", &[3, 4]),
+                node(3, "https://app.example.com/static/home.3c4d.js", &[5]),
+                node(4, "https://app.example.com/static/vendor.5e6f.js", &[]),
+                node(5, "https://app.example.com/static/vendor.5e6f.js", &[]),
+            ],
+            "startTime": 0,
+            "endTime": 10,
+            "samples": [2, 3, 3, 4, 5, 5, 5, 1],
+            "timeDeltas": [1, 1, 1, 1, 1, 1, 1, 1],
+        })
+        .to_string();
+        let profile: Profile = serde_json::from_str(&json).unwrap();
+        let report = profile.bundle_report(&map).unwrap();
+        let ms = Duration::from_millis;
+        let us = Duration::from_micros;
+
+        let names: alloc::vec::Vec<_> = report.chunks.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["vendor", "src_pages_home_js", "main"]);
+        let vendor = &report.chunks[0];
+        assert_eq!(vendor.self_time, us(4));
+        assert_eq!(vendor.total_time, us(4));
+        assert_eq!(
+            vendor.urls,
+            ["https://app.example.com/static/vendor.5e6f.js"]
+        );
+        let main = &report.chunks[2];
+        assert_eq!(main.self_time, us(1));
+        assert_eq!(main.total_time, us(7));
+        assert_eq!(report.chunks[1].total_time, us(5));
+        // the last sample lasts until endTime
+        assert_eq!(report.unattributed, us(2));
+
+        assert_eq!(report.routes[0].name, "home");
+        assert_eq!(report.routes[0].self_time, us(7));
+        assert_eq!(report.routes[1].name, "settings");
+        assert_eq!(report.routes[1].self_time, us(5));
+        assert_eq!(report.routes[2].name, "main");
+        assert_eq!(report.routes[2].chunks, ["main"]);
+        assert!(report.routes.iter().all(|route| route.self_time < ms(1)));
+    }
+}
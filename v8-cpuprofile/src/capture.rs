@@ -0,0 +1,88 @@
+use alloc::format;
+use alloc::string::String;
+
+/// What node encodes in the file names written by `--cpu-prof`,
+/// `CPU.<yyyymmdd>.<hhmmss>.<pid>.<tid>.<seq>.cpuprofile`.
+///
+/// Every thread of a process writes its own profile, the main thread has
+/// `tid` 0 and each worker the id of its `Worker`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CaptureName {
+    /// Local date the profile was written as `yyyymmdd`.
+    pub date: u32,
+    /// Local time the profile was written as `hhmmss`.
+    pub time: u32,
+    pub pid: u32,
+    pub tid: u32,
+    /// Counts up across all diagnostic files the process writes.
+    pub seq: u32,
+}
+
+impl CaptureName {
+    /// Parses a `--cpu-prof` file name, without any directory, returns
+    /// `None` for anything else.
+    #[must_use]
+    pub fn parse(file_name: &str) -> Option<Self> {
+        let mut parts = file_name
+            .strip_prefix("CPU.")?
+            .strip_suffix(".cpuprofile")?
+            .split('.');
+        let mut next = || -> Option<u32> { parts.next()?.parse().ok() };
+        let name = CaptureName {
+            date: next()?,
+            time: next()?,
+            pid: next()?,
+            tid: next()?,
+            seq: next()?,
+        };
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(name)
+    }
+
+    #[must_use]
+    pub fn is_main_thread(&self) -> bool {
+        self.tid == 0
+    }
+
+    /// `main`, or `worker <tid>` for a worker thread.
+    #[must_use]
+    pub fn thread_name(&self) -> String {
+        if self.is_main_thread() {
+            String::from("main")
+        } else {
+            format!("worker {}", self.tid)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CaptureName;
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            CaptureName::parse("CPU.20201017.103523.48210.1.003.cpuprofile"),
+            Some(CaptureName {
+                date: 20_201_017,
+                time: 103_523,
+                pid: 48210,
+                tid: 1,
+                seq: 3,
+            })
+        );
+        assert_eq!(CaptureName::parse("profile.cpuprofile"), None);
+        assert_eq!(
+            CaptureName::parse("CPU.20201017.103523.48210.1.cpuprofile"),
+            None
+        );
+        assert_eq!(
+            CaptureName::parse("CPU.20201017.103523.48210.1.003.4.cpuprofile"),
+            None
+        );
+        let name = CaptureName::parse("CPU.20201017.103523.48210.0.001.cpuprofile").unwrap();
+        assert_eq!(name.thread_name(), "main");
+    }
+}
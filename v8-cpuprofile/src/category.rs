@@ -0,0 +1,119 @@
+//! Coarse kinds of sampled time, like the breakdown in the Chrome
+//! performance panel's summary.
+
+use crate::{FrameKey, Profile};
+use alloc::vec::Vec;
+use core::fmt;
+use core::time::Duration;
+use hashbrown::HashMap;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Category {
+    /// Scripts of the application itself.
+    App,
+    /// Scripts under `node_modules`.
+    Dependencies,
+    /// Node's own scripts, `node:` urls.
+    Node,
+    /// Builtins and other functions without a script.
+    Native,
+    GarbageCollection,
+    /// Time in v8 outside of any script, such as compiling.
+    Program,
+    Idle,
+}
+
+impl Category {
+    #[must_use]
+    pub fn of(frame: &FrameKey) -> Self {
+        match frame.function_name.as_ref() {
+            "(idle)" => return Category::Idle,
+            "(program)" | "(root)" => return Category::Program,
+            "(garbage collector)" => return Category::GarbageCollection,
+            _ => {}
+        }
+        let url = frame.url.as_ref();
+        if url.is_empty() {
+            Category::Native
+        } else if url.starts_with("node:") || url.starts_with("internal/") {
+            Category::Node
+        } else if url.contains("/node_modules/") {
+            Category::Dependencies
+        } else {
+            Category::App
+        }
+    }
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Category::App => "app",
+            Category::Dependencies => "dependencies",
+            Category::Node => "node",
+            Category::Native => "native",
+            Category::GarbageCollection => "gc",
+            Category::Program => "program",
+            Category::Idle => "idle",
+        }
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Profile<'_> {
+    /// Self time summed by the category of each function, the largest
+    /// first. Categories without samples are left out.
+    ///
+    /// # Errors
+    ///
+    /// If a node has an invalid call frame.
+    pub fn category_times(&self) -> Result<Vec<(Category, Duration)>, serde_json::Error> {
+        let mut times: HashMap<Category, Duration> = HashMap::new();
+        for (frame, frame_times) in self.frame_times()? {
+            *times.entry(Category::of(&frame)).or_default() += frame_times.self_time;
+        }
+        let mut times: Vec<_> = times
+            .into_iter()
+            .filter(|(_, time)| *time > Duration::default())
+            .collect();
+        times.sort_unstable_by(|(a, a_time), (b, b_time)| b_time.cmp(a_time).then(a.cmp(b)));
+        Ok(times)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Category;
+    use crate::ser::tests::PROFILE;
+    use crate::{FrameKey, Profile};
+    use core::time::Duration;
+
+    #[test]
+    fn categories() {
+        let frame = |function_name: &'static str, url: &'static str| FrameKey {
+            function_name: function_name.into(),
+            url: url.into(),
+            line_number: 0,
+            column_number: 0,
+        };
+        assert_eq!(Category::of(&frame("(idle)", "")), Category::Idle);
+        assert_eq!(Category::of(&frame("map", "")), Category::Native);
+        assert_eq!(Category::of(&frame("a", "node:fs")), Category::Node);
+        assert_eq!(
+            Category::of(&frame("a", "file:///app/node_modules/x/index.js")),
+            Category::Dependencies
+        );
+        assert_eq!(Category::of(&frame("a", "file:///app/a.js")), Category::App);
+
+        let profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        let times = profile.category_times().unwrap();
+        let total: Duration = times.iter().map(|(_, time)| *time).sum();
+        assert_eq!(total, profile.self_times().values().sum());
+        assert_eq!(times[0].0, Category::Node);
+        assert!(times.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+    }
+}
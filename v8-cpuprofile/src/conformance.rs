@@ -0,0 +1,212 @@
+//! A golden file suite for a corpus of real profiles.
+//!
+//! Each `.cpuprofile` under a directory has to parse, serialize back to the
+//! same bytes and hold together as a tree, so integrators can check that
+//! their producers and this crate agree before relying on either.
+
+use crate::{ParseOptions, Profile, TimeDeltaPolicy, UnknownFields};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use hashbrown::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// One way a profile failed the suite.
+#[derive(Debug)]
+pub enum Failure {
+    Read(io::Error),
+    Parse(serde_json::Error),
+    Serialize(serde_json::Error),
+    /// The serialized profile first differs from the input at this byte.
+    Bytes {
+        offset: usize,
+    },
+    /// The profile parsed but doesn't hold together.
+    Invariant(String),
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Failure::Read(error) => write!(f, "read failed: {}", error),
+            Failure::Parse(error) => write!(f, "parse failed: {}", error),
+            Failure::Serialize(error) => write!(f, "serialize failed: {}", error),
+            Failure::Bytes { offset } => write!(f, "serialized differs at byte {}", offset),
+            Failure::Invariant(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for Failure {}
+
+/// The failures of one file, empty if it passed.
+#[derive(Debug)]
+pub struct Outcome {
+    pub path: PathBuf,
+    pub failures: Vec<Failure>,
+}
+
+impl Outcome {
+    #[must_use]
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// The options the suite parses with, which keep everything the format
+/// allows so a profile can be written back byte for byte.
+#[must_use]
+pub fn parse_options() -> ParseOptions {
+    ParseOptions {
+        preserve_sample_order: true,
+        time_deltas: TimeDeltaPolicy::KeepSigned,
+        unknown_fields: UnknownFields::Preserve,
+        ..ParseOptions::default()
+    }
+}
+
+/// Runs the suite on every `.cpuprofile` under `dir`, in path order.
+///
+/// # Errors
+///
+/// If the directory can't be listed. Files that can't be read are reported
+/// as failures.
+pub fn run(dir: &Path) -> io::Result<Vec<Outcome>> {
+    let mut paths = Vec::new();
+    find(dir, &mut paths)?;
+    paths.sort();
+    Ok(paths
+        .into_iter()
+        .map(|path| {
+            let failures = match fs::read(&path) {
+                Ok(json) => check(&json),
+                Err(error) => alloc::vec![Failure::Read(error)],
+            };
+            Outcome { path, failures }
+        })
+        .collect())
+}
+
+fn find(dir: &Path, paths: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            find(&path, paths)?;
+        } else if path.extension() == Some("cpuprofile".as_ref()) {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Runs the suite on one profile. Whitespace after the profile, such as a
+/// final newline, is ignored when comparing bytes.
+#[must_use]
+pub fn check(json: &[u8]) -> Vec<Failure> {
+    let profile = match Profile::parse_with(json, &parse_options()) {
+        Ok(profile) => profile,
+        Err(error) => return alloc::vec![Failure::Parse(error)],
+    };
+    let mut failures = Vec::new();
+    match serde_json::to_vec(&profile) {
+        Ok(written) => {
+            let end = json
+                .iter()
+                .rposition(|byte| !byte.is_ascii_whitespace())
+                .map_or(0, |last| last + 1);
+            let input = &json[..end];
+            if written != input {
+                let offset = written
+                    .iter()
+                    .zip(input)
+                    .position(|(a, b)| a != b)
+                    .unwrap_or_else(|| written.len().min(input.len()));
+                failures.push(Failure::Bytes { offset });
+            }
+        }
+        Err(error) => failures.push(Failure::Serialize(error)),
+    }
+    failures.extend(invariants(&profile).into_iter().map(Failure::Invariant));
+    failures
+}
+
+/// What is wrong with the shape of a profile: duplicate node ids, children
+/// that don't point back at their parent, other than one root, and samples
+/// of unknown nodes or after `endTime`.
+#[must_use]
+pub fn invariants(profile: &Profile) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut ids = HashSet::new();
+    for node in &profile.nodes {
+        if !ids.insert(node.id) {
+            problems.push(format!("duplicate node id {}", node.id));
+        }
+        for &child in node.children.iter().flatten() {
+            let parent_id = profile
+                .node_index
+                .get(&child)
+                .and_then(|&index| profile.nodes[index].parent_id);
+            if parent_id != Some(node.id) {
+                problems.push(format!("node {} is not the parent of {}", node.id, child));
+            }
+        }
+    }
+    let roots = profile
+        .nodes
+        .iter()
+        .filter(|node| node.parent_id.is_none())
+        .count();
+    if roots != 1 {
+        problems.push(format!("{} roots", roots));
+    }
+    let duration = profile.end_time.saturating_sub(profile.start_time);
+    for sample in &profile.samples {
+        if !ids.contains(&sample.node_id) {
+            problems.push(format!("sample of unknown node {}", sample.node_id));
+        }
+        if sample.ts > duration {
+            problems.push(format!("sample at {}us after endTime", sample.ts.0));
+        }
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, run, Failure};
+    use crate::ser::tests::PROFILE;
+    use std::path::Path;
+
+    #[test]
+    fn check_profiles() {
+        assert!(check(PROFILE.as_bytes()).is_empty());
+        assert!(check(alloc::format!("{}\n", PROFILE).as_bytes()).is_empty());
+
+        let spaced = PROFILE.replacen(',', ", ", 1);
+        let failures = check(spaced.as_bytes());
+        assert!(
+            matches!(failures[..], [Failure::Bytes { offset }] if offset == PROFILE.find(',').unwrap() + 1)
+        );
+
+        let two_roots = r#"{"nodes":[{"id":1,"callFrame":{},"hitCount":0},{"id":2,"callFrame":{},"hitCount":0}],"startTime":0,"endTime":5,"samples":[1,2],"timeDeltas":[1,10]}"#;
+        let problems: alloc::vec::Vec<_> = check(two_roots.as_bytes())
+            .iter()
+            .map(alloc::string::ToString::to_string)
+            .collect();
+        assert_eq!(problems, ["2 roots", "sample at 11us after endTime"]);
+
+        let unknown = two_roots.replace("[1,2]", "[1,3]");
+        let failures = check(unknown.as_bytes());
+        assert!(matches!(failures[..], [Failure::Parse(_)]));
+    }
+
+    #[test]
+    fn run_dir() {
+        let outcomes = run(&Path::new(env!("CARGO_MANIFEST_DIR")).join("tests")).unwrap();
+        assert!(!outcomes.is_empty());
+        assert!(outcomes.iter().all(|outcome| outcome.passed()));
+    }
+}
@@ -0,0 +1,125 @@
+use crate::Profile;
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+use core::time::Duration;
+use hashbrown::HashMap;
+
+/// The result of `Profiler.takePreciseCoverage`, which is also the format of
+/// the files node writes to `NODE_V8_COVERAGE`.
+#[derive(Debug)]
+pub struct Coverage<'raw> {
+    pub result: Vec<ScriptCoverage<'raw>>,
+}
+
+#[derive(Debug)]
+pub struct ScriptCoverage<'raw> {
+    pub script_id: Cow<'raw, str>,
+    pub url: Cow<'raw, str>,
+    pub functions: Vec<FunctionCoverage<'raw>>,
+}
+
+#[derive(Debug)]
+pub struct FunctionCoverage<'raw> {
+    pub function_name: Cow<'raw, str>,
+    /// The first range spans the whole function, block ranges follow.
+    pub ranges: Vec<CoverageRange>,
+    pub is_block_coverage: bool,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct CoverageRange {
+    pub start_offset: u32,
+    pub end_offset: u32,
+    pub count: u64,
+}
+
+impl FunctionCoverage<'_> {
+    /// The number of times the function was called.
+    #[must_use]
+    pub fn invocations(&self) -> u64 {
+        self.ranges.first().map_or(0, |range| range.count)
+    }
+
+    /// The number of blocks within the function that never ran.
+    #[must_use]
+    pub fn uncovered_blocks(&self) -> usize {
+        self.ranges
+            .iter()
+            .skip(1)
+            .filter(|range| range.count == 0)
+            .count()
+    }
+}
+
+/// A covered function that was sampled in a cpuprofile.
+#[derive(Debug)]
+pub struct HotFunction<'c, 'raw> {
+    pub script: &'c ScriptCoverage<'raw>,
+    pub function: &'c FunctionCoverage<'raw>,
+    pub self_time: Duration,
+}
+
+impl HotFunction<'_, '_> {
+    /// Hot but called at most `max_invocations` times, so each call is
+    /// expensive.
+    #[must_use]
+    pub fn is_rarely_covered(&self, max_invocations: u64) -> bool {
+        self.function.invocations() <= max_invocations
+    }
+
+    /// Hot but with blocks that never ran, dead code on a hot path.
+    #[must_use]
+    pub fn has_dead_blocks(&self) -> bool {
+        self.function.uncovered_blocks() > 0
+    }
+}
+
+impl<'raw> Coverage<'raw> {
+    /// Matches the sampled functions of a profile to their coverage, hottest
+    /// first.
+    ///
+    /// Coverage ranges are source offsets while call frames have lines, so
+    /// functions are matched by url and name. Anonymous functions can't be
+    /// told apart this way and are left out, as are functions that were
+    /// never sampled.
+    ///
+    /// # Errors
+    ///
+    /// If a node of the profile has an invalid call frame.
+    pub fn hot_functions<'c>(
+        &'c self,
+        profile: &Profile<'_>,
+    ) -> Result<Vec<HotFunction<'c, 'raw>>, serde_json::Error> {
+        let mut times: HashMap<(Cow<str>, Cow<str>), Duration> = HashMap::new();
+        let self_times = profile.self_times();
+        for node in &profile.nodes {
+            if let Some(&self_time) = self_times.get(&node.id) {
+                let frame = node.frame()?;
+                *times.entry((frame.url, frame.function_name)).or_default() += self_time;
+            }
+        }
+
+        let mut hot = Vec::new();
+        for script in &self.result {
+            for function in &script.functions {
+                if function.function_name.is_empty() {
+                    continue;
+                }
+                let key = (
+                    Cow::Borrowed(&*script.url),
+                    Cow::Borrowed(&*function.function_name),
+                );
+                if let Some(&self_time) = times.get(&key) {
+                    hot.push(HotFunction {
+                        script,
+                        function,
+                        self_time,
+                    });
+                }
+            }
+        }
+        hot.sort_by_key(|hot| Reverse(hot.self_time));
+        Ok(hot)
+    }
+}
@@ -0,0 +1,267 @@
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::time::Duration;
+use serde::de::Error;
+use serde::de::MapAccess;
+use serde::de::Visitor;
+
+use super::util::CowStr;
+use crate::aggregate::{FrameStats, ProfileSet, Stats};
+use crate::FrameKey;
+
+pub(super) fn profile_set<'de: 'raw, 'raw>() -> impl Visitor<'de, Value = ProfileSet<'raw>> {
+    ProfileSetVisitor(PhantomData)
+}
+
+pub(super) fn frame_stats<'de: 'raw, 'raw>() -> impl Visitor<'de, Value = FrameStats<'raw>> {
+    FrameStatsVisitor(PhantomData)
+}
+
+pub(super) fn frame_key<'de: 'raw, 'raw>() -> impl Visitor<'de, Value = FrameKey<'raw>> {
+    FrameKeyVisitor(PhantomData)
+}
+
+pub(super) fn stats<'de>() -> impl Visitor<'de, Value = Stats> {
+    StatsVisitor
+}
+
+const PROFILE_SET_FIELDS: &[&str] = &["runs", "samplingInterval", "frames"];
+
+struct ProfileSetVisitor<'raw>(PhantomData<fn() -> ProfileSet<'raw>>);
+
+impl<'de: 'raw, 'raw> Visitor<'de> for ProfileSetVisitor<'raw> {
+    type Value = ProfileSet<'raw>;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str("aggregate json object")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut runs = None;
+        let mut sampling_interval: Option<u64> = None;
+        let mut frames: Option<Vec<FrameStats>> = None;
+
+        while let Some(key) = access.next_key::<CowStr>()? {
+            match &*key.0 {
+                "runs" => {
+                    runs = access.next_value()?;
+                }
+                "samplingInterval" => {
+                    sampling_interval = access.next_value()?;
+                }
+                "frames" => {
+                    frames = access.next_value()?;
+                }
+                key => {
+                    return Err(M::Error::unknown_field(key, PROFILE_SET_FIELDS));
+                }
+            }
+        }
+
+        let runs = check_missing!(M::Error, runs);
+        let frames = check_missing!(M::Error, frames);
+        if let Some(frame) = frames.iter().find(|frame| frame.self_times.len() != runs) {
+            return Err(M::Error::invalid_length(
+                frame.self_times.len(),
+                &"a self time per run",
+            ));
+        }
+
+        Ok(ProfileSet {
+            runs,
+            sampling_interval: sampling_interval.map(Duration::from_micros),
+            frames,
+        })
+    }
+}
+
+const FRAME_STATS_FIELDS: &[&str] = &["callFrame", "selfTime", "totalTime", "selfTimes"];
+
+struct FrameStatsVisitor<'raw>(PhantomData<fn() -> FrameStats<'raw>>);
+
+impl<'de: 'raw, 'raw> Visitor<'de> for FrameStatsVisitor<'raw> {
+    type Value = FrameStats<'raw>;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str("aggregate frame json object")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut frame: Option<FrameKey> = None;
+        let mut self_time = None;
+        let mut total_time = None;
+        let mut self_times: Option<Vec<u64>> = None;
+
+        while let Some(key) = access.next_key::<CowStr>()? {
+            match &*key.0 {
+                "callFrame" => {
+                    frame = access.next_value()?;
+                }
+                "selfTime" => {
+                    self_time = access.next_value()?;
+                }
+                "totalTime" => {
+                    total_time = access.next_value()?;
+                }
+                "selfTimes" => {
+                    self_times = access.next_value()?;
+                }
+                key => {
+                    return Err(M::Error::unknown_field(key, FRAME_STATS_FIELDS));
+                }
+            }
+        }
+
+        let frame = check_missing!(M::Error, frame, "callFrame");
+        let self_time = check_missing!(M::Error, self_time, "selfTime");
+        let total_time = check_missing!(M::Error, total_time, "totalTime");
+        let self_times = check_missing!(M::Error, self_times, "selfTimes");
+
+        Ok(FrameStats {
+            frame,
+            self_time,
+            total_time,
+            self_times: self_times.into_iter().map(Duration::from_micros).collect(),
+        })
+    }
+}
+
+const FRAME_KEY_FIELDS: &[&str] = &["functionName", "url", "lineNumber", "columnNumber"];
+
+struct FrameKeyVisitor<'raw>(PhantomData<fn() -> FrameKey<'raw>>);
+
+impl<'de: 'raw, 'raw> Visitor<'de> for FrameKeyVisitor<'raw> {
+    type Value = FrameKey<'raw>;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str("call frame json object")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut function_name: Option<CowStr> = None;
+        let mut url: Option<CowStr> = None;
+        let mut line_number = None;
+        let mut column_number = None;
+
+        while let Some(key) = access.next_key::<CowStr>()? {
+            match &*key.0 {
+                "functionName" => {
+                    function_name = access.next_value()?;
+                }
+                "url" => {
+                    url = access.next_value()?;
+                }
+                "lineNumber" => {
+                    line_number = access.next_value()?;
+                }
+                "columnNumber" => {
+                    column_number = access.next_value()?;
+                }
+                key => {
+                    return Err(M::Error::unknown_field(key, FRAME_KEY_FIELDS));
+                }
+            }
+        }
+
+        let function_name = check_missing!(M::Error, function_name, "functionName");
+        let url = check_missing!(M::Error, url);
+        let line_number = check_missing!(M::Error, line_number, "lineNumber");
+        let column_number = check_missing!(M::Error, column_number, "columnNumber");
+
+        Ok(FrameKey {
+            function_name: function_name.0,
+            url: url.0,
+            line_number,
+            column_number,
+        })
+    }
+}
+
+const STATS_FIELDS: &[&str] = &[
+    "mean", "stddev", "min", "p50", "p90", "p99", "max", "margin",
+];
+
+struct StatsVisitor;
+
+impl<'de> Visitor<'de> for StatsVisitor {
+    type Value = Stats;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str("time stats json object")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut mean: Option<u64> = None;
+        let mut stddev: Option<u64> = None;
+        let mut min: Option<u64> = None;
+        let mut p50: Option<u64> = None;
+        let mut p90: Option<u64> = None;
+        let mut p99: Option<u64> = None;
+        let mut max: Option<u64> = None;
+        let mut margin: Option<u64> = None;
+
+        // microseconds like the cpuprofile itself
+        while let Some(key) = access.next_key::<CowStr>()? {
+            match &*key.0 {
+                "mean" => {
+                    mean = access.next_value()?;
+                }
+                "stddev" => {
+                    stddev = access.next_value()?;
+                }
+                "min" => {
+                    min = access.next_value()?;
+                }
+                "p50" => {
+                    p50 = access.next_value()?;
+                }
+                "p90" => {
+                    p90 = access.next_value()?;
+                }
+                "p99" => {
+                    p99 = access.next_value()?;
+                }
+                "max" => {
+                    max = access.next_value()?;
+                }
+                "margin" => {
+                    margin = access.next_value()?;
+                }
+                key => {
+                    return Err(M::Error::unknown_field(key, STATS_FIELDS));
+                }
+            }
+        }
+
+        let mean = check_missing!(M::Error, mean);
+        let stddev = check_missing!(M::Error, stddev);
+        let min = check_missing!(M::Error, min);
+        let p50 = check_missing!(M::Error, p50);
+        let p90 = check_missing!(M::Error, p90);
+        let p99 = check_missing!(M::Error, p99);
+        let max = check_missing!(M::Error, max);
+
+        Ok(Stats {
+            mean: Duration::from_micros(mean),
+            stddev: Duration::from_micros(stddev),
+            min: Duration::from_micros(min),
+            p50: Duration::from_micros(p50),
+            p90: Duration::from_micros(p90),
+            p99: Duration::from_micros(p99),
+            max: Duration::from_micros(max),
+            margin: margin.map(Duration::from_micros),
+        })
+    }
+}
@@ -0,0 +1,116 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::time::Duration;
+use serde::de::Error;
+use serde::de::MapAccess;
+use serde::de::Visitor;
+
+use super::util::CowStr;
+use crate::budget::{Budget, Budgets};
+
+pub(super) fn budgets<'de>() -> impl Visitor<'de, Value = Budgets> {
+    BudgetsVisitor
+}
+
+pub(super) fn budget<'de>() -> impl Visitor<'de, Value = Budget> {
+    BudgetVisitor
+}
+
+const BUDGETS_FIELDS: &[&str] = &["budget"];
+
+struct BudgetsVisitor;
+
+impl<'de> Visitor<'de> for BudgetsVisitor {
+    type Value = Budgets;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str("a list of budgets")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut budget: Option<Vec<Budget>> = None;
+
+        // config formats don't always hand out borrowed keys
+        while let Some(key) = access.next_key::<CowStr>()? {
+            match &*key.0 {
+                "budget" => {
+                    budget = access.next_value()?;
+                }
+                key => {
+                    return Err(M::Error::unknown_field(key, BUDGETS_FIELDS));
+                }
+            }
+        }
+
+        Ok(Budgets {
+            budget: budget.unwrap_or_default(),
+        })
+    }
+}
+
+const BUDGET_FIELDS: &[&str] = &[
+    "function",
+    "url",
+    "max_self_ms",
+    "max_total_ms",
+    "max_self_percent",
+    "max_total_percent",
+];
+
+struct BudgetVisitor;
+
+impl<'de> Visitor<'de> for BudgetVisitor {
+    type Value = Budget;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str("a budget")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut function: Option<String> = None;
+        let mut budget = Budget::default();
+
+        while let Some(key) = access.next_key::<CowStr>()? {
+            match &*key.0 {
+                "function" => {
+                    function = access.next_value()?;
+                }
+                "url" => {
+                    budget.url = access.next_value()?;
+                }
+                "max_self_ms" => {
+                    budget.max_self_time = Some(millis::<M::Error>(access.next_value()?)?);
+                }
+                "max_total_ms" => {
+                    budget.max_total_time = Some(millis::<M::Error>(access.next_value()?)?);
+                }
+                "max_self_percent" => {
+                    budget.max_self_percent = access.next_value()?;
+                }
+                "max_total_percent" => {
+                    budget.max_total_percent = access.next_value()?;
+                }
+                key => {
+                    return Err(M::Error::unknown_field(key, BUDGET_FIELDS));
+                }
+            }
+        }
+
+        budget.function = check_missing!(M::Error, function);
+        Ok(budget)
+    }
+}
+
+fn millis<E: Error>(ms: f64) -> Result<Duration, E> {
+    if ms.is_finite() && ms >= 0.0 {
+        Ok(Duration::from_secs_f64(ms / 1000.0))
+    } else {
+        Err(E::custom("budget times must be positive milliseconds"))
+    }
+}
@@ -0,0 +1,230 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use serde::de::IgnoredAny;
+use serde::de::MapAccess;
+use serde::de::Visitor;
+use serde::Deserialize;
+use serde::Deserializer;
+
+use super::util::CowStr;
+use crate::bundle::ChunkMap;
+
+pub(super) fn webpack_stats<'de>() -> impl Visitor<'de, Value = ChunkMap> {
+    StatsVisitor
+}
+
+struct StatsVisitor;
+
+impl<'de> Visitor<'de> for StatsVisitor {
+    type Value = ChunkMap;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str("webpack stats json")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut chunks: Vec<Chunk> = Vec::new();
+        let mut named_chunk_groups: Vec<(String, ChunkGroup)> = Vec::new();
+        let mut entrypoints: Vec<(String, ChunkGroup)> = Vec::new();
+
+        while let Some(key) = access.next_key::<CowStr>()? {
+            match &*key.0 {
+                "chunks" => {
+                    chunks = access.next_value()?;
+                }
+                "namedChunkGroups" => {
+                    named_chunk_groups = access.next_value::<Groups>()?.0;
+                }
+                "entrypoints" => {
+                    entrypoints = access.next_value::<Groups>()?.0;
+                }
+                // modules, assets and the rest of a stats file
+                _ => {
+                    access.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        let name = |id: &str| {
+            chunks
+                .iter()
+                .find(|chunk| chunk.id == id)
+                .and_then(|chunk| chunk.names.first())
+                .map_or_else(|| id.to_string(), String::clone)
+        };
+        let mut map = ChunkMap::new();
+        for chunk in &chunks {
+            map.add_chunk(name(&chunk.id), chunk.files.iter().cloned());
+        }
+        // webpack 5 lists the entrypoints in the named chunk groups too
+        for (route, group) in named_chunk_groups.iter().chain(
+            entrypoints
+                .iter()
+                .filter(|(route, _)| named_chunk_groups.iter().all(|(named, _)| named != route)),
+        ) {
+            map.add_route(route.clone(), group.chunks.iter().map(|id| name(id)));
+        }
+        Ok(map)
+    }
+}
+
+#[derive(Default)]
+struct Chunk {
+    id: String,
+    names: Vec<String>,
+    files: Vec<String>,
+}
+
+impl<'de> Deserialize<'de> for Chunk {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(ChunkVisitor)
+    }
+}
+
+struct ChunkVisitor;
+
+impl<'de> Visitor<'de> for ChunkVisitor {
+    type Value = Chunk;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str("a webpack chunk")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut chunk = Chunk::default();
+        while let Some(key) = access.next_key::<CowStr>()? {
+            match &*key.0 {
+                "id" => {
+                    chunk.id = access.next_value::<ChunkId>()?.0;
+                }
+                "names" => {
+                    chunk.names = access.next_value()?;
+                }
+                "files" => {
+                    chunk.files = access.next_value()?;
+                }
+                _ => {
+                    access.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(chunk)
+    }
+}
+
+/// Named chunk groups or entrypoints, by name.
+struct Groups(Vec<(String, ChunkGroup)>);
+
+impl<'de> Deserialize<'de> for Groups {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(GroupsVisitor)
+    }
+}
+
+struct GroupsVisitor;
+
+impl<'de> Visitor<'de> for GroupsVisitor {
+    type Value = Groups;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str("webpack chunk groups by name")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut groups = Vec::new();
+        while let Some((name, group)) = access.next_entry::<String, ChunkGroup>()? {
+            groups.push((name, group));
+        }
+        Ok(Groups(groups))
+    }
+}
+
+struct ChunkGroup {
+    chunks: Vec<String>,
+}
+
+impl<'de> Deserialize<'de> for ChunkGroup {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(ChunkGroupVisitor)
+    }
+}
+
+struct ChunkGroupVisitor;
+
+impl<'de> Visitor<'de> for ChunkGroupVisitor {
+    type Value = ChunkGroup;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str("a webpack chunk group")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut chunks = Vec::new();
+        while let Some(key) = access.next_key::<CowStr>()? {
+            match &*key.0 {
+                "chunks" => {
+                    chunks = access
+                        .next_value::<Vec<ChunkId>>()?
+                        .into_iter()
+                        .map(|id| id.0)
+                        .collect();
+                }
+                _ => {
+                    access.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(ChunkGroup { chunks })
+    }
+}
+
+/// A chunk id, a number or a string depending on the webpack config.
+struct ChunkId(String);
+
+impl<'de> Deserialize<'de> for ChunkId {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(ChunkIdVisitor)
+    }
+}
+
+struct ChunkIdVisitor;
+
+impl Visitor<'_> for ChunkIdVisitor {
+    type Value = ChunkId;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str("a chunk id")
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(ChunkId(v.to_string()))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(ChunkId(String::from(v)))
+    }
+}
@@ -0,0 +1,204 @@
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use serde::de::Error;
+use serde::de::IgnoredAny;
+use serde::de::MapAccess;
+use serde::de::Visitor;
+
+use super::util::CowStr;
+use crate::coverage::{Coverage, CoverageRange, FunctionCoverage, ScriptCoverage};
+
+pub(super) fn coverage<'de: 'raw, 'raw>() -> impl Visitor<'de, Value = Coverage<'raw>> {
+    CoverageVisitor(PhantomData)
+}
+
+pub(super) fn script_coverage<'de: 'raw, 'raw>() -> impl Visitor<'de, Value = ScriptCoverage<'raw>>
+{
+    ScriptCoverageVisitor(PhantomData)
+}
+
+pub(super) fn function_coverage<'de: 'raw, 'raw>(
+) -> impl Visitor<'de, Value = FunctionCoverage<'raw>> {
+    FunctionCoverageVisitor(PhantomData)
+}
+
+pub(super) fn coverage_range<'de>() -> impl Visitor<'de, Value = CoverageRange> {
+    CoverageRangeVisitor
+}
+
+struct CoverageVisitor<'raw>(PhantomData<fn() -> Coverage<'raw>>);
+
+impl<'de: 'raw, 'raw> Visitor<'de> for CoverageVisitor<'raw> {
+    type Value = Coverage<'raw>;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str("v8 precise coverage json object")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut result = None;
+
+        // node adds `timestamp` and `source-map-cache` next to the result
+        while let Some(key) = access.next_key::<CowStr>()? {
+            match &*key.0 {
+                "result" => {
+                    result = access.next_value()?;
+                }
+                _ => {
+                    access.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        let result = check_missing!(M::Error, result);
+
+        Ok(Coverage { result })
+    }
+}
+
+const SCRIPT_COVERAGE_FIELDS: &[&str] = &["scriptId", "url", "functions"];
+
+struct ScriptCoverageVisitor<'raw>(PhantomData<fn() -> ScriptCoverage<'raw>>);
+
+impl<'de: 'raw, 'raw> Visitor<'de> for ScriptCoverageVisitor<'raw> {
+    type Value = ScriptCoverage<'raw>;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str("v8 script coverage json object")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut script_id: Option<CowStr> = None;
+        let mut url: Option<CowStr> = None;
+        let mut functions: Option<Vec<FunctionCoverage>> = None;
+
+        while let Some(key) = access.next_key::<CowStr>()? {
+            match &*key.0 {
+                "scriptId" => {
+                    script_id = access.next_value()?;
+                }
+                "url" => {
+                    url = access.next_value()?;
+                }
+                "functions" => {
+                    functions = access.next_value()?;
+                }
+                key => {
+                    return Err(M::Error::unknown_field(key, SCRIPT_COVERAGE_FIELDS));
+                }
+            }
+        }
+
+        let script_id = check_missing!(M::Error, script_id, "scriptId");
+        let url = check_missing!(M::Error, url);
+        let functions = check_missing!(M::Error, functions);
+
+        Ok(ScriptCoverage {
+            script_id: script_id.0,
+            url: url.0,
+            functions,
+        })
+    }
+}
+
+const FUNCTION_COVERAGE_FIELDS: &[&str] = &["functionName", "ranges", "isBlockCoverage"];
+
+struct FunctionCoverageVisitor<'raw>(PhantomData<fn() -> FunctionCoverage<'raw>>);
+
+impl<'de: 'raw, 'raw> Visitor<'de> for FunctionCoverageVisitor<'raw> {
+    type Value = FunctionCoverage<'raw>;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str("v8 function coverage json object")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut function_name: Option<CowStr> = None;
+        let mut ranges = None;
+        let mut is_block_coverage = None;
+
+        while let Some(key) = access.next_key::<CowStr>()? {
+            match &*key.0 {
+                "functionName" => {
+                    function_name = access.next_value()?;
+                }
+                "ranges" => {
+                    ranges = access.next_value()?;
+                }
+                "isBlockCoverage" => {
+                    is_block_coverage = access.next_value()?;
+                }
+                key => {
+                    return Err(M::Error::unknown_field(key, FUNCTION_COVERAGE_FIELDS));
+                }
+            }
+        }
+
+        let function_name = check_missing!(M::Error, function_name, "functionName");
+        let ranges = check_missing!(M::Error, ranges);
+        let is_block_coverage = check_missing!(M::Error, is_block_coverage, "isBlockCoverage");
+
+        Ok(FunctionCoverage {
+            function_name: function_name.0,
+            ranges,
+            is_block_coverage,
+        })
+    }
+}
+
+const COVERAGE_RANGE_FIELDS: &[&str] = &["startOffset", "endOffset", "count"];
+
+struct CoverageRangeVisitor;
+
+impl<'de> Visitor<'de> for CoverageRangeVisitor {
+    type Value = CoverageRange;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str("v8 coverage range json object")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut start_offset = None;
+        let mut end_offset = None;
+        let mut count = None;
+
+        while let Some(key) = access.next_key::<CowStr>()? {
+            match &*key.0 {
+                "startOffset" => {
+                    start_offset = access.next_value()?;
+                }
+                "endOffset" => {
+                    end_offset = access.next_value()?;
+                }
+                "count" => {
+                    count = access.next_value()?;
+                }
+                key => {
+                    return Err(M::Error::unknown_field(key, COVERAGE_RANGE_FIELDS));
+                }
+            }
+        }
+
+        let start_offset = check_missing!(M::Error, start_offset, "startOffset");
+        let end_offset = check_missing!(M::Error, end_offset, "endOffset");
+        let count = check_missing!(M::Error, count);
+
+        Ok(CoverageRange {
+            start_offset,
+            end_offset,
+            count,
+        })
+    }
+}
@@ -0,0 +1,165 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::time::Duration;
+use serde::de::Error;
+use serde::de::IgnoredAny;
+use serde::de::MapAccess;
+use serde::de::Visitor;
+use serde::Deserialize;
+use serde::Deserializer;
+
+use super::util::{CowStr, Time};
+use crate::etw::{EtwCapture, EtwSample};
+
+pub(super) fn capture<'de>() -> impl Visitor<'de, Value = EtwCapture> {
+    CaptureVisitor
+}
+
+pub(super) fn sample<'de>() -> impl Visitor<'de, Value = EtwSample> {
+    SampleVisitor
+}
+
+struct CaptureVisitor;
+
+impl<'de> Visitor<'de> for CaptureVisitor {
+    type Value = EtwCapture;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str("etw cpu samples")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut samples = None;
+        let mut capture = EtwCapture::default();
+        while let Some(key) = access.next_key::<CowStr>()? {
+            match &*key.0 {
+                "samples" => {
+                    samples = Some(access.next_value()?);
+                }
+                "interval_us" => {
+                    let Time(interval, _) = access.next_value()?;
+                    capture.interval = Some(interval.as_duration());
+                }
+                "threads" => {
+                    capture.thread_names = access
+                        .next_value::<Vec<Thread>>()?
+                        .into_iter()
+                        .map(|thread| (thread.pid, thread.tid, thread.name))
+                        .collect();
+                }
+                // whatever else the export kept
+                _ => {
+                    access.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        capture.samples = check_missing!(M::Error, samples);
+        if capture.interval == Some(Duration::ZERO) {
+            return Err(M::Error::custom("interval_us must be positive"));
+        }
+        Ok(capture)
+    }
+}
+
+struct SampleVisitor;
+
+impl<'de> Visitor<'de> for SampleVisitor {
+    type Value = EtwSample;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str("an etw cpu sample")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut timestamp = None;
+        let mut pid = None;
+        let mut tid = None;
+        let mut stack = None;
+        while let Some(key) = access.next_key::<CowStr>()? {
+            match &*key.0 {
+                "timestamp" => {
+                    timestamp = Some(access.next_value::<Time>()?.0);
+                }
+                "pid" => {
+                    pid = Some(access.next_value()?);
+                }
+                "tid" => {
+                    tid = Some(access.next_value()?);
+                }
+                "stack" => {
+                    stack = Some(access.next_value()?);
+                }
+                _ => {
+                    access.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(EtwSample {
+            timestamp: check_missing!(M::Error, timestamp),
+            pid: check_missing!(M::Error, pid),
+            tid: check_missing!(M::Error, tid),
+            stack: check_missing!(M::Error, stack),
+        })
+    }
+}
+
+struct Thread {
+    pid: u32,
+    tid: u32,
+    name: String,
+}
+
+impl<'de> Deserialize<'de> for Thread {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(ThreadVisitor)
+    }
+}
+
+struct ThreadVisitor;
+
+impl<'de> Visitor<'de> for ThreadVisitor {
+    type Value = Thread;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str("an etw thread")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut pid = None;
+        let mut tid = None;
+        let mut name = None;
+        while let Some(key) = access.next_key::<CowStr>()? {
+            match &*key.0 {
+                "pid" => {
+                    pid = Some(access.next_value()?);
+                }
+                "tid" => {
+                    tid = Some(access.next_value()?);
+                }
+                "name" => {
+                    name = Some(access.next_value()?);
+                }
+                _ => {
+                    access.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(Thread {
+            pid: check_missing!(M::Error, pid),
+            tid: check_missing!(M::Error, tid),
+            name: check_missing!(M::Error, name),
+        })
+    }
+}
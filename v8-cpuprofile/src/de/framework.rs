@@ -0,0 +1,108 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use serde::de::Error;
+use serde::de::MapAccess;
+use serde::de::Visitor;
+
+use super::util::CowStr;
+use crate::framework::{Rule, Rules};
+
+pub(super) fn rules<'de>() -> impl Visitor<'de, Value = Rules> {
+    RulesVisitor
+}
+
+pub(super) fn rule<'de>() -> impl Visitor<'de, Value = Rule> {
+    RuleVisitor
+}
+
+const RULES_FIELDS: &[&str] = &["presets", "rule"];
+
+struct RulesVisitor;
+
+impl<'de> Visitor<'de> for RulesVisitor {
+    type Value = Rules;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str("a list of rules")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut presets: Option<Vec<String>> = None;
+        let mut rule: Option<Vec<Rule>> = None;
+
+        // config formats don't always hand out borrowed keys
+        while let Some(key) = access.next_key::<CowStr>()? {
+            match &*key.0 {
+                "presets" => {
+                    presets = access.next_value()?;
+                }
+                "rule" => {
+                    rule = access.next_value()?;
+                }
+                key => {
+                    return Err(M::Error::unknown_field(key, RULES_FIELDS));
+                }
+            }
+        }
+
+        let mut rules = Rules {
+            rule: rule.unwrap_or_default(),
+        };
+        for name in presets.unwrap_or_default() {
+            match Rules::preset(&name) {
+                Some(preset) => rules.rule.extend(preset.rule),
+                None => return Err(M::Error::unknown_variant(&name, Rules::PRESETS)),
+            }
+        }
+        Ok(rules)
+    }
+}
+
+const RULE_FIELDS: &[&str] = &["category", "url", "functions", "inherit"];
+
+struct RuleVisitor;
+
+impl<'de> Visitor<'de> for RuleVisitor {
+    type Value = Rule;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str("a rule")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut category: Option<String> = None;
+        let mut rule = Rule::default();
+
+        while let Some(key) = access.next_key::<CowStr>()? {
+            match &*key.0 {
+                "category" => {
+                    category = access.next_value()?;
+                }
+                "url" => {
+                    rule.url = access.next_value()?;
+                }
+                "functions" => {
+                    rule.functions = access.next_value()?;
+                }
+                "inherit" => {
+                    rule.inherit = access.next_value()?;
+                }
+                key => {
+                    return Err(M::Error::unknown_field(key, RULE_FIELDS));
+                }
+            }
+        }
+
+        rule.category = check_missing!(M::Error, category);
+        if rule.url.is_none() && rule.functions.is_empty() {
+            return Err(M::Error::custom("a rule needs a url or functions"));
+        }
+        Ok(rule)
+    }
+}
@@ -0,0 +1,307 @@
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::marker::PhantomData;
+use serde::de::DeserializeSeed;
+use serde::de::Error;
+use serde::de::IgnoredAny;
+use serde::de::MapAccess;
+use serde::de::SeqAccess;
+use serde::de::Visitor;
+use serde::Deserializer;
+
+use super::util::CowStr;
+use crate::heap::{AllocationTrace, TraceFunction, TraceNode};
+
+/// The fields of a trace function info that are kept, in the order V8
+/// writes them when the snapshot's `meta` doesn't say.
+const TRACE_FUNCTION_INFO_FIELDS: [&str; 6] = [
+    "function_id",
+    "name",
+    "script_name",
+    "script_id",
+    "line",
+    "column",
+];
+
+/// The fields of a trace node, likewise.
+const TRACE_NODE_FIELDS: [&str; 5] = ["id", "function_info_index", "count", "size", "children"];
+
+/// Where the fields sit in the flat trace arrays, read from
+/// `snapshot.meta` like the node and edge fields of a snapshot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Layout {
+    /// Positions of [`TRACE_FUNCTION_INFO_FIELDS`].
+    function_info: [usize; 6],
+    function_info_len: usize,
+    /// Positions of [`TRACE_NODE_FIELDS`].
+    node: [usize; 5],
+    node_len: usize,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout {
+            function_info: [0, 1, 2, 3, 4, 5],
+            function_info_len: TRACE_FUNCTION_INFO_FIELDS.len(),
+            node: [0, 1, 2, 3, 4],
+            node_len: TRACE_NODE_FIELDS.len(),
+        }
+    }
+}
+
+/// The position in `fields` of each of `names`.
+fn positions<E: Error>(
+    fields: &[CowStr],
+    names: &[&str],
+    positions: &mut [usize],
+) -> Result<(), E> {
+    for (name, position) in names.iter().zip(positions) {
+        *position = fields
+            .iter()
+            .position(|field| field.0 == *name)
+            .ok_or_else(|| {
+                E::custom(alloc::format!(
+                    "snapshot meta lacks the trace field {}",
+                    name
+                ))
+            })?;
+    }
+    Ok(())
+}
+
+pub(super) fn allocation_trace<'de: 'raw, 'raw>() -> impl Visitor<'de, Value = AllocationTrace<'raw>>
+{
+    AllocationTraceVisitor(PhantomData)
+}
+
+struct AllocationTraceVisitor<'raw>(PhantomData<fn() -> AllocationTrace<'raw>>);
+
+impl<'de: 'raw, 'raw> Visitor<'de> for AllocationTraceVisitor<'raw> {
+    type Value = AllocationTrace<'raw>;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str("v8 heap snapshot json object")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut function_infos: Vec<u64> = Vec::new();
+        let mut trace_tree: Vec<TraceNode> = Vec::new();
+        let mut strings: Vec<CowStr<'raw>> = Vec::new();
+        let mut layout = Layout::default();
+        let mut has_trace_tree = false;
+
+        // nodes, edges, locations etc. are the bulk of the snapshot and are
+        // skipped without allocating
+        while let Some(key) = access.next_key::<CowStr>()? {
+            match &*key.0 {
+                "snapshot" => {
+                    layout = access.next_value_seed(SnapshotVisitor)?;
+                    // V8 writes the snapshot first, the tree would be read
+                    // in the wrong layout otherwise
+                    if has_trace_tree && layout != Layout::default() {
+                        return Err(M::Error::custom("snapshot meta after trace_tree"));
+                    }
+                }
+                "trace_function_infos" => {
+                    function_infos = access.next_value()?;
+                }
+                "trace_tree" => {
+                    trace_tree = access.next_value_seed(TraceNodesVisitor(&layout))?;
+                    has_trace_tree = true;
+                }
+                "strings" => {
+                    strings = access.next_value()?;
+                }
+                _ => {
+                    access.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        let root = match trace_tree.pop() {
+            Some(root) if trace_tree.is_empty() => root,
+            _ => return Err(M::Error::custom(
+                "heap snapshot has no allocation trace, record it with allocation tracking enabled",
+            )),
+        };
+
+        let string = |index: u64| {
+            usize::try_from(index)
+                .ok()
+                .and_then(|index| strings.get(index))
+                .map(|s| s.0.clone())
+                .ok_or_else(|| M::Error::custom("trace function string index out of range"))
+        };
+
+        let [function_id, name, script_name, script_id, line, column] = layout.function_info;
+        let mut functions = Vec::with_capacity(function_infos.len() / layout.function_info_len);
+        for info in function_infos.chunks_exact(layout.function_info_len) {
+            functions.push(TraceFunction {
+                function_id: info[function_id],
+                name: string(info[name])?,
+                script_name: string(info[script_name])?,
+                script_id: info[script_id],
+                line: u32::try_from(info[line]).map_err(M::Error::custom)?,
+                column: u32::try_from(info[column]).map_err(M::Error::custom)?,
+            });
+        }
+
+        let trace = AllocationTrace { functions, root };
+        if trace
+            .nodes()
+            .any(|node| node.function_index >= trace.functions.len())
+        {
+            return Err(M::Error::custom("trace node function index out of range"));
+        }
+        Ok(trace)
+    }
+}
+
+/// The layout of the trace sections in the `meta` of the `snapshot`.
+struct SnapshotVisitor;
+
+impl<'de> Visitor<'de> for SnapshotVisitor {
+    type Value = Layout;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str("v8 heap snapshot description")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut layout = Layout::default();
+        while let Some(key) = access.next_key::<CowStr>()? {
+            if key.0 == "meta" {
+                layout = access.next_value_seed(MetaVisitor)?;
+            } else {
+                access.next_value::<IgnoredAny>()?;
+            }
+        }
+        Ok(layout)
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for SnapshotVisitor {
+    type Value = Layout;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(self)
+    }
+}
+
+struct MetaVisitor;
+
+impl<'de> Visitor<'de> for MetaVisitor {
+    type Value = Layout;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str("v8 heap snapshot meta")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut layout = Layout::default();
+        while let Some(key) = access.next_key::<CowStr>()? {
+            match &*key.0 {
+                "trace_function_info_fields" => {
+                    let fields: Vec<CowStr> = access.next_value()?;
+                    positions(
+                        &fields,
+                        &TRACE_FUNCTION_INFO_FIELDS,
+                        &mut layout.function_info,
+                    )?;
+                    layout.function_info_len = fields.len();
+                }
+                "trace_node_fields" => {
+                    let fields: Vec<CowStr> = access.next_value()?;
+                    positions(&fields, &TRACE_NODE_FIELDS, &mut layout.node)?;
+                    layout.node_len = fields.len();
+                }
+                _ => {
+                    access.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(layout)
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for MetaVisitor {
+    type Value = Layout;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(self)
+    }
+}
+
+/// The trace tree is a flat sequence of the fields of each node, by default
+/// `id, function_info_index, count, size, [children]`, where children is the
+/// same flat layout again.
+#[derive(Clone, Copy)]
+struct TraceNodesVisitor<'a>(&'a Layout);
+
+impl<'de> Visitor<'de> for TraceNodesVisitor<'_> {
+    type Value = Vec<TraceNode>;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str("a flat sequence of allocation trace nodes")
+    }
+
+    fn visit_seq<S>(self, mut seq: S) -> Result<Self::Value, S::Error>
+    where
+        S: SeqAccess<'de>,
+    {
+        let mut nodes = Vec::new();
+        loop {
+            let mut node = TraceNode {
+                id: 0,
+                function_index: 0,
+                count: 0,
+                size: 0,
+                children: Vec::new(),
+            };
+            for field in 0..self.0.node_len {
+                let read = match self.0.node.iter().position(|&position| position == field) {
+                    Some(0) => seq.next_element()?.map(|id| node.id = id),
+                    Some(1) => seq.next_element()?.map(|index| node.function_index = index),
+                    Some(2) => seq.next_element()?.map(|count| node.count = count),
+                    Some(3) => seq.next_element()?.map(|size| node.size = size),
+                    Some(_) => seq
+                        .next_element_seed(self)?
+                        .map(|children| node.children = children),
+                    None => seq.next_element::<IgnoredAny>()?.map(|_| ()),
+                };
+                match read {
+                    Some(()) => {}
+                    None if field == 0 => return Ok(nodes),
+                    None => return Err(S::Error::invalid_length(field, &self)),
+                }
+            }
+            nodes.push(node);
+        }
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for TraceNodesVisitor<'_> {
+    type Value = Vec<TraceNode>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(self)
+    }
+}
@@ -0,0 +1,193 @@
+use alloc::format;
+use core::cell::Cell;
+use serde::de::Error;
+
+/// Bounds on what a parse may read, for profiles from untrusted sources.
+/// `None` is unbounded.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Limits {
+    /// Also bounds the child ids of all nodes together.
+    pub max_nodes: Option<usize>,
+    /// Bounds both `samples` and `timeDeltas`.
+    pub max_samples: Option<usize>,
+    /// The deepest nesting of arrays and objects inside a value kept as raw
+    /// json, such as a call frame or `metadata`.
+    pub max_nesting: Option<usize>,
+    /// The bytes of all strings in values kept as raw json and of the names
+    /// of preserved unknown fields, as written in the input.
+    pub max_string_bytes: Option<usize>,
+}
+
+/// Checks what has been read so far against the limits.
+pub(super) struct Tracker {
+    limits: Limits,
+    string_bytes: Cell<usize>,
+    children: Cell<usize>,
+}
+
+impl Tracker {
+    pub(super) fn new(limits: Limits) -> Self {
+        Tracker {
+            limits,
+            string_bytes: Cell::new(0),
+            children: Cell::new(0),
+        }
+    }
+
+    pub(super) fn max_nodes(&self) -> Option<usize> {
+        self.limits.max_nodes
+    }
+
+    /// How many more child ids may be read.
+    pub(super) fn max_children(&self) -> Option<usize> {
+        let max = self.limits.max_nodes?;
+        Some(max.saturating_sub(self.children.get()))
+    }
+
+    /// Counts the child ids of a node.
+    pub(super) fn children(&self, count: usize) {
+        self.children.set(self.children.get().saturating_add(count));
+    }
+
+    pub(super) fn max_samples(&self) -> Option<usize> {
+        self.limits.max_samples
+    }
+
+    /// Counts the strings of a raw json value and checks its nesting.
+    pub(super) fn raw<E: Error>(&self, json: &str) -> Result<(), E> {
+        let (nesting, string_bytes) = scan(json);
+        if let Some(max) = self.limits.max_nesting {
+            if nesting > max {
+                return Err(E::custom(format!("nesting deeper than {}", max)));
+            }
+        }
+        self.strings(string_bytes)
+    }
+
+    /// Counts the name of a preserved field.
+    pub(super) fn key<E: Error>(&self, key: &str) -> Result<(), E> {
+        self.strings(key.len())
+    }
+
+    fn strings<E: Error>(&self, bytes: usize) -> Result<(), E> {
+        let total = self.string_bytes.get().saturating_add(bytes);
+        self.string_bytes.set(total);
+        match self.limits.max_string_bytes {
+            Some(max) if total > max => {
+                Err(E::custom(format!("more than {} bytes of strings", max)))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The deepest nesting and the bytes inside strings of valid json.
+fn scan(json: &str) -> (usize, usize) {
+    let (mut depth, mut deepest, mut string_bytes) = (0_usize, 0, 0);
+    let (mut in_string, mut escaped) = (false, false);
+    for byte in json.bytes() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if byte == b'\\' {
+                escaped = true;
+            } else if byte == b'"' {
+                in_string = false;
+                continue;
+            }
+            string_bytes += 1;
+            continue;
+        }
+        match byte {
+            b'"' => in_string = true,
+            b'[' | b'{' => {
+                depth += 1;
+                deepest = deepest.max(depth);
+            }
+            b']' | b'}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    (deepest, string_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{scan, Limits};
+    use crate::ser::tests::PROFILE;
+    use crate::{ParseOptions, Profile};
+    use alloc::string::ToString;
+
+    #[test]
+    fn scan_raw() {
+        assert_eq!(scan(r#"{"a":[1,{"b":"]}"}]}"#), (3, 4));
+        assert_eq!(scan(r#""\"x""#), (0, 3));
+        assert_eq!(scan("12"), (0, 0));
+    }
+
+    #[test]
+    fn limits() {
+        let parse = |limits| {
+            let options = ParseOptions {
+                limits,
+                ..ParseOptions::default()
+            };
+            Profile::parse_with(PROFILE.as_bytes(), &options)
+        };
+        let profile = parse(Limits::default()).unwrap();
+        let (nodes, samples) = (profile.nodes.len(), profile.samples.len());
+
+        let exact = Limits {
+            max_nodes: Some(nodes),
+            max_samples: Some(samples),
+            max_nesting: Some(2),
+            max_string_bytes: Some(PROFILE.len()),
+        };
+        assert!(parse(exact).is_ok());
+        for limits in &[
+            Limits {
+                max_nodes: Some(nodes - 1),
+                ..exact
+            },
+            Limits {
+                max_samples: Some(samples - 1),
+                ..exact
+            },
+            Limits {
+                max_nesting: Some(1),
+                ..exact
+            },
+            Limits {
+                max_string_bytes: Some(10),
+                ..exact
+            },
+        ] {
+            assert!(parse(*limits).is_err(), "{:?}", limits);
+        }
+    }
+
+    #[test]
+    fn children() {
+        let limits = Limits {
+            max_nodes: Some(4),
+            ..Limits::default()
+        };
+        let options = ParseOptions {
+            limits,
+            ..ParseOptions::default()
+        };
+        let parse = |children: &str| {
+            let json = alloc::format!(
+                r#"{{"nodes":[{{"id":1,"callFrame":{{}},"hitCount":0,"children":{}}},{{"id":2,"callFrame":{{}},"hitCount":0,"children":[1,1]}}],"startTime":0,"endTime":0}}"#,
+                children
+            );
+            Profile::parse_with(json.as_bytes(), &options).map(|_| ())
+        };
+        assert!(parse("[2,2]").is_ok());
+        // a single node may not list more children than there are nodes
+        let error = parse("[2,2,2,2,2]").unwrap_err().to_string();
+        assert!(error.contains("more than 4 elements"), "{}", error);
+        // nor may all of them together
+        assert!(parse("[2,2,2]").is_err());
+    }
+}
@@ -1,6 +1,47 @@
+macro_rules! check_missing {
+    ($error:ty, $field:ident) => {
+        match $field {
+            Some(value) => value,
+            None => return Err(<$error>::missing_field(stringify!($field))),
+        }
+    };
+    ($error:ty, $field:ident, $name:expr) => {
+        match $field {
+            Some(value) => value,
+            None => return Err(<$error>::missing_field($name)),
+        }
+    };
+}
+
+mod aggregate;
+mod budget;
+mod bundle;
+mod coverage;
+#[cfg(feature = "std")]
+mod etw;
+mod framework;
+mod heap;
+mod limits;
+mod options;
+#[cfg(feature = "std")]
+mod pipeline;
+mod script;
+#[cfg(feature = "std")]
+mod sidecar;
 mod util;
 mod visitors;
 
+pub use limits::Limits;
+pub use options::{ParseOptions, TimeDeltaPolicy, UnknownFields};
+pub(crate) use util::{round_micros, CowStr};
+
+use crate::aggregate::{FrameStats, ProfileSet, Stats};
+use crate::budget::{Budget, Budgets};
+use crate::coverage::{Coverage, CoverageRange, FunctionCoverage, ScriptCoverage};
+use crate::heap::AllocationTrace;
+use crate::lines::PositionTick;
+use crate::CallFrame;
+use crate::FrameKey;
 use crate::Node;
 use crate::Profile;
 use serde::Deserialize;
@@ -11,10 +52,50 @@ impl<'de: 'r, 'r> Deserialize<'de> for Profile<'r> {
     where
         D: Deserializer<'de>,
     {
-        deserializer.deserialize_map(visitors::profile())
+        let _span = span!("parse_profile");
+        parsed(deserializer.deserialize_map(visitors::profile(ParseOptions::default())))
+    }
+}
+
+impl Profile<'static> {
+    /// Deserializes a profile that owns its raw json, for deserializers
+    /// that can't lend from their input such as `serde_json::from_reader`.
+    /// This copies every node's call frame, prefer the borrowing
+    /// `Deserialize` impl with `serde_json::from_slice` where possible.
+    ///
+    /// # Errors
+    ///
+    /// If the input is not a valid v8 cpuprofile.
+    pub fn deserialize_owned<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let _span = span!("parse_profile", owned = true);
+        parsed(deserializer.deserialize_map(visitors::owned_profile(ParseOptions::default())))
+    }
+
+    /// Reads a profile from already parsed json, for example the result of
+    /// `Profiler.stop` from a devtools protocol client.
+    ///
+    /// # Errors
+    ///
+    /// If the value is not a valid v8 cpuprofile.
+    pub fn from_value(value: &serde_json::Value) -> Result<Self, serde_json::Error> {
+        Self::deserialize_owned(value)
     }
 }
 
+fn parsed<E>(result: Result<Profile, E>) -> Result<Profile, E> {
+    if let Ok(ref profile) = result {
+        event!(
+            "parsed profile",
+            nodes = profile.nodes.len(),
+            samples = profile.samples.len(),
+        );
+    }
+    result
+}
+
 impl<'de: 'r, 'r> Deserialize<'de> for Node<'r> {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -23,3 +104,215 @@ impl<'de: 'r, 'r> Deserialize<'de> for Node<'r> {
         deserializer.deserialize_map(visitors::node())
     }
 }
+
+impl<'de: 'r, 'r> Deserialize<'de> for CallFrame<'r> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(visitors::call_frame())
+    }
+}
+
+impl<'de> Deserialize<'de> for PositionTick {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(visitors::position_tick())
+    }
+}
+
+impl<'de: 'r, 'r> Deserialize<'de> for AllocationTrace<'r> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(heap::allocation_trace())
+    }
+}
+
+impl<'de: 'r, 'r> Deserialize<'de> for Coverage<'r> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(coverage::coverage())
+    }
+}
+
+impl<'de: 'r, 'r> Deserialize<'de> for ScriptCoverage<'r> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(coverage::script_coverage())
+    }
+}
+
+impl<'de: 'r, 'r> Deserialize<'de> for FunctionCoverage<'r> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(coverage::function_coverage())
+    }
+}
+
+impl<'de> Deserialize<'de> for CoverageRange {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(coverage::coverage_range())
+    }
+}
+
+impl<'de: 'r, 'r> Deserialize<'de> for ProfileSet<'r> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(aggregate::profile_set())
+    }
+}
+
+impl<'de: 'r, 'r> Deserialize<'de> for FrameStats<'r> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(aggregate::frame_stats())
+    }
+}
+
+impl<'de: 'r, 'r> Deserialize<'de> for FrameKey<'r> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(aggregate::frame_key())
+    }
+}
+
+impl<'de> Deserialize<'de> for Stats {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(aggregate::stats())
+    }
+}
+
+impl<'de> Deserialize<'de> for Budgets {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(budget::budgets())
+    }
+}
+
+impl<'de> Deserialize<'de> for Budget {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(budget::budget())
+    }
+}
+
+impl<'de> Deserialize<'de> for crate::framework::Rules {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(framework::rules())
+    }
+}
+
+impl<'de> Deserialize<'de> for crate::framework::Rule {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(framework::rule())
+    }
+}
+
+impl<'de> Deserialize<'de> for crate::bundle::ChunkMap {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(bundle::webpack_stats())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'de> Deserialize<'de> for crate::etw::EtwCapture {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(etw::capture())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'de> Deserialize<'de> for crate::etw::EtwSample {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(etw::sample())
+    }
+}
+
+impl<'de> Deserialize<'de> for crate::script::ScriptTable {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(script::script_table())
+    }
+}
+
+impl<'de> Deserialize<'de> for crate::script::ScriptParsed {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(script::script_parsed())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'de> Deserialize<'de> for crate::pipeline::PipelineConfig {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(pipeline::pipeline())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'de> Deserialize<'de> for crate::pipeline::PassConfig {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(pipeline::pass())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'de> Deserialize<'de> for crate::sidecar::Environment {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(sidecar::environment())
+    }
+}
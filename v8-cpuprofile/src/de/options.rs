@@ -0,0 +1,277 @@
+use super::{parsed, visitors, Limits};
+use crate::Profile;
+use serde::Deserializer;
+
+/// How [`Profile::parse_with`] reads a profile. The default is as strict as
+/// the `Deserialize` impl, [`ParseOptions::lenient`] loads what it can.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Keeps samples in the order they were recorded instead of sorting them
+    /// by time, so time deltas that go backwards serialize unchanged.
+    /// Methods that look samples up by time, such as
+    /// [`ProfileChunk::time_range`](crate::ProfileChunk::time_range), expect
+    /// them sorted.
+    pub preserve_sample_order: bool,
+    /// What to do when time deltas add up to before `startTime` or
+    /// overflow.
+    pub time_deltas: TimeDeltaPolicy,
+    /// What to do with fields of the profile and its nodes that aren't part
+    /// of the format.
+    pub unknown_fields: UnknownFields,
+    /// Bounds on the size of the input, unbounded by default.
+    pub limits: Limits,
+    /// Checks that every call frame has its keys with the right types while
+    /// parsing, instead of when it is first read. Call frames are still kept
+    /// as raw json, and keys they don't know are errors only under
+    /// [`UnknownFields::Error`].
+    pub validate_call_frames: bool,
+}
+
+/// How fields of a profile or node that aren't part of the format are
+/// handled.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum UnknownFields {
+    /// Fails the parse.
+    #[default]
+    Error,
+    /// Skips them.
+    Ignore,
+    /// Keeps their raw json in `extra_fields`, written back after the known
+    /// fields.
+    Preserve,
+}
+
+/// How time deltas that add up to before the profile's `startTime`, or
+/// overflow, are handled.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum TimeDeltaPolicy {
+    /// Timestamps saturate, never going before `startTime`.
+    Clamp,
+    /// Fails the parse.
+    #[default]
+    Error,
+    /// Keeps the signed sums and moves `startTime` back to the earliest
+    /// sample, so every sample keeps its absolute time.
+    KeepSigned,
+}
+
+impl ParseOptions {
+    /// Skips unknown fields and clamps time deltas that go before
+    /// `startTime`, for profiles from producers that bend the format.
+    #[must_use]
+    pub fn lenient() -> Self {
+        ParseOptions {
+            time_deltas: TimeDeltaPolicy::Clamp,
+            unknown_fields: UnknownFields::Ignore,
+            ..ParseOptions::default()
+        }
+    }
+}
+
+impl<'raw> Profile<'raw> {
+    /// Parses a profile from json read as `options` say, borrowing from
+    /// `json` like the `Deserialize` impl.
+    ///
+    /// # Errors
+    ///
+    /// If the input is not a valid v8 cpuprofile.
+    pub fn parse_with(json: &'raw [u8], options: &ParseOptions) -> Result<Self, serde_json::Error> {
+        let _span = span!("parse_profile");
+        let mut deserializer = serde_json::Deserializer::from_slice(json);
+        let profile = parsed(deserializer.deserialize_map(visitors::profile(*options)))?;
+        deserializer.end()?;
+        Ok(profile)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ParseOptions, TimeDeltaPolicy, UnknownFields};
+    use crate::ser::tests::PROFILE;
+    use crate::Profile;
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn preserve_sample_order() {
+        const PROFILE: &str =
+            r#"{"nodes":[],"startTime":0,"endTime":30,"samples":[1,2,3],"timeDeltas":[10,-5,20]}"#;
+        let sorted = Profile::parse_with(PROFILE.as_bytes(), &ParseOptions::default()).unwrap();
+        let node_ids: alloc::vec::Vec<_> = sorted.samples.iter().map(|s| s.node_id).collect();
+        assert_eq!(node_ids, [2, 1, 3]);
+
+        let options = ParseOptions {
+            preserve_sample_order: true,
+            ..ParseOptions::default()
+        };
+        let recorded = Profile::parse_with(PROFILE.as_bytes(), &options).unwrap();
+        assert_eq!(serde_json::to_string(&recorded).unwrap(), PROFILE);
+    }
+
+    #[test]
+    fn time_delta_policy() {
+        let parse = |json: &str, time_deltas| {
+            let options = ParseOptions {
+                time_deltas,
+                ..ParseOptions::default()
+            };
+            Profile::parse_with(json.as_bytes(), &options).map(|profile| {
+                let ts: Vec<_> = profile
+                    .samples
+                    .iter()
+                    .map(|sample| (sample.node_id, sample.ts.0))
+                    .collect();
+                (profile.start_time.0, ts)
+            })
+        };
+
+        // goes before startTime after the first sample
+        let backwards = r#"{"nodes":[],"startTime":100,"endTime":200,"samples":[1,2,3],"timeDeltas":[5,-20,30]}"#;
+        assert_eq!(
+            parse(backwards, TimeDeltaPolicy::Clamp).unwrap(),
+            (100, alloc::vec![(2, 0), (1, 5), (3, 15)])
+        );
+        assert!(parse(backwards, TimeDeltaPolicy::Error).is_err());
+        assert!(serde_json::from_str::<Profile>(backwards).is_err());
+        assert!(Profile::parse_with(backwards.as_bytes(), &ParseOptions::lenient()).is_ok());
+        assert_eq!(
+            parse(backwards, TimeDeltaPolicy::KeepSigned).unwrap(),
+            (85, alloc::vec![(2, 0), (1, 20), (3, 30)])
+        );
+
+        let long_gap =
+            r#"{"nodes":[],"startTime":0,"endTime":0,"samples":[1,2],"timeDeltas":[0,3000000000]}"#;
+        assert_eq!(
+            parse(long_gap, TimeDeltaPolicy::Error).unwrap().1,
+            [(1, 0), (2, 3_000_000_000)]
+        );
+
+        let overflow = r#"{"nodes":[],"startTime":0,"endTime":0,"samples":[1,2],"timeDeltas":[9223372036854775807,1]}"#;
+        assert!(parse(overflow, TimeDeltaPolicy::Error).is_err());
+        let max = i64::MAX.unsigned_abs();
+        assert_eq!(
+            parse(overflow, TimeDeltaPolicy::Clamp).unwrap().1,
+            [(1, max), (2, max)]
+        );
+    }
+
+    #[test]
+    fn backwards_deltas() {
+        // the fixture with its first sample before startTime and a sample
+        // recorded out of order, as v8 writes when ticks are reordered
+        const BACKWARDS: &str =
+            core::include_str!("../../tests/pathological/backwards-deltas.json");
+        let fixture: Profile = serde_json::from_str(PROFILE).unwrap();
+        assert!(serde_json::from_str::<Profile>(BACKWARDS).is_err());
+        let parse = |time_deltas| {
+            let options = ParseOptions {
+                time_deltas,
+                preserve_sample_order: true,
+                ..ParseOptions::default()
+            };
+            Profile::parse_with(BACKWARDS.as_bytes(), &options).unwrap()
+        };
+
+        let clamped = parse(TimeDeltaPolicy::Clamp);
+        assert_eq!(clamped.start_time, fixture.start_time);
+        assert_eq!(clamped.samples[0].ts.0, 0);
+        // the first delta is -200 instead of 1573, which moves the others
+        // 1773us earlier than in the fixture
+        let shift = 1573 + 200;
+        for (index, (clamped, sample)) in clamped
+            .samples
+            .iter()
+            .zip(&fixture.samples)
+            .enumerate()
+            .skip(1)
+        {
+            let expected = if index == 10 {
+                sample.ts.0 - shift - 3000
+            } else {
+                sample.ts.0 - shift
+            };
+            assert_eq!(clamped.ts.0, expected, "sample {}", index);
+        }
+
+        let signed = parse(TimeDeltaPolicy::KeepSigned);
+        assert_eq!(signed.start_time.0, fixture.start_time.0 - 200);
+        assert_eq!(signed.samples.len(), fixture.samples.len());
+        assert_eq!(signed.samples[27].ts.0, clamped.samples[27].ts.0 + 200);
+    }
+
+    #[test]
+    fn unknown_fields() {
+        const PROFILE: &str = r#"{"nodes":[{"id":1,"callFrame":{},"hitCount":0,"codeType":"JS"}],"startTime":0,"endTime":0,"samples":[],"timeDeltas":[],"title":"boot"}"#;
+        let parse = |unknown_fields| {
+            let options = ParseOptions {
+                unknown_fields,
+                ..ParseOptions::default()
+            };
+            Profile::parse_with(PROFILE.as_bytes(), &options)
+        };
+        assert!(parse(UnknownFields::Error).is_err());
+        assert!(serde_json::from_str::<Profile>(PROFILE).is_err());
+        assert!(Profile::parse_with(PROFILE.as_bytes(), &ParseOptions::lenient()).is_ok());
+
+        let ignored = parse(UnknownFields::Ignore).unwrap();
+        assert_eq!(
+            serde_json::to_string(&ignored).unwrap(),
+            r#"{"nodes":[{"id":1,"callFrame":{},"hitCount":0}],"startTime":0,"endTime":0,"samples":[],"timeDeltas":[]}"#
+        );
+
+        let preserved = parse(UnknownFields::Preserve).unwrap();
+        assert_eq!(preserved.extra_fields[0].0, "title");
+        assert_eq!(preserved.nodes[0].extra_fields[0].1.get(), r#""JS""#);
+        assert_eq!(serde_json::to_string(&preserved).unwrap(), PROFILE);
+    }
+
+    #[test]
+    fn validate_call_frames() {
+        let parse = |call_frame: &str| {
+            let json = alloc::format!(
+                r#"{{"nodes":[{{"id":7,"callFrame":{},"hitCount":0}}],"startTime":0,"endTime":0}}"#,
+                call_frame
+            );
+            let options = ParseOptions {
+                validate_call_frames: true,
+                ..ParseOptions::default()
+            };
+            let unchecked = Profile::parse_with(json.as_bytes(), &ParseOptions::default());
+            assert!(unchecked.is_ok());
+            Profile::parse_with(json.as_bytes(), &options)
+                .map(|_| ())
+                .map_err(|error| error.to_string())
+        };
+        let valid =
+            r#"{"functionName":"f","scriptId":"1","url":"a.js","lineNumber":1,"columnNumber":2}"#;
+        assert_eq!(parse(valid), Ok(()));
+
+        let missing =
+            parse(r#"{"functionName":"f","scriptId":"1","lineNumber":1,"columnNumber":2}"#);
+        assert!(missing
+            .unwrap_err()
+            .starts_with("invalid callFrame of node 7: missing field `url`"));
+        let fraction = parse(
+            r#"{"functionName":"f","scriptId":"1","url":"","lineNumber":1.5,"columnNumber":2}"#,
+        );
+        assert!(fraction
+            .unwrap_err()
+            .contains("invalid type: floating point `1.5`"));
+        assert!(parse("[]").is_err());
+
+        // extra keys follow the unknown fields policy
+        let code_type = r#"{"functionName":"f","scriptId":"1","url":"","lineNumber":1,"columnNumber":2,"codeType":"JS"}"#;
+        assert!(parse(code_type)
+            .unwrap_err()
+            .contains("unknown field `codeType`"));
+        let json = alloc::format!(
+            r#"{{"nodes":[{{"id":7,"callFrame":{},"hitCount":0}}],"startTime":0,"endTime":0}}"#,
+            code_type
+        );
+        let lenient = ParseOptions {
+            validate_call_frames: true,
+            ..ParseOptions::lenient()
+        };
+        assert!(Profile::parse_with(json.as_bytes(), &lenient).is_ok());
+    }
+}
@@ -0,0 +1,145 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use serde::de::Error;
+use serde::de::MapAccess;
+use serde::de::Visitor;
+use std::path::PathBuf;
+
+use super::util::CowStr;
+use crate::pipeline::{PassConfig, PipelineConfig};
+
+pub(super) fn pipeline<'de>() -> impl Visitor<'de, Value = PipelineConfig> {
+    PipelineVisitor
+}
+
+pub(super) fn pass<'de>() -> impl Visitor<'de, Value = PassConfig> {
+    PassVisitor
+}
+
+const PIPELINE_FIELDS: &[&str] = &["pass"];
+
+struct PipelineVisitor;
+
+impl<'de> Visitor<'de> for PipelineVisitor {
+    type Value = PipelineConfig;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str("a list of passes")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut pass: Option<Vec<PassConfig>> = None;
+
+        while let Some(key) = access.next_key::<CowStr>()? {
+            match &*key.0 {
+                "pass" => {
+                    pass = access.next_value()?;
+                }
+                key => {
+                    return Err(M::Error::unknown_field(key, PIPELINE_FIELDS));
+                }
+            }
+        }
+
+        Ok(PipelineConfig {
+            pass: pass.unwrap_or_default(),
+        })
+    }
+}
+
+const PASSES: &[&str] = &[
+    "strip-idle",
+    "attribute-gc",
+    "remap-scripts",
+    "group-anonymous",
+    "prune",
+    "summary",
+    "export",
+];
+
+const PASS_FIELDS: &[&str] = &["name", "table", "threshold", "format", "out"];
+
+struct PassVisitor;
+
+impl<'de> Visitor<'de> for PassVisitor {
+    type Value = PassConfig;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str("a pass")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut name: Option<String> = None;
+        let mut table: Option<PathBuf> = None;
+        let mut threshold: Option<f64> = None;
+        let mut format: Option<String> = None;
+        let mut out: Option<PathBuf> = None;
+
+        while let Some(key) = access.next_key::<CowStr>()? {
+            match &*key.0 {
+                "name" => {
+                    name = access.next_value()?;
+                }
+                "table" => {
+                    table = access.next_value()?;
+                }
+                "threshold" => {
+                    threshold = access.next_value()?;
+                }
+                "format" => {
+                    format = access.next_value()?;
+                }
+                "out" => {
+                    out = access.next_value()?;
+                }
+                key => {
+                    return Err(M::Error::unknown_field(key, PASS_FIELDS));
+                }
+            }
+        }
+
+        let name = check_missing!(M::Error, name);
+        // options are only allowed on the passes that take them
+        let options: &[&str] = match name.as_str() {
+            "remap-scripts" => &["table"],
+            "prune" => &["threshold"],
+            "export" => &["format", "out"],
+            "strip-idle" | "attribute-gc" | "group-anonymous" | "summary" => &[],
+            name => return Err(M::Error::unknown_variant(name, PASSES)),
+        };
+        let given = [
+            ("table", table.is_some()),
+            ("threshold", threshold.is_some()),
+            ("format", format.is_some()),
+            ("out", out.is_some()),
+        ];
+        for (option, _) in given.iter().filter(|(_, given)| *given) {
+            if !options.contains(option) {
+                return Err(M::Error::unknown_field(option, options));
+            }
+        }
+
+        Ok(match name.as_str() {
+            "strip-idle" => PassConfig::StripIdle,
+            "attribute-gc" => PassConfig::AttributeGc,
+            "remap-scripts" => PassConfig::RemapScripts {
+                table: check_missing!(M::Error, table),
+            },
+            "prune" => PassConfig::Prune {
+                threshold: check_missing!(M::Error, threshold),
+            },
+            "group-anonymous" => PassConfig::GroupAnonymous,
+            "summary" => PassConfig::Summary,
+            _ => PassConfig::Export {
+                format: check_missing!(M::Error, format),
+                out: out.unwrap_or_else(|| PathBuf::from("-")),
+            },
+        })
+    }
+}
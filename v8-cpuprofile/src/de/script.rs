@@ -0,0 +1,86 @@
+use alloc::string::String;
+use serde::de::IgnoredAny;
+use serde::de::MapAccess;
+use serde::de::SeqAccess;
+use serde::de::Visitor;
+
+use super::util::CowStr;
+use crate::script::{ScriptParsed, ScriptTable};
+
+pub(super) fn script_table<'de>() -> impl Visitor<'de, Value = ScriptTable> {
+    ScriptTableVisitor
+}
+
+pub(super) fn script_parsed<'de>() -> impl Visitor<'de, Value = ScriptParsed> {
+    ScriptParsedVisitor
+}
+
+struct ScriptTableVisitor;
+
+impl<'de> Visitor<'de> for ScriptTableVisitor {
+    type Value = ScriptTable;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str("a sequence of Debugger.scriptParsed events")
+    }
+
+    fn visit_seq<S>(self, mut seq: S) -> Result<Self::Value, S::Error>
+    where
+        S: SeqAccess<'de>,
+    {
+        let mut table = ScriptTable::new();
+        while let Some(event) = seq.next_element()? {
+            table.add(event);
+        }
+        Ok(table)
+    }
+}
+
+struct ScriptParsedVisitor;
+
+impl<'de> Visitor<'de> for ScriptParsedVisitor {
+    type Value = ScriptParsed;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str("a Debugger.scriptParsed event or its params")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut script = ScriptParsed::default();
+        let mut method: Option<CowStr> = None;
+        let mut params: Option<ScriptParsed> = None;
+
+        while let Some(key) = access.next_key::<CowStr>()? {
+            match &*key.0 {
+                "method" => {
+                    method = Some(access.next_value()?);
+                }
+                "params" => {
+                    params = Some(access.next_value()?);
+                }
+                "scriptId" => {
+                    script.script_id = Some(access.next_value::<String>()?);
+                }
+                "url" => {
+                    script.url = access.next_value()?;
+                }
+                "hash" => {
+                    script.hash = access.next_value()?;
+                }
+                // the rest of the event, and the other fields of other events
+                _ => {
+                    access.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        match method {
+            Some(method) if method.0 == "Debugger.scriptParsed" => Ok(params.unwrap_or_default()),
+            Some(_) => Ok(ScriptParsed::default()),
+            None => Ok(script),
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use serde::de::IgnoredAny;
+use serde::de::MapAccess;
+use serde::de::Visitor;
+
+use super::util::CowStr;
+use crate::sidecar::Environment;
+
+pub(super) fn environment<'de>() -> impl Visitor<'de, Value = Environment> {
+    EnvironmentVisitor
+}
+
+struct EnvironmentVisitor;
+
+impl<'de> Visitor<'de> for EnvironmentVisitor {
+    type Value = Environment;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str("a capture environment")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut environment = Environment::default();
+
+        while let Some(key) = access.next_key::<CowStr>()? {
+            match &*key.0 {
+                "hostname" => {
+                    environment.hostname = access.next_value()?;
+                }
+                "nodeVersion" => {
+                    environment.node_version = access.next_value()?;
+                }
+                "v8Version" => {
+                    environment.v8_version = access.next_value()?;
+                }
+                "cpuModel" => {
+                    environment.cpu_model = access.next_value()?;
+                }
+                "gitRevision" => {
+                    environment.git_revision = access.next_value()?;
+                }
+                // left for sidecars written by newer versions
+                _ => {
+                    access.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        Ok(environment)
+    }
+}
@@ -1,42 +1,59 @@
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
 use core::marker::PhantomData;
-use core::time::Duration;
 use serde::de::DeserializeSeed;
+use serde::de::Error;
 use serde::de::SeqAccess;
+use serde::de::Unexpected;
 use serde::de::Visitor;
 use serde::Deserialize;
 use serde::Deserializer;
 
-pub fn visit_seq<'de, F, V>(callback: F, expecting: &'static str) -> VisitSeq<F, V>
+use super::TimeDeltaPolicy;
+use crate::{Micros, Sample};
+
+pub fn visit_seq<'de, F, V>(callback: F, expecting: &'static str) -> VisitSeq<F, PhantomData<V>>
 where
     F: FnMut(V, usize),
     V: Deserialize<'de>,
 {
-    VisitSeq::new(callback, expecting)
+    visit_seq_seed(PhantomData, callback, expecting)
 }
 
-pub struct VisitSeq<F, V> {
+/// Like [`visit_seq`] with each element deserialized by a copy of `seed`.
+pub fn visit_seq_seed<'de, F, E>(seed: E, callback: F, expecting: &'static str) -> VisitSeq<F, E>
+where
+    F: FnMut(E::Value, usize),
+    E: DeserializeSeed<'de> + Copy,
+{
+    VisitSeq {
+        callback,
+        expecting,
+        seed,
+        limit: None,
+    }
+}
+
+pub struct VisitSeq<F, E> {
     callback: F,
     expecting: &'static str,
-    _marker: PhantomData<fn(V)>,
+    seed: E,
+    limit: Option<usize>,
 }
 
-impl<F, V> VisitSeq<F, V>
-where
-    F: FnMut(V, usize),
-{
-    fn new(callback: F, expecting: &'static str) -> Self {
-        VisitSeq {
-            callback,
-            expecting,
-            _marker: PhantomData,
-        }
+impl<F, E> VisitSeq<F, E> {
+    /// Fails on more than `limit` elements.
+    pub fn limit(self, limit: Option<usize>) -> Self {
+        VisitSeq { limit, ..self }
     }
 }
 
-impl<'de, F, V> Visitor<'de> for VisitSeq<F, V>
+impl<'de, F, E> Visitor<'de> for VisitSeq<F, E>
 where
-    F: FnMut(V, usize),
-    V: Deserialize<'de>,
+    F: FnMut(E::Value, usize),
+    E: DeserializeSeed<'de> + Copy,
 {
     type Value = ();
 
@@ -49,7 +66,16 @@ where
         S: SeqAccess<'de>,
     {
         let mut index = 0;
-        while let Some(value) = seq.next_element()? {
+        while let Some(value) = seq.next_element_seed(self.seed)? {
+            if let Some(limit) = self.limit {
+                if index >= limit {
+                    return Err(S::Error::custom(alloc::format!(
+                        "more than {} elements in {}",
+                        limit,
+                        self.expecting
+                    )));
+                }
+            }
             (self.callback)(value, index);
             index += 1;
         }
@@ -57,10 +83,10 @@ where
     }
 }
 
-impl<'de, F, V> DeserializeSeed<'de> for VisitSeq<F, V>
+impl<'de, F, E> DeserializeSeed<'de> for VisitSeq<F, E>
 where
-    F: FnMut(V, usize),
-    V: Deserialize<'de>,
+    F: FnMut(E::Value, usize),
+    E: DeserializeSeed<'de> + Copy,
 {
     type Value = ();
 
@@ -72,11 +98,164 @@ where
     }
 }
 
-pub fn offset_duration(duration: Duration, offset_micros: i32) -> Duration {
-    let abs_offset = Duration::from_micros(offset_micros.abs() as u64);
-    if offset_micros.is_negative() {
-        duration - abs_offset
+/// Sums time deltas into sample timestamps as the policy says.
+pub struct TimeDeltas {
+    policy: TimeDeltaPolicy,
+    current: i64,
+    signed: Vec<i64>,
+    error: Option<&'static str>,
+}
+
+impl TimeDeltas {
+    pub fn new(policy: TimeDeltaPolicy) -> Self {
+        TimeDeltas {
+            policy,
+            current: 0,
+            signed: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// The timestamp of the next sample, or `None` until [`TimeDeltas::finish`]
+    /// when timestamps are kept signed.
+    pub fn add(&mut self, delta: i64) -> Option<Micros> {
+        if self.error.is_some() {
+            return None;
+        }
+        let sum = match (self.current.checked_add(delta), self.policy) {
+            (Some(sum), _) => sum,
+            (None, TimeDeltaPolicy::Error) => {
+                self.error = Some("time deltas overflow");
+                return None;
+            }
+            (None, _) => self.current.saturating_add(delta),
+        };
+        match self.policy {
+            TimeDeltaPolicy::Error if sum < 0 => {
+                self.error = Some("time deltas go before startTime");
+                None
+            }
+            // a clamped timestamp leaves the sum alone, later ones stay
+            // where the deltas put them
+            TimeDeltaPolicy::Clamp | TimeDeltaPolicy::Error => {
+                self.current = sum;
+                Some(micros(sum))
+            }
+            TimeDeltaPolicy::KeepSigned => {
+                self.current = sum;
+                self.signed.push(sum);
+                None
+            }
+        }
+    }
+
+    /// Sets the timestamps kept signed and returns the start time, moved
+    /// back to the earliest sample if any is before it.
+    pub fn finish(
+        self,
+        samples: &mut [Sample],
+        start_time: Micros,
+    ) -> Result<Micros, &'static str> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        let min = self.signed.iter().copied().min().unwrap_or(0).min(0);
+        for (sample, ts) in samples.iter_mut().zip(self.signed) {
+            sample.ts = micros(ts.saturating_sub(min));
+        }
+        Ok(start_time.saturating_sub(Micros(min.unsigned_abs())))
+    }
+}
+
+fn micros(micros: i64) -> Micros {
+    Micros(u64::try_from(micros).unwrap_or(0))
+}
+
+/// A profile time, the value as read when it was a float.
+pub struct Time(pub Micros, pub Option<f64>);
+
+impl<'de> Deserialize<'de> for Time {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(TimeVisitor)
+    }
+}
+
+struct TimeVisitor;
+
+impl Visitor<'_> for TimeVisitor {
+    type Value = Time;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str("a non-negative number of microseconds")
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(Time(Micros(v), None))
+    }
+
+    fn visit_i64<E: Error>(self, v: i64) -> Result<Self::Value, E> {
+        u64::try_from(v)
+            .map(|v| Time(Micros(v), None))
+            .map_err(|_| E::invalid_value(Unexpected::Signed(v), &self))
+    }
+
+    fn visit_f64<E: Error>(self, v: f64) -> Result<Self::Value, E> {
+        if v.is_finite() && v >= 0.0 {
+            Ok(Time(round_micros(v), Some(v)))
+        } else {
+            Err(E::invalid_value(Unexpected::Float(v), &self))
+        }
+    }
+}
+
+/// Rounds half up to whole microseconds, saturating at `u64::MAX`.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss
+)]
+pub fn round_micros(micros: f64) -> Micros {
+    let whole = micros as u64;
+    if micros - whole as f64 >= 0.5 {
+        Micros(whole.saturating_add(1))
     } else {
-        duration + abs_offset
+        Micros(whole)
+    }
+}
+
+/// A string that borrows from the input unless it had to be unescaped.
+pub struct CowStr<'de>(pub Cow<'de, str>);
+
+impl<'de> Deserialize<'de> for CowStr<'de> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(CowStrVisitor)
+    }
+}
+
+struct CowStrVisitor;
+
+impl<'de> Visitor<'de> for CowStrVisitor {
+    type Value = CowStr<'de>;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str("a string")
+    }
+
+    fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E> {
+        Ok(CowStr(Cow::Borrowed(v)))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(CowStr(Cow::Owned(v.into())))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E> {
+        Ok(CowStr(Cow::Owned(v)))
     }
 }
@@ -1,39 +1,166 @@
 use crate::Sample;
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
 use alloc::vec::Vec;
 use core::marker::PhantomData;
-use core::time::Duration;
 use hashbrown::HashMap;
+use serde::de::DeserializeSeed;
 use serde::de::Error;
+use serde::de::IgnoredAny;
 use serde::de::MapAccess;
 use serde::de::Visitor;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde_json::value::RawValue;
 
-use super::util::{offset_duration, visit_seq};
-use crate::{Node, Profile};
+use super::limits::Tracker;
+use super::util::{visit_seq, visit_seq_seed, CowStr, Time, TimeDeltas};
+use super::{ParseOptions, UnknownFields};
+use crate::lines::PositionTick;
+use crate::{CallFrame, ExtraFields, Node, Profile, RawJson, Weighting};
 
 pub(super) fn node<'de: 'raw, 'raw>() -> impl Visitor<'de, Value = Node<'raw>> {
-    NodeVisitor(PhantomData)
+    NodeVisitor::<'_, 'raw, Borrowed>(UnknownFields::Error, false, None, PhantomData)
 }
 
-pub(super) fn profile<'de: 'raw, 'raw>() -> impl Visitor<'de, Value = Profile<'raw>> {
-    ProfileVisitor(PhantomData)
+pub(super) fn call_frame<'de: 'raw, 'raw>() -> impl Visitor<'de, Value = CallFrame<'raw>> {
+    CallFrameVisitor(false, PhantomData)
 }
 
-macro_rules! check_missing {
-    ($error:ty, $field:ident) => {
-        match $field {
-            Some(value) => value,
-            None => return Err(<$error>::missing_field(stringify!($field))),
-        }
-    };
-    ($error:ty, $field:ident, $name:expr) => {
-        match $field {
-            Some(value) => value,
-            None => return Err(<$error>::missing_field($name)),
-        }
-    };
+pub(super) fn position_tick<'de>() -> impl Visitor<'de, Value = PositionTick> {
+    PositionTickVisitor
+}
+
+pub(super) fn profile<'de: 'raw, 'raw>(
+    options: ParseOptions,
+) -> impl Visitor<'de, Value = Profile<'raw>> {
+    ProfileVisitor::<'raw, Borrowed>(options, PhantomData)
+}
+
+pub(super) fn owned_profile<'de>(
+    options: ParseOptions,
+) -> impl Visitor<'de, Value = Profile<'static>> {
+    ProfileVisitor::<'static, Owned>(options, PhantomData)
+}
+
+/// How the raw json of a node is kept.
+trait RawValues<'de, 'raw> {
+    type Raw: Deserialize<'de>;
+
+    fn into_raw(raw: Self::Raw) -> RawJson<'raw>;
+
+    fn into_key(key: Cow<'de, str>) -> Cow<'raw, str>;
+}
+
+/// Borrowed from the input, which needs a deserializer that can lend it.
+struct Borrowed;
+
+impl<'de: 'raw, 'raw> RawValues<'de, 'raw> for Borrowed {
+    type Raw = &'raw RawValue;
+
+    fn into_raw(raw: Self::Raw) -> RawJson<'raw> {
+        raw.into()
+    }
+
+    fn into_key(key: Cow<'de, str>) -> Cow<'raw, str> {
+        key
+    }
 }
 
-struct NodeVisitor<'a>(PhantomData<fn() -> Node<'a>>);
+/// Copied, for readers and other deserializers that can't lend.
+struct Owned;
+
+impl RawValues<'_, 'static> for Owned {
+    type Raw = Box<RawValue>;
+
+    fn into_raw(raw: Self::Raw) -> RawJson<'static> {
+        raw.into()
+    }
+
+    fn into_key(key: Cow<'_, str>) -> Cow<'static, str> {
+        Cow::Owned(key.into_owned())
+    }
+}
+
+/// With the unknown field policy, whether call frames are validated and the
+/// limits, if any.
+struct NodeVisitor<'t, 'raw, R>(
+    UnknownFields,
+    bool,
+    Option<&'t Tracker>,
+    PhantomData<fn() -> (Node<'raw>, R)>,
+);
+
+/// Deserializes a node with the profile's `RawValues`, unknown field policy,
+/// call frame validation and limits.
+struct NodeSeed<'t, 'raw, R>(
+    UnknownFields,
+    bool,
+    &'t Tracker,
+    PhantomData<fn() -> (Node<'raw>, R)>,
+);
+
+impl<R> Clone for NodeSeed<'_, '_, R> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<R> Copy for NodeSeed<'_, '_, R> {}
+
+impl<'de, 'raw, R: RawValues<'de, 'raw>> DeserializeSeed<'de> for NodeSeed<'_, 'raw, R> {
+    type Value = Node<'raw>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(NodeVisitor::<'_, 'raw, R>(
+            self.0,
+            self.1,
+            Some(self.2),
+            PhantomData,
+        ))
+    }
+}
+
+/// Checks a raw json value against the limits, if any.
+fn tracked<'raw, E: Error>(
+    tracker: Option<&Tracker>,
+    raw: RawJson<'raw>,
+) -> Result<RawJson<'raw>, E> {
+    if let Some(tracker) = tracker {
+        tracker.raw(raw.get())?;
+    }
+    Ok(raw)
+}
+
+/// Handles a field that isn't part of the format as `policy` says.
+fn unknown_field<'de, 'raw, R, M>(
+    access: &mut M,
+    key: CowStr<'de>,
+    policy: UnknownFields,
+    tracker: Option<&Tracker>,
+    expected: &'static [&'static str],
+    extra_fields: &mut ExtraFields<'raw>,
+) -> Result<(), M::Error>
+where
+    R: RawValues<'de, 'raw>,
+    M: MapAccess<'de>,
+{
+    match policy {
+        UnknownFields::Error => Err(M::Error::unknown_field(&key.0, expected)),
+        UnknownFields::Ignore => access.next_value::<IgnoredAny>().map(|_| ()),
+        UnknownFields::Preserve => {
+            if let Some(tracker) = tracker {
+                tracker.key(&key.0)?;
+            }
+            let value = tracked(tracker, R::into_raw(access.next_value()?))?;
+            extra_fields.push((R::into_key(key.0), value));
+            Ok(())
+        }
+    }
+}
 
 const NODE_FIELDS: &[&str] = &[
     "id",
@@ -44,7 +171,7 @@ const NODE_FIELDS: &[&str] = &[
     "positionTicks",
 ];
 
-impl<'de: 'raw, 'raw> Visitor<'de> for NodeVisitor<'raw> {
+impl<'de, 'raw, R: RawValues<'de, 'raw>> Visitor<'de> for NodeVisitor<'_, 'raw, R> {
     type Value = Node<'raw>;
 
     fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
@@ -61,29 +188,51 @@ impl<'de: 'raw, 'raw> Visitor<'de> for NodeVisitor<'raw> {
         let mut children = None;
         let mut deopt_reason = None;
         let mut position_ticks = None;
+        let mut extra_fields = Vec::new();
 
-        while let Some(key) = access.next_key()? {
-            match key {
+        while let Some(key) = access.next_key::<CowStr>()? {
+            match &*key.0 {
                 "id" => {
                     id = access.next_value()?;
                 }
                 "callFrame" => {
-                    call_frame = access.next_value()?;
+                    call_frame = Some(tracked(self.2, R::into_raw(access.next_value()?))?);
                 }
                 "hitCount" => {
                     hit_count = access.next_value()?;
                 }
                 "children" => {
-                    children = access.next_value()?;
+                    let mut ids = Vec::new();
+                    access.next_value_seed(
+                        visit_seq(|id, _| ids.push(id), "a sequence of child ids")
+                            .limit(self.2.and_then(Tracker::max_children)),
+                    )?;
+                    if let Some(tracker) = self.2 {
+                        tracker.children(ids.len());
+                    }
+                    children = Some(ids);
                 }
                 "deoptReason" => {
-                    deopt_reason = access.next_value()?;
+                    deopt_reason = access
+                        .next_value::<Option<R::Raw>>()?
+                        .map(|raw| tracked(self.2, R::into_raw(raw)))
+                        .transpose()?;
                 }
                 "positionTicks" => {
-                    position_ticks = access.next_value()?;
+                    position_ticks = access
+                        .next_value::<Option<R::Raw>>()?
+                        .map(|raw| tracked(self.2, R::into_raw(raw)))
+                        .transpose()?;
                 }
                 _ => {
-                    return Err(M::Error::unknown_field(key, NODE_FIELDS));
+                    unknown_field::<R, M>(
+                        &mut access,
+                        key,
+                        self.0,
+                        self.2,
+                        NODE_FIELDS,
+                        &mut extra_fields,
+                    )?;
                 }
             }
         }
@@ -91,6 +240,17 @@ impl<'de: 'raw, 'raw> Visitor<'de> for NodeVisitor<'raw> {
         let id = check_missing!(M::Error, id);
         let call_frame = check_missing!(M::Error, call_frame, "callFrame");
         let hit_count = check_missing!(M::Error, hit_count, "hitCount");
+        if self.1 {
+            let visitor = CallFrameVisitor(self.0 == UnknownFields::Error, PhantomData);
+            let mut deserializer = serde_json::Deserializer::from_str(call_frame.get());
+            if let Err(error) = deserializer.deserialize_map(visitor) {
+                return Err(M::Error::custom(alloc::format!(
+                    "invalid callFrame of node {}: {}",
+                    id,
+                    error
+                )));
+            }
+        }
 
         Ok(Node {
             id,
@@ -100,15 +260,134 @@ impl<'de: 'raw, 'raw> Visitor<'de> for NodeVisitor<'raw> {
             children,
             deopt_reason,
             position_ticks,
+            extra_fields,
+        })
+    }
+}
+
+/// Rejects keys that aren't part of a call frame if strict.
+struct CallFrameVisitor<'a>(bool, PhantomData<fn() -> CallFrame<'a>>);
+
+const CALL_FRAME_FIELDS: &[&str] = &[
+    "functionName",
+    "scriptId",
+    "url",
+    "lineNumber",
+    "columnNumber",
+];
+
+impl<'de: 'raw, 'raw> Visitor<'de> for CallFrameVisitor<'raw> {
+    type Value = CallFrame<'raw>;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str("v8 profile call frame json")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut function_name: Option<CowStr> = None;
+        let mut script_id: Option<CowStr> = None;
+        let mut url: Option<CowStr> = None;
+        let mut line_number = None;
+        let mut column_number = None;
+
+        while let Some(key) = access.next_key::<CowStr>()? {
+            match &*key.0 {
+                "functionName" => {
+                    function_name = access.next_value()?;
+                }
+                "scriptId" => {
+                    script_id = access.next_value()?;
+                }
+                "url" => {
+                    url = access.next_value()?;
+                }
+                "lineNumber" => {
+                    line_number = access.next_value()?;
+                }
+                "columnNumber" => {
+                    column_number = access.next_value()?;
+                }
+                key if self.0 => {
+                    return Err(M::Error::unknown_field(key, CALL_FRAME_FIELDS));
+                }
+                // newer profiles add keys like codeType
+                _ => {
+                    access.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        let function_name = check_missing!(M::Error, function_name, "functionName");
+        let script_id = check_missing!(M::Error, script_id, "scriptId");
+        let url = check_missing!(M::Error, url);
+        let line_number = check_missing!(M::Error, line_number, "lineNumber");
+        let column_number = check_missing!(M::Error, column_number, "columnNumber");
+
+        Ok(CallFrame {
+            function_name: function_name.0,
+            script_id: script_id.0,
+            url: url.0,
+            line_number,
+            column_number,
         })
     }
 }
 
-const PROFILE_FIELDS: &[&str] = &["nodes", "startTime", "endTime", "samples", "timeDeltas"];
+struct PositionTickVisitor;
 
-struct ProfileVisitor<'raw>(PhantomData<fn() -> Profile<'raw>>);
+const POSITION_TICK_FIELDS: &[&str] = &["line", "ticks"];
+
+impl<'de> Visitor<'de> for PositionTickVisitor {
+    type Value = PositionTick;
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        formatter.write_str("v8 profile position tick json")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut line = None;
+        let mut ticks = None;
+
+        while let Some(key) = access.next_key::<CowStr>()? {
+            match &*key.0 {
+                "line" => {
+                    line = access.next_value()?;
+                }
+                "ticks" => {
+                    ticks = access.next_value()?;
+                }
+                key => {
+                    return Err(M::Error::unknown_field(key, POSITION_TICK_FIELDS));
+                }
+            }
+        }
 
-impl<'de: 'raw, 'raw> Visitor<'de> for ProfileVisitor<'raw> {
+        let line = check_missing!(M::Error, line);
+        let ticks = check_missing!(M::Error, ticks);
+
+        Ok(PositionTick { line, ticks })
+    }
+}
+
+const PROFILE_FIELDS: &[&str] = &[
+    "nodes",
+    "startTime",
+    "endTime",
+    "samples",
+    "timeDeltas",
+    "metadata",
+    "samplingInterval",
+];
+
+struct ProfileVisitor<'raw, R>(ParseOptions, PhantomData<fn() -> (Profile<'raw>, R)>);
+
+impl<'de, 'raw, R: RawValues<'de, 'raw>> Visitor<'de> for ProfileVisitor<'raw, R> {
     type Value = Profile<'raw>;
 
     fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
@@ -119,106 +398,187 @@ impl<'de: 'raw, 'raw> Visitor<'de> for ProfileVisitor<'raw> {
     where
         M: MapAccess<'de>,
     {
-        let mut node_index: HashMap<u64, usize> = HashMap::new();
-        let mut parent_ids: Vec<(u64, u64)> = Vec::new();
-        let mut nodes: Option<Vec<Node<'raw>>> = None;
+        let mut nodes: Option<Nodes<'raw>> = None;
         let mut start_time = None;
         let mut end_time = None;
         let mut samples: Vec<Sample> = Vec::new();
         let mut has_samples = false;
         let mut has_time_deltas = false;
-        let mut current = Duration::default();
-        while let Some(key) = access.next_key()? {
-            match key {
+        let mut time_deltas = TimeDeltas::new(self.0.time_deltas);
+        let mut metadata = None;
+        let mut sampling_interval = None;
+        let mut extra_fields = Vec::new();
+        let tracker = Tracker::new(self.0.limits);
+        while let Some(key) = access.next_key::<CowStr>()? {
+            match &*key.0 {
                 "nodes" => {
-                    let inner = nodes.insert(Vec::new());
-                    access.next_value_seed(visit_seq(
-                        |node: Node, index| {
-                            node_index.insert(node.id, index);
-                            if let Some(ref children) = node.children {
-                                parent_ids
-                                    .extend(children.iter().map(|&child_id| (node.id, child_id)));
-                            }
-                            inner.push(node);
-                        },
-                        "a sequence of v8 profile nodes",
-                    ))?;
+                    let seed = NodeSeed::<R>(
+                        self.0.unknown_fields,
+                        self.0.validate_call_frames,
+                        &tracker,
+                        PhantomData,
+                    );
+                    nodes = Some(read_nodes(&mut access, seed, &tracker)?);
                 }
                 "startTime" => {
-                    start_time = access.next_value()?;
+                    start_time = Some(access.next_value::<Time>()?);
                 }
                 "endTime" => {
-                    end_time = access.next_value()?;
+                    end_time = Some(access.next_value::<Time>()?);
                 }
                 "samples" => {
                     has_samples = true;
-                    access.next_value_seed(visit_seq(
-                        |node_id: u64, index| {
-                            if let Some(sample) = samples.get_mut(index) {
-                                sample.node_id = node_id;
-                            } else {
-                                samples.insert(
-                                    index,
-                                    Sample {
-                                        node_id,
-                                        ts: Duration::default(),
-                                    },
-                                );
-                            }
-                        },
-                        "a sequence of node ids",
-                    ))?;
+                    access.next_value_seed(
+                        visit_seq(
+                            |node_id: u64, index| sample_at(&mut samples, index).node_id = node_id,
+                            "a sequence of node ids",
+                        )
+                        .limit(tracker.max_samples()),
+                    )?;
                 }
                 "timeDeltas" => {
                     has_time_deltas = true;
-                    access.next_value_seed(visit_seq(
-                        |delta: i32, index| {
-                            current = offset_duration(current, delta);
-                            if let Some(sample) = samples.get_mut(index) {
-                                sample.ts = current;
-                            } else {
-                                samples.insert(
-                                    index,
-                                    Sample {
-                                        node_id: 0,
-                                        ts: current,
-                                    },
-                                );
-                            }
-                        },
-                        "a sequence of time deltas",
-                    ))?;
+                    access.next_value_seed(
+                        visit_seq(
+                            |delta: i64, index| {
+                                let sample = sample_at(&mut samples, index);
+                                if let Some(ts) = time_deltas.add(delta) {
+                                    sample.ts = ts;
+                                }
+                            },
+                            "a sequence of time deltas",
+                        )
+                        .limit(tracker.max_samples()),
+                    )?;
+                }
+                "metadata" => {
+                    metadata = Some(tracked(Some(&tracker), R::into_raw(access.next_value()?))?);
+                }
+                "samplingInterval" => {
+                    sampling_interval = Some(access.next_value::<Time>()?.0);
                 }
                 _ => {
-                    return Err(M::Error::unknown_field(key, PROFILE_FIELDS));
+                    unknown_field::<R, M>(
+                        &mut access,
+                        key,
+                        self.0.unknown_fields,
+                        Some(&tracker),
+                        PROFILE_FIELDS,
+                        &mut extra_fields,
+                    )?;
                 }
             }
         }
-        let mut nodes = check_missing!(M::Error, nodes);
-        let start_time = check_missing!(M::Error, start_time, "startTime");
-        let end_time = check_missing!(M::Error, end_time, "endTime");
-
-        for (parent_id, ref node_id) in parent_ids {
-            let node = &mut nodes[node_index[node_id]];
-            node.parent_id = Some(parent_id);
-        }
+        let (nodes, node_index) = check_missing!(M::Error, nodes);
+        let Time(start_time, exact_start_time) = check_missing!(M::Error, start_time, "startTime");
+        let Time(end_time, exact_end_time) = check_missing!(M::Error, end_time, "endTime");
 
-        if !has_samples {
+        // a profile without samples may leave out both, but not just one
+        if !has_samples && !samples.is_empty() {
             return Err(M::Error::missing_field("samples"));
         }
 
-        if !has_time_deltas {
+        if !has_time_deltas && !samples.is_empty() {
             return Err(M::Error::missing_field("timeDeltas"));
         }
 
-        samples.sort();
+        check_sample_nodes::<M::Error>(&samples, &node_index)?;
+
+        let start_time = time_deltas
+            .finish(&mut samples, start_time)
+            .map_err(M::Error::custom)?;
+
+        if !self.0.preserve_sample_order {
+            // stable, samples with the same timestamp keep their order
+            samples.sort_by_key(|sample| sample.ts);
+        }
 
         Ok(Profile {
             nodes,
-            start_time: Duration::from_micros(start_time),
-            end_time: Duration::from_micros(end_time),
+            start_time,
+            end_time,
             samples,
+            metadata,
+            sampling_interval,
+            extra_fields,
+            exact_times: (exact_start_time, exact_end_time),
             node_index,
+            weighting: Weighting::default(),
         })
     }
 }
+
+/// Nodes and the index of each by id.
+type Nodes<'raw> = (Vec<Node<'raw>>, HashMap<u64, usize>);
+
+/// Reads the nodes and their index by id, and sets their parent ids.
+fn read_nodes<'de, 'raw, R, M>(
+    access: &mut M,
+    seed: NodeSeed<'_, 'raw, R>,
+    tracker: &Tracker,
+) -> Result<Nodes<'raw>, M::Error>
+where
+    R: RawValues<'de, 'raw>,
+    M: MapAccess<'de>,
+{
+    let mut nodes = Vec::new();
+    let mut node_index: HashMap<u64, usize> = HashMap::new();
+    let mut parent_ids: Vec<(u64, u64)> = Vec::new();
+    access.next_value_seed(
+        visit_seq_seed(
+            seed,
+            |node: Node<'raw>, index| {
+                node_index.insert(node.id, index);
+                if let Some(ref children) = node.children {
+                    parent_ids.extend(children.iter().map(|&child_id| (node.id, child_id)));
+                }
+                nodes.push(node);
+            },
+            "a sequence of v8 profile nodes",
+        )
+        .limit(tracker.max_nodes()),
+    )?;
+    for (parent_id, node_id) in parent_ids {
+        match node_index.get(&node_id) {
+            Some(&index) => nodes[index].parent_id = Some(parent_id),
+            None => {
+                return Err(M::Error::custom(alloc::format!(
+                    "unknown child id {}",
+                    node_id
+                )))
+            }
+        }
+    }
+    Ok((nodes, node_index))
+}
+
+/// Fails on the first sample of a node that isn't in the profile. A profile
+/// without nodes is a chunk written with shared nodes, whose samples are of
+/// the nodes in the node table.
+fn check_sample_nodes<E: Error>(
+    samples: &[Sample],
+    node_index: &HashMap<u64, usize>,
+) -> Result<(), E> {
+    if node_index.is_empty() {
+        return Ok(());
+    }
+    match samples
+        .iter()
+        .find(|sample| !node_index.contains_key(&sample.node_id))
+    {
+        Some(sample) => Err(E::custom(alloc::format!(
+            "unknown sample node id {}",
+            sample.node_id
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// The sample at `index`, samples and time deltas are visited one after the
+/// other so either may come first.
+fn sample_at(samples: &mut Vec<Sample>, index: usize) -> &mut Sample {
+    if index >= samples.len() {
+        samples.resize(index + 1, Sample::default());
+    }
+    &mut samples[index]
+}
@@ -0,0 +1,122 @@
+use crate::de::CowStr;
+use crate::{CallFrame, Node, Profile};
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+use core::time::Duration;
+use hashbrown::HashMap;
+
+/// Sampled time in deoptimized functions, see [`Profile::deopts`].
+#[derive(Debug, Default)]
+pub struct DeoptReport<'raw> {
+    /// Self time of all nodes with a deopt reason.
+    pub total: Duration,
+    /// Heaviest first.
+    pub reasons: Vec<DeoptReason<'raw>>,
+    /// Call sites by reason, heaviest first.
+    pub sites: Vec<DeoptSite<'raw>>,
+}
+
+#[derive(Debug)]
+pub struct DeoptReason<'raw> {
+    pub reason: Cow<'raw, str>,
+    pub nodes: usize,
+    pub self_time: Duration,
+}
+
+#[derive(Debug)]
+pub struct DeoptSite<'raw> {
+    pub frame: CallFrame<'raw>,
+    pub reason: Cow<'raw, str>,
+    pub self_time: Duration,
+}
+
+impl<'raw> Node<'raw> {
+    /// Parses the deopt reason, an empty reason is treated as none.
+    ///
+    /// # Errors
+    ///
+    /// If the deopt reason is not a string.
+    pub fn deopt_reason(&self) -> Result<Option<Cow<'raw, str>>, serde_json::Error> {
+        let reason = match self.deopt_reason {
+            Some(ref raw) => match raw.borrowed() {
+                Some(json) => serde_json::from_str::<CowStr>(json)?.0,
+                None => Cow::Owned(serde_json::from_str::<CowStr>(raw.get())?.0.into_owned()),
+            },
+            None => return Ok(None),
+        };
+        Ok(Some(reason).filter(|reason| !reason.is_empty()))
+    }
+}
+
+impl<'raw> Profile<'raw> {
+    /// Aggregates the self time of deoptimized nodes by reason and call site.
+    ///
+    /// # Errors
+    ///
+    /// If a deoptimized node has an invalid call frame or deopt reason.
+    pub fn deopts(&self) -> Result<DeoptReport<'raw>, serde_json::Error> {
+        let self_times = self.self_times();
+        let mut report = DeoptReport::default();
+        let mut reasons: HashMap<Cow<'raw, str>, DeoptReason<'raw>> = HashMap::new();
+        let mut sites: HashMap<(CallFrame<'raw>, Cow<'raw, str>), Duration> = HashMap::new();
+
+        for node in &self.nodes {
+            if let Some(reason) = node.deopt_reason()? {
+                let self_time = self_times.get(&node.id).copied().unwrap_or_default();
+                report.total += self_time;
+
+                let entry = reasons
+                    .entry(reason.clone())
+                    .or_insert_with(|| DeoptReason {
+                        reason: reason.clone(),
+                        nodes: 0,
+                        self_time: Duration::default(),
+                    });
+                entry.nodes += 1;
+                entry.self_time += self_time;
+
+                *sites.entry((node.frame()?, reason)).or_default() += self_time;
+            }
+        }
+
+        report.reasons = reasons.into_iter().map(|(_, reason)| reason).collect();
+        report
+            .reasons
+            .sort_by_key(|reason| Reverse(reason.self_time));
+        report.sites = sites
+            .into_iter()
+            .map(|((frame, reason), self_time)| DeoptSite {
+                frame,
+                reason,
+                self_time,
+            })
+            .collect();
+        report.sites.sort_by_key(|site| Reverse(site.self_time));
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    #[test]
+    fn deopts() {
+        const PROFILE: &str = concat!(
+            r#"{"nodes":["#,
+            r#"{"id":1,"callFrame":{"functionName":"(root)","scriptId":"0","url":"","lineNumber":-1,"columnNumber":-1},"hitCount":0,"children":[2,3]},"#,
+            r#"{"id":2,"callFrame":{"functionName":"add","scriptId":"1","url":"app.js","lineNumber":4,"columnNumber":2},"hitCount":2,"deoptReason":"not a Smi"},"#,
+            r#"{"id":3,"callFrame":{"functionName":"sub","scriptId":"1","url":"app.js","lineNumber":9,"columnNumber":2},"hitCount":1,"deoptReason":""}"#,
+            r#"],"startTime":0,"endTime":400,"samples":[2,3,2],"timeDeltas":[100,100,100]}"#
+        );
+
+        let profile: crate::Profile<'_> = serde_json::from_str(PROFILE).unwrap();
+        let report = profile.deopts().unwrap();
+
+        assert_eq!(report.total, Duration::from_micros(200));
+        assert_eq!(report.reasons.len(), 1);
+        assert_eq!(report.reasons[0].reason, "not a Smi");
+        assert_eq!(report.sites[0].frame.function_name, "add");
+    }
+}
@@ -0,0 +1,81 @@
+use crate::Profile;
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+use hashbrown::HashMap;
+
+/// Stack depth of the samples, see [`Profile::depth_stats`].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DepthStats {
+    pub max: usize,
+    /// Mean over all samples.
+    pub mean: f64,
+    /// Each sampled node with its depth, deepest first.
+    pub deepest: Vec<(u64, usize)>,
+}
+
+impl Profile<'_> {
+    /// Stack depth statistics over the samples, where the depth of a node is
+    /// the number of frames below the root, useful to spot runaway recursion.
+    #[must_use]
+    pub fn depth_stats(&self) -> DepthStats {
+        let mut depths: HashMap<u64, usize> = HashMap::new();
+        let mut sum = 0;
+        for sample in &self.samples {
+            let depth = *depths
+                .entry(sample.node_id)
+                .or_insert_with(|| self.parent_ids_iter(sample.node_id).count());
+            sum += depth;
+        }
+
+        let mut deepest: Vec<_> = depths.into_iter().collect();
+        deepest.sort_by_key(|&(node_id, depth)| (Reverse(depth), node_id));
+        #[allow(clippy::cast_precision_loss)]
+        let mean = if self.samples.is_empty() {
+            0.0
+        } else {
+            sum as f64 / self.samples.len() as f64
+        };
+        DepthStats {
+            max: deepest.first().map_or(0, |&(_, depth)| depth),
+            mean,
+            deepest,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ser::tests::PROFILE;
+    use crate::Profile;
+
+    #[test]
+    fn depth_stats() {
+        let profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        let stats = profile.depth_stats();
+
+        let depth = |mut node_id| {
+            let mut depth = 0;
+            while let Some(parent_id) = profile[node_id].parent_id {
+                node_id = parent_id;
+                depth += 1;
+            }
+            depth
+        };
+        let depths: alloc::vec::Vec<usize> = profile
+            .samples
+            .iter()
+            .map(|sample| depth(sample.node_id))
+            .collect();
+        assert_eq!(stats.max, *depths.iter().max().unwrap());
+        assert!(stats.max > 1);
+        #[allow(clippy::cast_precision_loss)]
+        let mean = depths.iter().sum::<usize>() as f64 / depths.len() as f64;
+        assert!((stats.mean - mean).abs() < 1e-9);
+        assert_eq!(stats.deepest[0].1, stats.max);
+        assert!(stats.deepest.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+
+        let mut empty: Profile = serde_json::from_str(PROFILE).unwrap();
+        empty.samples.clear();
+        assert_eq!(empty.depth_stats(), super::DepthStats::default());
+    }
+}
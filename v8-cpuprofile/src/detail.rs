@@ -0,0 +1,119 @@
+//! The callers and callees of one function, see
+//! [`Profile::function_detail`].
+
+use crate::{FrameKey, Profile};
+use alloc::vec::Vec;
+use core::time::Duration;
+use hashbrown::HashMap;
+
+/// Where the time of a function came from and went, like pprof's `peek`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FunctionDetail<'raw> {
+    pub self_time: Duration,
+    /// Time with the function anywhere on the stack, recursive calls are only
+    /// counted once.
+    pub total_time: Duration,
+    /// The functions calling it with the time spent in it through each call,
+    /// the most first. Adds up to the total time unless it is a root.
+    pub callers: Vec<(FrameKey<'raw>, Duration)>,
+    /// The functions it calls with the time spent in each, the most first.
+    /// Adds up to the total time less the self time.
+    pub callees: Vec<(FrameKey<'raw>, Duration)>,
+}
+
+impl<'raw> Profile<'raw> {
+    /// Splits the total time of a function by its callers and callees. In a
+    /// recursion the caller is the one of the outermost call and the callee
+    /// the one of the innermost.
+    ///
+    /// # Errors
+    ///
+    /// If a node has an invalid call frame.
+    pub fn function_detail(
+        &self,
+        frame: &FrameKey<'_>,
+    ) -> Result<FunctionDetail<'raw>, serde_json::Error> {
+        let mut keys = Vec::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            keys.push(FrameKey::from(node.frame()?));
+        }
+        let key = |node_id: u64| &keys[self.node_index[&node_id]];
+
+        let mut detail = FunctionDetail::default();
+        let mut from_callers: HashMap<&FrameKey<'raw>, Duration> = HashMap::new();
+        let mut into_callees: HashMap<&FrameKey<'raw>, Duration> = HashMap::new();
+        // from the sampled frame up to the root
+        let mut stack: Vec<&FrameKey<'raw>> = Vec::new();
+        for (node_id, time) in self.self_times() {
+            stack.clear();
+            stack.push(key(node_id));
+            stack.extend(self.parent_ids_iter(node_id).map(key));
+            let Some(innermost) = stack.iter().position(|key| *key == frame) else {
+                continue;
+            };
+            detail.total_time += time;
+            match innermost.checked_sub(1) {
+                Some(callee) => *into_callees.entry(stack[callee]).or_default() += time,
+                None => detail.self_time += time,
+            }
+            let outermost = stack
+                .iter()
+                .rposition(|key| *key == frame)
+                .unwrap_or(innermost);
+            if let Some(&caller) = stack.get(outermost + 1) {
+                *from_callers.entry(caller).or_default() += time;
+            }
+        }
+        detail.callers = sorted(from_callers);
+        detail.callees = sorted(into_callees);
+        Ok(detail)
+    }
+}
+
+fn sorted<'raw>(times: HashMap<&FrameKey<'raw>, Duration>) -> Vec<(FrameKey<'raw>, Duration)> {
+    let mut times: Vec<_> = times
+        .into_iter()
+        .filter(|(_, time)| *time > Duration::default())
+        .map(|(frame, time)| (frame.clone(), time))
+        .collect();
+    times.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    times
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ser::tests::PROFILE;
+    use crate::{FrameKey, Profile};
+    use core::time::Duration;
+
+    #[test]
+    fn function_detail() {
+        let profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        let frame_times = profile.frame_times().unwrap();
+        let sum =
+            |times: &[(FrameKey, Duration)]| times.iter().map(|(_, time)| *time).sum::<Duration>();
+
+        for (frame, times) in &frame_times {
+            let detail = profile.function_detail(frame).unwrap();
+            assert_eq!(detail.self_time, times.self_time, "{}", frame);
+            assert_eq!(detail.total_time, times.total_time, "{}", frame);
+            assert_eq!(
+                Some(sum(&detail.callees)),
+                detail.total_time.checked_sub(detail.self_time),
+                "{}",
+                frame
+            );
+            if frame.function_name != "(root)" {
+                assert_eq!(sum(&detail.callers), detail.total_time, "{}", frame);
+            }
+        }
+
+        let loader = frame_times
+            .keys()
+            .find(|frame| frame.function_name == "compileForInternalLoader")
+            .unwrap();
+        let detail = profile.function_detail(loader).unwrap();
+        assert_eq!(detail.callers[0].0.function_name, "nativeModuleRequire");
+        assert!(detail.callers.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+    }
+}
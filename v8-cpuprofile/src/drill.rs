@@ -0,0 +1,141 @@
+//! Big trees cut down to the paths worth reading, see
+//! [`Profile::drill_down`].
+
+use crate::join::copy;
+use crate::{Node, Profile, RawJson};
+use alloc::vec::Vec;
+use hashbrown::{HashMap, HashSet};
+
+/// Call frame of the node standing in for pruned children.
+const OTHER: &str =
+    r#"{"functionName":"(other)","scriptId":"0","url":"","lineNumber":-1,"columnNumber":-1}"#;
+
+impl<'raw> Profile<'raw> {
+    /// The tree pruned to the paths with at least `threshold` of the total
+    /// time, so 0.05 keeps the paths with 5% or more. The children pruned
+    /// from a node are replaced by one `(other)` child with their samples,
+    /// so times still add up.
+    #[must_use]
+    pub fn drill_down(&self, threshold: f64) -> Profile<'raw> {
+        let rollups = self.with_rollups();
+        let root = self.root().map(|root| root.id);
+        let minimum = root.map_or_else(Default::default, |root| {
+            rollups.total_time(root).mul_f64(threshold.clamp(0.0, 1.0))
+        });
+
+        let mut next_id = self.nodes.iter().map(|node| node.id).max().unwrap_or(0);
+        let mut kept = HashSet::new();
+        // pruned node ids to the `(other)` node taking their samples
+        let mut moved: HashMap<u64, u64> = HashMap::new();
+        // kept node ids to their `(other)` child
+        let mut others: HashMap<u64, Node<'raw>> = HashMap::new();
+        let mut stack: Vec<u64> = root.into_iter().collect();
+        kept.extend(root);
+        while let Some(node_id) = stack.pop() {
+            for child_id in self.child_ids(node_id) {
+                if rollups.total_time(child_id) >= minimum {
+                    kept.insert(child_id);
+                    stack.push(child_id);
+                    continue;
+                }
+                let other = others.entry(node_id).or_insert_with(|| {
+                    next_id += 1;
+                    other(next_id, node_id)
+                });
+                for pruned_id in core::iter::once(child_id).chain(self.dfs(child_id)) {
+                    other.hit_count += self[pruned_id].hit_count;
+                    moved.insert(pruned_id, other.id);
+                }
+            }
+        }
+
+        let mut nodes = Vec::with_capacity(kept.len() + others.len());
+        for node in self.nodes.iter().filter(|node| kept.contains(&node.id)) {
+            let mut node = copy(node);
+            if let Some(children) = &mut node.children {
+                children.retain(|child_id| kept.contains(child_id));
+                children.extend(others.get(&node.id).map(|other| other.id));
+            }
+            let other = others.remove(&node.id);
+            nodes.push(node);
+            nodes.extend(other);
+        }
+        let node_index = nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (node.id, index))
+            .collect();
+        let samples = self
+            .samples
+            .iter()
+            .filter_map(|sample| {
+                let mut sample = *sample;
+                if !kept.contains(&sample.node_id) {
+                    sample.node_id = *moved.get(&sample.node_id)?;
+                }
+                Some(sample)
+            })
+            .collect();
+
+        Profile {
+            nodes,
+            start_time: self.start_time,
+            end_time: self.end_time,
+            samples,
+            metadata: self.metadata.clone(),
+            sampling_interval: self.sampling_interval,
+            extra_fields: self.extra_fields.clone(),
+            exact_times: self.exact_times,
+            node_index,
+            weighting: self.weighting,
+        }
+    }
+}
+
+fn other(id: u64, parent_id: u64) -> Node<'static> {
+    Node {
+        id,
+        parent_id: Some(parent_id),
+        call_frame: RawJson::new(OTHER).expect("a call frame is valid json"),
+        hit_count: 0,
+        children: None,
+        deopt_reason: None,
+        position_ticks: None,
+        extra_fields: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ser::tests::PROFILE;
+    use crate::Profile;
+    use core::time::Duration;
+
+    #[test]
+    fn drill_down() {
+        let profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        let root = profile.root().unwrap().id;
+        let total = profile.with_rollups().total_time(root);
+
+        let pruned = profile.drill_down(0.05);
+        pruned.roundtrip_check().unwrap();
+        assert!(pruned.nodes.len() < profile.nodes.len());
+        assert_eq!(pruned.samples.len(), profile.samples.len());
+        let rollups = pruned.with_rollups();
+        assert_eq!(rollups.total_time(root), total);
+        let mut others = 0;
+        for node in &pruned.nodes {
+            if node.frame().unwrap().function_name == "(other)" {
+                others += 1;
+                assert!(rollups.total_time(node.id) > Duration::default());
+            } else {
+                assert!(rollups.total_time(node.id) >= total.mul_f64(0.05));
+            }
+        }
+        assert!(others > 0);
+
+        let everything = profile.drill_down(0.0);
+        assert_eq!(everything.nodes.len(), profile.nodes.len());
+        assert_eq!(everything.samples, profile.samples);
+    }
+}
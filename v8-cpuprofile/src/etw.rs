@@ -0,0 +1,230 @@
+//! Native cpu samples of Windows ETW traces, to line up with a cpuprofile in
+//! one [`Trace`](crate::trace::Trace) when native code is suspected of
+//! using the cpu, see [`EtwCapture::threads`].
+
+use crate::format::StackTree;
+use crate::{CallFrame, Micros, Profile};
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::time::Duration;
+use hashbrown::HashMap;
+use std::io;
+
+/// One stack sampled by ETW cpu sampling.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct EtwSample {
+    /// Microseconds in the clock of the trace.
+    pub timestamp: Micros,
+    pub pid: u32,
+    pub tid: u32,
+    /// Frames as `module!function`, outermost first.
+    pub stack: Vec<String>,
+}
+
+/// The cpu samples of an ETW trace, like those `wpr` records and `xperf`
+/// or WPA export, converted to json.
+///
+/// It deserializes from `samples`, each with `timestamp` in microseconds,
+/// `pid`, `tid` and `stack`, an optional `interval_us`, the sampling
+/// interval, and optional `threads`, each with `pid`, `tid` and `name`.
+/// Other fields are ignored.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct EtwCapture {
+    pub samples: Vec<EtwSample>,
+    /// 1ms, ETW's default, if `None`.
+    pub interval: Option<Duration>,
+    /// Names by process and thread id.
+    pub thread_names: Vec<(u32, u32, String)>,
+}
+
+/// The samples of one thread of an [`EtwCapture`].
+#[derive(Debug)]
+pub struct EtwThread {
+    pub pid: u32,
+    pub tid: u32,
+    pub name: String,
+    pub profile: Profile<'static>,
+}
+
+impl EtwCapture {
+    /// A profile per thread, in the order the threads first appear. Each
+    /// frame's function is what follows the `!` and its url the module.
+    ///
+    /// V8 on Windows keeps time with the same performance counter ETW does,
+    /// so a trace in microseconds of that counter lines up with a
+    /// cpuprofile as is. `offset` is added to the timestamps otherwise.
+    ///
+    /// # Errors
+    ///
+    /// If the offset moves a sample before 0 or a frame can't be written.
+    pub fn threads(&self, offset: i64) -> io::Result<Vec<EtwThread>> {
+        let interval = Micros::from(self.interval.unwrap_or(Duration::from_millis(1)));
+        let mut threads: Vec<((u32, u32), Vec<&EtwSample>)> = Vec::new();
+        for sample in &self.samples {
+            let key = (sample.pid, sample.tid);
+            match threads.iter_mut().find(|(thread, _)| *thread == key) {
+                Some((_, samples)) => samples.push(sample),
+                None => threads.push((key, alloc::vec![sample])),
+            }
+        }
+
+        threads
+            .into_iter()
+            .map(|((pid, tid), mut samples)| {
+                samples.sort_by_key(|sample| sample.timestamp);
+                let name = self
+                    .thread_names
+                    .iter()
+                    .find(|&&(name_pid, name_tid, _)| (name_pid, name_tid) == (pid, tid))
+                    .map_or_else(
+                        || alloc::format!("native {}:{}", pid, tid),
+                        |(_, _, name)| name.clone(),
+                    );
+                Ok(EtwThread {
+                    pid,
+                    tid,
+                    name,
+                    profile: profile(&samples, offset, interval)?,
+                })
+            })
+            .collect()
+    }
+}
+
+fn profile(samples: &[&EtwSample], offset: i64, interval: Micros) -> io::Result<Profile<'static>> {
+    let mut frames = Vec::new();
+    let mut indexes: HashMap<&str, usize> = HashMap::new();
+    for sample in samples {
+        for frame in &sample.stack {
+            indexes.entry(frame.as_str()).or_insert_with(|| {
+                frames.push(call_frame(frame));
+                frames.len() - 1
+            });
+        }
+    }
+    let mut tree = StackTree::new(&frames)?;
+
+    let times = samples
+        .iter()
+        .map(|sample| {
+            sample.timestamp.checked_add_delta(offset).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "the offset moves a sample out of range",
+                )
+            })
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    let mut weights = Vec::with_capacity(samples.len());
+    for (index, sample) in samples.iter().enumerate() {
+        let node_id = tree.node(sample.stack.iter().map(|frame| indexes[frame.as_str()]))?;
+        let weight = times
+            .get(index + 1)
+            .map_or(interval, |next| next.saturating_sub(times[index]));
+        weights.push((node_id, weight));
+    }
+    let start = times.first().copied().unwrap_or_default();
+    let end = times
+        .last()
+        .map_or(start, |last| last.saturating_add(interval));
+    let mut profile = tree.finish(start, end, &weights);
+    profile.sampling_interval = Some(interval);
+    Ok(profile)
+}
+
+fn call_frame(frame: &str) -> CallFrame<'static> {
+    let (module, function) = match frame.find('!') {
+        Some(index) => (&frame[..index], &frame[index + 1..]),
+        None => ("", frame),
+    };
+    CallFrame {
+        function_name: Cow::Owned(String::from(function)),
+        script_id: Cow::Borrowed("0"),
+        url: Cow::Owned(String::from(module)),
+        line_number: -1,
+        column_number: -1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EtwCapture;
+    use crate::ser::tests::PROFILE;
+    use crate::trace::{Trace, Track};
+    use crate::{Micros, Profile};
+    use core::time::Duration;
+
+    const ETW: &str = r#"{
+        "trace": "node.etl",
+        "interval_us": 1000,
+        "threads": [{"pid": 4200, "tid": 8, "name": "node main"}],
+        "samples": [
+            {"timestamp": 2000.4, "pid": 4200, "tid": 8, "stack": ["ntdll.dll!RtlUserThreadStart", "node.exe!uv_run"]},
+            {"timestamp": 1000, "pid": 4200, "tid": 8, "stack": ["ntdll.dll!RtlUserThreadStart", "node.exe!uv_run", "node.exe!uv__io_poll"]},
+            {"timestamp": 1500, "pid": 4200, "tid": 12, "stack": ["ntdll.dll!RtlUserThreadStart", "zlib"]},
+            {"timestamp": 3000, "pid": 4200, "tid": 8, "stack": []}
+        ]
+    }"#;
+
+    #[test]
+    fn threads() {
+        let capture: EtwCapture = serde_json::from_str(ETW).unwrap();
+        assert_eq!(capture.samples.len(), 4);
+        assert_eq!(capture.samples[0].timestamp, Micros(2000));
+        let threads = capture.threads(0).unwrap();
+        assert_eq!(threads.len(), 2);
+
+        let main = &threads[0];
+        assert_eq!((main.pid, main.tid), (4200, 8));
+        assert_eq!(main.name, "node main");
+        assert_eq!(main.profile.start_time, Micros(1000));
+        assert_eq!(main.profile.end_time, Micros(4000));
+        assert_eq!(main.profile.samples.len(), 3);
+        let leaf = main.profile[main.profile.samples[0].node_id]
+            .frame()
+            .unwrap();
+        assert_eq!(leaf.function_name, "uv__io_poll");
+        assert_eq!(leaf.url, "node.exe");
+        // the empty stack is a sample of the root
+        assert!(main.profile[main.profile.samples[2].node_id]
+            .parent_id
+            .is_none());
+        assert_eq!(threads[1].name, "native 4200:12");
+        let leaf = threads[1].profile[threads[1].profile.samples[0].node_id]
+            .frame()
+            .unwrap();
+        assert_eq!((&*leaf.function_name, &*leaf.url), ("zlib", ""));
+
+        let shifted = capture.threads(-500).unwrap();
+        assert_eq!(shifted[0].profile.start_time, Micros(500));
+        assert_eq!(
+            shifted[0].profile.self_times().values().sum::<Duration>(),
+            Duration::from_millis(3)
+        );
+        assert!(capture.threads(-1001).is_err());
+
+        let js: Profile = serde_json::from_str(PROFILE).unwrap();
+        let mut tracks: alloc::vec::Vec<_> = threads
+            .iter()
+            .map(|thread| Track {
+                pid: thread.pid,
+                tid: thread.tid,
+                name: thread.name.clone(),
+                profile: &thread.profile,
+            })
+            .collect();
+        tracks.push(Track {
+            pid: 4200,
+            tid: 0,
+            name: "JavaScript".into(),
+            profile: &js,
+        });
+        let trace = serde_json::to_value(&Trace { tracks }).unwrap();
+        let events = trace["traceEvents"].as_array().unwrap();
+        assert!(events.iter().any(|event| event["name"] == "uv__io_poll"));
+        assert!(events
+            .iter()
+            .any(|event| event["tid"] == 0 && event["ph"] == "X"));
+    }
+}
@@ -0,0 +1,406 @@
+//! A small expression language to select functions by their frame and
+//! times, like `url ~= "node_modules" && self_ms > 5`.
+//!
+//! Comparisons are joined with `&&` and `||`, negated with `!` and grouped
+//! with parentheses. The fields are:
+//!
+//! - `function` and `url`, strings compared with `==`, `!=` or `~=`, which
+//!   matches when the field contains the string
+//! - `line`, the 1-based line of the function, 0 if unknown
+//! - `self_ms` and `total_ms`, times in milliseconds
+//! - `self_percent` and `total_percent`, times as a percent of the profile
+//!
+//! Numbers are compared with `==`, `!=`, `<`, `<=`, `>` or `>=`.
+
+use crate::{FrameKey, FrameTimes};
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+use core::time::Duration;
+
+/// A parsed expression, see the [module docs](self).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    expr: Expr,
+}
+
+/// Why an expression didn't parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterError {
+    /// Byte offset in the expression.
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FilterError {}
+
+impl Filter {
+    /// Whether a function with these times matches, percents are of
+    /// `duration`.
+    #[must_use]
+    pub fn matches(&self, frame: &FrameKey<'_>, times: &FrameTimes, duration: Duration) -> bool {
+        self.expr.eval(&Function {
+            frame,
+            times,
+            duration,
+        })
+    }
+}
+
+impl FromStr for Filter {
+    type Err = FilterError;
+
+    fn from_str(source: &str) -> Result<Self, FilterError> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            next: 0,
+            end: source.len(),
+        };
+        let expr = parser.or()?;
+        match parser.peek() {
+            None => Ok(Filter { expr }),
+            Some((position, _)) => Err(error(position, "expected && or ||")),
+        }
+    }
+}
+
+struct Function<'a, 'raw> {
+    frame: &'a FrameKey<'raw>,
+    times: &'a FrameTimes,
+    duration: Duration,
+}
+
+impl Function<'_, '_> {
+    fn text(&self, field: TextField) -> &str {
+        match field {
+            TextField::Function => &self.frame.function_name,
+            TextField::Url => &self.frame.url,
+        }
+    }
+
+    fn number(&self, field: NumberField) -> f64 {
+        let percent = |time: Duration| {
+            if self.duration.is_zero() {
+                0.0
+            } else {
+                time.as_secs_f64() / self.duration.as_secs_f64() * 100.0
+            }
+        };
+        match field {
+            NumberField::Line => f64::from(self.frame.line_number.max(-1) + 1),
+            NumberField::SelfMs => self.times.self_time.as_secs_f64() * 1000.0,
+            NumberField::TotalMs => self.times.total_time.as_secs_f64() * 1000.0,
+            NumberField::SelfPercent => percent(self.times.self_time),
+            NumberField::TotalPercent => percent(self.times.total_time),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Text(TextField, Op, String),
+    Number(NumberField, Op, f64),
+}
+
+impl Expr {
+    fn eval(&self, function: &Function<'_, '_>) -> bool {
+        match self {
+            Expr::And(left, right) => left.eval(function) && right.eval(function),
+            Expr::Or(left, right) => left.eval(function) || right.eval(function),
+            Expr::Not(expr) => !expr.eval(function),
+            Expr::Text(field, op, value) => {
+                let text = function.text(*field);
+                match op {
+                    Op::Eq => text == value,
+                    Op::Ne => text != value,
+                    _ => text.contains(value.as_str()),
+                }
+            }
+            Expr::Number(field, op, value) => {
+                let number = function.number(*field);
+                match op {
+                    Op::Eq => (number - value).abs() < f64::EPSILON,
+                    Op::Ne => (number - value).abs() >= f64::EPSILON,
+                    Op::Lt => number < *value,
+                    Op::Le => number <= *value,
+                    Op::Gt => number > *value,
+                    Op::Ge => number >= *value,
+                    Op::Contains => false,
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum TextField {
+    Function,
+    Url,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum NumberField {
+    Line,
+    SelfMs,
+    TotalMs,
+    SelfPercent,
+    TotalPercent,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Text(String),
+    Number(f64),
+    Op(Op),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+fn error(position: usize, message: &str) -> FilterError {
+    FilterError {
+        position,
+        message: String::from(message),
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, FilterError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some((position, c)) = chars.next() {
+        let mut next_is = |expected: char| chars.next_if(|&(_, c)| c == expected).is_some();
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '&' if next_is('&') => Token::And,
+            '|' if next_is('|') => Token::Or,
+            '=' if next_is('=') => Token::Op(Op::Eq),
+            '~' if next_is('=') => Token::Op(Op::Contains),
+            '!' if next_is('=') => Token::Op(Op::Ne),
+            '!' => Token::Not,
+            '<' if next_is('=') => Token::Op(Op::Le),
+            '<' => Token::Op(Op::Lt),
+            '>' if next_is('=') => Token::Op(Op::Ge),
+            '>' => Token::Op(Op::Gt),
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, c)) => text.push(c),
+                            None => return Err(error(source.len(), "unterminated string")),
+                        },
+                        Some((_, c)) => text.push(c),
+                        None => return Err(error(source.len(), "unterminated string")),
+                    }
+                }
+                Token::Text(text)
+            }
+            c if c.is_ascii_digit() || c == '.' || c == '-' => {
+                let mut end = position + c.len_utf8();
+                while let Some((index, c)) = chars.next_if(|&(_, c)| c.is_ascii_digit() || c == '.')
+                {
+                    end = index + c.len_utf8();
+                }
+                match source[position..end].parse() {
+                    Ok(number) => Token::Number(number),
+                    Err(_) => return Err(error(position, "invalid number")),
+                }
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut end = position + 1;
+                while let Some((index, _)) =
+                    chars.next_if(|&(_, c)| c.is_ascii_alphanumeric() || c == '_')
+                {
+                    end = index + 1;
+                }
+                Token::Ident(String::from(&source[position..end]))
+            }
+            _ => return Err(error(position, "unexpected character")),
+        };
+        tokens.push((position, token));
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    next: usize,
+    /// Where errors at the end of the expression point.
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<(usize, &Token)> {
+        self.tokens
+            .get(self.next)
+            .map(|(position, token)| (*position, token))
+    }
+
+    fn take(&mut self) -> Result<(usize, Token), FilterError> {
+        let token = self.tokens.get(self.next).cloned();
+        self.next += 1;
+        token.ok_or_else(|| error(self.end, "unexpected end of expression"))
+    }
+
+    fn or(&mut self) -> Result<Expr, FilterError> {
+        let mut expr = self.and()?;
+        while let Some((_, Token::Or)) = self.peek() {
+            self.next += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, FilterError> {
+        let mut expr = self.unary()?;
+        while let Some((_, Token::And)) = self.peek() {
+            self.next += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, FilterError> {
+        match self.take()? {
+            (_, Token::Not) => Ok(Expr::Not(Box::new(self.unary()?))),
+            (_, Token::Open) => {
+                let expr = self.or()?;
+                match self.take()? {
+                    (_, Token::Close) => Ok(expr),
+                    (position, _) => Err(error(position, "expected )")),
+                }
+            }
+            (position, Token::Ident(field)) => self.comparison(position, &field),
+            (position, _) => Err(error(position, "expected a field, ! or (")),
+        }
+    }
+
+    fn comparison(&mut self, position: usize, field: &str) -> Result<Expr, FilterError> {
+        let (op_position, op) = match self.take()? {
+            (op_position, Token::Op(op)) => (op_position, op),
+            (position, _) => return Err(error(position, "expected a comparison")),
+        };
+        let (value_position, value) = self.take()?;
+        let text = match field {
+            "function" => Some(TextField::Function),
+            "url" => Some(TextField::Url),
+            _ => None,
+        };
+        if let Some(field) = text {
+            return match (op, value) {
+                (Op::Eq | Op::Ne | Op::Contains, Token::Text(value)) => {
+                    Ok(Expr::Text(field, op, value))
+                }
+                (Op::Eq | Op::Ne | Op::Contains, _) => {
+                    Err(error(value_position, "expected a string"))
+                }
+                _ => Err(error(op_position, "strings compare with ==, != or ~=")),
+            };
+        }
+        let field = match field {
+            "line" => NumberField::Line,
+            "self_ms" => NumberField::SelfMs,
+            "total_ms" => NumberField::TotalMs,
+            "self_percent" => NumberField::SelfPercent,
+            "total_percent" => NumberField::TotalPercent,
+            _ => return Err(error(position, "unknown field")),
+        };
+        match (op, value) {
+            (Op::Contains, _) => Err(error(op_position, "~= compares strings")),
+            (_, Token::Number(value)) => Ok(Expr::Number(field, op, value)),
+            _ => Err(error(value_position, "expected a number")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Filter;
+    use crate::ser::tests::PROFILE;
+    use crate::{FrameKey, FrameTimes, Profile};
+    use alloc::borrow::Cow;
+    use core::time::Duration;
+
+    #[test]
+    fn filter() {
+        let frame = FrameKey {
+            function_name: Cow::Borrowed("parse"),
+            url: Cow::Borrowed("file:///app/node_modules/json/index.js"),
+            line_number: 9,
+            column_number: 4,
+        };
+        let times = FrameTimes {
+            self_time: Duration::from_millis(8),
+            total_time: Duration::from_millis(20),
+        };
+        let duration = Duration::from_millis(100);
+        let matches = |source: &str| {
+            source
+                .parse::<Filter>()
+                .unwrap()
+                .matches(&frame, &times, duration)
+        };
+        assert!(matches(r#"url ~= "node_modules" && self_ms > 5"#));
+        assert!(!matches(r#"url ~= "node_modules" && self_ms > 10"#));
+        assert!(matches(r#"function == "parse" || self_ms > 10"#));
+        assert!(matches(r#"!(function != "parse") && line == 10"#));
+        assert!(matches("total_percent >= 20 && self_percent < 8.5"));
+        assert!(matches("self_ms > 1 || self_ms > 1 && total_ms > 100"));
+        assert!(!matches(r#"!url ~= "node_modules""#));
+        assert!(matches(r#"function == "p\"arse" || line > -1"#));
+
+        let position = |source: &str| source.parse::<Filter>().unwrap_err().position;
+        assert_eq!(position("self_ms >"), 9);
+        assert_eq!(position("size > 1"), 0);
+        assert_eq!(position(r#"self_ms ~= "a""#), 8);
+        assert_eq!(position(r#"url > "a""#), 4);
+        assert_eq!(position("url == 1"), 7);
+        assert_eq!(position("(self_ms > 1"), 12);
+        assert_eq!(position("self_ms > 1 self_ms"), 12);
+        assert_eq!(position(r#"url == "a"#), 9);
+        assert_eq!(position("self_ms # 1"), 8);
+
+        let profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        let filter: Filter = r#"url ~= "node:internal" && self_ms > 0"#.parse().unwrap();
+        let selected: alloc::vec::Vec<_> = profile
+            .frame_times()
+            .unwrap()
+            .into_iter()
+            .filter(|(frame, times)| filter.matches(frame, times, profile.duration()))
+            .collect();
+        assert!(!selected.is_empty());
+        assert!(selected
+            .iter()
+            .all(|(frame, times)| frame.url.contains("node:internal")
+                && times.self_time > Duration::default()));
+    }
+}
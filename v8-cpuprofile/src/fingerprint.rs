@@ -0,0 +1,137 @@
+//! Structural hashes to find identical captures in a corpus, see
+//! [`Profile::fingerprint`].
+
+use crate::{FrameKey, Profile};
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use hashbrown::HashMap;
+
+/// 64-bit FNV-1a, which unlike the hasher of a `HashMap` gives the same
+/// hash on every platform and run, so fingerprints can be stored.
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Self {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_str(&mut self, string: &str) {
+        self.write(string.as_bytes());
+        // no string has this byte, so adjacent strings can't run together
+        self.write(&[0xff]);
+    }
+}
+
+/// Hashes a stack of frames, root first, by function name, url, line and
+/// column. Script ids change between runs and are left out.
+#[must_use]
+pub fn stack_fingerprint(stack: &[FrameKey]) -> u64 {
+    let mut hasher = Fnv::new();
+    for frame in stack {
+        hasher.write_str(&frame.function_name);
+        hasher.write_str(&frame.url);
+        hasher.write(&frame.line_number.to_le_bytes());
+        hasher.write(&frame.column_number.to_le_bytes());
+    }
+    hasher.0
+}
+
+/// Indexes of the fingerprints that occur more than once, grouped by
+/// fingerprint in the order each was first seen.
+#[must_use]
+pub fn duplicates(fingerprints: &[u64]) -> Vec<Vec<usize>> {
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut by_fingerprint: HashMap<u64, usize> = HashMap::new();
+    for (index, fingerprint) in fingerprints.iter().enumerate() {
+        let group = *by_fingerprint.entry(*fingerprint).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[group].push(index);
+    }
+    groups.retain(|group| group.len() > 1);
+    groups
+}
+
+impl Profile<'_> {
+    /// Hashes the sampled stacks with their self time and the number of
+    /// samples. Node ids, the order of nodes and children and the start
+    /// time don't change it, so two copies of a capture match even after
+    /// being rewritten by another tool. Unsampled nodes are left out.
+    ///
+    /// # Errors
+    ///
+    /// If a node has an invalid call frame.
+    pub fn fingerprint(&self) -> Result<u64, serde_json::Error> {
+        let mut stacks: Vec<(u64, u64)> = self
+            .stack_times()?
+            .into_iter()
+            .map(|(stack, time)| {
+                let micros = u64::try_from(time.as_micros()).unwrap_or(u64::MAX);
+                (stack_fingerprint(&stack), micros)
+            })
+            .collect();
+        stacks.sort_unstable();
+        let mut hasher = Fnv::new();
+        hasher.write(&(self.samples.len() as u64).to_le_bytes());
+        for (stack, micros) in stacks {
+            hasher.write(&stack.to_le_bytes());
+            hasher.write(&micros.to_le_bytes());
+        }
+        Ok(hasher.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{duplicates, stack_fingerprint};
+    use crate::ser::tests::PROFILE;
+    use crate::{FrameKey, Micros, Profile};
+    use alloc::borrow::Cow;
+    use alloc::vec;
+
+    #[test]
+    fn fingerprint() {
+        let profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        let fingerprint = profile.fingerprint().unwrap();
+
+        let mut shifted: Profile = serde_json::from_str(PROFILE).unwrap();
+        shifted.start_time = shifted.start_time.saturating_add(Micros(1_000_000));
+        shifted.end_time = shifted.end_time.saturating_add(Micros(1_000_000));
+        assert_eq!(shifted.fingerprint().unwrap(), fingerprint);
+
+        let mut fewer: Profile = serde_json::from_str(PROFILE).unwrap();
+        fewer.samples.pop();
+        assert_ne!(fewer.fingerprint().unwrap(), fingerprint);
+
+        assert_eq!(
+            duplicates(&[fingerprint, 1, fingerprint, 2, 1]),
+            vec![vec![0, 2], vec![1, 4]]
+        );
+    }
+
+    #[test]
+    fn stack() {
+        let frame = |function_name: &'static str, url: &'static str| FrameKey {
+            function_name: Cow::Borrowed(function_name),
+            url: Cow::Borrowed(url),
+            line_number: 0,
+            column_number: 0,
+        };
+        let stack = [frame("(root)", ""), frame("main", "a.js")];
+        // a known value, so stored fingerprints stay comparable
+        assert_eq!(stack_fingerprint(&stack), 4_547_131_328_688_082_143);
+        assert_ne!(
+            stack_fingerprint(&[frame("ab", ""), frame("c", "")]),
+            stack_fingerprint(&[frame("a", ""), frame("bc", "")])
+        );
+        assert_ne!(stack_fingerprint(&stack), stack_fingerprint(&stack[..1]));
+    }
+}
@@ -0,0 +1,86 @@
+//! Flamegraph and icicle chart svgs drawn by inferno.
+
+use crate::Profile;
+use alloc::string::String;
+use inferno::flamegraph::color::MultiPalette;
+use inferno::flamegraph::{self, Direction};
+use std::io::{self, Write};
+
+pub use inferno::flamegraph::Palette;
+
+/// How [`Profile::write_flamegraph`] draws the chart.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlameOptions {
+    pub title: String,
+    /// Draws the root at the top, an icicle chart, instead of the bottom.
+    pub icicle: bool,
+    /// Merges stacks from the sampled function up to the root, so the
+    /// callers of where time was spent are drawn above it.
+    pub reverse: bool,
+    /// How frames are colored, inferno's `js` palette by default.
+    pub palette: Palette,
+}
+
+impl Default for FlameOptions {
+    fn default() -> Self {
+        FlameOptions {
+            title: String::from("Flame Graph"),
+            icicle: false,
+            reverse: false,
+            palette: Palette::Multi(MultiPalette::Js),
+        }
+    }
+}
+
+impl Profile<'_> {
+    /// Writes the sampled stacks as an interactive svg, each frame as wide
+    /// as its total time.
+    ///
+    /// # Errors
+    ///
+    /// If writing fails or a node has an invalid call frame.
+    pub fn write_flamegraph<W: Write>(&self, writer: W, options: &FlameOptions) -> io::Result<()> {
+        let lines = crate::format::folded_lines(self)?;
+        let mut inferno = flamegraph::Options::default();
+        inferno.title.clone_from(&options.title);
+        inferno.colors = options.palette;
+        inferno.count_name = String::from("us");
+        inferno.reverse_stack_order = options.reverse;
+        inferno.direction = if options.icicle {
+            Direction::Inverted
+        } else {
+            Direction::Straight
+        };
+        flamegraph::from_lines(&mut inferno, lines.iter().map(String::as_str), writer)
+            .map_err(io::Error::other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FlameOptions;
+    use crate::ser::tests::PROFILE;
+    use crate::Profile;
+
+    #[test]
+    fn flamegraph() {
+        let profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        let svg = |options: &FlameOptions| {
+            let mut svg = alloc::vec::Vec::new();
+            profile.write_flamegraph(&mut svg, options).unwrap();
+            alloc::string::String::from_utf8(svg).unwrap()
+        };
+        let flame = svg(&FlameOptions::default());
+        assert!(flame.starts_with("<?xml"));
+        assert!(flame.contains("compileForInternalLoader"));
+
+        let icicle = svg(&FlameOptions {
+            icicle: true,
+            reverse: true,
+            palette: "aqua".parse().unwrap(),
+            ..FlameOptions::default()
+        });
+        assert_ne!(icicle, flame);
+        assert!(icicle.contains("WriteStream"));
+    }
+}
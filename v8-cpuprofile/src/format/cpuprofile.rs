@@ -0,0 +1,38 @@
+use super::{Format, Sniffed};
+use crate::Profile;
+use std::io::{self, Write};
+
+/// The `.cpuprofile` json v8 and Chrome write.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Cpuprofile;
+
+impl Format for Cpuprofile {
+    fn name(&self) -> &'static str {
+        "cpuprofile"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["cpuprofile"]
+    }
+
+    fn can_read(&self) -> bool {
+        true
+    }
+
+    fn can_write(&self) -> bool {
+        true
+    }
+
+    fn sniff(&self, sniffed: &Sniffed) -> bool {
+        sniffed.has_key("nodes") && sniffed.has_key("startTime")
+    }
+
+    fn read(&self, input: &[u8]) -> io::Result<Profile<'static>> {
+        Profile::from_reader(input)
+    }
+
+    fn write(&self, profile: &Profile, writer: &mut dyn Write) -> io::Result<()> {
+        serde_json::to_writer(writer, profile)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,110 @@
+use super::Format;
+use crate::links::SourceLinks;
+use crate::Profile;
+use alloc::borrow::Cow;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use std::io::{self, Write};
+
+/// A row per function with its self and total time in microseconds,
+/// hottest first. Lines and columns are 1-based and empty if unknown.
+#[derive(Debug, Default, Clone)]
+pub struct Csv {
+    /// Adds a `link` column to each function's source.
+    pub links: Option<SourceLinks>,
+}
+
+impl Format for Csv {
+    fn name(&self) -> &'static str {
+        "csv"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["csv"]
+    }
+
+    fn can_write(&self) -> bool {
+        true
+    }
+
+    fn write(&self, profile: &Profile, writer: &mut dyn Write) -> io::Result<()> {
+        let mut rows: Vec<_> = profile.frame_times()?.into_iter().collect();
+        rows.sort_unstable_by(|(a_frame, a), (b_frame, b)| {
+            b.self_time
+                .cmp(&a.self_time)
+                .then(b.total_time.cmp(&a.total_time))
+                .then(a_frame.cmp(b_frame))
+        });
+        write!(writer, "function,url,line,column,self_us,total_us")?;
+        if self.links.is_some() {
+            write!(writer, ",link")?;
+        }
+        writeln!(writer)?;
+        for (frame, times) in rows {
+            write!(
+                writer,
+                "{},{},{},{},{},{}",
+                quote(&frame.function_name),
+                quote(&frame.url),
+                position(frame.line_number),
+                position(frame.column_number),
+                times.self_time.as_micros(),
+                times.total_time.as_micros()
+            )?;
+            if let Some(ref links) = self.links {
+                let link = links.link(&frame.url, frame.line_number, frame.column_number);
+                write!(writer, ",{}", quote(link.as_deref().unwrap_or("")))?;
+            }
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn quote(field: &str) -> Cow<'_, str> {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        Cow::Owned(alloc::format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+pub(crate) fn position(zero_based: i32) -> Cow<'static, str> {
+    if zero_based < 0 {
+        Cow::Borrowed("")
+    } else {
+        Cow::Owned((i64::from(zero_based) + 1).to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Csv;
+    use crate::format::Format;
+    use crate::links::SourceLinks;
+    use crate::Profile;
+
+    #[test]
+    fn links() {
+        let profile: Profile = serde_json::from_str(crate::ser::tests::PROFILE).unwrap();
+        let csv = Csv {
+            links: Some(SourceLinks {
+                template: "https://example.com/{rev}/{path}#L{line}".into(),
+                rev: "main".into(),
+                root: "node:internal/".into(),
+            }),
+        };
+        let mut out = alloc::vec::Vec::new();
+        csv.write(&profile, &mut out).unwrap();
+        let out = alloc::string::String::from_utf8(out).unwrap();
+        let mut lines = out.lines();
+        assert_eq!(
+            lines.next(),
+            Some("function,url,line,column,self_us,total_us,link")
+        );
+        assert!(
+            lines.any(|line| line.starts_with("compileForInternalLoader,")
+                && line.ends_with(",https://example.com/main/bootstrap/loaders#L270"))
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use super::{Format, Sniffed};
+use crate::Profile;
+use std::io::{self, Write};
+
+/// A format only read, like a trace system's dump, for crates adding their
+/// own formats with [`Registry::register_importer`](super::Registry::register_importer).
+/// A format that is read and written implements [`Format`] instead.
+pub trait ProfileImporter: Send + Sync {
+    /// The name to select the format by with `--from`.
+    fn name(&self) -> &'static str;
+
+    /// File extensions without the dot, the most specific first.
+    fn extensions(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Whether the input looks like this format, see [`Format::sniff`].
+    fn sniff(&self, sniffed: &Sniffed) -> bool {
+        let _ = sniffed;
+        false
+    }
+
+    /// Parses a profile in this format.
+    ///
+    /// # Errors
+    ///
+    /// If the input is invalid.
+    fn import(&self, input: &[u8]) -> io::Result<Profile<'static>>;
+}
+
+/// A format only written, for crates adding their own formats with
+/// [`Registry::register_exporter`](super::Registry::register_exporter).
+pub trait ProfileExporter: Send + Sync {
+    /// The name to select the format by with `--to`.
+    fn name(&self) -> &'static str;
+
+    /// File extensions without the dot, the most specific first.
+    fn extensions(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Writes `profile` in this format.
+    ///
+    /// # Errors
+    ///
+    /// If writing fails or a node's raw json is invalid.
+    fn export(&self, profile: &Profile, writer: &mut dyn Write) -> io::Result<()>;
+}
+
+pub(super) struct Importer<I>(pub(super) I);
+
+impl<I: ProfileImporter> Format for Importer<I> {
+    fn name(&self) -> &'static str {
+        self.0.name()
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        self.0.extensions()
+    }
+
+    fn can_read(&self) -> bool {
+        true
+    }
+
+    fn sniff(&self, sniffed: &Sniffed) -> bool {
+        self.0.sniff(sniffed)
+    }
+
+    fn read(&self, input: &[u8]) -> io::Result<Profile<'static>> {
+        self.0.import(input)
+    }
+}
+
+pub(super) struct Exporter<E>(pub(super) E);
+
+impl<E: ProfileExporter> Format for Exporter<E> {
+    fn name(&self) -> &'static str {
+        self.0.name()
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        self.0.extensions()
+    }
+
+    fn can_write(&self) -> bool {
+        true
+    }
+
+    fn write(&self, profile: &Profile, writer: &mut dyn Write) -> io::Result<()> {
+        self.0.export(profile, writer)
+    }
+}
@@ -0,0 +1,107 @@
+use super::Format;
+use crate::Profile;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use hashbrown::HashMap;
+use serde::ser::{SerializeMap, Serializer};
+use serde::Serialize;
+use std::io::{self, Write};
+
+/// The nested `{name, value, children}` json d3-flame-graph draws, from
+/// the root down with each value the total time in microseconds. Nodes
+/// that were never on a sampled stack are left out.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct D3;
+
+impl Format for D3 {
+    fn name(&self) -> &'static str {
+        "d3"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["d3.json"]
+    }
+
+    fn can_write(&self) -> bool {
+        true
+    }
+
+    fn write(&self, profile: &Profile, writer: &mut dyn Write) -> io::Result<()> {
+        let root = match profile.root() {
+            Some(root) => root.id,
+            None => return Err(io::Error::new(io::ErrorKind::InvalidData, "no root node")),
+        };
+        let mut names = HashMap::with_capacity(profile.nodes.len());
+        for node in &profile.nodes {
+            names.insert(node.id, node.frame()?.to_string());
+        }
+        let tree = Tree {
+            profile,
+            names: &names,
+            total_times: &profile.total_times(),
+        };
+        serde_json::to_writer(writer, &Frame(&tree, root))?;
+        Ok(())
+    }
+}
+
+struct Tree<'t, 'p, 'raw> {
+    profile: &'p Profile<'raw>,
+    names: &'t HashMap<u64, String>,
+    total_times: &'t HashMap<u64, core::time::Duration>,
+}
+
+struct Frame<'a, 't, 'p, 'raw>(&'a Tree<'t, 'p, 'raw>, u64);
+
+impl Serialize for Frame<'_, '_, '_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let Frame(tree, node_id) = *self;
+        let total_time = tree.total_times.get(&node_id).copied().unwrap_or_default();
+        let children: Vec<_> = tree.profile[node_id]
+            .children
+            .iter()
+            .flatten()
+            .filter(|child| tree.total_times.contains_key(*child))
+            .map(|&child| Frame(tree, child))
+            .collect();
+        let mut map = serializer.serialize_map(Some(3))?;
+        map.serialize_entry("name", &tree.names[&node_id])?;
+        map.serialize_entry("value", &total_time.as_micros())?;
+        map.serialize_entry("children", &children)?;
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::D3;
+    use crate::format::Format;
+    use crate::Profile;
+    use serde_json::Value;
+
+    /// A node's value covers its children's.
+    fn check(node: &Value) {
+        let children = node["children"].as_array().unwrap();
+        let sum: u64 = children
+            .iter()
+            .map(|child| child["value"].as_u64().unwrap())
+            .sum();
+        assert!(sum <= node["value"].as_u64().unwrap());
+        children.iter().for_each(check);
+    }
+
+    #[test]
+    fn d3() {
+        let profile: Profile = serde_json::from_str(crate::ser::tests::PROFILE).unwrap();
+        let mut out = alloc::vec::Vec::new();
+        D3.write(&profile, &mut out).unwrap();
+        let root: Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(root["name"], "(root)");
+        let total = profile.duration().as_micros() - u128::from(profile.samples[0].ts.0);
+        assert_eq!(root["value"].as_u64().map(u128::from), Some(total));
+        check(&root);
+    }
+}
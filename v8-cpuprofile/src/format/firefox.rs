@@ -0,0 +1,32 @@
+use super::Format;
+use crate::trace::{Trace, Track};
+use crate::Profile;
+use alloc::string::String;
+use std::io::{self, Write};
+
+/// A Chrome trace of one thread, which the Firefox profiler imports.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Firefox;
+
+impl Format for Firefox {
+    fn name(&self) -> &'static str {
+        "firefox"
+    }
+
+    fn can_write(&self) -> bool {
+        true
+    }
+
+    fn write(&self, profile: &Profile, writer: &mut dyn Write) -> io::Result<()> {
+        let trace = Trace {
+            tracks: alloc::vec![Track {
+                pid: 1,
+                tid: 1,
+                name: String::from("main"),
+                profile,
+            }],
+        };
+        serde_json::to_writer(writer, &trace)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,136 @@
+use super::{Format, Frames};
+use crate::trace::Slice;
+use crate::{CallFrame, Micros, Profile};
+use serde::ser::{SerializeMap, SerializeSeq, Serializer};
+use serde::Serialize;
+use std::io::{self, Write};
+
+/// The flame chart of the samples as a json array of spans in time order,
+/// each a run of consecutive samples with a frame on their stack: its
+/// `name`, `url`, 1-based `line` and `column` when known, `depth` from 0
+/// below the root, and `start` and `duration` in microseconds from the
+/// profile's start.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct FlameChart;
+
+impl Format for FlameChart {
+    fn name(&self) -> &'static str {
+        "flamechart"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["flamechart.json"]
+    }
+
+    fn can_write(&self) -> bool {
+        true
+    }
+
+    fn write(&self, profile: &Profile, writer: &mut dyn Write) -> io::Result<()> {
+        let frames = Frames::new(profile)?;
+        let slices = profile.slices();
+        serde_json::to_writer(&mut *writer, &Spans(&frames, &slices))?;
+        writeln!(writer)
+    }
+}
+
+struct Spans<'s, 'raw>(&'s Frames<'raw>, &'s [Slice]);
+
+impl Serialize for Spans<'_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let Spans(frames, slices) = *self;
+        let mut seq = serializer.serialize_seq(Some(slices.len()))?;
+        for slice in slices {
+            let frame = &frames.frames[frames.index(slice.node_id)];
+            seq.serialize_element(&Span(frame, slice))?;
+        }
+        seq.end()
+    }
+}
+
+struct Span<'s, 'raw>(&'s CallFrame<'raw>, &'s Slice);
+
+impl Serialize for Span<'_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let Span(frame, slice) = *self;
+        let name = if frame.function_name.is_empty() {
+            "(anonymous)"
+        } else {
+            &frame.function_name
+        };
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("name", name)?;
+        if !frame.url.is_empty() {
+            map.serialize_entry("url", &frame.url)?;
+        }
+        if frame.line_number >= 0 {
+            map.serialize_entry("line", &(i64::from(frame.line_number) + 1))?;
+        }
+        if frame.column_number >= 0 {
+            map.serialize_entry("column", &(i64::from(frame.column_number) + 1))?;
+        }
+        map.serialize_entry("depth", &slice.depth)?;
+        map.serialize_entry("start", &Micros::from(slice.start).0)?;
+        map.serialize_entry("duration", &Micros::from(slice.duration).0)?;
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FlameChart;
+    use crate::format::{Format, SpeedscopeEvented};
+    use crate::Profile;
+    use serde_json::Value;
+
+    #[test]
+    fn flame_chart() {
+        let profile: Profile = serde_json::from_str(crate::ser::tests::PROFILE).unwrap();
+        let duration = profile.duration().as_micros() as u64;
+
+        let mut out = alloc::vec::Vec::new();
+        FlameChart.write(&profile, &mut out).unwrap();
+        let spans: Value = serde_json::from_slice(&out).unwrap();
+        let spans = spans.as_array().unwrap();
+        assert_eq!(spans.len(), profile.slices().len());
+        let field = |span: &Value, key: &str| span[key].as_u64().unwrap();
+        assert!(spans
+            .windows(2)
+            .all(|pair| field(&pair[0], "start") <= field(&pair[1], "start")));
+        let top_level: u64 = spans
+            .iter()
+            .filter(|span| field(span, "depth") == 0)
+            .map(|span| field(span, "duration"))
+            .sum();
+        assert!(top_level <= duration);
+        assert!(spans
+            .iter()
+            .all(|span| field(span, "start") + field(span, "duration") <= duration));
+
+        let mut out = alloc::vec::Vec::new();
+        SpeedscopeEvented.write(&profile, &mut out).unwrap();
+        let file: Value = serde_json::from_slice(&out).unwrap();
+        let events = file["profiles"][0]["events"].as_array().unwrap();
+        assert_eq!(events.len(), spans.len() * 2);
+        // opens and closes nest like a stack
+        let mut open = alloc::vec::Vec::new();
+        let mut at = 0;
+        for event in events {
+            let frame = event["frame"].as_u64().unwrap();
+            assert!(event["at"].as_u64().unwrap() >= at);
+            at = event["at"].as_u64().unwrap();
+            if event["type"] == "O" {
+                open.push(frame);
+            } else {
+                assert_eq!(open.pop(), Some(frame));
+            }
+        }
+        assert!(open.is_empty());
+    }
+}
@@ -0,0 +1,266 @@
+use super::{invalid, stack, Format, Sniffed, StackTree};
+use crate::{CallFrame, Micros, Profile};
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::Write as _;
+use core::time::Duration;
+use hashbrown::HashMap;
+use std::io::{self, Write};
+
+/// The most samples counts with an interval are read as.
+const MAX_SAMPLES: u64 = 1 << 24;
+
+/// Folded stacks as flamegraph tools take them, a line per stack of
+/// frames joined by `;` and its self time in microseconds.
+///
+/// Reads the folded stacks of `dtrace` and `bpftrace` profiles too, where the
+/// count is a number of samples `interval` apart, at most 2^24 in all.
+/// Frames like `` module`function+0x1f `` are read as the function in the
+/// module without the offset, and those written here as the frame they were
+/// written from.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Folded {
+    /// How long each counted sample lasted when reading, `None` for counts
+    /// in microseconds.
+    pub interval: Option<Duration>,
+}
+
+impl Format for Folded {
+    fn name(&self) -> &'static str {
+        "folded"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["folded"]
+    }
+
+    fn can_read(&self) -> bool {
+        true
+    }
+
+    fn can_write(&self) -> bool {
+        true
+    }
+
+    /// Text whose first line is a stack and a count.
+    fn sniff(&self, sniffed: &Sniffed) -> bool {
+        if sniffed.keys.is_some() || sniffed.array {
+            return false;
+        }
+        let first = core::str::from_utf8(sniffed.input)
+            .ok()
+            .and_then(|input| input.lines().map(str::trim).find(|line| !line.is_empty()));
+        first.and_then(stack_and_count).is_some()
+    }
+
+    fn read(&self, input: &[u8]) -> io::Result<Profile<'static>> {
+        let input = core::str::from_utf8(input).map_err(invalid)?;
+        let interval = self.interval.map(Micros::from);
+        if interval == Some(Micros::ZERO) {
+            return Err(invalid("the sample interval has to be positive"));
+        }
+        let mut stacks = Vec::new();
+        let mut indexes: HashMap<&str, usize> = HashMap::new();
+        let mut frames = Vec::new();
+        for (index, line) in input.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (stack, count) = stack_and_count(line).ok_or_else(|| {
+                invalid(alloc::format!(
+                    "line {} is not a stack and a count",
+                    index + 1
+                ))
+            })?;
+            // the samples of the root itself
+            let stack = if stack == "(root)" { "" } else { stack };
+            let stack = stack
+                .split(';')
+                .filter(|frame| !frame.is_empty())
+                .map(|frame| {
+                    *indexes.entry(frame).or_insert_with(|| {
+                        frames.push(call_frame(frame));
+                        frames.len() - 1
+                    })
+                })
+                .collect::<Vec<_>>();
+            stacks.push((stack, count));
+        }
+
+        if interval.is_some() {
+            let total = stacks
+                .iter()
+                .fold(0_u64, |total, &(_, count)| total.saturating_add(count));
+            if total > MAX_SAMPLES {
+                return Err(invalid(alloc::format!(
+                    "{} samples, more than {}",
+                    total,
+                    MAX_SAMPLES
+                )));
+            }
+        }
+
+        let mut tree = StackTree::new(&frames)?;
+        let mut weights = Vec::new();
+        for (stack, count) in stacks {
+            let node_id = tree.node(stack)?;
+            match interval {
+                // synthetic samples, back to back
+                Some(interval) => weights.extend((0..count).map(|_| (node_id, interval))),
+                None => weights.push((node_id, Micros(count))),
+            }
+        }
+        let end = weights
+            .iter()
+            .fold(Micros::ZERO, |end, &(_, weight)| end.saturating_add(weight));
+        let mut profile = tree.finish(Micros::ZERO, end, &weights);
+        profile.sampling_interval = interval;
+        Ok(profile)
+    }
+
+    fn write(&self, profile: &Profile, writer: &mut dyn Write) -> io::Result<()> {
+        for line in lines(profile)? {
+            writeln!(writer, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+/// A folded line for each sampled node, in node id order.
+pub(crate) fn lines(profile: &Profile) -> io::Result<Vec<String>> {
+    let mut self_times: Vec<_> = profile.self_times().into_iter().collect();
+    self_times.sort_unstable();
+    let mut lines = Vec::with_capacity(self_times.len());
+    for (node_id, self_time) in self_times {
+        let mut line = String::new();
+        for (depth, node) in stack(profile, node_id).into_iter().enumerate() {
+            if depth > 0 {
+                line.push(';');
+            }
+            // `;` separates frames, the count is after the last space
+            line.push_str(&node.frame()?.to_string().replace(';', ":"));
+        }
+        if line.is_empty() {
+            line.push_str("(root)");
+        }
+        let _ = write!(line, " {}", self_time.as_micros());
+        lines.push(line);
+    }
+    Ok(lines)
+}
+
+fn stack_and_count(line: &str) -> Option<(&str, u64)> {
+    let (stack, count) = line.rsplit_once(char::is_whitespace)?;
+    Some((stack.trim_end(), count.parse().ok()?)).filter(|(stack, _)| !stack.is_empty())
+}
+
+/// The frame of `function (url:line:column)` as written by
+/// [`lines`], `` module`function+0x1f `` as `dtrace` writes it or else a
+/// function without a url.
+fn call_frame(frame: &str) -> CallFrame<'static> {
+    let mut call_frame = CallFrame {
+        function_name: Cow::Owned(String::from(frame)),
+        script_id: Cow::Borrowed("0"),
+        url: Cow::Borrowed(""),
+        line_number: -1,
+        column_number: -1,
+    };
+    if let Some((function, location)) = frame
+        .strip_suffix(')')
+        .and_then(|frame| frame.rsplit_once(" ("))
+    {
+        let mut parts = location.rsplitn(3, ':');
+        let column = parts.next().and_then(|column| column.parse::<i32>().ok());
+        let line = parts.next().and_then(|line| line.parse::<i32>().ok());
+        if let (Some(column), Some(line), Some(url)) = (column, line, parts.next()) {
+            call_frame.function_name = Cow::Owned(String::from(function));
+            call_frame.url = Cow::Owned(String::from(url));
+            call_frame.line_number = line - 1;
+            call_frame.column_number = column - 1;
+            return call_frame;
+        }
+    }
+    if let Some((module, function)) = frame.split_once('`') {
+        let function = match function.rsplit_once("+0x") {
+            Some((function, offset)) if offset.chars().all(|c| c.is_ascii_hexdigit()) => function,
+            _ => function,
+        };
+        call_frame.function_name = Cow::Owned(String::from(function));
+        call_frame.url = Cow::Owned(String::from(module));
+    }
+    call_frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Folded;
+    use crate::format::Format;
+    use crate::Profile;
+    use core::time::Duration;
+
+    #[test]
+    fn folded() {
+        let profile: Profile = serde_json::from_str(crate::ser::tests::PROFILE).unwrap();
+        let mut out = alloc::vec::Vec::new();
+        Folded::default().write(&profile, &mut out).unwrap();
+        let out = alloc::string::String::from_utf8(out).unwrap();
+        let total: u128 = out
+            .lines()
+            .map(|line| line.rsplit(' ').next().unwrap().parse::<u128>().unwrap())
+            .sum();
+        assert_eq!(
+            total,
+            profile.duration().as_micros() - u128::from(profile.samples[0].ts.0)
+        );
+        assert!(out.lines().all(|line| !line.starts_with("(root)")));
+    }
+
+    #[test]
+    fn read() {
+        let profile: Profile = serde_json::from_str(crate::ser::tests::PROFILE).unwrap();
+        let mut out = alloc::vec::Vec::new();
+        Folded::default().write(&profile, &mut out).unwrap();
+        let read = Folded::default().read(&out).unwrap();
+        let mut again = alloc::vec::Vec::new();
+        Folded::default().write(&read, &mut again).unwrap();
+        let lines = |out: &[u8]| {
+            let mut lines: alloc::vec::Vec<_> = core::str::from_utf8(out)
+                .unwrap()
+                .lines()
+                .map(alloc::string::String::from)
+                .collect();
+            lines.sort();
+            lines
+        };
+        assert_eq!(lines(&again), lines(&out));
+
+        let dtrace = b"node`uv_run+0x1f;node`uv__io_poll 3\n\nnode`uv_run+0x1f;libsystem_kernel.dylib`kevent+0x8 2\n";
+        let folded = Folded {
+            interval: Some(Duration::from_millis(1)),
+        };
+        let read = folded.read(dtrace).unwrap();
+        assert_eq!(read.samples.len(), 5);
+        assert_eq!(read.duration(), Duration::from_millis(5));
+        assert_eq!(
+            read.nominal_sampling_interval(),
+            Some(Duration::from_millis(1))
+        );
+        let leaf = read[read.samples[4].node_id].frame().unwrap();
+        assert_eq!(
+            (&*leaf.function_name, &*leaf.url),
+            ("kevent", "libsystem_kernel.dylib")
+        );
+        let parent = read[read[read.samples[0].node_id].parent_id.unwrap()]
+            .frame()
+            .unwrap();
+        assert_eq!(&*parent.function_name, "uv_run");
+        assert!(folded.read(b"uv_run;uv__io_poll\n").is_err());
+        assert!(folded.read(b"a 18446744073709551615\n").is_err());
+        assert!(folded.read(b"a 16777216\nb 1\n").is_err());
+        // microseconds are one sample each
+        let huge = Folded::default().read(b"a 18446744073709551615\n").unwrap();
+        assert_eq!(huge.samples.len(), 1);
+    }
+}
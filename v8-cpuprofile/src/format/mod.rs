@@ -0,0 +1,546 @@
+//! Profile formats other tools read and write, behind one [`Format`] trait
+//! so a converter can look them up by name in a [`Registry`] and new ones
+//! plug in without changes to its callers.
+
+mod cpuprofile;
+mod csv;
+mod custom;
+mod d3;
+mod firefox;
+mod flamechart;
+mod folded;
+mod perfetto;
+mod pprof;
+mod proto;
+mod sample;
+mod sniff;
+mod speedscope;
+mod summary;
+mod trace;
+
+pub use self::cpuprofile::Cpuprofile;
+pub use self::csv::Csv;
+pub(crate) use self::csv::{position, quote};
+pub use self::custom::{ProfileExporter, ProfileImporter};
+pub use self::d3::D3;
+pub use self::firefox::Firefox;
+pub use self::flamechart::FlameChart;
+#[cfg(feature = "flame")]
+pub(crate) use self::folded::lines as folded_lines;
+pub use self::folded::Folded;
+pub use self::perfetto::Perfetto;
+pub use self::pprof::Pprof;
+pub use self::sample::MacosSample;
+pub use self::sniff::{decompress, is_gzip, Sniffed};
+pub use self::speedscope::{Speedscope, SpeedscopeEvented};
+pub use self::summary::{ServerTiming, Summary};
+pub use self::trace::DevtoolsTrace;
+
+use crate::trace::Slice;
+use crate::{CallFrame, Micros, Node, Profile, RawJson, Sample, Weighting};
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use hashbrown::HashMap;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// A profile format that can be read, written or both.
+pub trait Format: Send + Sync {
+    /// The name to select the format by, like `speedscope`.
+    fn name(&self) -> &'static str;
+
+    /// File extensions without the dot, the most specific first.
+    fn extensions(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    fn can_read(&self) -> bool {
+        false
+    }
+
+    fn can_write(&self) -> bool {
+        false
+    }
+
+    /// Whether the input looks like this format, for picking a format
+    /// when none was given.
+    fn sniff(&self, sniffed: &Sniffed) -> bool {
+        let _ = sniffed;
+        false
+    }
+
+    /// Parses a profile in this format.
+    ///
+    /// # Errors
+    ///
+    /// If the input is invalid or the format can't be read.
+    fn read(&self, input: &[u8]) -> io::Result<Profile<'static>> {
+        let _ = input;
+        Err(unsupported(self.name(), "read"))
+    }
+
+    /// Writes `profile` in this format.
+    ///
+    /// # Errors
+    ///
+    /// If writing fails, a node's raw json is invalid or the format can't be
+    /// written.
+    fn write(&self, profile: &Profile, writer: &mut dyn Write) -> io::Result<()> {
+        let _ = (profile, writer);
+        Err(unsupported(self.name(), "written"))
+    }
+}
+
+fn unsupported(name: &str, action: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("{} profiles can't be {}", name, action),
+    )
+}
+
+fn invalid(message: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Formats by name, [`Registry::default`] has the built in ones.
+pub struct Registry {
+    formats: Vec<Box<dyn Format>>,
+}
+
+impl Registry {
+    /// A registry without any formats.
+    #[must_use]
+    pub fn empty() -> Self {
+        Registry {
+            formats: Vec::new(),
+        }
+    }
+
+    /// Adds a format, replacing one with the same name.
+    pub fn register(&mut self, format: Box<dyn Format>) {
+        self.formats.retain(|known| known.name() != format.name());
+        self.formats.push(format);
+    }
+
+    /// Adds a format that is only read, replacing one with the same name.
+    pub fn register_importer(&mut self, importer: impl ProfileImporter + 'static) {
+        self.register(Box::new(custom::Importer(importer)));
+    }
+
+    /// Adds a format that is only written, replacing one with the same name.
+    pub fn register_exporter(&mut self, exporter: impl ProfileExporter + 'static) {
+        self.register(Box::new(custom::Exporter(exporter)));
+    }
+
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&dyn Format> {
+        self.formats
+            .iter()
+            .find(|format| format.name() == name)
+            .map(AsRef::as_ref)
+    }
+
+    /// The format whose extension the file name ends with, the longest
+    /// match wins so `.speedscope.json` beats `.json`. A `.gz` suffix is
+    /// ignored.
+    #[must_use]
+    pub fn for_path(&self, path: &Path) -> Option<&dyn Format> {
+        let name = path.file_name()?.to_str()?;
+        let name = name.strip_suffix(".gz").unwrap_or(name);
+        self.formats
+            .iter()
+            .flat_map(|format| format.extensions().iter().map(move |ext| (format, ext)))
+            .filter(|(_, ext)| {
+                name.len() > ext.len()
+                    && name.ends_with(*ext)
+                    && name[..name.len() - ext.len()].ends_with('.')
+            })
+            .max_by_key(|(_, ext)| ext.len())
+            .map(|(format, _)| format.as_ref())
+    }
+
+    /// The first format that recognizes the input, which should already be
+    /// decompressed.
+    #[must_use]
+    pub fn detect(&self, input: &[u8]) -> Option<&dyn Format> {
+        let sniffed = Sniffed::new(input);
+        self.iter().find(|format| format.sniff(&sniffed))
+    }
+
+    /// Decompresses and reads a profile in whichever format it is in.
+    ///
+    /// # Errors
+    ///
+    /// If the format isn't recognized or the profile is invalid.
+    pub fn read(&self, input: &[u8]) -> io::Result<Profile<'static>> {
+        let input = decompress(input)?;
+        self.detect(&input)
+            .ok_or_else(|| invalid("unrecognized profile format"))?
+            .read(&input)
+    }
+
+    /// The format to read an input with, the one named `from` or else the
+    /// one detected from the decompressed input or its path's extension.
+    ///
+    /// # Errors
+    ///
+    /// If `from` isn't known, the format can't be told or it can't be read.
+    pub fn reader(&self, input: &[u8], path: &Path, from: Option<&str>) -> io::Result<&dyn Format> {
+        let reader = match from {
+            Some(name) => self.named(name)?,
+            None => self
+                .detect(input)
+                .or_else(|| self.for_path(path))
+                .ok_or_else(|| invalid(format!("can't tell the format of {:?}", path)))?,
+        };
+        if reader.can_read() {
+            Ok(reader)
+        } else {
+            Err(unsupported(reader.name(), "read"))
+        }
+    }
+
+    /// The format named `to` to write with.
+    ///
+    /// # Errors
+    ///
+    /// If the format isn't known or can't be written.
+    pub fn writer(&self, to: &str) -> io::Result<&dyn Format> {
+        let writer = self.named(to)?;
+        if writer.can_write() {
+            Ok(writer)
+        } else {
+            Err(unsupported(writer.name(), "written"))
+        }
+    }
+
+    fn named(&self, name: &str) -> io::Result<&dyn Format> {
+        self.get(name).ok_or_else(|| {
+            let names: Vec<_> = self.iter().map(Format::name).collect();
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "unknown format {}, expected one of {}",
+                    name,
+                    names.join(", ")
+                ),
+            )
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Format> {
+        self.formats.iter().map(AsRef::as_ref)
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        let mut registry = Registry::empty();
+        registry.register(Box::new(Cpuprofile));
+        registry.register(Box::new(Speedscope));
+        registry.register(Box::new(SpeedscopeEvented));
+        registry.register(Box::new(Pprof::default()));
+        registry.register(Box::new(DevtoolsTrace));
+        registry.register(Box::new(Folded::default()));
+        registry.register(Box::new(Csv::default()));
+        registry.register(Box::new(D3));
+        registry.register(Box::new(Firefox));
+        registry.register(Box::new(FlameChart));
+        registry.register(Box::new(Perfetto));
+        registry.register(Box::new(MacosSample));
+        registry.register(Box::new(Summary::default()));
+        registry.register(Box::new(ServerTiming));
+        registry
+    }
+}
+
+/// Builds a profile from stacks of call frames, for formats that don't
+/// keep v8's node tree.
+pub(crate) struct StackTree {
+    nodes: Vec<Node<'static>>,
+    /// child node id by parent node id and frame
+    children: HashMap<(u64, usize), u64>,
+    frames: Vec<RawJson<'static>>,
+}
+
+impl StackTree {
+    pub(crate) fn new(frames: &[CallFrame]) -> io::Result<Self> {
+        let frames = frames
+            .iter()
+            .map(|frame| {
+                serde_json::value::to_raw_value(frame)
+                    .map(RawJson::from)
+                    .map_err(io::Error::from)
+            })
+            .collect::<io::Result<_>>()?;
+        let root = Node {
+            id: 1,
+            parent_id: None,
+            call_frame: RawJson::from_string(
+                r#"{"functionName":"(root)","scriptId":"0","url":"","lineNumber":-1,"columnNumber":-1}"#.into(),
+            )?,
+            hit_count: 0,
+            children: Some(Vec::new()),
+            deopt_reason: None,
+            position_ticks: None,
+            extra_fields: Vec::new(),
+        };
+        Ok(StackTree {
+            nodes: alloc::vec![root],
+            children: HashMap::new(),
+            frames,
+        })
+    }
+
+    /// The id of the node for a stack of frame indexes, outermost first,
+    /// added if it is new.
+    pub(crate) fn node(&mut self, stack: impl IntoIterator<Item = usize>) -> io::Result<u64> {
+        let mut node_id = 1;
+        for frame in stack {
+            let call_frame = self
+                .frames
+                .get(frame)
+                .ok_or_else(|| invalid(format!("unknown frame {}", frame)))?
+                .clone();
+            let next_id = self.nodes.len() as u64 + 1;
+            let child = *self.children.entry((node_id, frame)).or_insert(next_id);
+            if child == next_id {
+                self.nodes[usize::try_from(node_id - 1).map_err(invalid)?]
+                    .children
+                    .get_or_insert_with(Vec::new)
+                    .push(child);
+                self.nodes.push(Node {
+                    id: child,
+                    parent_id: Some(node_id),
+                    call_frame,
+                    hit_count: 0,
+                    children: Some(Vec::new()),
+                    deopt_reason: None,
+                    position_ticks: None,
+                    extra_fields: Vec::new(),
+                });
+            }
+            node_id = child;
+        }
+        Ok(node_id)
+    }
+
+    /// A profile with a sample for each of `weights`, a node and how long
+    /// it was sampled for, back to back so the last ends at `end_time`.
+    pub(crate) fn finish(
+        mut self,
+        start_time: Micros,
+        end_time: Micros,
+        weights: &[(u64, Micros)],
+    ) -> Profile<'static> {
+        let total = weights.iter().fold(Micros::ZERO, |total, &(_, weight)| {
+            total.saturating_add(weight)
+        });
+        // whatever isn't sampled is before the first sample
+        let mut ts = end_time.saturating_sub(start_time).saturating_sub(total);
+        let mut samples = Vec::with_capacity(weights.len());
+        for &(node_id, weight) in weights {
+            if let Some(node) = usize::try_from(node_id - 1)
+                .ok()
+                .and_then(|index| self.nodes.get_mut(index))
+            {
+                node.hit_count += 1;
+            }
+            samples.push(Sample { node_id, ts });
+            ts = ts.saturating_add(weight);
+        }
+        let node_index = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (node.id, index))
+            .collect();
+        Profile {
+            nodes: self.nodes,
+            start_time,
+            end_time: start_time.saturating_add(ts),
+            samples,
+            metadata: None,
+            sampling_interval: None,
+            extra_fields: Vec::new(),
+            exact_times: (None, None),
+            node_index,
+            weighting: Weighting::default(),
+        }
+    }
+}
+
+/// The distinct call frames of a profile and which one each node has, for
+/// formats with a table of frames.
+pub(crate) struct Frames<'raw> {
+    pub(crate) frames: Vec<CallFrame<'raw>>,
+    by_node: HashMap<u64, usize>,
+}
+
+impl<'raw> Frames<'raw> {
+    pub(crate) fn new(profile: &Profile<'raw>) -> io::Result<Self> {
+        let mut frames = Vec::new();
+        let mut indexes = HashMap::new();
+        let mut by_node = HashMap::with_capacity(profile.nodes.len());
+        for node in &profile.nodes {
+            let frame = node.frame()?;
+            let index = *indexes.entry(frame.clone()).or_insert_with(|| {
+                frames.push(frame);
+                frames.len() - 1
+            });
+            by_node.insert(node.id, index);
+        }
+        Ok(Frames { frames, by_node })
+    }
+
+    /// The index of the frame of a node.
+    pub(crate) fn index(&self, node_id: u64) -> usize {
+        self.by_node[&node_id]
+    }
+
+    /// The frames from below the root to `node_id`, outermost first.
+    pub(crate) fn stack(&self, profile: &Profile, node_id: u64) -> Vec<usize> {
+        stack(profile, node_id)
+            .into_iter()
+            .map(|node| self.by_node[&node.id])
+            .collect()
+    }
+}
+
+/// The opens and closes of the slices of a flame chart in time order, as
+/// the time, whether the slice opens and the slice. Closes come before
+/// opens at the same time, the innermost first, so the events nest.
+pub(crate) fn slice_events(slices: &[Slice]) -> Vec<(Micros, bool, &Slice)> {
+    let mut events = Vec::with_capacity(slices.len() * 2);
+    for slice in slices {
+        let start = Micros::from(slice.start);
+        events.push((start, true, slice));
+        events.push((start + Micros::from(slice.duration), false, slice));
+    }
+    events.sort_by_key(|&(at, open, slice)| {
+        let depth = if open {
+            slice.depth
+        } else {
+            usize::MAX - slice.depth
+        };
+        (at, open, depth)
+    });
+    events
+}
+
+/// The frames of a stack from below the root to `node_id`, outermost first.
+fn stack<'p, 'raw>(profile: &'p Profile<'raw>, node_id: u64) -> Vec<&'p Node<'raw>> {
+    let mut stack: Vec<_> = core::iter::once(node_id)
+        .chain(profile.parent_ids_iter(node_id))
+        .map(|node_id| &profile[node_id])
+        .filter(|node| node.parent_id.is_some())
+        .collect();
+    stack.reverse();
+    stack
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cpuprofile, Format, ProfileExporter, ProfileImporter, Registry, Sniffed};
+    use crate::ser::tests::PROFILE;
+    use crate::Profile;
+    use alloc::string::ToString;
+    use std::io::{self, Write};
+    use std::path::Path;
+
+    /// A cpuprofile behind a header, like an in-house trace format.
+    struct Tagged;
+
+    const TAG: &[u8] = b"TAGGED\n";
+
+    impl ProfileImporter for Tagged {
+        fn name(&self) -> &'static str {
+            "tagged"
+        }
+
+        fn extensions(&self) -> &'static [&'static str] {
+            &["tagged"]
+        }
+
+        fn sniff(&self, sniffed: &Sniffed) -> bool {
+            sniffed.input.starts_with(TAG)
+        }
+
+        fn import(&self, input: &[u8]) -> io::Result<Profile<'static>> {
+            Cpuprofile.read(&input[TAG.len()..])
+        }
+    }
+
+    impl ProfileExporter for Tagged {
+        fn name(&self) -> &'static str {
+            "tagged-out"
+        }
+
+        fn export(&self, profile: &Profile, writer: &mut dyn Write) -> io::Result<()> {
+            writer.write_all(TAG)?;
+            Cpuprofile.write(profile, writer)
+        }
+    }
+
+    #[test]
+    fn registry() {
+        let registry = Registry::default();
+        let name = |path: &str| registry.for_path(Path::new(path)).map(super::Format::name);
+        assert_eq!(name("a.cpuprofile"), Some("cpuprofile"));
+        assert_eq!(name("a.speedscope.json"), Some("speedscope"));
+        assert_eq!(name("a.json"), Some("trace"));
+        assert_eq!(name("a.cpuprofile.gz"), Some("cpuprofile"));
+        assert_eq!(name("cpuprofile"), None);
+        assert!(registry.get("folded").unwrap().can_write());
+        assert!(registry.get("folded").unwrap().can_read());
+
+        // every format reads back what it writes
+        let profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        for format in registry.iter() {
+            let mut out = alloc::vec::Vec::new();
+            if !format.can_write() {
+                assert!(format.write(&profile, &mut out).is_err());
+                continue;
+            }
+            format.write(&profile, &mut out).unwrap();
+            assert!(!out.is_empty(), "{}", format.name());
+            if format.can_read() {
+                assert_eq!(registry.detect(&out).unwrap().name(), format.name());
+                let read = format.read(&out).unwrap();
+                // folded stacks leave out the time before the first sample
+                let duration = if format.name() == "folded" {
+                    profile.duration() - profile.samples[0].ts.as_duration()
+                } else {
+                    profile.duration()
+                };
+                assert_eq!(read.duration(), duration, "{}", format.name());
+            }
+        }
+    }
+
+    #[test]
+    fn custom() {
+        let mut registry = Registry::default();
+        registry.register_importer(Tagged);
+        registry.register_exporter(Tagged);
+        let profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        let mut out = alloc::vec::Vec::new();
+        let writer = registry.writer("tagged-out").unwrap();
+        writer.write(&profile, &mut out).unwrap();
+
+        let path = Path::new("a.bin");
+        let reader = registry.reader(&out, path, None).unwrap();
+        assert_eq!(reader.name(), "tagged");
+        assert_eq!(reader.read(&out).unwrap().samples, profile.samples);
+        let by_path = registry.reader(b"", Path::new("a.tagged"), None).unwrap();
+        assert_eq!(by_path.name(), "tagged");
+
+        assert!(registry.reader(&out, path, Some("tagged-out")).is_err());
+        assert!(registry.reader(b"", path, None).is_err());
+        assert!(registry.writer("tagged").is_err());
+        let unknown = registry.writer("nope").err().unwrap().to_string();
+        assert!(unknown.contains("tagged-out"), "{}", unknown);
+    }
+}
@@ -0,0 +1,217 @@
+use super::proto::Encoder;
+use super::{slice_events, Format, Frames};
+use crate::{CallFrame, Micros, Profile};
+use alloc::string::String;
+use std::io::{self, Write};
+
+/// A Perfetto trace of `TracePacket`s with track events: a slice per span
+/// of consecutive samples with a function on their stack, on a
+/// `cpuprofile` track, and an instant event per sample named by the sampled
+/// function, on a `samples` track under it. The Perfetto UI and its SQL
+/// engine load it next to system traces of the same clock.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Perfetto;
+
+// field numbers of perfetto's trace.proto
+const TRACE_PACKET: u32 = 1;
+const TIMESTAMP: u32 = 8;
+const TRUSTED_PACKET_SEQUENCE_ID: u32 = 10;
+const TRACK_EVENT: u32 = 11;
+const TRACK_DESCRIPTOR: u32 = 60;
+const DESCRIPTOR_UUID: u32 = 1;
+const DESCRIPTOR_NAME: u32 = 2;
+const DESCRIPTOR_PARENT_UUID: u32 = 5;
+const EVENT_DEBUG_ANNOTATIONS: u32 = 4;
+const EVENT_TYPE: u32 = 9;
+const EVENT_TRACK_UUID: u32 = 11;
+const EVENT_CATEGORIES: u32 = 22;
+const EVENT_NAME: u32 = 23;
+const ANNOTATION_STRING_VALUE: u32 = 6;
+const ANNOTATION_NAME: u32 = 10;
+
+const SLICE_BEGIN: u64 = 1;
+const SLICE_END: u64 = 2;
+const INSTANT: u64 = 3;
+
+const SEQUENCE_ID: u64 = 1;
+const SPANS_TRACK: u64 = 1;
+const SAMPLES_TRACK: u64 = 2;
+
+impl Format for Perfetto {
+    fn name(&self) -> &'static str {
+        "perfetto"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["perfetto-trace", "pftrace"]
+    }
+
+    fn can_write(&self) -> bool {
+        true
+    }
+
+    fn write(&self, profile: &Profile, writer: &mut dyn Write) -> io::Result<()> {
+        let frames = Frames::new(profile)?;
+        let names: alloc::vec::Vec<_> = frames.frames.iter().map(Names::new).collect();
+        // perfetto times are nanoseconds
+        let nanos = |at: Micros| profile.start_time.saturating_add(at).0.saturating_mul(1000);
+        let mut trace = Encoder::default();
+
+        for (uuid, parent, name) in [
+            (SPANS_TRACK, None, "cpuprofile"),
+            (SAMPLES_TRACK, Some(SPANS_TRACK), "samples"),
+        ] {
+            trace.message(TRACE_PACKET, |packet| {
+                packet.uint64(TRUSTED_PACKET_SEQUENCE_ID, SEQUENCE_ID);
+                packet.message(TRACK_DESCRIPTOR, |track| {
+                    track.uint64(DESCRIPTOR_UUID, uuid);
+                    track.bytes(DESCRIPTOR_NAME, name.as_bytes());
+                    if let Some(parent) = parent {
+                        track.uint64(DESCRIPTOR_PARENT_UUID, parent);
+                    }
+                });
+            });
+        }
+
+        let slices = profile.slices();
+        for (at, open, slice) in slice_events(&slices) {
+            let names = &names[frames.index(slice.node_id)];
+            let event_type = if open { SLICE_BEGIN } else { SLICE_END };
+            trace.message(TRACE_PACKET, |packet| {
+                packet.uint64(TIMESTAMP, nanos(at));
+                packet.uint64(TRUSTED_PACKET_SEQUENCE_ID, SEQUENCE_ID);
+                packet.message(TRACK_EVENT, |event| {
+                    event.uint64(EVENT_TYPE, event_type);
+                    event.uint64(EVENT_TRACK_UUID, SPANS_TRACK);
+                    if open {
+                        names.write(event);
+                    }
+                });
+            });
+        }
+
+        for sample in &profile.samples {
+            let names = &names[frames.index(sample.node_id)];
+            trace.message(TRACE_PACKET, |packet| {
+                packet.uint64(TIMESTAMP, nanos(sample.ts));
+                packet.uint64(TRUSTED_PACKET_SEQUENCE_ID, SEQUENCE_ID);
+                packet.message(TRACK_EVENT, |event| {
+                    event.uint64(EVENT_TYPE, INSTANT);
+                    event.uint64(EVENT_TRACK_UUID, SAMPLES_TRACK);
+                    names.write(event);
+                });
+            });
+        }
+
+        writer.write_all(&trace.bytes)
+    }
+}
+
+/// The name and source position of a frame as a track event shows them.
+struct Names {
+    name: String,
+    source: Option<String>,
+}
+
+impl Names {
+    fn new(frame: &CallFrame<'_>) -> Self {
+        let name = if frame.function_name.is_empty() {
+            String::from("(anonymous)")
+        } else {
+            String::from(&*frame.function_name)
+        };
+        let source = if frame.url.is_empty() {
+            None
+        } else if frame.line_number < 0 {
+            Some(String::from(&*frame.url))
+        } else {
+            Some(alloc::format!(
+                "{}:{}",
+                frame.url,
+                i64::from(frame.line_number) + 1
+            ))
+        };
+        Names { name, source }
+    }
+
+    fn write(&self, event: &mut Encoder) {
+        event.bytes(EVENT_CATEGORIES, b"v8.cpu_profile");
+        event.bytes(EVENT_NAME, self.name.as_bytes());
+        if let Some(source) = &self.source {
+            event.message(EVENT_DEBUG_ANNOTATIONS, |annotation| {
+                annotation.bytes(ANNOTATION_NAME, b"source");
+                annotation.bytes(ANNOTATION_STRING_VALUE, source.as_bytes());
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Perfetto, INSTANT, SLICE_BEGIN, SLICE_END};
+    use crate::format::proto::Decoder;
+    use crate::format::Format;
+    use crate::Profile;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn perfetto() {
+        let profile: Profile = serde_json::from_str(crate::ser::tests::PROFILE).unwrap();
+        let mut out = Vec::new();
+        Perfetto.write(&profile, &mut out).unwrap();
+
+        let mut tracks = 0;
+        let mut open = Vec::new();
+        let mut instants = 0;
+        let mut last = 0;
+        let mut trace = Decoder(&out);
+        while let Some((field, packet)) = trace.field().unwrap() {
+            assert_eq!(field, 1);
+            let mut packet = Decoder(packet.bytes().unwrap());
+            let mut timestamp = 0;
+            while let Some((field, value)) = packet.field().unwrap() {
+                match field {
+                    8 => timestamp = value.uint64().unwrap(),
+                    10 => assert_eq!(value.uint64().unwrap(), 1),
+                    60 => tracks += 1,
+                    11 => {
+                        let (mut event_type, mut track_uuid, mut name) = (0, 0, None);
+                        let mut event = Decoder(value.bytes().unwrap());
+                        while let Some((field, value)) = event.field().unwrap() {
+                            match field {
+                                9 => event_type = value.uint64().unwrap(),
+                                11 => track_uuid = value.uint64().unwrap(),
+                                23 => name = Some(value.bytes().unwrap()),
+                                _ => {}
+                            }
+                        }
+                        match event_type {
+                            SLICE_BEGIN => {
+                                assert_eq!(track_uuid, 1);
+                                assert!(timestamp >= last);
+                                last = timestamp;
+                                open.push(name.unwrap());
+                            }
+                            SLICE_END => {
+                                assert!(timestamp >= last);
+                                last = timestamp;
+                                open.pop().unwrap();
+                            }
+                            INSTANT => {
+                                assert_eq!(track_uuid, 2);
+                                assert!(name.is_some());
+                                instants += 1;
+                            }
+                            _ => unreachable!(),
+                        }
+                    }
+                    _ => unreachable!(),
+                }
+            }
+        }
+        assert_eq!(tracks, 2);
+        assert!(open.is_empty());
+        assert_eq!(instants, profile.samples.len());
+        assert!(last >= profile.start_time.0 * 1000);
+    }
+}
@@ -0,0 +1,507 @@
+use super::proto::{Decoder, Encoder};
+use super::{decompress, invalid, Format, Frames, Sniffed, StackTree};
+use crate::{CallFrame, Micros, Profile, RawJson};
+use alloc::borrow::Cow;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use hashbrown::HashMap;
+use std::io::{self, Write};
+
+/// pprof's protocol buffer, uncompressed, with a sample per stack holding
+/// its sample count and cpu time in nanoseconds.
+///
+/// The string values of a `labels` object in the profile's metadata become
+/// labels on every sample, for backends that index profiles by them. Reading
+/// keeps the labels all samples share there.
+#[derive(Debug, Default, Clone)]
+pub struct Pprof {
+    /// More labels like `service` or `region`, replacing those of the
+    /// metadata with the same key.
+    pub labels: Vec<(String, String)>,
+}
+
+impl Pprof {
+    /// The labels written on every sample of `profile`, sorted by key.
+    ///
+    /// # Errors
+    ///
+    /// If the profile's metadata is invalid json.
+    pub fn labels(&self, profile: &Profile) -> io::Result<BTreeMap<String, String>> {
+        let mut labels = BTreeMap::new();
+        if let Some(metadata) = &profile.metadata {
+            let metadata: serde_json::Value = serde_json::from_str(metadata.get())?;
+            if let Some(serde_json::Value::Object(object)) = metadata.get("labels") {
+                labels.extend(
+                    object.iter().filter_map(|(key, value)| {
+                        Some((key.clone(), String::from(value.as_str()?)))
+                    }),
+                );
+            }
+        }
+        labels.extend(self.labels.iter().cloned());
+        Ok(labels)
+    }
+}
+
+impl Format for Pprof {
+    fn name(&self) -> &'static str {
+        "pprof"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["pprof", "pb"]
+    }
+
+    fn can_read(&self) -> bool {
+        true
+    }
+
+    fn can_write(&self) -> bool {
+        true
+    }
+
+    /// A protocol buffer of only fields a pprof profile has, with a string
+    /// table.
+    fn sniff(&self, sniffed: &Sniffed) -> bool {
+        let mut decoder = Decoder(sniffed.input);
+        let mut strings = false;
+        loop {
+            match decoder.field() {
+                Ok(Some((field, _))) if (1..=14).contains(&field) => strings |= field == 6,
+                Ok(None) => return strings,
+                _ => return false,
+            }
+        }
+    }
+
+    /// Reads the samples' time value, or their count times the period if
+    /// they have none. The input may be gzip compressed, as pprof writes.
+    fn read(&self, input: &[u8]) -> io::Result<Profile<'static>> {
+        let input = decompress(input)?;
+        let pprof = Pprof::decode(&input)?;
+        let string = |index: u64| {
+            usize::try_from(index)
+                .ok()
+                .and_then(|index| pprof.strings.get(index))
+                .map_or("", String::as_str)
+        };
+        let nanos_per_unit = |unit: u64| match string(unit) {
+            "nanoseconds" => Some(1),
+            "microseconds" => Some(1000),
+            "milliseconds" => Some(1_000_000),
+            "seconds" => Some(1_000_000_000),
+            _ => None,
+        };
+        let (value, nanos) = match pprof
+            .sample_types
+            .iter()
+            .enumerate()
+            .find_map(|(index, &(_, unit))| Some((index, nanos_per_unit(unit)?)))
+        {
+            Some(value) => value,
+            None => (
+                0,
+                pprof
+                    .period
+                    .saturating_mul(nanos_per_unit(pprof.period_unit).unwrap_or(1)),
+            ),
+        };
+
+        let functions: HashMap<u64, (u64, u64)> = pprof
+            .functions
+            .iter()
+            .map(|&(id, name, filename)| (id, (name, filename)))
+            .collect();
+        let mut frames = Vec::new();
+        let mut frame_ids = HashMap::new();
+        let mut locations = HashMap::new();
+        for (id, lines) in &pprof.locations {
+            // the inlined lines are innermost first
+            let stack = lines
+                .iter()
+                .rev()
+                .map(|&line| {
+                    *frame_ids.entry(line).or_insert_with(|| {
+                        let (function_id, line_number, column_number) = line;
+                        let (name, filename) =
+                            functions.get(&function_id).copied().unwrap_or_default();
+                        frames.push(CallFrame {
+                            function_name: Cow::Owned(string(name).into()),
+                            script_id: Cow::Borrowed("0"),
+                            url: Cow::Owned(string(filename).into()),
+                            line_number: position(line_number),
+                            column_number: position(column_number),
+                        });
+                        frames.len() - 1
+                    })
+                })
+                .collect::<Vec<_>>();
+            locations.insert(*id, stack);
+        }
+
+        let mut tree = StackTree::new(&frames)?;
+        let mut weighted = Vec::with_capacity(pprof.samples.len());
+        for (location_ids, values, _) in &pprof.samples {
+            let mut stack = Vec::new();
+            for id in location_ids.iter().rev() {
+                stack.extend(
+                    locations
+                        .get(id)
+                        .ok_or_else(|| invalid("unknown location"))?,
+                );
+            }
+            // an int64, anything negative is ignored
+            let weight = values
+                .get(value)
+                .copied()
+                .filter(|&weight| i64::try_from(weight).is_ok())
+                .unwrap_or(0);
+            weighted.push((
+                tree.node(stack)?,
+                Micros(weight.saturating_mul(nanos) / 1000),
+            ));
+        }
+        #[allow(clippy::cast_sign_loss)]
+        let start = Micros(pprof.time_nanos.max(0) as u64 / 1000);
+        #[allow(clippy::cast_sign_loss)]
+        let end = start.saturating_add(Micros(pprof.duration_nanos.max(0) as u64 / 1000));
+        let mut profile = tree.finish(start, end, &weighted);
+        profile.sampling_interval = nanos_per_unit(pprof.period_unit)
+            .filter(|_| pprof.period > 0)
+            .map(|nanos| Micros(pprof.period.saturating_mul(nanos) / 1000));
+        let labels: BTreeMap<&str, &str> = shared_labels(&pprof.samples)
+            .into_iter()
+            .map(|(key, value)| (string(key), string(value)))
+            .collect();
+        if !labels.is_empty() {
+            let metadata = serde_json::to_string(&serde_json::json!({ "labels": labels }))?;
+            profile.metadata = Some(RawJson::from_string(metadata)?);
+        }
+        Ok(profile)
+    }
+
+    fn write(&self, profile: &Profile, writer: &mut dyn Write) -> io::Result<()> {
+        let frames = Frames::new(profile)?;
+        let labels = self.labels(profile)?;
+        let mut strings = Strings::default();
+        let mut out = Encoder::default();
+        let (samples, count, cpu, nanoseconds) = (
+            strings.id("samples"),
+            strings.id("count"),
+            strings.id("cpu"),
+            strings.id("nanoseconds"),
+        );
+        for &(kind, unit) in &[(samples, count), (cpu, nanoseconds)] {
+            out.message(1, |value_type| {
+                value_type.uint64(1, kind);
+                value_type.uint64(2, unit);
+            });
+        }
+
+        let label_ids: Vec<(u64, u64)> = labels
+            .iter()
+            .map(|(key, value)| (strings.id(key), strings.id(value)))
+            .collect();
+
+        let mut by_node: HashMap<u64, (u64, u64)> = HashMap::new();
+        for (sample, duration) in profile.samples_with_weights() {
+            let (count, nanos) = by_node.entry(sample.node_id).or_default();
+            *count += 1;
+            *nanos += u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+        }
+        let mut by_node: Vec<_> = by_node.into_iter().collect();
+        by_node.sort_unstable();
+        for (node_id, (count, nanos)) in by_node {
+            out.message(2, |sample| {
+                // the sampled location first
+                let stack = frames.stack(profile, node_id);
+                sample.packed(1, stack.iter().rev().map(|&frame| frame as u64 + 1));
+                sample.packed(2, [count, nanos].iter().copied());
+                for &(key, value) in &label_ids {
+                    sample.message(3, |label| {
+                        label.uint64(1, key);
+                        label.uint64(2, value);
+                    });
+                }
+            });
+        }
+
+        for (index, frame) in frames.frames.iter().enumerate() {
+            let id = index as u64 + 1;
+            out.message(4, |location| {
+                location.uint64(1, id);
+                location.message(4, |line| {
+                    line.uint64(1, id);
+                    line.int64(2, i64::from(frame.line_number) + 1);
+                    line.int64(3, i64::from(frame.column_number) + 1);
+                });
+            });
+        }
+        for (index, frame) in frames.frames.iter().enumerate() {
+            let name = strings.id(if frame.function_name.is_empty() {
+                "(anonymous)"
+            } else {
+                &frame.function_name
+            });
+            let filename = strings.id(&frame.url);
+            out.message(5, |function| {
+                function.uint64(1, index as u64 + 1);
+                function.uint64(2, name);
+                function.uint64(3, name);
+                function.uint64(4, filename);
+            });
+        }
+        for string in &strings.strings {
+            out.bytes(6, string.as_bytes());
+        }
+
+        let nanos =
+            |micros: Micros| i64::try_from(micros.0.saturating_mul(1000)).unwrap_or(i64::MAX);
+        let duration = profile.end_time.saturating_sub(profile.start_time);
+        out.int64(9, nanos(profile.start_time));
+        out.int64(10, nanos(duration));
+        out.message(11, |value_type| {
+            value_type.uint64(1, cpu);
+            value_type.uint64(2, nanoseconds);
+        });
+        // pprof has no way to say the interval varies
+        let period = profile
+            .nominal_sampling_interval()
+            .map_or(Micros::ZERO, Micros::from);
+        out.int64(12, nanos(period));
+        writer.write_all(&out.bytes)
+    }
+}
+
+/// The labels of key and string every sample has with the same value.
+fn shared_labels(samples: &[Sample]) -> Vec<(u64, u64)> {
+    let mut samples = samples.iter().map(|(_, _, labels)| labels);
+    let mut shared = samples.next().cloned().unwrap_or_default();
+    for labels in samples {
+        shared.retain(|label| labels.contains(label));
+    }
+    shared
+}
+
+/// The string table, where 0 is the empty string.
+struct Strings<'a> {
+    strings: Vec<&'a str>,
+    ids: HashMap<&'a str, u64>,
+}
+
+impl Default for Strings<'_> {
+    fn default() -> Self {
+        Strings {
+            strings: alloc::vec![""],
+            ids: HashMap::new(),
+        }
+    }
+}
+
+impl<'a> Strings<'a> {
+    fn id(&mut self, string: &'a str) -> u64 {
+        if string.is_empty() {
+            return 0;
+        }
+        let strings = &mut self.strings;
+        *self.ids.entry(string).or_insert_with(|| {
+            strings.push(string);
+            strings.len() as u64 - 1
+        })
+    }
+}
+
+/// A function id, line and column.
+type Line = (u64, i64, i64);
+
+/// Location ids, sampled one first, values and labels of key and string.
+type Sample = (Vec<u64>, Vec<u64>, Vec<(u64, u64)>);
+
+/// The parts of a pprof profile this reads.
+#[derive(Default)]
+struct Decoded {
+    /// type and unit
+    sample_types: Vec<(u64, u64)>,
+    samples: Vec<Sample>,
+    /// id and lines of function id, line and column, innermost first
+    locations: Vec<(u64, Vec<Line>)>,
+    /// id, name and filename
+    functions: Vec<(u64, u64, u64)>,
+    strings: Vec<String>,
+    time_nanos: i64,
+    duration_nanos: i64,
+    period_unit: u64,
+    period: u64,
+}
+
+impl Pprof {
+    fn decode(input: &[u8]) -> io::Result<Decoded> {
+        let mut pprof = Decoded::default();
+        let mut decoder = Decoder(input);
+        while let Some((field, value)) = decoder.field()? {
+            match field {
+                1 => pprof.sample_types.push(value_type(value.bytes()?)?),
+                2 => {
+                    let mut sample = (Vec::new(), Vec::new(), Vec::new());
+                    let mut decoder = Decoder(value.bytes()?);
+                    while let Some((field, value)) = decoder.field()? {
+                        match field {
+                            1 => value.push_to(&mut sample.0)?,
+                            2 => value.push_to(&mut sample.1)?,
+                            3 => {
+                                // numeric labels have no string and are skipped
+                                let label = value_type(value.bytes()?)?;
+                                if label.1 != 0 {
+                                    sample.2.push(label);
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                    pprof.samples.push(sample);
+                }
+                4 => {
+                    let mut location = (0, Vec::new());
+                    let mut decoder = Decoder(value.bytes()?);
+                    while let Some((field, value)) = decoder.field()? {
+                        match field {
+                            1 => location.0 = value.uint64()?,
+                            4 => {
+                                let mut line = (0, 0, 0);
+                                let mut decoder = Decoder(value.bytes()?);
+                                while let Some((field, value)) = decoder.field()? {
+                                    match field {
+                                        1 => line.0 = value.uint64()?,
+                                        2 => line.1 = value.int64()?,
+                                        3 => line.2 = value.int64()?,
+                                        _ => {}
+                                    }
+                                }
+                                location.1.push(line);
+                            }
+                            _ => {}
+                        }
+                    }
+                    pprof.locations.push(location);
+                }
+                5 => {
+                    let mut function = (0, 0, 0);
+                    let mut decoder = Decoder(value.bytes()?);
+                    while let Some((field, value)) = decoder.field()? {
+                        match field {
+                            1 => function.0 = value.uint64()?,
+                            2 => function.1 = value.uint64()?,
+                            4 => function.2 = value.uint64()?,
+                            _ => {}
+                        }
+                    }
+                    pprof.functions.push(function);
+                }
+                6 => pprof
+                    .strings
+                    .push(String::from_utf8(value.bytes()?.to_vec()).map_err(invalid)?),
+                9 => pprof.time_nanos = value.int64()?,
+                10 => pprof.duration_nanos = value.int64()?,
+                11 => pprof.period_unit = value_type(value.bytes()?)?.1,
+                12 => pprof.period = value.uint64()?,
+                _ => {}
+            }
+        }
+        Ok(pprof)
+    }
+}
+
+fn value_type(bytes: &[u8]) -> io::Result<(u64, u64)> {
+    let mut value_type = (0, 0);
+    let mut decoder = Decoder(bytes);
+    while let Some((field, value)) = decoder.field()? {
+        match field {
+            1 => value_type.0 = value.uint64()?,
+            2 => value_type.1 = value.uint64()?,
+            _ => {}
+        }
+    }
+    Ok(value_type)
+}
+
+/// A 0-based position from pprof's 1-based one, where 0 is unknown.
+fn position(one_based: i64) -> i32 {
+    i32::try_from(one_based - 1).unwrap_or(-1).max(-1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Pprof;
+    use crate::format::Format;
+    use crate::{Profile, RawJson};
+    use alloc::string::String;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn pprof() {
+        let profile: Profile = serde_json::from_str(crate::ser::tests::PROFILE).unwrap();
+        let mut out = Vec::new();
+        Pprof::default().write(&profile, &mut out).unwrap();
+        let read = Pprof::default().read(&out).unwrap();
+        read.roundtrip_check().unwrap();
+        assert_eq!(read.start_time, profile.start_time);
+        assert_eq!(read.duration(), profile.duration());
+        assert_eq!(
+            read.nominal_sampling_interval(),
+            profile.nominal_sampling_interval()
+        );
+        assert!(read.sampling_interval.is_some());
+
+        let frame_times = profile.frame_times().unwrap();
+        for (frame, times) in read.frame_times().unwrap() {
+            // written with a name
+            if frame.function_name == "(anonymous)" {
+                continue;
+            }
+            assert_eq!(
+                frame_times[&frame].self_time, times.self_time,
+                "{:?}",
+                frame
+            );
+        }
+        assert!(Pprof::default().read(&[0x1f, 0x8b, 8]).is_err());
+    }
+
+    #[test]
+    fn labels() {
+        let mut profile: Profile = serde_json::from_str(crate::ser::tests::PROFILE).unwrap();
+        let metadata = r#"{"labels":{"service":"api","region":"eu","shards":3}}"#;
+        profile.metadata = Some(RawJson::new(metadata).unwrap());
+        let pprof = Pprof {
+            labels: vec![(String::from("region"), String::from("us"))],
+        };
+        let mut out = Vec::new();
+        pprof.write(&profile, &mut out).unwrap();
+        let read = Pprof::default().read(&out).unwrap();
+        assert_eq!(
+            read.metadata.unwrap().get(),
+            r#"{"labels":{"region":"us","service":"api"}}"#
+        );
+
+        profile.metadata = None;
+        let mut out = Vec::new();
+        Pprof::default().write(&profile, &mut out).unwrap();
+        assert!(Pprof::default().read(&out).unwrap().metadata.is_none());
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn gzipped() {
+        let profile: Profile = serde_json::from_str(crate::ser::tests::PROFILE).unwrap();
+        let mut out = Vec::new();
+        Pprof::default().write(&profile, &mut out).unwrap();
+        let mut gzip =
+            flate2::write::GzEncoder::new(alloc::vec::Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut gzip, &out).unwrap();
+        let read = Pprof::default().read(&gzip.finish().unwrap()).unwrap();
+        assert_eq!(read.duration(), profile.duration());
+    }
+}
@@ -0,0 +1,193 @@
+//! Just enough protocol buffers for pprof and Perfetto, which need no other
+//! schema features than varints, length delimited fields and packed
+//! repeats.
+
+use super::invalid;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use std::io;
+
+#[derive(Default)]
+pub(crate) struct Encoder {
+    pub(crate) bytes: Vec<u8>,
+}
+
+impl Encoder {
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            #[allow(clippy::cast_possible_truncation)]
+            self.bytes.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        #[allow(clippy::cast_possible_truncation)]
+        self.bytes.push(value as u8);
+    }
+
+    fn tag(&mut self, field: u32, wire_type: u8) {
+        self.varint(u64::from(field) << 3 | u64::from(wire_type));
+    }
+
+    /// A varint field, left out if 0 like proto3 does.
+    pub(crate) fn uint64(&mut self, field: u32, value: u64) {
+        if value != 0 {
+            self.tag(field, 0);
+            self.varint(value);
+        }
+    }
+
+    #[allow(clippy::cast_sign_loss)]
+    pub(crate) fn int64(&mut self, field: u32, value: i64) {
+        self.uint64(field, value as u64);
+    }
+
+    pub(crate) fn bytes(&mut self, field: u32, bytes: &[u8]) {
+        self.tag(field, 2);
+        self.varint(bytes.len() as u64);
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    pub(crate) fn packed(&mut self, field: u32, values: impl IntoIterator<Item = u64>) {
+        let mut packed = Encoder::default();
+        for value in values {
+            packed.varint(value);
+        }
+        if !packed.bytes.is_empty() {
+            self.bytes(field, &packed.bytes);
+        }
+    }
+
+    pub(crate) fn message(&mut self, field: u32, write: impl FnOnce(&mut Encoder)) {
+        let mut message = Encoder::default();
+        write(&mut message);
+        self.bytes(field, &message.bytes);
+    }
+}
+
+/// The value of a field, fixed width values are skipped over.
+pub(crate) enum Value<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+impl<'a> Value<'a> {
+    pub(crate) fn uint64(&self) -> io::Result<u64> {
+        match *self {
+            Value::Varint(value) => Ok(value),
+            _ => Err(invalid("expected a varint")),
+        }
+    }
+
+    #[allow(clippy::cast_possible_wrap)]
+    pub(crate) fn int64(&self) -> io::Result<i64> {
+        self.uint64().map(|value| value as i64)
+    }
+
+    pub(crate) fn bytes(&self) -> io::Result<&'a [u8]> {
+        match *self {
+            Value::Bytes(bytes) => Ok(bytes),
+            _ => Err(invalid("expected a length delimited field")),
+        }
+    }
+
+    /// Adds a repeated varint, packed or not.
+    pub(crate) fn push_to(&self, values: &mut Vec<u64>) -> io::Result<()> {
+        match *self {
+            Value::Varint(value) => values.push(value),
+            Value::Bytes(bytes) => {
+                let mut decoder = Decoder(bytes);
+                while !decoder.0.is_empty() {
+                    values.push(decoder.varint()?);
+                }
+            }
+            Value::Fixed => return Err(invalid("expected varints")),
+        }
+        Ok(())
+    }
+}
+
+/// Reads the fields of a message in order.
+pub(crate) struct Decoder<'a>(pub(crate) &'a [u8]);
+
+impl<'a> Decoder<'a> {
+    fn varint(&mut self) -> io::Result<u64> {
+        let mut value = 0;
+        for (index, &byte) in self.0.iter().enumerate().take(10) {
+            value |= u64::from(byte & 0x7f) << (7 * index);
+            if byte < 0x80 {
+                self.0 = &self.0[index + 1..];
+                return Ok(value);
+            }
+        }
+        Err(invalid("truncated varint"))
+    }
+
+    fn take(&mut self, len: u64) -> io::Result<&'a [u8]> {
+        let len = usize::try_from(len)
+            .ok()
+            .filter(|&len| len <= self.0.len())
+            .ok_or_else(|| invalid("truncated field"))?;
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    /// The next field number and value, `None` at the end.
+    pub(crate) fn field(&mut self) -> io::Result<Option<(u64, Value<'a>)>> {
+        if self.0.is_empty() {
+            return Ok(None);
+        }
+        let tag = self.varint()?;
+        let value = match tag & 7 {
+            0 => Value::Varint(self.varint()?),
+            1 => {
+                self.take(8)?;
+                Value::Fixed
+            }
+            2 => {
+                let len = self.varint()?;
+                Value::Bytes(self.take(len)?)
+            }
+            5 => {
+                self.take(4)?;
+                Value::Fixed
+            }
+            _ => return Err(invalid("unsupported wire type")),
+        };
+        Ok(Some((tag >> 3, value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Decoder, Encoder};
+
+    #[test]
+    fn fields() {
+        let mut encoder = Encoder::default();
+        encoder.uint64(1, 300);
+        encoder.int64(2, -1);
+        encoder.packed(3, [1, 2, 3]);
+        encoder.message(4, |message| message.bytes(1, b"hi"));
+        encoder.uint64(5, 0);
+        assert_eq!(&encoder.bytes[..3], [0x08, 0xac, 0x02]);
+
+        let mut decoder = Decoder(&encoder.bytes);
+        let mut fields = alloc::vec::Vec::new();
+        let mut repeated = alloc::vec::Vec::new();
+        while let Some((field, value)) = decoder.field().unwrap() {
+            match field {
+                1 | 2 => fields.push(value.int64().unwrap()),
+                3 => value.push_to(&mut repeated).unwrap(),
+                4 => {
+                    let (_, text) = Decoder(value.bytes().unwrap()).field().unwrap().unwrap();
+                    assert_eq!(text.bytes().unwrap(), b"hi");
+                }
+                _ => unreachable!(),
+            }
+        }
+        assert_eq!(fields, [300, -1]);
+        assert_eq!(repeated, [1, 2, 3]);
+        assert!(Decoder(&[0x08, 0x80]).field().is_err());
+    }
+}
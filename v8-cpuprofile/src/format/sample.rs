@@ -0,0 +1,195 @@
+use super::Format;
+use crate::{CallFrame, Profile};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use hashbrown::HashMap;
+use std::io::{self, Write};
+
+/// The call graph macOS `sample` prints, a line per node with the number of
+/// samples with it on the stack, indented by depth and its children sorted
+/// by count, then the samples of each function at the top of the stack.
+/// Frames read `function  (in <script>)  url:line:column`, so tools that
+/// parse `sample` output, like the `stackcollapse-sample` flamegraph
+/// script, read them.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct MacosSample;
+
+impl Format for MacosSample {
+    fn name(&self) -> &'static str {
+        "sample"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["sample.txt"]
+    }
+
+    fn can_write(&self) -> bool {
+        true
+    }
+
+    fn write(&self, profile: &Profile, writer: &mut dyn Write) -> io::Result<()> {
+        let mut self_counts: HashMap<u64, u64> = HashMap::new();
+        for sample in &profile.samples {
+            *self_counts.entry(sample.node_id).or_default() += 1;
+        }
+        let mut counts = self_counts.clone();
+        for (&node_id, &count) in &self_counts {
+            for parent_id in profile.parent_ids_iter(node_id) {
+                *counts.entry(parent_id).or_default() += count;
+            }
+        }
+
+        if let Some(interval) = profile.nominal_sampling_interval() {
+            writeln!(
+                writer,
+                "Analysis of sampling cpuprofile every {:.3} milliseconds",
+                interval.as_secs_f64() * 1000.0
+            )?;
+        }
+        writeln!(
+            writer,
+            "Duration: {:.3} seconds, {} samples",
+            profile.duration().as_secs_f64(),
+            profile.samples.len()
+        )?;
+        writeln!(writer)?;
+        writeln!(writer, "Call graph:")?;
+        let children = |node_id: u64| -> Vec<(u64, u64)> {
+            let mut children: Vec<_> = profile[node_id]
+                .children
+                .iter()
+                .flatten()
+                .filter_map(|&child| counts.get(&child).map(|&count| (child, count)))
+                .collect();
+            children.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+            children
+        };
+        // (node id, count, depth), the next line last
+        let mut stack: Vec<_> = profile
+            .nodes
+            .iter()
+            .filter(|node| node.parent_id.is_none())
+            .filter_map(|node| counts.get(&node.id).map(|&count| (node.id, count, 0)))
+            .collect();
+        stack.reverse();
+        while let Some((node_id, count, depth)) = stack.pop() {
+            let mut line = String::from("    ");
+            for level in 0..depth {
+                line.push(MARKERS[level % MARKERS.len()]);
+                line.push(' ');
+            }
+            writeln!(
+                writer,
+                "{}{} {}",
+                line,
+                count,
+                Frame(&profile[node_id].frame()?)
+            )?;
+            stack.extend(
+                children(node_id)
+                    .into_iter()
+                    .rev()
+                    .map(|(child, count)| (child, count, depth + 1)),
+            );
+        }
+
+        let mut top: HashMap<String, u64> = HashMap::new();
+        for (node_id, count) in self_counts {
+            let frame = profile[node_id].frame()?;
+            *top.entry(Frame(&frame).to_string()).or_default() += count;
+        }
+        let mut top: Vec<_> = top.into_iter().collect();
+        top.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+        writeln!(writer)?;
+        writeln!(writer, "Sort by top of stack, same collapsed:")?;
+        for (frame, count) in top {
+            writeln!(writer, "        {}        {}", frame, count)?;
+        }
+        Ok(())
+    }
+}
+
+/// The characters `sample` draws down each level of the call graph.
+const MARKERS: [char; 4] = ['+', '!', ':', '|'];
+
+struct Frame<'f, 'raw>(&'f CallFrame<'raw>);
+
+impl core::fmt::Display for Frame<'_, '_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let frame = self.0;
+        if frame.function_name.is_empty() {
+            f.write_str("(anonymous)")?;
+        } else {
+            f.write_str(&frame.function_name)?;
+        }
+        if frame.url.is_empty() {
+            return Ok(());
+        }
+        let script = frame.url.rsplit('/').next().unwrap_or_default();
+        if frame.line_number < 0 {
+            return write!(f, "  (in {})", script);
+        }
+        write!(
+            f,
+            "  (in {})  {}:{}:{}",
+            script,
+            frame.url,
+            frame.line_number + 1,
+            frame.column_number + 1
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MacosSample;
+    use crate::format::Format;
+    use crate::Profile;
+
+    fn count(line: &str) -> u64 {
+        line.trim_start_matches(&[' ', '+', '!', ':', '|'][..])
+            .split(' ')
+            .next()
+            .unwrap()
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn sample() {
+        let profile: Profile = serde_json::from_str(crate::ser::tests::PROFILE).unwrap();
+        let mut out = alloc::vec::Vec::new();
+        MacosSample.write(&profile, &mut out).unwrap();
+        let out = alloc::string::String::from_utf8(out).unwrap();
+        let mut sections = out.split("\n\n");
+        assert!(sections.next().unwrap().starts_with("Analysis of sampling"));
+
+        let graph: alloc::vec::Vec<_> = sections.next().unwrap().lines().skip(1).collect();
+        assert_eq!(
+            graph[0],
+            alloc::format!("    {} (root)", profile.samples.len())
+        );
+        // a child is one level deeper and has at most its parent's count
+        for pair in graph.windows(2) {
+            let indent = |line: &str| {
+                line.len()
+                    - line
+                        .trim_start_matches(&[' ', '+', '!', ':', '|'][..])
+                        .len()
+            };
+            assert!(indent(pair[1]) <= indent(pair[0]) + 2);
+            if indent(pair[1]) > indent(pair[0]) {
+                assert!(count(pair[1]) <= count(pair[0]));
+            }
+        }
+        assert!(graph.iter().any(|line| line.contains("  (in ")));
+
+        let top = sections.next().unwrap();
+        let total: usize = top
+            .lines()
+            .skip(1)
+            .map(|line| line.rsplit(' ').next().unwrap().parse::<usize>().unwrap())
+            .sum();
+        assert_eq!(total, profile.samples.len());
+    }
+}
@@ -0,0 +1,150 @@
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use serde::de::{Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use std::io;
+
+/// What an input looks like, worked out once for every format's
+/// [`Format::sniff`](super::Format::sniff).
+#[derive(Debug)]
+pub struct Sniffed<'a> {
+    pub input: &'a [u8],
+    /// The keys of a json object, in order, `None` for other inputs.
+    pub keys: Option<Vec<String>>,
+    /// Whether the input is a json array.
+    pub array: bool,
+}
+
+impl<'a> Sniffed<'a> {
+    /// Scans `input`, without keeping any values if it is json.
+    #[must_use]
+    pub fn new(input: &'a [u8]) -> Self {
+        let mut sniffed = Sniffed {
+            input,
+            keys: None,
+            array: false,
+        };
+        let first = input.iter().find(|byte| !byte.is_ascii_whitespace());
+        if matches!(first, Some(b'{' | b'[')) {
+            let mut deserializer = serde_json::Deserializer::from_slice(input);
+            match deserializer.deserialize_any(TopLevel) {
+                Ok(Json::Object(keys)) => sniffed.keys = Some(keys),
+                Ok(Json::Array) => sniffed.array = true,
+                Err(_) => {}
+            }
+        }
+        sniffed
+    }
+
+    /// Whether the input is a json object with `key`.
+    #[must_use]
+    pub fn has_key(&self, key: &str) -> bool {
+        self.keys.iter().flatten().any(|known| known == key)
+    }
+}
+
+enum Json {
+    Object(Vec<String>),
+    Array,
+}
+
+struct TopLevel;
+
+impl<'de> Visitor<'de> for TopLevel {
+    type Value = Json;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a json object or array")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut keys = Vec::new();
+        while let Some(key) = map.next_key()? {
+            map.next_value::<IgnoredAny>()?;
+            keys.push(key);
+        }
+        Ok(Json::Object(keys))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        while seq.next_element::<IgnoredAny>()?.is_some() {}
+        Ok(Json::Array)
+    }
+}
+
+/// Whether `input` starts like a gzip stream.
+#[must_use]
+pub fn is_gzip(input: &[u8]) -> bool {
+    input.starts_with(&[0x1f, 0x8b])
+}
+
+/// The input with gzip compression undone, or as it is if it isn't gzip
+/// compressed.
+///
+/// # Errors
+///
+/// If the gzip stream is invalid, or the `gzip` feature is off.
+pub fn decompress(input: &[u8]) -> io::Result<Cow<'_, [u8]>> {
+    if !is_gzip(input) {
+        return Ok(Cow::Borrowed(input));
+    }
+    #[cfg(feature = "gzip")]
+    {
+        use std::io::Read;
+        let mut bytes = Vec::new();
+        flate2::read::MultiGzDecoder::new(input).read_to_end(&mut bytes)?;
+        Ok(Cow::Owned(bytes))
+    }
+    #[cfg(not(feature = "gzip"))]
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "gzip compressed input needs the gzip feature",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decompress, Sniffed};
+    use crate::format::Registry;
+    use crate::ser::tests::PROFILE;
+
+    #[test]
+    fn sniff() {
+        let sniffed = Sniffed::new(br#" {"nodes":[{"id":1}],"startTime":0} "#);
+        assert_eq!(
+            sniffed.keys.as_deref(),
+            Some(&["nodes".into(), "startTime".into()][..])
+        );
+        assert!(Sniffed::new(b"[{}]").array);
+        assert_eq!(Sniffed::new(b"{\"a\":").keys, None);
+
+        let registry = Registry::default();
+        let name = |input: &[u8]| registry.detect(input).map(super::super::Format::name);
+        assert_eq!(name(br#"{"traceEvents":[]}"#), Some("trace"));
+        assert_eq!(name(b"[]"), Some("trace"));
+        assert_eq!(name(br#"{"shared":{},"profiles":[]}"#), Some("speedscope"));
+        assert_eq!(name(b"fn 12"), Some("folded"));
+        assert_eq!(name(b"fn"), None);
+        assert_eq!(name(b""), None);
+        assert_eq!(decompress(b"{}").unwrap().as_ref(), b"{}");
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn gzip() {
+        use std::io::Write;
+        let mut gzip =
+            flate2::write::GzEncoder::new(alloc::vec::Vec::new(), flate2::Compression::default());
+        gzip.write_all(PROFILE.as_bytes()).unwrap();
+        let gzip = gzip.finish().unwrap();
+        assert_eq!(decompress(&gzip).unwrap().as_ref(), PROFILE.as_bytes());
+        assert_eq!(Registry::default().read(&gzip).unwrap().samples.len(), 28);
+    }
+}
@@ -0,0 +1,256 @@
+use super::{invalid, slice_events, Format, Frames, Sniffed, StackTree};
+use crate::{CallFrame, Micros, Profile};
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use serde_json::{json, Map, Value};
+use std::io::{self, Write};
+
+/// speedscope's json, its `sampled` profiles with weights in microseconds.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Speedscope;
+
+impl Format for Speedscope {
+    fn name(&self) -> &'static str {
+        "speedscope"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["speedscope.json"]
+    }
+
+    fn can_read(&self) -> bool {
+        true
+    }
+
+    fn can_write(&self) -> bool {
+        true
+    }
+
+    fn sniff(&self, sniffed: &Sniffed) -> bool {
+        sniffed.has_key("shared") && sniffed.has_key("profiles")
+    }
+
+    /// Reads the active profile, which has to be a sampled one.
+    fn read(&self, input: &[u8]) -> io::Result<Profile<'static>> {
+        let file: Value = serde_json::from_slice(input)?;
+        let frames = file
+            .pointer("/shared/frames")
+            .and_then(Value::as_array)
+            .ok_or_else(|| invalid("missing shared.frames"))?
+            .iter()
+            .map(frame)
+            .collect::<Vec<_>>();
+        let active = file
+            .get("activeProfileIndex")
+            .and_then(Value::as_u64)
+            .unwrap_or(0);
+        let profile = file
+            .get("profiles")
+            .and_then(Value::as_array)
+            .and_then(|profiles| profiles.get(usize::try_from(active).ok()?))
+            .ok_or_else(|| invalid("missing profile"))?;
+        if profile.get("type").and_then(Value::as_str) != Some("sampled") {
+            return Err(invalid("only sampled profiles can be read"));
+        }
+        let micros_per_unit = match profile.get("unit").and_then(Value::as_str) {
+            Some("nanoseconds") => 0.001,
+            Some("microseconds") => 1.0,
+            Some("milliseconds") => 1000.0,
+            Some("seconds") => 1_000_000.0,
+            _ => return Err(invalid("a sampled profile has to be in units of time")),
+        };
+        let micros = |key: &str| -> io::Result<Micros> {
+            profile
+                .get(key)
+                .and_then(Value::as_f64)
+                .map(|value| to_micros(value * micros_per_unit))
+                .ok_or_else(|| invalid(alloc::format!("missing {}", key)))
+        };
+        let (start, end) = (micros("startValue")?, micros("endValue")?);
+        let array = |key: &str| {
+            profile
+                .get(key)
+                .and_then(Value::as_array)
+                .ok_or_else(|| invalid(alloc::format!("missing {}", key)))
+        };
+        let (samples, weights) = (array("samples")?, array("weights")?);
+        if samples.len() != weights.len() {
+            return Err(invalid("samples and weights differ in length"));
+        }
+
+        let mut tree = StackTree::new(&frames)?;
+        let mut weighted = Vec::with_capacity(samples.len());
+        for (stack, weight) in samples.iter().zip(weights) {
+            let stack = stack
+                .as_array()
+                .ok_or_else(|| invalid("a sample is not a stack"))?
+                .iter()
+                .map(|frame| {
+                    frame
+                        .as_u64()
+                        .and_then(|frame| usize::try_from(frame).ok())
+                        .ok_or_else(|| invalid("a stack frame is not an index"))
+                })
+                .collect::<io::Result<Vec<_>>>()?;
+            let weight = weight
+                .as_f64()
+                .ok_or_else(|| invalid("a weight is not a number"))?;
+            weighted.push((tree.node(stack)?, to_micros(weight * micros_per_unit)));
+        }
+        Ok(tree.finish(start, end.max(start), &weighted))
+    }
+
+    fn write(&self, profile: &Profile, writer: &mut dyn Write) -> io::Result<()> {
+        let frames = Frames::new(profile)?;
+        let mut samples = Vec::with_capacity(profile.samples.len());
+        let mut weights = Vec::with_capacity(profile.samples.len());
+        for (sample, duration) in profile.samples_with_weights() {
+            samples.push(frames.stack(profile, sample.node_id));
+            weights.push(Micros::from(duration).0);
+        }
+        let shared = shared_frames(&frames);
+        let file = json!({
+            "$schema": "https://www.speedscope.app/file-format-schema.json",
+            "shared": { "frames": shared },
+            "profiles": [{
+                "type": "sampled",
+                "name": "cpuprofile",
+                "unit": "microseconds",
+                "startValue": 0,
+                "endValue": Micros::from(profile.duration()).0,
+                "samples": samples,
+                "weights": weights,
+            }],
+            "activeProfileIndex": 0,
+            "exporter": "v8-cpuprofile",
+        });
+        serde_json::to_writer(writer, &file)?;
+        Ok(())
+    }
+}
+
+/// speedscope's json with an `evented` profile, the flame chart of the
+/// samples in time order, opening a frame when a run of samples with it on
+/// their stack starts and closing it when the run ends.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct SpeedscopeEvented;
+
+impl Format for SpeedscopeEvented {
+    fn name(&self) -> &'static str {
+        "speedscope-evented"
+    }
+
+    fn can_write(&self) -> bool {
+        true
+    }
+
+    fn write(&self, profile: &Profile, writer: &mut dyn Write) -> io::Result<()> {
+        let frames = Frames::new(profile)?;
+        let slices = profile.slices();
+        let events: Vec<Value> = slice_events(&slices)
+            .into_iter()
+            .map(|(at, open, slice)| {
+                json!({
+                    "type": if open { "O" } else { "C" },
+                    "frame": frames.index(slice.node_id),
+                    "at": at.0,
+                })
+            })
+            .collect();
+        let file = json!({
+            "$schema": "https://www.speedscope.app/file-format-schema.json",
+            "shared": { "frames": shared_frames(&frames) },
+            "profiles": [{
+                "type": "evented",
+                "name": "cpuprofile",
+                "unit": "microseconds",
+                "startValue": 0,
+                "endValue": Micros::from(profile.duration()).0,
+                "events": events,
+            }],
+            "activeProfileIndex": 0,
+            "exporter": "v8-cpuprofile",
+        });
+        serde_json::to_writer(writer, &file)?;
+        Ok(())
+    }
+}
+
+fn shared_frames(frames: &Frames) -> Vec<Value> {
+    frames
+        .frames
+        .iter()
+        .map(|frame| {
+            let mut json = Map::new();
+            let name = if frame.function_name.is_empty() {
+                "(anonymous)"
+            } else {
+                &frame.function_name
+            };
+            json.insert("name".into(), name.into());
+            if !frame.url.is_empty() {
+                json.insert("file".into(), frame.url.as_ref().into());
+            }
+            // speedscope's lines and columns are 1-based
+            if frame.line_number >= 0 {
+                json.insert("line".into(), (frame.line_number + 1).into());
+            }
+            if frame.column_number >= 0 {
+                json.insert("col".into(), (frame.column_number + 1).into());
+            }
+            Value::Object(json)
+        })
+        .collect()
+}
+
+fn frame(json: &Value) -> CallFrame<'static> {
+    let position = |key: &str| {
+        json.get(key)
+            .and_then(Value::as_i64)
+            .and_then(|position| i32::try_from(position - 1).ok())
+            .unwrap_or(-1)
+    };
+    let text = |key: &str| Cow::Owned(json.get(key).and_then(Value::as_str).unwrap_or("").into());
+    CallFrame {
+        function_name: text("name"),
+        script_id: Cow::Borrowed("0"),
+        url: text("file"),
+        line_number: position("line"),
+        column_number: position("col"),
+    }
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn to_micros(value: f64) -> Micros {
+    Micros(libm::round(value.max(0.0)) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Speedscope;
+    use crate::format::Format;
+    use crate::Profile;
+
+    #[test]
+    fn speedscope() {
+        let profile: Profile = serde_json::from_str(crate::ser::tests::PROFILE).unwrap();
+        let mut out = alloc::vec::Vec::new();
+        Speedscope.write(&profile, &mut out).unwrap();
+        let read = Speedscope.read(&out).unwrap();
+        read.roundtrip_check().unwrap();
+        assert_eq!(read.duration(), profile.duration());
+        assert_eq!(read.samples.len(), profile.samples.len());
+        assert_eq!(read.self_times().len(), profile.self_times().len());
+
+        // weights in milliseconds and a sample of the root
+        let json = br#"{"shared":{"frames":[{"name":"a","file":"a.js","line":3}]},"profiles":[{"type":"sampled","unit":"milliseconds","startValue":1,"endValue":5,"samples":[[0],[]],"weights":[1.5,0.5]}]}"#;
+        let read = Speedscope.read(json).unwrap();
+        assert_eq!(read.start_time.0, 1000);
+        assert_eq!(read.end_time.0, 5000);
+        assert_eq!(read.samples[0].ts.0, 2000);
+        assert_eq!(read.samples[1].node_id, 1);
+        let frame = read.nodes[1].frame().unwrap();
+        assert_eq!((frame.line_number, frame.column_number), (2, -1));
+    }
+}
@@ -0,0 +1,195 @@
+use super::Format;
+use crate::category::Category;
+use crate::{FrameKey, FrameTimes, Profile};
+use alloc::vec::Vec;
+use core::time::Duration;
+use serde::ser::{SerializeMap, SerializeSeq, Serializer};
+use serde::Serialize;
+use std::io::{self, Write};
+
+/// A compact json summary small enough to attach to a trace span: the
+/// duration, sample count, self time by category and the hottest functions
+/// by self time. Times are in milliseconds rounded to a tenth.
+#[derive(Debug, Copy, Clone)]
+pub struct Summary {
+    /// Number of functions listed.
+    pub top: usize,
+}
+
+impl Default for Summary {
+    fn default() -> Self {
+        Summary { top: 10 }
+    }
+}
+
+impl Format for Summary {
+    fn name(&self) -> &'static str {
+        "summary"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["summary.json"]
+    }
+
+    fn can_write(&self) -> bool {
+        true
+    }
+
+    fn write(&self, profile: &Profile, writer: &mut dyn Write) -> io::Result<()> {
+        let mut frames: Vec<_> = profile.frame_times()?.into_iter().collect();
+        frames.sort_unstable_by(|(a_frame, a), (b_frame, b)| {
+            b.self_time.cmp(&a.self_time).then(a_frame.cmp(b_frame))
+        });
+        frames.truncate(self.top);
+        let summary = Json {
+            duration: profile.duration(),
+            samples: profile.samples.len(),
+            categories: &profile.category_times()?,
+            frames: &frames,
+        };
+        serde_json::to_writer(&mut *writer, &summary)?;
+        writeln!(writer)
+    }
+}
+
+/// The value of a `Server-Timing` header with the duration as `cpu` and
+/// the self time of each category as `cpu-<category>`, like
+/// `cpu;dur=12.3, cpu-app;dur=8.1`.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ServerTiming;
+
+impl Format for ServerTiming {
+    fn name(&self) -> &'static str {
+        "server-timing"
+    }
+
+    fn can_write(&self) -> bool {
+        true
+    }
+
+    fn write(&self, profile: &Profile, writer: &mut dyn Write) -> io::Result<()> {
+        write!(writer, "cpu;dur={}", millis(profile.duration()))?;
+        for (category, time) in profile.category_times()? {
+            write!(writer, ", cpu-{};dur={}", category, millis(time))?;
+        }
+        writeln!(writer)
+    }
+}
+
+/// Milliseconds rounded to a tenth.
+#[allow(clippy::cast_precision_loss)]
+fn millis(time: Duration) -> f64 {
+    ((time.as_micros() + 50) / 100) as f64 / 10.0
+}
+
+struct Json<'s, 'raw> {
+    duration: Duration,
+    samples: usize,
+    categories: &'s [(Category, Duration)],
+    frames: &'s [(FrameKey<'raw>, FrameTimes)],
+}
+
+impl Serialize for Json<'_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(4))?;
+        map.serialize_entry("duration_ms", &millis(self.duration))?;
+        map.serialize_entry("samples", &self.samples)?;
+        map.serialize_entry("categories", &Categories(self.categories))?;
+        map.serialize_entry("top", &Top(self.frames))?;
+        map.end()
+    }
+}
+
+struct Categories<'s>(&'s [(Category, Duration)]);
+
+impl Serialize for Categories<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (category, time) in self.0 {
+            map.serialize_entry(category.name(), &millis(*time))?;
+        }
+        map.end()
+    }
+}
+
+struct Top<'s, 'raw>(&'s [(FrameKey<'raw>, FrameTimes)]);
+
+impl Serialize for Top<'_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+        for (frame, times) in self.0 {
+            seq.serialize_element(&Function(frame, times))?;
+        }
+        seq.end()
+    }
+}
+
+struct Function<'s, 'raw>(&'s FrameKey<'raw>, &'s FrameTimes);
+
+impl Serialize for Function<'_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let Function(frame, times) = *self;
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("function", &frame.function_name)?;
+        if !frame.url.is_empty() {
+            map.serialize_entry("url", &frame.url)?;
+        }
+        if frame.line_number >= 0 {
+            map.serialize_entry("line", &(i64::from(frame.line_number) + 1))?;
+        }
+        map.serialize_entry("self_ms", &millis(times.self_time))?;
+        map.serialize_entry("total_ms", &millis(times.total_time))?;
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ServerTiming, Summary};
+    use crate::format::Format;
+    use crate::Profile;
+    use alloc::string::String;
+    use serde_json::Value;
+
+    #[test]
+    fn summary() {
+        let profile: Profile = serde_json::from_str(crate::ser::tests::PROFILE).unwrap();
+        let mut out = alloc::vec::Vec::new();
+        Summary::default().write(&profile, &mut out).unwrap();
+        assert!(out.len() < 2048, "{} bytes", out.len());
+        let summary: Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(summary["samples"], profile.samples.len());
+        let top = summary["top"].as_array().unwrap();
+        assert_eq!(top.len(), 10);
+        assert!(top
+            .windows(2)
+            .all(|pair| pair[0]["self_ms"].as_f64() >= pair[1]["self_ms"].as_f64()));
+        assert!(top.iter().all(|function| function["function"].is_string()));
+        let categories: f64 = summary["categories"]
+            .as_object()
+            .unwrap()
+            .values()
+            .map(|time| time.as_f64().unwrap())
+            .sum();
+        let duration = summary["duration_ms"].as_f64().unwrap();
+        assert!(categories > 0.0 && categories <= duration + 0.5);
+
+        let mut out = alloc::vec::Vec::new();
+        ServerTiming.write(&profile, &mut out).unwrap();
+        let header = String::from_utf8(out).unwrap();
+        assert!(header.starts_with(&alloc::format!("cpu;dur={}, cpu-node;dur=", duration)));
+        assert!(header.ends_with('\n') && !header.trim_end().contains('\n'));
+    }
+}
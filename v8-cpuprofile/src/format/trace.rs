@@ -0,0 +1,180 @@
+use super::{invalid, Format, Sniffed};
+use crate::Profile;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use hashbrown::HashMap;
+use serde_json::{json, Value};
+use std::io;
+
+/// A Chrome devtools performance recording, the `Profile` and
+/// `ProfileChunk` events of its first cpu profile.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct DevtoolsTrace;
+
+impl Format for DevtoolsTrace {
+    fn name(&self) -> &'static str {
+        "trace"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["json"]
+    }
+
+    fn can_read(&self) -> bool {
+        true
+    }
+
+    /// An array of events or an object with them.
+    fn sniff(&self, sniffed: &Sniffed) -> bool {
+        sniffed.array || sniffed.has_key("traceEvents")
+    }
+
+    fn read(&self, input: &[u8]) -> io::Result<Profile<'static>> {
+        let trace: Value = serde_json::from_slice(input)?;
+        // either an array of events or an object with them
+        let events = trace
+            .as_array()
+            .or_else(|| trace.get("traceEvents").and_then(Value::as_array))
+            .ok_or_else(|| invalid("missing traceEvents"))?;
+        let data = |event: &'_ Value| event.pointer("/args/data").cloned().unwrap_or(Value::Null);
+        let profile = events
+            .iter()
+            .find(|event| event.get("name").and_then(Value::as_str) == Some("Profile"))
+            .ok_or_else(|| invalid("the trace has no cpu profile"))?;
+        let id = profile.get("id");
+        let start_time = data(profile)
+            .get("startTime")
+            .and_then(Value::as_u64)
+            .unwrap_or(0);
+
+        let mut nodes = Vec::new();
+        let mut samples = Vec::new();
+        let mut time_deltas = Vec::new();
+        let mut end_time = None;
+        for event in events {
+            if event.get("name").and_then(Value::as_str) != Some("ProfileChunk")
+                || event.get("id") != id
+            {
+                continue;
+            }
+            let data = data(event);
+            let cpu_profile = data.get("cpuProfile");
+            let array = |value: Option<&Value>| value.and_then(Value::as_array).cloned();
+            nodes.extend(array(cpu_profile.and_then(|p| p.get("nodes"))).unwrap_or_default());
+            samples.extend(array(cpu_profile.and_then(|p| p.get("samples"))).unwrap_or_default());
+            time_deltas.extend(array(data.get("timeDeltas")).unwrap_or_default());
+            if let Some(end) = data.get("endTime").and_then(Value::as_u64) {
+                end_time = Some(end);
+            }
+        }
+        if samples.len() != time_deltas.len() {
+            return Err(invalid("samples and timeDeltas differ in length"));
+        }
+
+        // chunks give each node's parent, a profile its children
+        let mut children: HashMap<u64, Vec<u64>> = HashMap::new();
+        for node in &nodes {
+            let parent = node.get("parent").and_then(Value::as_u64);
+            let id = node.get("id").and_then(Value::as_u64);
+            if let (Some(parent), Some(id)) = (parent, id) {
+                children.entry(parent).or_default().push(id);
+            }
+        }
+        let mut hit_counts: HashMap<u64, u64> = HashMap::new();
+        for sample in &samples {
+            if let Some(node_id) = sample.as_u64() {
+                *hit_counts.entry(node_id).or_default() += 1;
+            }
+        }
+        let nodes: Vec<Value> = nodes
+            .into_iter()
+            .map(|node| {
+                let id = node.get("id").and_then(Value::as_u64).unwrap_or(0);
+                json!({
+                    "id": id,
+                    "callFrame": call_frame(node.get("callFrame")),
+                    "hitCount": hit_counts.get(&id).copied().unwrap_or(0),
+                    "children": children.remove(&id).unwrap_or_default(),
+                })
+            })
+            .collect();
+        let last_sample = time_deltas.iter().filter_map(Value::as_i64).fold(
+            i64::try_from(start_time).unwrap_or(i64::MAX),
+            i64::saturating_add,
+        );
+        let profile = json!({
+            "nodes": nodes,
+            "startTime": start_time,
+            "endTime": end_time.unwrap_or_else(|| u64::try_from(last_sample).unwrap_or(start_time)),
+            "samples": samples,
+            "timeDeltas": time_deltas,
+        });
+        Ok(Profile::from_value(&profile)?)
+    }
+}
+
+/// A trace's call frame as a profile writes it: chunks give numeric
+/// script ids, leave out unknown positions and add a `codeType`.
+fn call_frame(frame: Option<&Value>) -> Value {
+    let field = |key: &str| frame.and_then(|frame| frame.get(key));
+    let script_id = match field("scriptId") {
+        Some(Value::Number(id)) => Value::String(id.to_string()),
+        Some(id @ Value::String(_)) => id.clone(),
+        _ => Value::String("0".into()),
+    };
+    let position = |key: &str| field(key).and_then(Value::as_i64).unwrap_or(-1);
+    json!({
+        "functionName": field("functionName").and_then(Value::as_str).unwrap_or(""),
+        "scriptId": script_id,
+        "url": field("url").and_then(Value::as_str).unwrap_or(""),
+        "lineNumber": position("lineNumber"),
+        "columnNumber": position("columnNumber"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DevtoolsTrace;
+    use crate::format::Format;
+
+    #[test]
+    fn devtools_trace() {
+        let trace = br#"{"traceEvents":[
+            {"name":"Profile","ph":"P","id":"0x1","args":{"data":{"startTime":1000}}},
+            {"name":"ProfileChunk","ph":"P","id":"0x1","args":{"data":{"cpuProfile":{"nodes":[{"id":1,"callFrame":{"functionName":"(root)","scriptId":"0","url":"","lineNumber":-1,"columnNumber":-1}},{"id":2,"parent":1,"callFrame":{"functionName":"a","scriptId":"1","url":"a.js","lineNumber":0,"columnNumber":0}}],"samples":[2,2]},"timeDeltas":[5,10]}}},
+            {"name":"ProfileChunk","ph":"P","id":"0x2","args":{"data":{"cpuProfile":{"samples":[9]},"timeDeltas":[1]}}},
+            {"name":"ProfileChunk","ph":"P","id":"0x1","args":{"data":{"cpuProfile":{"nodes":[{"id":3,"parent":2,"callFrame":{"functionName":"b","scriptId":"1","url":"a.js","lineNumber":4,"columnNumber":2}}],"samples":[3]},"timeDeltas":[20],"endTime":1040}}}
+        ]}"#;
+        let profile = DevtoolsTrace.read(trace).unwrap();
+        profile.roundtrip_check().unwrap();
+        assert_eq!(profile.nodes.len(), 3);
+        assert_eq!(profile.nodes[1].children, Some(alloc::vec![3]));
+        assert_eq!(profile.nodes[1].hit_count, 2);
+        let ts: alloc::vec::Vec<_> = profile.samples.iter().map(|sample| sample.ts.0).collect();
+        assert_eq!(ts, [5, 15, 35]);
+        assert_eq!(profile.end_time.0, 1040);
+        assert!(DevtoolsTrace.read(b"[]").is_err());
+    }
+
+    #[test]
+    fn chrome_call_frames() {
+        // as Chrome records them: numeric script ids, a codeType and no
+        // position for native code
+        let trace = br#"[
+            {"args":{"data":{"startTime":1000}},"cat":"disabled-by-default-v8.cpu_profiler","id":"0x1","name":"Profile","ph":"P","pid":1,"tid":2,"ts":1000},
+            {"args":{"data":{"cpuProfile":{"nodes":[{"callFrame":{"codeType":"other","functionName":"(root)","scriptId":0},"id":1},{"callFrame":{"codeType":"other","functionName":"(program)","scriptId":0},"id":2,"parent":1},{"callFrame":{"codeType":"JS","columnNumber":17,"functionName":"main","lineNumber":3,"scriptId":12,"url":"https://example.com/app.js"},"id":3,"parent":1}],"samples":[2,3,3]},"lines":[0,4,4],"timeDeltas":[10,5,5]}},"cat":"disabled-by-default-v8.cpu_profiler","id":"0x1","name":"ProfileChunk","ph":"P","pid":1,"tid":2,"ts":1100}
+        ]"#;
+        let profile = DevtoolsTrace.read(trace).unwrap();
+        profile.roundtrip_check().unwrap();
+        let root = profile.nodes[0].frame().unwrap();
+        assert_eq!(root.function_name, "(root)");
+        assert_eq!(root.script_id, "0");
+        assert_eq!((root.line_number, root.column_number), (-1, -1));
+        let main = profile.nodes[2].frame().unwrap();
+        assert_eq!(main.script_id, "12");
+        assert_eq!(main.url, "https://example.com/app.js");
+        assert_eq!((main.line_number, main.column_number), (3, 17));
+        assert_eq!(profile.nodes[2].hit_count, 2);
+    }
+}
@@ -0,0 +1,320 @@
+use crate::{Node, Profile};
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
+use core::fmt;
+use core::time::Duration;
+use hashbrown::HashMap;
+
+/// The parsed contents of a node's `callFrame`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CallFrame<'raw> {
+    pub function_name: Cow<'raw, str>,
+    pub script_id: Cow<'raw, str>,
+    pub url: Cow<'raw, str>,
+    /// 0-based, -1 if unknown
+    pub line_number: i32,
+    /// 0-based, -1 if unknown
+    pub column_number: i32,
+}
+
+/// Identifies a function across profiles, unlike the script id which is only
+/// stable within one run.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FrameKey<'raw> {
+    pub function_name: Cow<'raw, str>,
+    pub url: Cow<'raw, str>,
+    /// 0-based, -1 if unknown
+    pub line_number: i32,
+    /// 0-based, -1 if unknown
+    pub column_number: i32,
+}
+
+impl CallFrame<'_> {
+    /// Copies any borrowed strings.
+    #[must_use]
+    pub fn into_owned(self) -> CallFrame<'static> {
+        CallFrame {
+            function_name: Cow::Owned(self.function_name.into_owned()),
+            script_id: Cow::Owned(self.script_id.into_owned()),
+            url: Cow::Owned(self.url.into_owned()),
+            line_number: self.line_number,
+            column_number: self.column_number,
+        }
+    }
+}
+
+/// Like a stack trace line, `render (app.js:120:8)` with a 1-based line
+/// and column, or the name alone without a url.
+impl fmt::Display for CallFrame<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_location(
+            f,
+            &self.function_name,
+            &self.url,
+            self.line_number,
+            self.column_number,
+        )
+    }
+}
+
+/// The same as the [`CallFrame`] it was made from.
+impl fmt::Display for FrameKey<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_location(
+            f,
+            &self.function_name,
+            &self.url,
+            self.line_number,
+            self.column_number,
+        )
+    }
+}
+
+/// The node's call frame, or its id if the call frame is invalid.
+impl fmt::Display for Node<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.frame() {
+            Ok(frame) => frame.fmt(f),
+            Err(_) => write!(f, "(node {})", self.id),
+        }
+    }
+}
+
+fn write_location(
+    f: &mut fmt::Formatter<'_>,
+    function_name: &str,
+    url: &str,
+    line_number: i32,
+    column_number: i32,
+) -> fmt::Result {
+    let name = if function_name.is_empty() {
+        "(anonymous)"
+    } else {
+        function_name
+    };
+    if url.is_empty() {
+        return f.write_str(name);
+    }
+    write!(
+        f,
+        "{} ({}:{}:{})",
+        name,
+        url,
+        line_number + 1,
+        column_number + 1
+    )
+}
+
+impl<'raw> From<CallFrame<'raw>> for FrameKey<'raw> {
+    fn from(frame: CallFrame<'raw>) -> Self {
+        FrameKey {
+            function_name: frame.function_name,
+            url: frame.url,
+            line_number: frame.line_number,
+            column_number: frame.column_number,
+        }
+    }
+}
+
+/// Sampled time of a function summed over all of its nodes.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct FrameTimes {
+    pub self_time: Duration,
+    /// Time with the function anywhere on the stack, recursive calls are only
+    /// counted once.
+    pub total_time: Duration,
+}
+
+/// Node ids by call frame, see [`Profile::frame_index`].
+#[derive(Debug, Default, Clone)]
+pub struct FrameIndex<'raw> {
+    by_frame: HashMap<FrameKey<'raw>, Vec<u64>>,
+    /// function name to url to node ids
+    by_function: HashMap<Cow<'raw, str>, HashMap<Cow<'raw, str>, Vec<u64>>>,
+}
+
+impl<'raw> FrameIndex<'raw> {
+    /// Ids of the nodes with exactly this frame, in profile order.
+    #[must_use]
+    pub fn nodes(&self, frame: &FrameKey<'raw>) -> &[u64] {
+        self.by_frame.get(frame).map_or(&[], Vec::as_slice)
+    }
+
+    /// Ids of the nodes of a function in a script at any position, in
+    /// profile order.
+    #[must_use]
+    pub fn find(&self, function_name: &str, url: &str) -> &[u64] {
+        self.by_function
+            .get(function_name)
+            .and_then(|urls| urls.get(url))
+            .map_or(&[], Vec::as_slice)
+    }
+}
+
+impl<'raw> Node<'raw> {
+    /// Parses the call frame, which is kept as raw json on the node.
+    ///
+    /// # Errors
+    ///
+    /// If the call frame is not a valid v8 call frame object.
+    pub fn frame(&self) -> Result<CallFrame<'raw>, serde_json::Error> {
+        match self.call_frame.borrowed() {
+            Some(json) => serde_json::from_str(json),
+            // parsed from the node's own copy, which doesn't live for 'raw
+            None => serde_json::from_str(self.call_frame.get()).map(CallFrame::into_owned),
+        }
+    }
+}
+
+impl<'raw> Profile<'raw> {
+    /// Ids of the nodes of a function in a script, in profile order.
+    ///
+    /// This parses every call frame, build a [`FrameIndex`] with
+    /// [`Profile::frame_index`] for repeated lookups.
+    ///
+    /// # Errors
+    ///
+    /// If a node has an invalid call frame.
+    pub fn find_nodes(
+        &self,
+        function_name: &str,
+        url: &str,
+    ) -> Result<Vec<u64>, serde_json::Error> {
+        let mut node_ids = Vec::new();
+        for node in &self.nodes {
+            let frame = node.frame()?;
+            if frame.function_name == function_name && frame.url == url {
+                node_ids.push(node.id);
+            }
+        }
+        Ok(node_ids)
+    }
+
+    /// Indexes the nodes by call frame, parsing each call frame once.
+    ///
+    /// # Errors
+    ///
+    /// If a node has an invalid call frame.
+    pub fn frame_index(&self) -> Result<FrameIndex<'raw>, serde_json::Error> {
+        let mut index = FrameIndex::default();
+        for node in &self.nodes {
+            let frame = FrameKey::from(node.frame()?);
+            index
+                .by_function
+                .entry(frame.function_name.clone())
+                .or_default()
+                .entry(frame.url.clone())
+                .or_default()
+                .push(node.id);
+            index.by_frame.entry(frame).or_default().push(node.id);
+        }
+        Ok(index)
+    }
+
+    /// Sampled time of each function, merging all nodes with the same frame.
+    ///
+    /// # Errors
+    ///
+    /// If a node has an invalid call frame.
+    pub fn frame_times(&self) -> Result<HashMap<FrameKey<'raw>, FrameTimes>, serde_json::Error> {
+        self.frame_times_by(|node_id| self[node_id].parent_id)
+    }
+
+    /// Frame times where the stack of a node is walked with `parent_id`
+    /// instead of the node's own parent.
+    pub(crate) fn frame_times_by(
+        &self,
+        parent_id: impl Fn(u64) -> Option<u64>,
+    ) -> Result<HashMap<FrameKey<'raw>, FrameTimes>, serde_json::Error> {
+        let mut keys = Vec::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            keys.push(FrameKey::from(node.frame()?));
+        }
+        let key = |node_id: u64| &keys[self.node_index[&node_id]];
+
+        let mut times: HashMap<FrameKey<'raw>, FrameTimes> = HashMap::new();
+        let mut stack: Vec<&FrameKey<'raw>> = Vec::new();
+        for (node_id, self_time) in self.self_times() {
+            times.entry(key(node_id).clone()).or_default().self_time += self_time;
+
+            stack.clear();
+            stack.push(key(node_id));
+            let mut next = parent_id(node_id);
+            while let Some(id) = next {
+                stack.push(key(id));
+                next = parent_id(id);
+            }
+            stack.sort_unstable();
+            stack.dedup();
+            for frame in &stack {
+                times.entry((*frame).clone()).or_default().total_time += self_time;
+            }
+        }
+        Ok(times)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ser::tests::PROFILE;
+    use crate::{CallFrame, FrameKey, Profile};
+    use alloc::string::ToString;
+
+    #[test]
+    fn find_nodes() {
+        let profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        let index = profile.frame_index().unwrap();
+
+        let found = profile
+            .find_nodes(
+                "compileForInternalLoader",
+                "node:internal/bootstrap/loaders",
+            )
+            .unwrap();
+        assert!(found.len() > 1);
+        assert_eq!(
+            index.find(
+                "compileForInternalLoader",
+                "node:internal/bootstrap/loaders"
+            ),
+            &found[..]
+        );
+        let frame = profile[found[0]].frame().unwrap().into();
+        assert!(index.nodes(&frame).contains(&found[0]));
+        assert!(index
+            .find("compileForInternalLoader", "other.js")
+            .is_empty());
+    }
+
+    #[test]
+    fn display() {
+        let profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        let root = profile.root().unwrap();
+        assert_eq!(root.to_string(), "(root)");
+        let node = &profile[root.children.as_ref().unwrap()[0]];
+        let frame = node.frame().unwrap();
+        assert_eq!(node.to_string(), frame.to_string());
+        assert_eq!(FrameKey::from(frame).to_string(), node.to_string());
+
+        let frame = CallFrame {
+            function_name: "".into(),
+            script_id: "1".into(),
+            url: "app.js".into(),
+            line_number: 119,
+            column_number: 7,
+        };
+        assert_eq!(frame.to_string(), "(anonymous) (app.js:120:8)");
+
+        assert_eq!(profile.summary(), "77 nodes, 28 samples over 34.1ms");
+    }
+
+    #[test]
+    fn unknown_keys() {
+        let frame: CallFrame = serde_json::from_str(
+            r#"{"codeType":"JS","functionName":"f","scriptId":"3","url":"a.js","lineNumber":0,"columnNumber":4,"extra":{"a":[1]}}"#,
+        )
+        .unwrap();
+        assert_eq!(frame.to_string(), "f (a.js:1:5)");
+        assert!(serde_json::from_str::<CallFrame>(r#"{"codeType":"JS"}"#).is_err());
+    }
+}
@@ -0,0 +1,308 @@
+//! Time by framework subsystem, like React's render and commit phases, see
+//! [`Profile::framework_times`].
+
+use crate::{CallFrame, Profile};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::time::Duration;
+use hashbrown::HashMap;
+
+/// Tags the frames a url and function names match with a category.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Rule {
+    pub category: String,
+    /// Matches the urls that contain it, any url if `None`.
+    pub url: Option<String>,
+    /// The function names it matches, any function if empty.
+    pub functions: Vec<String>,
+    /// Whether the functions a matching frame calls that no rule matches
+    /// get its category too, like the components React renders.
+    pub inherit: bool,
+}
+
+impl Rule {
+    #[must_use]
+    pub fn matches(&self, frame: &CallFrame<'_>) -> bool {
+        if let Some(url) = &self.url {
+            if !frame.url.contains(url.as_str()) {
+                return false;
+            }
+        }
+        self.functions.is_empty()
+            || self
+                .functions
+                .iter()
+                .any(|function| *function == frame.function_name)
+    }
+}
+
+/// Rules tried in order, the first match wins.
+///
+/// Deserializes from a `rule` list and a `presets` list of
+/// [`Rules::PRESETS`] names, for example a TOML file with a `[[rule]]`
+/// table per rule. The rules of the presets come after the others, so a
+/// rule can override them.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Rules {
+    pub rule: Vec<Rule>,
+}
+
+/// Rule tables: category, url, function names and whether it's inherited.
+type Preset = &'static [(
+    &'static str,
+    Option<&'static str>,
+    &'static [&'static str],
+    bool,
+)];
+
+/// Matched by function name, so bundled code still matches as long as it
+/// isn't minified.
+const REACT: Preset = &[
+    (
+        "react:commit",
+        None,
+        &[
+            "commitRoot",
+            "commitRootImpl",
+            "commitMutationEffects",
+            "commitLayoutEffects",
+            "commitPassiveMountEffects",
+            "commitPassiveUnmountEffects",
+            "flushPassiveEffects",
+        ],
+        true,
+    ),
+    (
+        "react:render",
+        None,
+        &[
+            "renderWithHooks",
+            "mountIndeterminateComponent",
+            "updateFunctionComponent",
+            "updateClassComponent",
+            "finishClassComponent",
+            "beginWork",
+            "beginWork$1",
+            "completeWork",
+            "performUnitOfWork",
+            "workLoopSync",
+            "workLoopConcurrent",
+            "renderRootSync",
+            "renderRootConcurrent",
+        ],
+        true,
+    ),
+    (
+        "react:scheduler",
+        Some("/node_modules/scheduler/"),
+        &[],
+        false,
+    ),
+    ("react", Some("/node_modules/react-dom/"), &[], false),
+    ("react", Some("/node_modules/react/"), &[], false),
+];
+
+const VUE: Preset = &[
+    (
+        "vue:render",
+        None,
+        &["renderComponentRoot", "componentUpdateFn"],
+        true,
+    ),
+    (
+        "vue:scheduler",
+        Some("/@vue/runtime-core/"),
+        &["flushJobs", "flushPreFlushCbs", "flushPostFlushCbs"],
+        false,
+    ),
+    ("vue:reactivity", Some("/@vue/reactivity/"), &[], false),
+    ("vue", Some("/node_modules/@vue/"), &[], false),
+    ("vue", Some("/node_modules/vue/"), &[], false),
+];
+
+const EMBER: Preset = &[
+    (
+        "ember:render",
+        None,
+        &["_renderRoots", "_renderRootsTransaction"],
+        true,
+    ),
+    ("ember:runloop", Some("/backburner"), &[], false),
+    ("ember:glimmer", Some("/@glimmer/"), &[], false),
+    ("ember", Some("/ember-source/"), &[], false),
+];
+
+const EXPRESS: Preset = &[
+    (
+        "express:body-parser",
+        Some("/node_modules/body-parser/"),
+        &[],
+        false,
+    ),
+    (
+        "express:router",
+        Some("/node_modules/express/lib/router/"),
+        &[],
+        false,
+    ),
+    ("express:router", Some("/node_modules/router/"), &[], false),
+    ("express", Some("/node_modules/express/"), &[], false),
+];
+
+impl Rules {
+    /// The names of the built-in rules.
+    pub const PRESETS: &'static [&'static str] = &["react", "vue", "ember", "express"];
+
+    /// The built-in rules of a framework, see [`Rules::PRESETS`].
+    #[must_use]
+    pub fn preset(name: &str) -> Option<Self> {
+        let preset = match name {
+            "react" => REACT,
+            "vue" => VUE,
+            "ember" => EMBER,
+            "express" => EXPRESS,
+            _ => return None,
+        };
+        let rule = preset
+            .iter()
+            .map(|&(category, url, functions, inherit)| Rule {
+                category: String::from(category),
+                url: url.map(String::from),
+                functions: functions.iter().copied().map(String::from).collect(),
+                inherit,
+            })
+            .collect();
+        Some(Rules { rule })
+    }
+
+    /// The first rule that matches a frame.
+    #[must_use]
+    pub fn rule_of(&self, frame: &CallFrame<'_>) -> Option<&Rule> {
+        self.rule.iter().find(|rule| rule.matches(frame))
+    }
+}
+
+impl Profile<'_> {
+    /// Self time summed by the category of each sampled frame, the largest
+    /// first, `None` for frames no rule matches. A frame no rule matches
+    /// gets the category of its closest caller with an inherited rule.
+    ///
+    /// # Errors
+    ///
+    /// If a node has an invalid call frame.
+    pub fn framework_times<'r>(
+        &self,
+        rules: &'r Rules,
+    ) -> Result<Vec<(Option<&'r str>, Duration)>, serde_json::Error> {
+        // rule of each node by the node's index
+        let mut node_rules = Vec::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            node_rules.push(rules.rule_of(&node.frame()?));
+        }
+        let rule = |node_id: u64| node_rules[self.node_index[&node_id]];
+
+        let mut times: HashMap<Option<&str>, Duration> = HashMap::new();
+        for (node_id, time) in self.self_times() {
+            let category = rule(node_id)
+                .or_else(|| {
+                    self.parent_ids_iter(node_id)
+                        .filter_map(rule)
+                        .find(|rule| rule.inherit)
+                })
+                .map(|rule| rule.category.as_str());
+            *times.entry(category).or_default() += time;
+        }
+        let mut times: Vec<_> = times
+            .into_iter()
+            .filter(|(_, time)| *time > Duration::default())
+            .collect();
+        times.sort_unstable_by(|(a, a_time), (b, b_time)| b_time.cmp(a_time).then(a.cmp(b)));
+        Ok(times)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Rules;
+    use crate::Profile;
+    use alloc::string::ToString;
+    use core::time::Duration;
+    use serde_json::json;
+
+    fn node(id: u64, function_name: &str, url: &str, children: &[u64]) -> serde_json::Value {
+        json!({
+            "id": id,
+            "callFrame": {
+                "functionName": function_name,
+                "scriptId": "1",
+                "url": url,
+                "lineNumber": id,
+                "columnNumber": 0,
+            },
+            "hitCount": 0,
+            "children": children,
+        })
+    }
+
+    #[test]
+    fn framework_times() {
+        const DOM: &str = "file:///app/node_modules/react-dom/cjs/react-dom.development.js";
+        let json = json!({
+            "nodes": [
+                node(1, "(root)", "", &[2, 7]),
+                node(2, "performSyncWorkOnRoot", DOM, &[3, 5]),
+                node(3, "renderWithHooks", DOM, &[4]),
+                node(4, "App", "file:///app/src/App.js", &[]),
+                node(5, "commitRoot", DOM, &[6]),
+                node(6, "query", "file:///app/src/db.js", &[]),
+                node(7, "handler", "file:///app/src/server.js", &[]),
+            ],
+            "startTime": 0,
+            "endTime": 9,
+            "samples": [2, 3, 4, 4, 5, 6, 7, 7],
+            "timeDeltas": [1, 1, 1, 1, 1, 1, 1, 1],
+        })
+        .to_string();
+        let profile: Profile = serde_json::from_str(&json).unwrap();
+        let us = Duration::from_micros;
+
+        let react = Rules::preset("react").unwrap();
+        assert_eq!(
+            profile.framework_times(&react).unwrap(),
+            [
+                (Some("react:render"), us(3)),
+                (None, us(2)),
+                (Some("react:commit"), us(2)),
+                (Some("react"), us(1)),
+            ]
+        );
+
+        let rules: Rules = serde_json::from_str(
+            r#"{
+                "presets": ["react"],
+                "rule": [
+                    {"category": "db", "url": "/src/db.js", "functions": ["query"]},
+                    {"category": "server", "url": "/src/server.js", "inherit": true}
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(rules.rule.len(), react.rule.len() + 2);
+        assert_eq!(
+            profile.framework_times(&rules).unwrap(),
+            [
+                (Some("react:render"), us(3)),
+                (Some("server"), us(2)),
+                (Some("db"), us(1)),
+                (Some("react"), us(1)),
+                (Some("react:commit"), us(1)),
+            ]
+        );
+
+        assert!(Rules::PRESETS
+            .iter()
+            .all(|name| Rules::preset(name).is_some()));
+        assert!(serde_json::from_str::<Rules>(r#"{"presets": ["angular"]}"#).is_err());
+        assert!(serde_json::from_str::<Rules>(r#"{"rule": [{"category": "x"}]}"#).is_err());
+    }
+}
@@ -0,0 +1,108 @@
+//! Charging garbage collection to the code that caused it.
+
+use crate::Profile;
+use hashbrown::HashSet;
+
+/// Functions v8 samples outside of javascript, a garbage collection after
+/// one of these isn't charged to any function.
+const NOT_JAVASCRIPT: &[&str] = &["(root)", "(program)", "(idle)", "(garbage collector)"];
+
+impl Profile<'_> {
+    /// Reassigns each `(garbage collector)` sample to the javascript
+    /// function sampled right before the pause, like Chrome's performance
+    /// panel does, so functions that allocate a lot are charged for the
+    /// collections they cause. Returns how many samples moved.
+    ///
+    /// # Errors
+    ///
+    /// If a node has an invalid call frame.
+    pub fn attribute_gc(&mut self) -> Result<usize, serde_json::Error> {
+        let mut gc = HashSet::new();
+        let mut not_javascript = HashSet::new();
+        for node in &self.nodes {
+            let frame = node.frame()?;
+            if frame.function_name == "(garbage collector)" {
+                gc.insert(node.id);
+            }
+            if NOT_JAVASCRIPT.contains(&frame.function_name.as_ref()) {
+                not_javascript.insert(node.id);
+            }
+        }
+        if gc.is_empty() {
+            return Ok(0);
+        }
+
+        let mut moved = 0;
+        let mut last_javascript = None;
+        for index in 0..self.samples.len() {
+            let node_id = self.samples[index].node_id;
+            if gc.contains(&node_id) {
+                // a collection during another one stays charged the same
+                if let Some(javascript) = last_javascript {
+                    let gc_index = self.node_index.get(&node_id).copied();
+                    let javascript_index = self.node_index.get(&javascript).copied();
+                    if let (Some(gc_index), Some(javascript_index)) = (gc_index, javascript_index) {
+                        self.samples[index].node_id = javascript;
+                        let gc_node = &mut self.nodes[gc_index];
+                        gc_node.hit_count = gc_node.hit_count.saturating_sub(1);
+                        let javascript_node = &mut self.nodes[javascript_index];
+                        javascript_node.hit_count = javascript_node.hit_count.saturating_add(1);
+                        moved += 1;
+                    }
+                }
+            } else if not_javascript.contains(&node_id) || !self.node_index.contains_key(&node_id) {
+                // nor is a collection after a node the profile lacks
+                last_javascript = None;
+            } else {
+                last_javascript = Some(node_id);
+            }
+        }
+        Ok(moved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Profile;
+
+    #[test]
+    fn attribute_gc() {
+        let frame = |name: &str| {
+            alloc::format!(
+                r#"{{"functionName":"{}","scriptId":"1","url":"","lineNumber":-1,"columnNumber":-1}}"#,
+                name
+            )
+        };
+        let json = alloc::format!(
+            r#"{{"nodes":[
+                {{"id":1,"callFrame":{},"hitCount":0,"children":[2,3,4]}},
+                {{"id":2,"callFrame":{},"hitCount":1}},
+                {{"id":3,"callFrame":{},"hitCount":2}},
+                {{"id":4,"callFrame":{},"hitCount":3}}
+            ],"startTime":0,"endTime":60,
+            "samples":[3,4,4,2,4,3],"timeDeltas":[0,10,10,10,10,10]}}"#,
+            frame("(root)"),
+            frame("(program)"),
+            frame("allocate"),
+            frame("(garbage collector)")
+        );
+        let mut profile: Profile = serde_json::from_str(&json).unwrap();
+        assert_eq!(profile.attribute_gc().unwrap(), 2);
+        let node_ids: alloc::vec::Vec<_> = profile
+            .samples
+            .iter()
+            .map(|sample| sample.node_id)
+            .collect();
+        // the collection after (program) isn't charged to anything
+        assert_eq!(node_ids, [3, 3, 3, 2, 4, 3]);
+        assert_eq!(profile.nodes[2].hit_count, 4);
+        assert_eq!(profile.nodes[3].hit_count, 1);
+        profile.roundtrip_check().unwrap();
+
+        // samples of missing nodes are left alone
+        profile.samples[0].node_id = 9;
+        profile.samples[1].node_id = 4;
+        assert_eq!(profile.attribute_gc().unwrap(), 0);
+        assert_eq!(profile.samples[1].node_id, 4);
+    }
+}
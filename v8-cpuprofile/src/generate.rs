@@ -0,0 +1,202 @@
+//! Generated profiles for fuzzing the parser and serializer.
+//!
+//! [`Profile`] implements [`Arbitrary`] with structurally valid profiles, a
+//! tree of nodes under a `(root)` with samples in time order, which should
+//! pass [`Profile::roundtrip_check`]. [`adversarial_json`] bends one of them
+//! into input a parser has to reject or survive.
+
+use crate::{Micros, Node, Profile, RawJson, Sample, Weighting};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use arbitrary::{Arbitrary, Error, Result, Unstructured};
+use hashbrown::HashMap;
+use serde_json::Value;
+
+const FUNCTION_NAMES: &[&str] = &[
+    "",
+    "main",
+    "(garbage collector)",
+    "processTicksAndRejections",
+];
+
+impl<'a> Arbitrary<'a> for Profile<'static> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let len = u.int_in_range(1..=64)?;
+        let mut id = u.int_in_range(1..=1000)?;
+        let mut nodes: Vec<Node<'static>> = Vec::with_capacity(len);
+        for index in 0..len {
+            let parent = if index == 0 {
+                None
+            } else {
+                Some(u.int_in_range(0..=index - 1)?)
+            };
+            let node = Node {
+                id,
+                parent_id: parent.map(|parent: usize| nodes[parent].id),
+                call_frame: call_frame(u, index == 0)?,
+                hit_count: 0,
+                children: Some(Vec::new()),
+                deopt_reason: if u.ratio(1, 8)? {
+                    Some(raw(&Value::from(String::arbitrary(u)?)))
+                } else {
+                    None
+                },
+                position_ticks: None,
+                extra_fields: Vec::new(),
+            };
+            if let Some(parent) = parent {
+                nodes[parent].children.get_or_insert_with(Vec::new).push(id);
+            }
+            nodes.push(node);
+            id += u.int_in_range(1..=3)?;
+        }
+
+        let start_time = Micros(u.int_in_range(0..=u64::from(u32::MAX))?);
+        let mut ts = Micros::ZERO;
+        let mut samples = Vec::new();
+        for _ in 0..u.int_in_range(0..=256)? {
+            ts += Micros(u.int_in_range(0..=2000)?);
+            let node = u.choose_index(nodes.len())?;
+            nodes[node].hit_count += 1;
+            samples.push(Sample {
+                node_id: nodes[node].id,
+                ts,
+            });
+        }
+        let end_time = start_time + ts + Micros(u.int_in_range(0..=2000)?);
+        let metadata = if u.ratio(1, 4)? {
+            Some(raw(&Value::from(u64::arbitrary(u)?)))
+        } else {
+            None
+        };
+
+        let node_index: HashMap<u64, usize> = nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (node.id, index))
+            .collect();
+        Ok(Profile {
+            nodes,
+            start_time,
+            end_time,
+            samples,
+            metadata,
+            sampling_interval: None,
+            extra_fields: Vec::new(),
+            exact_times: (None, None),
+            node_index,
+            weighting: Weighting::default(),
+        })
+    }
+}
+
+fn call_frame(u: &mut Unstructured, root: bool) -> Result<RawJson<'static>> {
+    let function_name = if root {
+        String::from("(root)")
+    } else if u.arbitrary()? {
+        String::from(*u.choose(FUNCTION_NAMES)?)
+    } else {
+        String::arbitrary(u)?
+    };
+    let mut frame = serde_json::Map::new();
+    frame.insert("functionName".into(), function_name.into());
+    frame.insert(
+        "scriptId".into(),
+        format!("{}", u.int_in_range(0..=99)?).into(),
+    );
+    frame.insert("url".into(), String::arbitrary(u)?.into());
+    frame.insert("lineNumber".into(), u.int_in_range(-1_i32..=10_000)?.into());
+    frame.insert("columnNumber".into(), u.int_in_range(-1_i32..=200)?.into());
+    Ok(raw(&Value::Object(frame)))
+}
+
+fn raw(value: &Value) -> RawJson<'static> {
+    serde_json::value::to_raw_value(value)
+        .expect("a json value serializes")
+        .into()
+}
+
+/// The json of an arbitrary profile with one defect: a missing or mistyped
+/// field, an unknown field, a child or sample that points nowhere, time
+/// deltas that go back before `startTime`, or input cut short.
+///
+/// # Errors
+///
+/// If `u` runs out of data.
+pub fn adversarial_json(u: &mut Unstructured) -> Result<Vec<u8>> {
+    let profile = Profile::arbitrary(u)?;
+    let mut value = profile.to_value().map_err(|_| Error::IncorrectFormat)?;
+    let map = value.as_object_mut().ok_or(Error::IncorrectFormat)?;
+    match u.int_in_range(0..=6)? {
+        0 => {
+            let field = *u.choose(&["nodes", "startTime", "endTime", "samples", "timeDeltas"])?;
+            map.remove(field);
+        }
+        1 => {
+            let field = *u.choose(&["nodes", "startTime", "samples", "timeDeltas"])?;
+            map.insert(field.into(), String::arbitrary(u)?.into());
+        }
+        2 => {
+            map.insert(String::arbitrary(u)?, Value::Null);
+        }
+        3 => {
+            if let Some(Value::Object(node)) = map["nodes"].get_mut(0) {
+                node.insert("children".into(), Value::from(alloc::vec![u64::MAX]));
+            }
+        }
+        4 => {
+            map.insert("samples".into(), Value::from(alloc::vec![u64::MAX]));
+            map.insert("timeDeltas".into(), Value::from(alloc::vec![0]));
+        }
+        5 => {
+            map.insert("timeDeltas".into(), Value::from(alloc::vec![-1, i64::MIN]));
+        }
+        _ => {
+            let mut json = serde_json::to_vec(&value).map_err(|_| Error::IncorrectFormat)?;
+            json.truncate(u.choose_index(json.len())?);
+            return Ok(json);
+        }
+    }
+    serde_json::to_vec(&value).map_err(|_| Error::IncorrectFormat)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::adversarial_json;
+    use crate::{ParseOptions, Profile};
+    use alloc::vec::Vec;
+    use arbitrary::{Arbitrary, Unstructured};
+
+    /// Deterministic bytes to generate from.
+    fn data(seed: u64) -> Vec<u8> {
+        let mut state = seed.wrapping_mul(6_364_136_223_846_793_005) | 1;
+        (0..4096)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state.to_le_bytes()[0]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn generated_profiles_round_trip() {
+        for seed in 0..64 {
+            let data = data(seed);
+            let profile = Profile::arbitrary(&mut Unstructured::new(&data)).unwrap();
+            profile.roundtrip_check().unwrap();
+        }
+    }
+
+    #[test]
+    fn adversarial_profiles_dont_panic() {
+        for seed in 0..256 {
+            let data = data(seed);
+            let json = adversarial_json(&mut Unstructured::new(&data)).unwrap();
+            let _ = serde_json::from_slice::<Profile>(&json);
+            let _ = Profile::parse_with(&json, &ParseOptions::lenient());
+        }
+    }
+}
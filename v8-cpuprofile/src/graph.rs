@@ -0,0 +1,94 @@
+use crate::{FrameKey, Node, Profile};
+use core::time::Duration;
+use hashbrown::HashMap;
+use petgraph::graph::{DiGraph, NodeIndex};
+
+impl<'raw> Profile<'raw> {
+    /// The call tree as a graph with an edge from each node to its children,
+    /// for running existing graph algorithms such as dominators on it.
+    #[must_use]
+    pub fn to_graph(&self) -> DiGraph<&Node<'raw>, ()> {
+        self.tree_graph(|_| ())
+    }
+
+    /// Like [`Profile::to_graph`] with each edge weighted by the total time
+    /// of the child.
+    #[must_use]
+    pub fn to_weighted_graph(&self) -> DiGraph<&Node<'raw>, Duration> {
+        let rollups = self.with_rollups();
+        self.tree_graph(|child_id| rollups.total_time(child_id))
+    }
+
+    /// The call graph of functions, nodes with the same frame are merged so
+    /// recursion shows up as cycles, for example as strongly connected
+    /// components. Edges are weighted by the total time of the calls.
+    ///
+    /// # Errors
+    ///
+    /// If a node has an invalid call frame.
+    pub fn to_call_graph(&self) -> Result<DiGraph<FrameKey<'raw>, Duration>, serde_json::Error> {
+        let rollups = self.with_rollups();
+        let mut graph = DiGraph::new();
+        let mut frames: HashMap<FrameKey<'raw>, NodeIndex> = HashMap::new();
+        let mut indices: HashMap<u64, NodeIndex> = HashMap::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            let frame = FrameKey::from(node.frame()?);
+            let index = *frames
+                .entry(frame)
+                .or_insert_with_key(|frame| graph.add_node(frame.clone()));
+            indices.insert(node.id, index);
+        }
+        for node in &self.nodes {
+            let parent = indices[&node.id];
+            for child_id in self.child_ids(node.id) {
+                let child = indices[&child_id];
+                let time = rollups.total_time(child_id);
+                match graph.find_edge(parent, child) {
+                    Some(edge) => graph[edge] += time,
+                    None => {
+                        graph.add_edge(parent, child, time);
+                    }
+                }
+            }
+        }
+        Ok(graph)
+    }
+
+    fn tree_graph<E>(&self, weight: impl Fn(u64) -> E) -> DiGraph<&Node<'raw>, E> {
+        let mut graph = DiGraph::with_capacity(self.nodes.len(), self.nodes.len());
+        for node in &self.nodes {
+            graph.add_node(node);
+        }
+        for (index, node) in self.nodes.iter().enumerate() {
+            for child_id in self.child_ids(node.id) {
+                graph.add_edge(
+                    NodeIndex::new(index),
+                    NodeIndex::new(self.node_index[&child_id]),
+                    weight(child_id),
+                );
+            }
+        }
+        graph
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ser::tests::PROFILE;
+    use crate::Profile;
+    use petgraph::algo::{is_cyclic_directed, kosaraju_scc};
+
+    #[test]
+    fn graphs() {
+        let profile: Profile = serde_json::from_str(PROFILE).unwrap();
+
+        let tree = profile.to_weighted_graph();
+        assert_eq!(tree.node_count(), profile.nodes.len());
+        assert_eq!(tree.edge_count(), profile.nodes.len() - 1);
+        assert!(!is_cyclic_directed(&tree));
+
+        // compileForInternalLoader and nativeModuleRequire call each other
+        let calls = profile.to_call_graph().unwrap();
+        assert!(kosaraju_scc(&calls).iter().any(|scc| scc.len() > 1));
+    }
+}
@@ -0,0 +1,53 @@
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
+
+/// The allocation stack tree embedded in a heap snapshot or allocation
+/// timeline recorded with allocation tracking enabled.
+///
+/// Only the `trace_function_infos`, `trace_tree` and `strings` sections are
+/// kept, the node and edge graph is skipped while parsing. It serializes as a
+/// cpuprofile where each node's `hitCount` is its allocation count, so it can
+/// be loaded in dev tools when the snapshot itself is too big.
+#[derive(Debug)]
+pub struct AllocationTrace<'raw> {
+    pub functions: Vec<TraceFunction<'raw>>,
+    pub root: TraceNode,
+}
+
+#[derive(Debug)]
+pub struct TraceFunction<'raw> {
+    pub function_id: u64,
+    pub name: Cow<'raw, str>,
+    pub script_name: Cow<'raw, str>,
+    pub script_id: u64,
+    /// 1-based, 0 if unknown
+    pub line: u32,
+    /// 1-based, 0 if unknown
+    pub column: u32,
+}
+
+#[derive(Debug)]
+pub struct TraceNode {
+    pub id: u64,
+    pub function_index: usize,
+    pub count: u32,
+    pub size: u64,
+    pub children: Vec<TraceNode>,
+}
+
+impl<'raw> AllocationTrace<'raw> {
+    #[must_use]
+    pub fn function(&self, node: &TraceNode) -> &TraceFunction<'raw> {
+        &self.functions[node.function_index]
+    }
+
+    /// Iterates all trace nodes depth first.
+    pub fn nodes(&self) -> impl Iterator<Item = &TraceNode> + '_ {
+        let mut stack = alloc::vec![&self.root];
+        core::iter::from_fn(move || {
+            let node = stack.pop()?;
+            stack.extend(node.children.iter().rev());
+            Some(node)
+        })
+    }
+}
@@ -0,0 +1,121 @@
+//! Profiles fetched over http, like artifacts of a CI server, see
+//! [`Profile::from_url`].
+
+use crate::format::is_gzip;
+use crate::Profile;
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::vec::Vec;
+use std::io::{self, BufRead, BufReader, Read};
+
+/// Starts downloading `url` and reads the body as it arrives. Gzip is undone
+/// while reading, whether the server compressed the response or the file
+/// itself is gzipped.
+///
+/// # Errors
+///
+/// If the request fails or the server doesn't answer with a success.
+pub fn open(url: &str) -> io::Result<Box<dyn Read + Send>> {
+    let response = match ureq::get(url).call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(status, _)) => {
+            return Err(io::Error::other(format!("{} answered {}", url, status)))
+        }
+        Err(error) => return Err(io::Error::other(error)),
+    };
+    let mut reader = BufReader::new(response.into_reader());
+    if !is_gzip(reader.fill_buf()?) {
+        return Ok(Box::new(reader));
+    }
+    #[cfg(feature = "gzip")]
+    return Ok(Box::new(flate2::bufread::MultiGzDecoder::new(reader)));
+    #[cfg(not(feature = "gzip"))]
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "gzip compressed input needs the gzip feature",
+    ))
+}
+
+/// Downloads `url` to memory, see [`open`].
+///
+/// # Errors
+///
+/// If the download fails.
+pub fn download(url: &str) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    open(url)?.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+impl Profile<'static> {
+    /// Downloads and parses a cpuprofile, decompressing it while it
+    /// downloads, see [`open`].
+    ///
+    /// # Errors
+    ///
+    /// If the download fails or the body is not a valid profile.
+    pub fn from_url(url: &str) -> io::Result<Self> {
+        Self::from_reader(open(url)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ser::tests::PROFILE;
+    use crate::Profile;
+    use alloc::format;
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    /// Answers each request with the next body, a 404 once they run out.
+    fn serve(bodies: Vec<Vec<u8>>) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let mut bodies = bodies.into_iter();
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request = [0; 4096];
+                let _ = stream.read(&mut request).unwrap();
+                let (status, body) = match bodies.next() {
+                    Some(body) => ("200 OK", body),
+                    None => ("404 Not Found", Vec::new()),
+                };
+                let head = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len()
+                );
+                stream.write_all(head.as_bytes()).unwrap();
+                stream.write_all(&body).unwrap();
+            }
+        });
+        port
+    }
+
+    #[test]
+    fn from_url() {
+        let mut bodies = alloc::vec![PROFILE.as_bytes().to_vec()];
+        #[cfg(feature = "gzip")]
+        {
+            let mut gzip =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            gzip.write_all(PROFILE.as_bytes()).unwrap();
+            bodies.push(gzip.finish().unwrap());
+        }
+        let downloads = bodies.len();
+        let port = serve(bodies);
+        let url = format!("http://127.0.0.1:{}/CPU.cpuprofile", port);
+
+        let expected: Profile = serde_json::from_str(PROFILE).unwrap();
+        for _ in 0..downloads {
+            let profile = Profile::from_url(&url).unwrap();
+            assert_eq!(profile.nodes.len(), expected.nodes.len());
+            assert_eq!(profile.samples, expected.samples);
+        }
+        let error = Profile::from_url(&url).unwrap_err();
+        assert!(error.to_string().ends_with("answered 404"), "{}", error);
+    }
+}
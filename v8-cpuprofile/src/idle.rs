@@ -0,0 +1,78 @@
+//! Profiles of cpu time instead of wall time.
+
+use crate::{Micros, Profile};
+use core::time::Duration;
+use hashbrown::HashSet;
+
+/// Samples of time not spent running code.
+const NOT_RUNNING: &[&str] = &["(idle)", "(program)"];
+
+impl Profile<'_> {
+    /// Removes `(idle)` and `(program)` samples and moves the samples after
+    /// them earlier by their time, so the timeline is cpu time instead of
+    /// wall time and flamegraphs of cpu bound work get denser. Returns how
+    /// much time was removed.
+    ///
+    /// # Errors
+    ///
+    /// If a node has an invalid call frame.
+    pub fn strip_idle(&mut self) -> Result<Duration, serde_json::Error> {
+        let mut idle = HashSet::new();
+        for node in &mut self.nodes {
+            if NOT_RUNNING.contains(&node.frame()?.function_name.as_ref()) {
+                idle.insert(node.id);
+                node.hit_count = 0;
+            }
+        }
+
+        let mut removed = Micros::ZERO;
+        let mut samples = alloc::vec::Vec::with_capacity(self.samples.len());
+        for (mut sample, duration) in self.samples_with_durations() {
+            if idle.contains(&sample.node_id) {
+                removed += Micros::from(duration);
+            } else {
+                sample.ts = sample.ts.saturating_sub(removed);
+                samples.push(sample);
+            }
+        }
+        self.samples = samples;
+        self.end_time = self.end_time.saturating_sub(removed);
+        Ok(removed.as_duration())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ser::tests::PROFILE;
+    use crate::Profile;
+    use core::time::Duration;
+
+    #[test]
+    fn strip_idle() {
+        let mut profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        let before = profile.frame_times().unwrap();
+        let duration = profile.duration();
+        let removed = profile.strip_idle().unwrap();
+
+        assert!(removed > Duration::default());
+        assert_eq!(Some(profile.duration()), duration.checked_sub(removed));
+        assert_eq!(
+            Some(profile.self_times().values().sum::<Duration>()),
+            profile
+                .duration()
+                .checked_sub(profile.samples[0].ts.as_duration())
+        );
+        assert!(profile
+            .samples
+            .windows(2)
+            .all(|pair| pair[0].ts <= pair[1].ts));
+        let after = profile.frame_times().unwrap();
+        let write_stream = before
+            .keys()
+            .find(|frame| frame.function_name == "WriteStream")
+            .unwrap();
+        assert_eq!(after[write_stream], before[write_stream]);
+        assert!(after.keys().all(|frame| frame.function_name != "(program)"));
+        profile.roundtrip_check().unwrap();
+    }
+}
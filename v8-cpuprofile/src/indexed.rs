@@ -0,0 +1,331 @@
+//! A container for long captures where samples are bucketed by time behind
+//! an offset table, so a time range can be read without loading every
+//! sample.
+//!
+//! The layout, all integers little endian `u64`:
+//!
+//! - the magic bytes `V8CPIDX1`
+//! - the length of the header, then the header: the profile as cpuprofile
+//!   json without samples
+//! - the bucket width in microseconds and the number of buckets
+//! - the offset table, the byte offset of each bucket's first sample
+//!   relative to the start of the samples and its sample count
+//! - the samples grouped by bucket, in profile order within one, each a
+//!   node id and a timestamp in microseconds relative to `startTime`
+
+use crate::{Micros, Profile, Sample};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::time::Duration;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 8] = b"V8CPIDX1";
+const SAMPLE_SIZE: u64 = 16;
+
+/// The profile without samples, written as the header.
+pub(crate) struct Header<'p, 'raw>(pub(crate) &'p Profile<'raw>);
+
+/// Writes `profile` in the indexed format with buckets of `bucket_width`.
+///
+/// # Errors
+///
+/// If writing fails, a node's raw json is invalid or `bucket_width` is
+/// shorter than a microsecond.
+pub fn write_indexed<W: Write>(
+    profile: &Profile,
+    bucket_width: Duration,
+    writer: W,
+) -> io::Result<()> {
+    let width = Micros::from(bucket_width).0;
+    if width == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "bucket width must be at least a microsecond",
+        ));
+    }
+    let mut writer = BufWriter::new(writer);
+    let _span = span!("write_indexed", samples = profile.samples.len());
+    let header = serde_json::to_vec(&Header(profile))?;
+    event!("serialized header", bytes = header.len());
+    writer.write_all(MAGIC)?;
+    write_u64(&mut writer, header.len() as u64)?;
+    writer.write_all(&header)?;
+
+    // samples may be out of time order when parsed with
+    // `preserve_sample_order`, stable so each bucket keeps their order
+    let mut samples: Vec<&Sample> = profile.samples.iter().collect();
+    samples.sort_by_key(|sample| sample.ts.0 / width);
+    let buckets = samples.last().map_or(0, |last| last.ts.0 / width + 1);
+    let mut table = alloc::vec![(0_u64, 0_u64); usize::try_from(buckets).map_err(invalid)?];
+    for sample in &samples {
+        table[usize::try_from(sample.ts.0 / width).map_err(invalid)?].1 += 1;
+    }
+    let mut offset = 0;
+    for (bucket_offset, count) in &mut table {
+        *bucket_offset = offset;
+        offset += *count * SAMPLE_SIZE;
+    }
+
+    write_u64(&mut writer, width)?;
+    write_u64(&mut writer, buckets)?;
+    for &(offset, count) in &table {
+        write_u64(&mut writer, offset)?;
+        write_u64(&mut writer, count)?;
+    }
+    for sample in samples {
+        write_u64(&mut writer, sample.node_id)?;
+        write_u64(&mut writer, sample.ts.0)?;
+    }
+    writer.flush()
+}
+
+/// An open indexed profile, see [`open_indexed`].
+#[derive(Debug)]
+pub struct IndexedProfile {
+    file: File,
+    header: String,
+    bucket_width: u64,
+    table: Vec<(u64, u64)>,
+    samples_start: u64,
+}
+
+/// Opens an indexed profile, reading only its header and offset table.
+///
+/// # Errors
+///
+/// If reading fails or the file is not in the indexed format, including
+/// lengths and offsets that reach past its end.
+pub fn open_indexed(path: impl AsRef<Path>) -> io::Result<IndexedProfile> {
+    let mut file = File::open(path)?;
+    let file_len = file.metadata()?.len();
+    let mut magic = [0; 8];
+    file.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not an indexed cpuprofile",
+        ));
+    }
+    // lengths are checked against what is left of the file before
+    // anything is allocated for them
+    let header_len = read_u64(&mut file)?;
+    if header_len > file_len.saturating_sub(file.stream_position()?) {
+        return Err(invalid("header longer than the file"));
+    }
+    let mut header = alloc::vec![0; usize::try_from(header_len).map_err(invalid)?];
+    file.read_exact(&mut header)?;
+    let header = String::from_utf8(header).map_err(invalid)?;
+
+    let bucket_width = read_u64(&mut file)?;
+    if bucket_width == 0 {
+        return Err(invalid("bucket width of zero"));
+    }
+    let buckets = read_u64(&mut file)?;
+    let left = file_len.saturating_sub(file.stream_position()?);
+    // an offset and a count per bucket
+    if buckets > left / 16 {
+        return Err(invalid("offset table longer than the file"));
+    }
+    let mut table = Vec::with_capacity(usize::try_from(buckets).map_err(invalid)?);
+    for _ in 0..buckets {
+        table.push((read_u64(&mut file)?, read_u64(&mut file)?));
+    }
+    let samples_start = file.stream_position()?;
+    let samples_len = file_len.saturating_sub(samples_start);
+    for &(offset, count) in &table {
+        let end = count
+            .checked_mul(SAMPLE_SIZE)
+            .and_then(|len| len.checked_add(offset));
+        match end {
+            Some(end) if end <= samples_len => {}
+            _ => return Err(invalid("bucket past the end of the file")),
+        }
+    }
+    Ok(IndexedProfile {
+        file,
+        header,
+        bucket_width,
+        table,
+        samples_start,
+    })
+}
+
+impl IndexedProfile {
+    /// The bucket width the file was written with.
+    #[must_use]
+    pub fn bucket_width(&self) -> Duration {
+        Duration::from_micros(self.bucket_width)
+    }
+
+    /// Reads the samples from `start` up to `end`, relative to the profile's
+    /// `start_time`. The profile keeps all nodes and its `start_time`, its
+    /// `end_time` is clamped to `end`.
+    ///
+    /// # Errors
+    ///
+    /// If reading fails or the file is corrupt.
+    pub fn slice(&self, start: Duration, end: Duration) -> io::Result<Profile<'_>> {
+        let mut profile: Profile = serde_json::from_str(&self.header)?;
+        let (start, end) = (Micros::from(start).0, Micros::from(end).0);
+        let first = usize::try_from(start / self.bucket_width).map_err(invalid)?;
+        let last = usize::try_from(end / self.bucket_width).map_err(invalid)?;
+
+        let mut file = &self.file;
+        let mut buffer = Vec::new();
+        for &(offset, count) in self.table.iter().take(last + 1).skip(first) {
+            buffer.resize(usize::try_from(count * SAMPLE_SIZE).map_err(invalid)?, 0);
+            file.seek(SeekFrom::Start(self.samples_start + offset))?;
+            file.read_exact(&mut buffer)?;
+            for sample in buffer.chunks_exact(16) {
+                let node_id = u64_at(&sample[..8]);
+                let ts = u64_at(&sample[8..]);
+                if ts >= start && ts < end {
+                    profile.samples.push(Sample {
+                        node_id,
+                        ts: Micros(ts),
+                    });
+                }
+            }
+        }
+
+        profile.end_time = profile.end_time.min(profile.start_time + Micros(end));
+        Ok(profile)
+    }
+}
+
+fn write_u64(writer: &mut impl Write, value: u64) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn u64_at(bytes: &[u8]) -> u64 {
+    let mut array = [0; 8];
+    array.copy_from_slice(bytes);
+    u64::from_le_bytes(array)
+}
+
+fn invalid(error: impl Into<alloc::boxed::Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{open_indexed, u64_at, write_indexed};
+    use crate::ser::tests::PROFILE;
+    use crate::{ParseOptions, Profile};
+    use alloc::string::ToString;
+    use core::convert::TryFrom;
+    use core::time::Duration;
+    use std::fs::File;
+
+    #[test]
+    fn slice() {
+        let profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        let path = std::env::temp_dir().join(alloc::format!(
+            "v8-cpuprofile-indexed-{}.idx",
+            std::process::id()
+        ));
+        write_indexed(
+            &profile,
+            Duration::from_millis(5),
+            File::create(&path).unwrap(),
+        )
+        .unwrap();
+
+        let indexed = open_indexed(&path).unwrap();
+        let all = indexed
+            .slice(Duration::default(), Duration::from_secs(100))
+            .unwrap();
+        assert_eq!(all.samples, profile.samples);
+        assert_eq!(all.nodes.len(), profile.nodes.len());
+        assert_eq!(all.end_time, profile.end_time);
+
+        let (start, end) = (Duration::from_millis(7), Duration::from_millis(21));
+        let slice = indexed.slice(start, end).unwrap();
+        let expected: alloc::vec::Vec<_> = profile
+            .samples
+            .iter()
+            .filter(|sample| sample.ts >= start.into() && sample.ts < end.into())
+            .collect();
+        assert!(!expected.is_empty());
+        assert_eq!(
+            slice.samples.iter().collect::<alloc::vec::Vec<_>>(),
+            expected
+        );
+        assert!(slice
+            .samples
+            .iter()
+            .zip(&expected)
+            .all(|(a, b)| a.node_id == b.node_id));
+        assert_eq!(slice.end_time, profile.start_time + end.into());
+
+        // a corrupt length or offset is an error, not an allocation
+        let bytes = std::fs::read(&path).unwrap();
+        let header_len = usize::try_from(u64_at(&bytes[8..16])).unwrap();
+        let table_start = 16 + header_len + 16;
+        let corrupt = |at: usize, value: u64| {
+            let mut bytes = bytes.clone();
+            bytes[at..at + 8].copy_from_slice(&value.to_le_bytes());
+            std::fs::write(&path, bytes).unwrap();
+            open_indexed(&path).unwrap_err().to_string()
+        };
+        assert_eq!(corrupt(8, u64::MAX), "header longer than the file");
+        assert_eq!(corrupt(table_start - 16, 0), "bucket width of zero");
+        assert_eq!(
+            corrupt(table_start - 8, u64::MAX),
+            "offset table longer than the file"
+        );
+        assert_eq!(
+            corrupt(table_start, u64::MAX),
+            "bucket past the end of the file"
+        );
+        assert_eq!(
+            corrupt(table_start + 8, u64::MAX),
+            "bucket past the end of the file"
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+    #[test]
+    fn unsorted_samples() {
+        const PROFILE: &str = r#"{"nodes":[{"id":1,"callFrame":{},"hitCount":1,"children":[2]},{"id":2,"callFrame":{},"hitCount":2}],"startTime":0,"endTime":40,"samples":[2,1,2],"timeDeltas":[30,-20,5]}"#;
+        let options = ParseOptions {
+            preserve_sample_order: true,
+            ..ParseOptions::default()
+        };
+        let profile = Profile::parse_with(PROFILE.as_bytes(), &options).unwrap();
+        let path = std::env::temp_dir().join(alloc::format!(
+            "v8-cpuprofile-indexed-unsorted-{}.idx",
+            std::process::id()
+        ));
+        write_indexed(
+            &profile,
+            Duration::from_micros(10),
+            File::create(&path).unwrap(),
+        )
+        .unwrap();
+
+        let indexed = open_indexed(&path).unwrap();
+        let samples = |start, end| {
+            let slice = indexed
+                .slice(Duration::from_micros(start), Duration::from_micros(end))
+                .unwrap();
+            slice
+                .samples
+                .iter()
+                .map(|sample| (sample.node_id, sample.ts.0))
+                .collect::<alloc::vec::Vec<_>>()
+        };
+        assert_eq!(samples(0, 100), [(1, 10), (2, 15), (2, 30)]);
+        assert_eq!(samples(12, 35), [(2, 15), (2, 30)]);
+        assert_eq!(samples(20, 30), []);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,44 @@
+//! `tracing` spans around parsing, chunking and serializing, so services
+//! embedding the library can see where profile processing time goes. Without
+//! the `tracing` feature these expand to nothing.
+
+/// Enters a debug span until the returned guard is dropped.
+#[cfg(feature = "tracing")]
+macro_rules! span {
+    ($name:expr $(, $field:ident = $value:expr)* $(,)?) => {
+        tracing::debug_span!($name $(, $field = $value)*).entered()
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! span {
+    ($name:expr $(, $field:ident = $value:expr)* $(,)?) => {
+        $crate::instrument::NoSpan::enter()
+    };
+}
+
+/// Records a debug event in the current span.
+#[cfg(feature = "tracing")]
+macro_rules! event {
+    ($message:expr $(, $field:ident = $value:expr)* $(,)?) => {
+        tracing::debug!($($field = $value,)* $message)
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! event {
+    ($message:expr $(, $field:ident = $value:expr)* $(,)?) => {
+        $(let _ = &$value;)*
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct NoSpan;
+
+#[cfg(not(feature = "tracing"))]
+impl NoSpan {
+    #[inline]
+    pub(crate) fn enter() -> Self {
+        NoSpan
+    }
+}
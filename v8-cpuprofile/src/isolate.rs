@@ -0,0 +1,147 @@
+//! Profiles of several isolates of one run, like the main thread and
+//! workers of a process or the processes of a cluster, side by side, see
+//! [`IsolateSet::new`].
+
+use crate::{FrameKey, FrameTimes, Profile};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+use core::time::Duration;
+use hashbrown::HashMap;
+
+/// The time of every function in each isolate and across all of them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IsolateSet<'raw> {
+    /// Names of the isolates in the order given, like `48210 worker 1`.
+    pub labels: Vec<String>,
+    /// Sampled time of each isolate.
+    pub durations: Vec<Duration>,
+    /// Every sampled function, the most self time across isolates first.
+    pub frames: Vec<IsolateFrame<'raw>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IsolateFrame<'raw> {
+    pub frame: FrameKey<'raw>,
+    /// Summed over the isolates.
+    pub combined: FrameTimes,
+    /// Per isolate in the order of [`IsolateSet::labels`], zero where the
+    /// function wasn't sampled.
+    pub isolates: Vec<FrameTimes>,
+}
+
+impl<'raw> IsolateSet<'raw> {
+    /// Groups the function times of each profile by function, labeling the
+    /// profile of each isolate.
+    ///
+    /// # Errors
+    ///
+    /// If a node of any profile has an invalid call frame.
+    ///
+    /// # Panics
+    ///
+    /// If there isn't a label for each profile.
+    pub fn new(profiles: &[Profile<'raw>], labels: Vec<String>) -> Result<Self, serde_json::Error> {
+        assert_eq!(profiles.len(), labels.len(), "a label per profile");
+        let len = profiles.len();
+        let mut frames: HashMap<FrameKey<'raw>, Vec<FrameTimes>> = HashMap::new();
+        let mut durations = Vec::with_capacity(len);
+        for (isolate, profile) in profiles.iter().enumerate() {
+            for (frame, times) in profile.frame_times()? {
+                frames
+                    .entry(frame)
+                    .or_insert_with(|| alloc::vec![FrameTimes::default(); len])[isolate] = times;
+            }
+            durations.push(profile.self_times().values().sum());
+        }
+
+        let mut frames: Vec<_> = frames
+            .into_iter()
+            .map(|(frame, isolates)| IsolateFrame {
+                frame,
+                combined: FrameTimes {
+                    self_time: isolates.iter().map(|times| times.self_time).sum(),
+                    total_time: isolates.iter().map(|times| times.total_time).sum(),
+                },
+                isolates,
+            })
+            .collect();
+        frames.sort_by(|a, b| {
+            Reverse(a.combined.self_time)
+                .cmp(&Reverse(b.combined.self_time))
+                .then_with(|| a.frame.cmp(&b.frame))
+        });
+        Ok(IsolateSet {
+            labels,
+            durations,
+            frames,
+        })
+    }
+
+    /// The functions sampled in one isolate, the most self time there
+    /// first.
+    #[must_use]
+    pub fn isolate_frames(&self, isolate: usize) -> Vec<&IsolateFrame<'raw>> {
+        let mut frames: Vec<_> = self
+            .frames
+            .iter()
+            .filter(|frame| frame.isolates[isolate].total_time > Duration::default())
+            .collect();
+        // stable, so ties keep the combined order
+        frames.sort_by_key(|frame| Reverse(frame.isolates[isolate].self_time));
+        frames
+    }
+
+    /// Sampled time of all isolates together.
+    #[must_use]
+    pub fn duration(&self) -> Duration {
+        self.durations.iter().sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IsolateSet;
+    use crate::ser::tests::PROFILE;
+    use crate::synth::SynthOptions;
+    use crate::Profile;
+    use alloc::string::String;
+    use core::time::Duration;
+
+    #[test]
+    fn isolate_set() {
+        let profiles = [
+            serde_json::from_str(PROFILE).unwrap(),
+            Profile::synthesize(&SynthOptions::default()),
+        ];
+        let labels = alloc::vec![String::from("1 main"), String::from("1 worker 1")];
+        let set = IsolateSet::new(&profiles, labels).unwrap();
+
+        assert_eq!(set.durations[0], profiles[0].self_times().values().sum());
+        assert_eq!(set.durations[1], Duration::from_secs(1));
+        let self_time: Duration = set
+            .frames
+            .iter()
+            .map(|frame| frame.combined.self_time)
+            .sum();
+        assert_eq!(self_time, set.duration());
+        assert!(set
+            .frames
+            .windows(2)
+            .all(|pair| pair[0].combined.self_time >= pair[1].combined.self_time));
+
+        let main = set.isolate_frames(0);
+        let sampled = profiles[0]
+            .frame_times()
+            .unwrap()
+            .values()
+            .filter(|times| times.total_time > Duration::default())
+            .count();
+        assert_eq!(main.len(), sampled);
+        assert_eq!(main[0].frame.function_name, "WriteStream");
+        assert!(set
+            .isolate_frames(1)
+            .iter()
+            .all(|frame| frame.frame.url == "synth.js" || frame.frame.function_name == "(root)"));
+    }
+}
@@ -0,0 +1,268 @@
+use crate::{Micros, Node, Profile, Sample, Weighting};
+use alloc::vec::Vec;
+use core::ops::Range;
+use hashbrown::HashMap;
+use serde::de::Error;
+
+impl<'raw> Profile<'raw> {
+    /// Merges profiles split from one capture, like the chunks of
+    /// [`Profile::chunks`], back into one. Nodes are matched by id with their
+    /// children merged, and samples are merged by timestamp so parts may
+    /// come in any order. Parts must not overlap, their samples are all
+    /// kept, see [`Profile::join_ranges`] for parts that do.
+    ///
+    /// A chunk only has the nodes on its sampled stacks, so nodes that were
+    /// never sampled can't be restored. v8 numbers nodes in the order it
+    /// creates them, so children are ordered by id and nodes listed depth
+    /// first like v8 does. The parts' `metadata` is left out.
+    ///
+    /// # Errors
+    ///
+    /// If a node id has a different call frame in two parts, which means
+    /// they weren't split from the same capture.
+    pub fn join(parts: &[Profile<'raw>]) -> Result<Self, serde_json::Error> {
+        join(parts, None)
+    }
+
+    /// Like [`Profile::join`] for parts that may overlap, `ranges` are the
+    /// indices of each part's samples in the split profile, like the sample
+    /// ranges of a split's manifest. A sample in several parts is kept once.
+    ///
+    /// # Errors
+    ///
+    /// Like [`Profile::join`], and if there isn't a range for each part as
+    /// long as its samples.
+    pub fn join_ranges(
+        parts: &[Profile<'raw>],
+        ranges: &[Range<usize>],
+    ) -> Result<Self, serde_json::Error> {
+        if ranges.len() != parts.len() {
+            return Err(serde_json::Error::custom(alloc::format!(
+                "{} sample ranges for {} parts",
+                ranges.len(),
+                parts.len()
+            )));
+        }
+        for (index, (part, range)) in parts.iter().zip(ranges).enumerate() {
+            if range.start > range.end || range.len() != part.samples.len() {
+                return Err(serde_json::Error::custom(alloc::format!(
+                    "part {} has {} samples but its range {:?} has {}",
+                    index,
+                    part.samples.len(),
+                    range,
+                    range.len()
+                )));
+            }
+        }
+        join(parts, Some(ranges))
+    }
+}
+
+fn join<'raw>(
+    parts: &[Profile<'raw>],
+    ranges: Option<&[Range<usize>]>,
+) -> Result<Profile<'raw>, serde_json::Error> {
+    let mut nodes: HashMap<u64, Node<'raw>> = HashMap::new();
+    let mut start_time: Option<Micros> = None;
+    let mut end_time = Micros::ZERO;
+    for part in parts {
+        for node in &part.nodes {
+            match nodes.get_mut(&node.id) {
+                Some(joined) => {
+                    if joined.call_frame != node.call_frame {
+                        return Err(serde_json::Error::custom(alloc::format!(
+                            "node {} has a different call frame in another part",
+                            node.id
+                        )));
+                    }
+                    if let Some(children) = &node.children {
+                        joined
+                            .children
+                            .get_or_insert_with(Vec::new)
+                            .extend(children);
+                    }
+                }
+                None => {
+                    nodes.insert(node.id, copy(node));
+                }
+            }
+        }
+        start_time = Some(start_time.map_or(part.start_time, |t| t.min(part.start_time)));
+        end_time = end_time.max(part.end_time);
+    }
+    let start_time = start_time.unwrap_or_default();
+
+    for node in nodes.values_mut() {
+        if let Some(children) = &mut node.children {
+            children.sort_unstable();
+            children.dedup();
+        }
+    }
+    // parts may have different start times, sample timestamps are
+    // relative to the earliest
+    let offsets = parts
+        .iter()
+        .map(|part| part.start_time.saturating_sub(start_time));
+    let samples = match ranges {
+        None => {
+            let mut samples = Vec::with_capacity(parts.iter().map(|part| part.samples.len()).sum());
+            for (part, offset) in parts.iter().zip(offsets) {
+                samples.extend(part.samples.iter().map(|sample| Sample {
+                    node_id: sample.node_id,
+                    ts: sample.ts + offset,
+                }));
+            }
+            // stable, samples with the same timestamp stay in order
+            samples.sort_by_key(|sample| sample.ts);
+            samples
+        }
+        // each sample goes to its index, once however many parts have it
+        Some(ranges) => {
+            let len = ranges.iter().map(|range| range.end).max().unwrap_or(0);
+            let mut slots: Vec<Option<Sample>> = alloc::vec![None; len];
+            for ((part, offset), range) in parts.iter().zip(offsets).zip(ranges) {
+                for (slot, sample) in slots[range.clone()].iter_mut().zip(&part.samples) {
+                    slot.get_or_insert(Sample {
+                        node_id: sample.node_id,
+                        ts: sample.ts + offset,
+                    });
+                }
+            }
+            slots.into_iter().flatten().collect()
+        }
+    };
+
+    let order = depth_first(&nodes);
+    let mut node_index = HashMap::with_capacity(order.len());
+    let mut ordered = Vec::with_capacity(order.len());
+    for node_id in order {
+        if let Some(node) = nodes.remove(&node_id) {
+            node_index.insert(node_id, ordered.len());
+            ordered.push(node);
+        }
+    }
+    // parts of one capture share its interval
+    let sampling_interval = parts
+        .first()
+        .and_then(|part| part.sampling_interval)
+        .filter(|&interval| {
+            parts
+                .iter()
+                .all(|part| part.sampling_interval == Some(interval))
+        });
+    Ok(Profile {
+        nodes: ordered,
+        start_time,
+        end_time,
+        samples,
+        metadata: None,
+        sampling_interval,
+        extra_fields: Vec::new(),
+        exact_times: (None, None),
+        node_index,
+        weighting: Weighting::default(),
+    })
+}
+
+/// Node ids depth first from the roots, children in order.
+fn depth_first(nodes: &HashMap<u64, Node>) -> Vec<u64> {
+    let mut roots: Vec<u64> = nodes
+        .values()
+        .filter(|node| node.parent_id.is_none())
+        .map(|node| node.id)
+        .collect();
+    roots.sort_unstable();
+
+    let mut order = Vec::with_capacity(nodes.len());
+    let mut stack: Vec<u64> = roots.into_iter().rev().collect();
+    while let Some(node_id) = stack.pop() {
+        order.push(node_id);
+        if let Some(children) = nodes.get(&node_id).and_then(|node| node.children.as_ref()) {
+            stack.extend(children.iter().rev());
+        }
+    }
+    order
+}
+
+pub(crate) fn copy<'raw>(node: &Node<'raw>) -> Node<'raw> {
+    Node {
+        id: node.id,
+        parent_id: node.parent_id,
+        call_frame: node.call_frame.clone(),
+        hit_count: node.hit_count,
+        children: node.children.clone(),
+        deopt_reason: node.deopt_reason.clone(),
+        position_ticks: node.position_ticks.clone(),
+        extra_fields: node.extra_fields.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ser::tests::PROFILE;
+    use crate::{Profile, ProfileChunk};
+    use alloc::vec::Vec;
+
+    #[test]
+    fn join_chunks() {
+        let profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        let parts: Vec<_> = profile
+            .chunks(3)
+            .map(|chunk| serde_json::to_string(&chunk).unwrap())
+            .collect();
+        // in any order
+        let mut parts: Vec<Profile> = parts
+            .iter()
+            .rev()
+            .map(|part| serde_json::from_str(part).unwrap())
+            .collect();
+        parts.swap(0, 1);
+
+        let joined = Profile::join(&parts).unwrap();
+        assert_eq!(joined.samples, profile.samples);
+        assert_eq!(
+            serde_json::to_string(&joined).unwrap(),
+            serde_json::to_string(&ProfileChunk::new(&profile, &profile.samples)).unwrap()
+        );
+    }
+    #[test]
+    fn same_sample_in_two_chunks() {
+        // two samples of one node at one time, one in each chunk
+        const PROFILE: &str = r#"{"nodes":[{"id":1,"callFrame":{"functionName":"(root)","scriptId":"0","url":"","lineNumber":-1,"columnNumber":-1},"hitCount":0,"children":[2]},{"id":2,"callFrame":{"functionName":"f","scriptId":"1","url":"a.js","lineNumber":0,"columnNumber":0},"hitCount":2}],"startTime":0,"endTime":10,"samples":[2,2],"timeDeltas":[5,0]}"#;
+        let profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        let parts: Vec<_> = [&profile.samples[..1], &profile.samples[1..]]
+            .iter()
+            .map(|samples| serde_json::to_string(&ProfileChunk::new(&profile, samples)).unwrap())
+            .collect();
+        let parts: Vec<Profile> = parts
+            .iter()
+            .map(|part| serde_json::from_str(part).unwrap())
+            .collect();
+        assert_eq!(Profile::join(&parts).unwrap().samples, profile.samples);
+        let joined = Profile::join_ranges(&parts, &[0..1, 1..2]).unwrap();
+        assert_eq!(joined.samples, profile.samples);
+    }
+
+    #[test]
+    fn overlapping_ranges() {
+        let profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        let len = profile.samples.len();
+        let ranges = [0..len / 2 + 3, len / 2..len];
+        let parts: Vec<_> = ranges
+            .iter()
+            .map(|range| {
+                let chunk = ProfileChunk::new(&profile, &profile.samples[range.clone()]);
+                serde_json::to_string(&chunk).unwrap()
+            })
+            .collect();
+        let parts: Vec<Profile> = parts
+            .iter()
+            .map(|part| serde_json::from_str(part).unwrap())
+            .collect();
+        let joined = Profile::join_ranges(&parts, &ranges).unwrap();
+        assert_eq!(joined.samples, profile.samples);
+        assert_eq!(Profile::join(&parts).unwrap().samples.len(), len + 3);
+        assert!(Profile::join_ranges(&parts, &[0..1, 1..2]).is_err());
+        assert!(Profile::join_ranges(&parts, &ranges[..1]).is_err());
+    }
+}
@@ -0,0 +1,63 @@
+//! What was on the cpu, from the sampled nodes alone, see
+//! [`Profile::leaf_histogram`].
+
+use crate::{FrameKey, Profile};
+use core::time::Duration;
+use hashbrown::HashMap;
+
+/// Samples with a function at the top of the stack.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct LeafCount {
+    pub samples: usize,
+    pub time: Duration,
+}
+
+impl<'raw> Profile<'raw> {
+    /// Samples and time of each function where it was the sampled frame.
+    /// Only the call frames of sampled nodes are parsed and stacks aren't
+    /// walked, so this is the cheapest way to see where time went when
+    /// total times aren't needed.
+    ///
+    /// # Errors
+    ///
+    /// If a sampled node has an invalid call frame.
+    pub fn leaf_histogram(&self) -> Result<HashMap<FrameKey<'raw>, LeafCount>, serde_json::Error> {
+        let mut by_node: HashMap<u64, LeafCount> = HashMap::new();
+        for (sample, time) in self.samples_with_weights() {
+            let count = by_node.entry(sample.node_id).or_default();
+            count.samples += 1;
+            count.time += time;
+        }
+        let mut histogram: HashMap<FrameKey<'raw>, LeafCount> =
+            HashMap::with_capacity(by_node.len());
+        for (node_id, count) in by_node {
+            let leaf = histogram
+                .entry(FrameKey::from(self[node_id].frame()?))
+                .or_default();
+            leaf.samples += count.samples;
+            leaf.time += count.time;
+        }
+        Ok(histogram)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ser::tests::PROFILE;
+    use crate::Profile;
+
+    #[test]
+    fn leaf_histogram() {
+        let profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        let histogram = profile.leaf_histogram().unwrap();
+
+        assert_eq!(
+            histogram.values().map(|leaf| leaf.samples).sum::<usize>(),
+            profile.samples.len()
+        );
+        for (frame, times) in profile.frame_times().unwrap() {
+            let time = histogram.get(&frame).map(|leaf| leaf.time);
+            assert_eq!(time.unwrap_or_default(), times.self_time, "{}", frame);
+        }
+    }
+}
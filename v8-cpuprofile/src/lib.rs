@@ -2,55 +2,131 @@
 #![no_std]
 #![feature(option_insert)]
 extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
 
 use crate::ser::MakeIter;
+use alloc::borrow::Cow;
+use alloc::collections::VecDeque;
+use alloc::string::String;
 use alloc::vec::Vec;
-use core::cmp::Ordering;
 use core::ops::Index;
-use core::slice::Chunks;
+use core::ops::Range;
 use core::time::Duration;
 use hashbrown::HashMap;
 use hashbrown::HashSet;
 use serde::Serialize;
-use serde_json::value::RawValue;
 
+#[macro_use]
+mod instrument;
+
+pub mod aggregate;
+pub mod anonymous;
+#[cfg(feature = "tokio")]
+mod async_io;
+mod balance;
+pub mod bisect;
+pub mod budget;
+pub mod bundle;
+pub mod capture;
+pub mod category;
+#[cfg(feature = "std")]
+pub mod conformance;
+pub mod coverage;
 mod de;
+pub mod deopt;
+pub mod depth;
+pub mod detail;
+mod drill;
+#[cfg(feature = "std")]
+pub mod etw;
+pub mod filter;
+pub mod fingerprint;
+#[cfg(feature = "flame")]
+pub mod flame;
+#[cfg(feature = "std")]
+pub mod format;
+mod frame;
+pub mod framework;
+mod gc;
+#[cfg(feature = "arbitrary")]
+pub mod generate;
+#[cfg(feature = "petgraph")]
+mod graph;
+pub mod heap;
+#[cfg(feature = "http")]
+pub mod http;
+mod idle;
+#[cfg(feature = "std")]
+pub mod indexed;
+pub mod isolate;
+mod join;
+pub mod leaf;
+pub mod lines;
+pub mod links;
+pub mod memory;
+mod micros;
+pub mod path;
+#[cfg(feature = "std")]
+pub mod pipeline;
+mod raw;
+pub mod request;
+pub mod rollup;
+pub mod script;
+#[cfg(feature = "regex")]
+pub mod search;
 mod ser;
+pub mod series;
+#[cfg(feature = "std")]
+pub mod sidecar;
+pub mod stack;
+#[cfg(feature = "std")]
+mod std_io;
+pub mod stitch;
+pub mod synth;
+pub mod template;
+pub mod trace;
+pub mod truncation;
+pub mod utilization;
+pub mod weighting;
+
+pub use de::{Limits, ParseOptions, TimeDeltaPolicy, UnknownFields};
+pub use frame::{CallFrame, FrameIndex, FrameKey, FrameTimes};
+pub use micros::Micros;
+pub use raw::RawJson;
+pub use ser::SerializeOptions;
+pub use weighting::Weighting;
 
-#[derive(Debug, Default, Copy, Clone, Eq)]
+/// A sample of the stack at `ts`, relative to the profile's `start_time`.
+///
+/// Samples are kept in time order, samples with the same timestamp stay in
+/// the order they were recorded in.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct Sample {
     pub node_id: u64,
-    pub ts: Duration,
-}
-
-/// samples should have unique timestamps and `node_id` is just a foreign key
-impl PartialEq for Sample {
-    #[inline]
-    fn eq(&self, other: &Self) -> bool {
-        self.ts == other.ts
-    }
-}
-
-impl PartialOrd for Sample {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(&other))
-    }
-}
-
-impl Ord for Sample {
-    #[inline]
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.ts.cmp(&other.ts)
-    }
+    pub ts: Micros,
 }
 
 #[derive(Debug)]
 pub struct Profile<'raw> {
     pub nodes: Vec<Node<'raw>>,
-    pub start_time: Duration,
-    pub end_time: Duration,
+    pub start_time: Micros,
+    pub end_time: Micros,
     pub samples: Vec<Sample>,
+    /// Raw json of an extra top-level `metadata` field, which v8 doesn't
+    /// write, kept as is so generated profiles can describe themselves.
+    pub metadata: Option<RawJson<'raw>>,
+    /// The interval the profiler was set to sample at, a `samplingInterval`
+    /// field some producers write, see
+    /// [`Profile::nominal_sampling_interval`].
+    pub sampling_interval: Option<Micros>,
+    /// Fields kept by [`UnknownFields::Preserve`].
+    pub extra_fields: ExtraFields<'raw>,
+    /// `startTime` and `endTime` as read when they were floats, written
+    /// back while the times still round to them.
+    exact_times: (Option<f64>, Option<f64>),
     node_index: HashMap<u64, usize>,
+    weighting: Weighting,
 }
 
 impl<'raw> Profile<'raw> {
@@ -61,10 +137,117 @@ impl<'raw> Profile<'raw> {
         }
     }
 
+    /// The node without a parent, `(root)` in profiles recorded by v8.
+    #[must_use]
+    pub fn root(&self) -> Option<&Node<'raw>> {
+        self.nodes.iter().find(|node| node.parent_id.is_none())
+    }
+
+    /// Ids of the descendants of a node depth first, children in order and
+    /// before their siblings. The node itself is not included.
+    pub fn dfs(&self, node_id: u64) -> impl Iterator<Item = u64> + '_ {
+        let mut stack: Vec<u64> = self.child_ids(node_id).rev().collect();
+        core::iter::from_fn(move || {
+            let node_id = stack.pop()?;
+            stack.extend(self.child_ids(node_id).rev());
+            Some(node_id)
+        })
+    }
+
+    /// Ids of the descendants of a node breadth first, level by level. The
+    /// node itself is not included.
+    pub fn bfs(&self, node_id: u64) -> impl Iterator<Item = u64> + '_ {
+        let mut queue: VecDeque<u64> = self.child_ids(node_id).collect();
+        core::iter::from_fn(move || {
+            let node_id = queue.pop_front()?;
+            queue.extend(self.child_ids(node_id));
+            Some(node_id)
+        })
+    }
+
+    fn child_ids(&self, node_id: u64) -> impl DoubleEndedIterator<Item = u64> + '_ {
+        self[node_id].children.iter().flatten().copied()
+    }
+
+    /// Splits the samples into at most `chunk_num` chunks in time order,
+    /// balanced by their estimated size when serialized. A chunk includes
+    /// the nodes of its samples, so chunks over many different stacks get
+    /// fewer samples than those over a few.
     #[must_use]
     pub fn chunks<'profile>(&'profile self, chunk_num: usize) -> ProfileChunks<'profile, 'raw> {
-        let chunk_size = div_ceil(self.samples.len(), chunk_num);
-        ProfileChunks(self, self.samples.chunks(chunk_size))
+        ProfileChunks(self, balance::chunks(self, chunk_num).into_iter())
+    }
+
+    /// Splits the samples into at most `chunk_num` chunks in time order
+    /// with the same number of samples, but the last.
+    #[must_use]
+    pub fn chunks_by_samples<'profile>(
+        &'profile self,
+        chunk_num: usize,
+    ) -> ProfileChunks<'profile, 'raw> {
+        // at least 1, a profile without samples has no chunks
+        let chunk_size = div_ceil(self.samples.len(), chunk_num.max(1)).max(1);
+        ProfileChunks(
+            self,
+            self.samples
+                .chunks(chunk_size)
+                .collect::<Vec<_>>()
+                .into_iter(),
+        )
+    }
+
+    /// Sums the sampled time of each node, excluding its children, see
+    /// [`Profile::set_weighting`].
+    #[must_use]
+    pub fn self_times(&self) -> HashMap<u64, Duration> {
+        let mut self_times = HashMap::new();
+        for (sample, duration) in self.samples_with_weights() {
+            *self_times.entry(sample.node_id).or_default() += duration;
+        }
+        self_times
+    }
+
+    /// Sums the sampled time of each node including its descendants.
+    #[must_use]
+    pub fn total_times(&self) -> HashMap<u64, Duration> {
+        let mut total_times = HashMap::new();
+        for (node_id, self_time) in self.self_times() {
+            *total_times.entry(node_id).or_default() += self_time;
+            for parent_id in self.parent_ids_iter(node_id) {
+                *total_times.entry(parent_id).or_default() += self_time;
+            }
+        }
+        total_times
+    }
+
+    /// Pairs each sample with the time until the next one, the last sample
+    /// lasts until `end_time`.
+    pub fn samples_with_durations(&self) -> impl Iterator<Item = (Sample, Duration)> + '_ {
+        let end = self.end_time.saturating_sub(self.start_time);
+        self.samples
+            .iter()
+            .enumerate()
+            .map(move |(index, &sample)| {
+                let next = self.samples.get(index + 1).map_or(end, |next| next.ts);
+                (sample, next.saturating_sub(sample.ts).as_duration())
+            })
+    }
+
+    /// The time from `start_time` to `end_time`.
+    #[must_use]
+    pub fn duration(&self) -> Duration {
+        self.end_time.saturating_sub(self.start_time).as_duration()
+    }
+
+    /// One line for logs, like `77 nodes, 28 samples over 34.1ms`.
+    #[must_use]
+    pub fn summary(&self) -> String {
+        alloc::format!(
+            "{} nodes, {} samples over {:.1}ms",
+            self.nodes.len(),
+            self.samples.len(),
+            self.duration().as_secs_f64() * 1000.0
+        )
     }
 }
 
@@ -95,27 +278,37 @@ impl<'p, 'raw> Iterator for ParentIter<'p, 'raw> {
     }
 }
 
+/// The raw json fields borrow from the input, or are owned when the profile
+/// was read with [`Profile::deserialize_owned`].
 #[derive(Debug)]
 pub struct Node<'raw> {
     pub id: u64,
     pub parent_id: Option<u64>,
-    pub call_frame: &'raw RawValue,
+    pub call_frame: RawJson<'raw>,
     pub hit_count: u32,
     pub children: Option<Vec<u64>>,
-    pub deopt_reason: Option<&'raw RawValue>,
-    pub position_ticks: Option<&'raw RawValue>,
+    pub deopt_reason: Option<RawJson<'raw>>,
+    pub position_ticks: Option<RawJson<'raw>>,
+    /// Fields kept by [`UnknownFields::Preserve`].
+    pub extra_fields: ExtraFields<'raw>,
 }
 
+/// Names and raw json of fields that aren't part of the format.
+pub type ExtraFields<'raw> = Vec<(Cow<'raw, str>, RawJson<'raw>)>;
+
 #[derive(Debug)]
 pub struct ProfileChunk<'profile, 'raw> {
     profile: &'profile Profile<'raw>,
     samples: &'profile [Sample],
     included: HashSet<u64>,
+    metadata: Option<RawJson<'profile>>,
+    shared_nodes: bool,
 }
 
 impl<'profile, 'raw> ProfileChunk<'profile, 'raw> {
     #[must_use]
     pub fn new(profile: &'profile Profile<'raw>, samples: &'profile [Sample]) -> Self {
+        let _span = span!("build_chunk", samples = samples.len());
         let mut included = HashSet::new();
         for sample in samples {
             let node_id = sample.node_id;
@@ -127,13 +320,81 @@ impl<'profile, 'raw> ProfileChunk<'profile, 'raw> {
                 }
             }
         }
+        event!("built chunk", nodes = included.len());
         ProfileChunk {
             profile,
             samples,
             included,
+            metadata: profile.metadata.as_ref().map(RawJson::reborrow),
+            shared_nodes: false,
+        }
+    }
+
+    /// A chunk of every node of the profile and none of its samples, the
+    /// node table chunks written with [`ProfileChunk::set_shared_nodes`]
+    /// leave out. [`Profile::join`] joins it with them.
+    #[must_use]
+    pub fn node_table(profile: &'profile Profile<'raw>) -> Self {
+        ProfileChunk {
+            profile,
+            samples: &[],
+            included: profile.nodes.iter().map(|node| node.id).collect(),
+            metadata: profile.metadata.as_ref().map(RawJson::reborrow),
+            shared_nodes: false,
+        }
+    }
+
+    /// Leaves the nodes out when the chunk is written, for chunks written
+    /// next to the [`ProfileChunk::node_table`] of their profile, so that
+    /// nodes on the stacks of many chunks are written once. Its
+    /// [`ProfileChunk::nodes`] are unchanged.
+    pub fn set_shared_nodes(&mut self, shared_nodes: bool) {
+        self.shared_nodes = shared_nodes;
+    }
+
+    /// Replaces the `metadata` written with the chunk, which is the profile's
+    /// by default.
+    pub fn set_metadata(&mut self, metadata: Option<RawJson<'profile>>) {
+        self.metadata = metadata;
+    }
+
+    #[must_use]
+    pub fn profile(&self) -> &'profile Profile<'raw> {
+        self.profile
+    }
+
+    #[must_use]
+    pub fn samples(&self) -> &'profile [Sample] {
+        self.samples
+    }
+
+    /// The time covered by the chunk relative to the profile's `start_time`,
+    /// from its first sample until the first sample of the next chunk or the
+    /// end of the profile.
+    #[must_use]
+    pub fn time_range(&self) -> Range<Micros> {
+        let profile_end = self
+            .profile
+            .end_time
+            .saturating_sub(self.profile.start_time);
+        match (self.samples.first(), self.samples.last()) {
+            (Some(first), Some(last)) => {
+                let samples = &self.profile.samples;
+                // samples may share a timestamp, find `last` itself among them
+                let same_ts = samples.partition_point(|sample| sample.ts < last.ts)
+                    ..samples.partition_point(|sample| sample.ts <= last.ts);
+                let next = samples[same_ts.clone()]
+                    .iter()
+                    .position(|sample| core::ptr::eq(sample, last))
+                    .map_or(same_ts.end, |index| same_ts.start + index + 1);
+                let end = samples.get(next).map_or(profile_end, |next| next.ts);
+                first.ts..end
+            }
+            _ => profile_end..profile_end,
         }
     }
 
+    /// The nodes sampled in this chunk and their parents, in profile order.
     #[must_use]
     pub fn nodes(
         &self,
@@ -152,8 +413,43 @@ impl<'profile, 'raw> ProfileChunk<'profile, 'raw> {
 
 pub struct FilteredNode<'profile, 'raw, 'set>(&'profile Node<'raw>, &'set HashSet<u64>);
 
-impl FilteredNode<'_, '_, '_> {
-    fn children(&self) -> Option<impl IntoIterator<Item = u64> + Serialize + '_> {
+impl<'profile, 'raw> FilteredNode<'profile, 'raw, '_> {
+    /// The unfiltered node.
+    #[must_use]
+    pub fn node(&self) -> &'profile Node<'raw> {
+        self.0
+    }
+
+    #[must_use]
+    pub fn id(&self) -> u64 {
+        self.0.id
+    }
+
+    /// Parses the call frame, see [`Node::frame`].
+    ///
+    /// # Errors
+    ///
+    /// If the call frame is not a valid v8 call frame object.
+    pub fn frame(&self) -> Result<CallFrame<'raw>, serde_json::Error> {
+        self.0.frame()
+    }
+
+    #[must_use]
+    pub fn hit_count(&self) -> u32 {
+        self.0.hit_count
+    }
+
+    /// Ids of the children that are part of the chunk.
+    pub fn children(&self) -> impl Iterator<Item = u64> + '_ {
+        self.0
+            .children
+            .iter()
+            .flatten()
+            .copied()
+            .filter(move |id| self.1.contains(id))
+    }
+
+    fn filtered_children(&self) -> Option<impl IntoIterator<Item = u64> + Serialize + '_> {
         self.0.children.as_ref().map(move |children| {
             MakeIter::from(move || {
                 children
@@ -164,7 +460,10 @@ impl FilteredNode<'_, '_, '_> {
     }
 }
 
-pub struct ProfileChunks<'profile, 'raw>(&'profile Profile<'raw>, Chunks<'profile, Sample>);
+pub struct ProfileChunks<'profile, 'raw>(
+    &'profile Profile<'raw>,
+    alloc::vec::IntoIter<&'profile [Sample]>,
+);
 
 impl<'profile, 'raw> Iterator for ProfileChunks<'profile, 'raw> {
     type Item = ProfileChunk<'profile, 'raw>;
@@ -0,0 +1,135 @@
+use crate::{Node, Profile};
+use alloc::borrow::Cow;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+use core::convert::TryFrom;
+use core::time::Duration;
+use hashbrown::HashMap;
+
+/// Samples attributed to a source line of a node's function.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct PositionTick {
+    /// 1-based
+    pub line: u32,
+    pub ticks: u32,
+}
+
+/// Ticks of a source line merged across all nodes of its function.
+#[derive(Debug)]
+pub struct LineHotspot<'raw> {
+    pub url: Cow<'raw, str>,
+    pub function_name: Cow<'raw, str>,
+    /// 1-based
+    pub line: u32,
+    pub ticks: u32,
+}
+
+impl Node<'_> {
+    /// Parses the position ticks, empty if the node has none.
+    ///
+    /// # Errors
+    ///
+    /// If the position ticks are not a sequence of v8 position tick objects.
+    pub fn position_ticks(&self) -> Result<Vec<PositionTick>, serde_json::Error> {
+        match self.position_ticks {
+            Some(ref raw) => serde_json::from_str(raw.get()),
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+impl<'raw> Profile<'raw> {
+    /// Merges position ticks of nodes sharing a script and function, the
+    /// hottest lines first.
+    ///
+    /// # Errors
+    ///
+    /// If a node with position ticks has an invalid call frame or invalid
+    /// position ticks.
+    pub fn line_hotspots(&self) -> Result<Vec<LineHotspot<'raw>>, serde_json::Error> {
+        let mut lines: HashMap<(Cow<'raw, str>, Cow<'raw, str>, u32), u32> = HashMap::new();
+        for node in &self.nodes {
+            if node.position_ticks.is_none() {
+                continue;
+            }
+            let frame = node.frame()?;
+            for tick in node.position_ticks()? {
+                *lines
+                    .entry((frame.url.clone(), frame.function_name.clone(), tick.line))
+                    .or_default() += tick.ticks;
+            }
+        }
+
+        let mut hotspots: Vec<_> = lines
+            .into_iter()
+            .map(|((url, function_name, line), ticks)| LineHotspot {
+                url,
+                function_name,
+                line,
+                ticks,
+            })
+            .collect();
+        hotspots.sort_by_key(|hotspot| Reverse(hotspot.ticks));
+        Ok(hotspots)
+    }
+
+    /// Sampled self time of each 1-based source line of a script.
+    ///
+    /// A node's self time is split across its position ticks, nodes without
+    /// position ticks are attributed to the line of their call frame.
+    ///
+    /// # Errors
+    ///
+    /// If a node has an invalid call frame or a node of the script has invalid
+    /// position ticks.
+    pub fn line_times(&self, url: &str) -> Result<BTreeMap<u32, Duration>, serde_json::Error> {
+        let self_times = self.self_times();
+        let mut lines = BTreeMap::new();
+        for node in &self.nodes {
+            if let Some(&self_time) = self_times.get(&node.id) {
+                let frame = node.frame()?;
+                if frame.url != url {
+                    continue;
+                }
+                let ticks = node.position_ticks()?;
+                let total: u32 = ticks.iter().map(|tick| tick.ticks).sum();
+                if total > 0 {
+                    for tick in ticks {
+                        *lines.entry(tick.line).or_default() += self_time * tick.ticks / total;
+                    }
+                } else if let Ok(line) = u32::try_from(frame.line_number + 1) {
+                    *lines.entry(line).or_default() += self_time;
+                }
+            }
+        }
+        Ok(lines)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ser::tests::PROFILE;
+    #[test]
+    fn line_hotspots() {
+        let profile: crate::Profile<'_> = serde_json::from_str(PROFILE).unwrap();
+        let hotspots = profile.line_hotspots().unwrap();
+
+        assert_eq!(hotspots[0].url, "node:tty");
+        assert_eq!(hotspots[0].line, 91);
+        assert_eq!(hotspots[0].ticks, 8);
+
+        // compileForInternalLoader has ticks on line 282 from three nodes
+        assert_eq!(hotspots[1].function_name, "compileForInternalLoader");
+        assert_eq!(hotspots[1].ticks, 3);
+    }
+
+    #[test]
+    fn line_times() {
+        let profile: crate::Profile<'_> = serde_json::from_str(PROFILE).unwrap();
+        let lines = profile.line_times("node:tty").unwrap();
+
+        assert_eq!(lines.keys().collect::<alloc::vec::Vec<_>>(), [&91]);
+        assert_eq!(lines[&91], profile.self_times()[&65]);
+    }
+}
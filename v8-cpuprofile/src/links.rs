@@ -0,0 +1,136 @@
+//! Links from frame locations to their source, for reports shared in code
+//! review.
+
+use crate::template::{render, TemplateError};
+use alloc::string::String;
+use core::fmt::Write;
+
+/// A url template like
+/// `https://github.com/org/repo/blob/{rev}/{path}#L{line}` applied to a
+/// script url and position, see [`template`](crate::template). `{column}`
+/// is also replaced, lines and columns are 1-based and empty if unknown.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SourceLinks {
+    pub template: String,
+    /// Replaces `{rev}`, such as a commit hash or branch.
+    pub rev: String,
+    /// Where the repository is in the profile's urls, like
+    /// `file:///home/ci/app/`, stripped from a url to get its `{path}`.
+    /// Scripts elsewhere get no link. If empty, the path of a url is used
+    /// as it is.
+    pub root: String,
+}
+
+impl SourceLinks {
+    /// The link to a 0-based line and column of a script, `None` for
+    /// scripts outside the root or without a path, like node's own, and
+    /// for templates [`SourceLinks::check_template`] rejects.
+    #[must_use]
+    pub fn link(&self, url: &str, line_number: i32, column_number: i32) -> Option<String> {
+        let path = repo_path(url, &self.root)?;
+        fill(&self.template, &self.rev, path, line_number, column_number).ok()
+    }
+
+    /// Checks that a template only has the placeholders a link fills in.
+    ///
+    /// # Errors
+    ///
+    /// If the template doesn't render, see [`render`].
+    pub fn check_template(template: &str) -> Result<(), TemplateError> {
+        fill(template, "", "", 0, 0).map(|_| ())
+    }
+}
+
+fn fill(
+    template: &str,
+    rev: &str,
+    path: &str,
+    line_number: i32,
+    column_number: i32,
+) -> Result<String, TemplateError> {
+    render(template, |name, value| {
+        match name {
+            "rev" => value.push_str(rev),
+            "path" => value.push_str(path),
+            "line" => position(value, line_number),
+            "column" => position(value, column_number),
+            _ => return false,
+        }
+        true
+    })
+}
+
+/// The path of a script in the repository at `root`, see
+/// [`SourceLinks::root`].
+#[must_use]
+pub fn repo_path<'u>(url: &'u str, root: &str) -> Option<&'u str> {
+    let path = if root.is_empty() {
+        match url.find("://") {
+            // past the host
+            Some(scheme) => {
+                let rest = &url[scheme + 3..];
+                &rest[rest.find('/')?..]
+            }
+            // node:fs and the like
+            None if url.contains(':') => return None,
+            None => url,
+        }
+    } else {
+        url.strip_prefix(root)?
+    };
+    let path = path.trim_start_matches("./").trim_start_matches('/');
+    if path.is_empty() {
+        None
+    } else {
+        Some(path)
+    }
+}
+
+fn position(link: &mut String, zero_based: i32) {
+    if zero_based >= 0 {
+        let _ = write!(link, "{}", i64::from(zero_based) + 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SourceLinks;
+    use alloc::string::String;
+
+    #[test]
+    fn links() {
+        let mut links = SourceLinks {
+            template: String::from("https://github.com/org/repo/blob/{rev}/{path}#L{line}"),
+            rev: String::from("abc123"),
+            root: String::from("file:///home/ci/app/"),
+        };
+        assert_eq!(
+            links.link("file:///home/ci/app/src/a.js", 9, 2).as_deref(),
+            Some("https://github.com/org/repo/blob/abc123/src/a.js#L10")
+        );
+        assert_eq!(links.link("file:///usr/lib/node.js", 9, 2), None);
+        assert_eq!(
+            links.link("file:///home/ci/app/a.js", -1, -1).as_deref(),
+            Some("https://github.com/org/repo/blob/abc123/a.js#L")
+        );
+        assert!(SourceLinks::check_template(&links.template).is_ok());
+
+        // unknown placeholders link nowhere
+        links.template.push_str("{x}");
+        assert!(SourceLinks::check_template(&links.template).is_err());
+        assert_eq!(links.link("file:///home/ci/app/a.js", 0, 0), None);
+
+        links.root.clear();
+        links.template = String::from("{path}:{line}:{column}");
+        assert_eq!(
+            links
+                .link("http://localhost:8080/js/app.js", 0, 4)
+                .as_deref(),
+            Some("js/app.js:1:5")
+        );
+        assert_eq!(links.link("./a.js", 0, 0).as_deref(), Some("a.js:1:1"));
+        assert_eq!(links.link("node:internal/main", 0, 0), None);
+        assert_eq!(links.link("", 0, 0), None);
+        assert_eq!(links.link("http://localhost", 0, 0), None);
+    }
+}
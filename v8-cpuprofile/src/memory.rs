@@ -0,0 +1,74 @@
+use crate::{Node, Profile, RawJson, Sample};
+use core::mem::size_of;
+
+/// Estimated heap usage of a parsed profile in bytes, see
+/// [`Profile::memory_footprint`].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct MemoryFootprint {
+    /// The nodes, their children and any raw json they own.
+    pub nodes: usize,
+    pub samples: usize,
+    /// The lookup from node id to node.
+    pub index: usize,
+}
+
+impl MemoryFootprint {
+    #[must_use]
+    pub fn total(&self) -> usize {
+        self.nodes + self.samples + self.index
+    }
+}
+
+impl Profile<'_> {
+    /// Estimates the bytes the parsed profile uses on top of its input, to
+    /// tell whether a machine can process a profile before splitting it.
+    /// Raw json borrowed from the input isn't counted, the input itself has
+    /// to be in memory, or mapped, for as long as the profile.
+    #[must_use]
+    pub fn memory_footprint(&self) -> MemoryFootprint {
+        let nodes = self.nodes.capacity() * size_of::<Node>()
+            + self.nodes.iter().map(node_heap).sum::<usize>();
+        MemoryFootprint {
+            nodes,
+            samples: self.samples.capacity() * size_of::<Sample>(),
+            // a hashbrown table has a control byte per bucket
+            index: self.node_index.capacity() * (size_of::<(u64, usize)>() + 1),
+        }
+    }
+}
+
+fn node_heap(node: &Node) -> usize {
+    let children = node
+        .children
+        .as_ref()
+        .map_or(0, |children| children.capacity() * size_of::<u64>());
+    children
+        + owned(Some(&node.call_frame))
+        + owned(node.deopt_reason.as_ref())
+        + owned(node.position_ticks.as_ref())
+}
+
+fn owned(raw: Option<&RawJson>) -> usize {
+    match raw {
+        Some(raw) if raw.borrowed().is_none() => raw.get().len(),
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ser::tests::PROFILE;
+    use crate::Profile;
+
+    #[test]
+    fn owned_profile_is_larger() {
+        let profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        let footprint = profile.memory_footprint();
+        assert!(footprint.samples >= profile.samples.len() * 16);
+        assert!(footprint.index > 0);
+
+        let mut deserializer = serde_json::Deserializer::from_str(PROFILE);
+        let owned = Profile::deserialize_owned(&mut deserializer).unwrap();
+        assert!(owned.memory_footprint().nodes > footprint.nodes);
+    }
+}
@@ -0,0 +1,124 @@
+use core::convert::TryFrom;
+use core::ops::{Add, AddAssign, Sub};
+use core::time::Duration;
+
+/// A time in whole microseconds, the resolution of v8 profiles. Profile
+/// times are microseconds since some epoch, sample times are microseconds
+/// since the profile's `startTime`.
+///
+/// `+` and `-` saturate at [`Micros::ZERO`] and [`Micros::MAX`] like
+/// [`Micros::saturating_add`] and [`Micros::saturating_sub`], times read from
+/// a profile may be anything.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Micros(pub u64);
+
+impl Micros {
+    pub const ZERO: Micros = Micros(0);
+    pub const MAX: Micros = Micros(u64::MAX);
+
+    /// Whole microseconds of `duration`, saturating at [`Micros::MAX`].
+    #[must_use]
+    pub fn from_duration(duration: Duration) -> Self {
+        Micros(u64::try_from(duration.as_micros()).unwrap_or(u64::MAX))
+    }
+
+    #[must_use]
+    pub fn as_duration(self) -> Duration {
+        Duration::from_micros(self.0)
+    }
+
+    #[must_use]
+    pub fn saturating_add(self, rhs: Micros) -> Self {
+        Micros(self.0.saturating_add(rhs.0))
+    }
+
+    #[must_use]
+    pub fn saturating_sub(self, rhs: Micros) -> Self {
+        Micros(self.0.saturating_sub(rhs.0))
+    }
+
+    /// The signed microseconds from `earlier` to this time, a time delta as
+    /// written in a profile, saturating at the bounds of `i64`.
+    #[must_use]
+    pub fn delta_since(self, earlier: Micros) -> i64 {
+        if self >= earlier {
+            i64::try_from(self.0 - earlier.0).unwrap_or(i64::MAX)
+        } else {
+            i64::try_from(earlier.0 - self.0).map_or(i64::MIN, |delta| -delta)
+        }
+    }
+
+    /// This time moved by a signed delta, `None` if that goes below zero
+    /// or overflows.
+    #[must_use]
+    pub fn checked_add_delta(self, delta: i64) -> Option<Self> {
+        if delta < 0 {
+            self.0.checked_sub(delta.unsigned_abs()).map(Micros)
+        } else {
+            self.0.checked_add(delta.unsigned_abs()).map(Micros)
+        }
+    }
+}
+
+impl From<Duration> for Micros {
+    fn from(duration: Duration) -> Self {
+        Micros::from_duration(duration)
+    }
+}
+
+impl From<Micros> for Duration {
+    fn from(micros: Micros) -> Self {
+        micros.as_duration()
+    }
+}
+
+impl Add for Micros {
+    type Output = Micros;
+
+    fn add(self, rhs: Micros) -> Micros {
+        self.saturating_add(rhs)
+    }
+}
+
+impl AddAssign for Micros {
+    fn add_assign(&mut self, rhs: Micros) {
+        *self = self.saturating_add(rhs);
+    }
+}
+
+impl Sub for Micros {
+    type Output = Micros;
+
+    fn sub(self, rhs: Micros) -> Micros {
+        self.saturating_sub(rhs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Micros;
+    use core::time::Duration;
+
+    #[test]
+    fn deltas() {
+        assert_eq!(Micros(5).delta_since(Micros(20)), -15);
+        assert_eq!(Micros(20).delta_since(Micros(5)), 15);
+        assert_eq!(Micros::MAX.delta_since(Micros::ZERO), i64::MAX);
+        assert_eq!(Micros::ZERO.delta_since(Micros::MAX), i64::MIN);
+
+        assert_eq!(Micros(20).checked_add_delta(-15), Some(Micros(5)));
+        assert_eq!(Micros(5).checked_add_delta(-15), None);
+        assert_eq!(Micros::MAX.checked_add_delta(1), None);
+        assert_eq!(Micros(1).checked_add_delta(i64::MIN), None);
+
+        assert_eq!(Micros::from(Duration::from_nanos(1999)), Micros(1));
+        assert_eq!(Micros::from(Duration::MAX), Micros::MAX);
+        assert_eq!(Duration::from(Micros(3)), Duration::from_micros(3));
+
+        assert_eq!(Micros::MAX + Micros(1), Micros::MAX);
+        assert_eq!(Micros(1) - Micros(2), Micros::ZERO);
+        let mut sum = Micros(u64::MAX - 1);
+        sum += Micros(5);
+        assert_eq!(sum, Micros::MAX);
+    }
+}
@@ -0,0 +1,66 @@
+use crate::Profile;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+/// A node on the dominant path, see [`Profile::dominant_path`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PathStep {
+    pub node_id: u64,
+    pub total_time: Duration,
+    /// Share of the root's total time.
+    pub fraction: f64,
+}
+
+impl Profile<'_> {
+    /// Walks down from the root always taking the child with the most total
+    /// time, which answers where the time goes without rendering a tree.
+    ///
+    /// The path ends at a node whose children were never sampled.
+    #[must_use]
+    pub fn dominant_path(&self) -> Vec<PathStep> {
+        let rollups = self.with_rollups();
+        let time = |node_id: u64| rollups.total_time(node_id);
+
+        let mut path = Vec::new();
+        let mut next = self.root();
+        let root_time = next.map_or(0.0, |root| time(root.id).as_secs_f64());
+        while let Some(node) = next {
+            let total_time = time(node.id);
+            path.push(PathStep {
+                node_id: node.id,
+                total_time,
+                fraction: if root_time > 0.0 {
+                    total_time.as_secs_f64() / root_time
+                } else {
+                    0.0
+                },
+            });
+            next = self
+                .child_ids(node.id)
+                .filter(|&child_id| time(child_id) > Duration::default())
+                .max_by_key(|&child_id| time(child_id))
+                .map(|child_id| &self[child_id]);
+        }
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ser::tests::PROFILE;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn dominant_path() {
+        let profile: crate::Profile<'_> = serde_json::from_str(PROFILE).unwrap();
+        let path = profile.dominant_path();
+        let ids: Vec<_> = path.iter().map(|step| step.node_id).collect();
+
+        assert_eq!(
+            ids,
+            [1, 3, 45, 48, 49, 50, 51, 52, 53, 54, 55, 56, 57, 58, 65]
+        );
+        assert!((path[0].fraction - 1.0).abs() < f64::EPSILON);
+        assert!(path.windows(2).all(|w| w[0].total_time >= w[1].total_time));
+    }
+}
@@ -0,0 +1,326 @@
+//! Passes over a profile run one after another, like stripping idle time,
+//! pruning and exporting, configured in code or from a file.
+
+use crate::anonymous::AnonymousGroups;
+use crate::format::{Format, Registry};
+use crate::script::ScriptTable;
+use crate::Profile;
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// One step of a [`Pipeline`].
+pub trait Pass {
+    /// The name reports are labeled with, like `strip-idle`.
+    fn name(&self) -> &'static str;
+
+    /// Transforms the profile in place, or leaves it as it is and reports on
+    /// it. Either way the returned text is shown to the user.
+    ///
+    /// # Errors
+    ///
+    /// If the pass fails, which stops the pipeline.
+    fn run(&self, profile: &mut Profile<'_>) -> io::Result<Option<String>>;
+}
+
+/// A pass as written in a pipeline file, a table with a `name` and the
+/// options of that pass.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PassConfig {
+    /// See [`Profile::strip_idle`].
+    StripIdle,
+    /// See [`Profile::attribute_gc`].
+    AttributeGc,
+    /// Renames scripts by id with the `Debugger.scriptParsed` events in a
+    /// file, see [`ScriptTable::parse`].
+    RemapScripts { table: PathBuf },
+    /// Merges eval'd and anonymous scripts with the default
+    /// [`AnonymousGroups`].
+    GroupAnonymous,
+    /// Keeps the paths with at least `threshold` of the total time, see
+    /// [`Profile::drill_down`].
+    Prune { threshold: f64 },
+    /// Reports the nodes, samples and duration.
+    Summary,
+    /// Writes the profile in a format of the registry, `-` for stdout.
+    Export { format: String, out: PathBuf },
+}
+
+/// The passes under a `pass` key, for example a TOML file with a `[[pass]]`
+/// table per pass.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PipelineConfig {
+    pub pass: Vec<PassConfig>,
+}
+
+/// Passes in the order they run.
+#[derive(Default)]
+pub struct Pipeline<'r> {
+    passes: Vec<Box<dyn Pass + 'r>>,
+}
+
+impl<'r> Pipeline<'r> {
+    #[must_use]
+    pub fn new() -> Self {
+        Pipeline::default()
+    }
+
+    /// Builds the configured passes, exporting with the formats of
+    /// `registry`.
+    ///
+    /// # Errors
+    ///
+    /// If an export format is unknown or can't be written, or a script
+    /// table can't be read.
+    pub fn from_config(config: &PipelineConfig, registry: &'r Registry) -> io::Result<Self> {
+        let mut pipeline = Pipeline::new();
+        for pass in &config.pass {
+            match pass {
+                PassConfig::StripIdle => pipeline.push(StripIdle),
+                PassConfig::AttributeGc => pipeline.push(AttributeGc),
+                PassConfig::RemapScripts { table } => {
+                    let table = ScriptTable::parse(&std::fs::read_to_string(table)?)?;
+                    pipeline.push(RemapScripts { table });
+                }
+                PassConfig::GroupAnonymous => pipeline.push(GroupAnonymous),
+                PassConfig::Prune { threshold } => pipeline.push(Prune {
+                    threshold: *threshold,
+                }),
+                PassConfig::Summary => pipeline.push(Summary),
+                PassConfig::Export { format, out } => pipeline.push(Export {
+                    format: registry.writer(format)?,
+                    out: out.clone(),
+                }),
+            }
+        }
+        Ok(pipeline)
+    }
+
+    /// Adds a pass to run after the others.
+    pub fn push(&mut self, pass: impl Pass + 'r) {
+        self.passes.push(Box::new(pass));
+    }
+
+    /// Runs the passes in order and returns their reports, each labeled by
+    /// the name of its pass.
+    ///
+    /// # Errors
+    ///
+    /// The error of the first pass that fails, labeled by its name.
+    pub fn run(&self, profile: &mut Profile<'_>) -> io::Result<Vec<(&'static str, String)>> {
+        let mut reports = Vec::new();
+        for pass in &self.passes {
+            let report = pass.run(profile).map_err(|error| {
+                io::Error::new(error.kind(), format!("{}: {}", pass.name(), error))
+            })?;
+            reports.extend(report.map(|report| (pass.name(), report)));
+        }
+        Ok(reports)
+    }
+}
+
+struct StripIdle;
+
+impl Pass for StripIdle {
+    fn name(&self) -> &'static str {
+        "strip-idle"
+    }
+
+    fn run(&self, profile: &mut Profile<'_>) -> io::Result<Option<String>> {
+        let removed = profile.strip_idle()?;
+        Ok(Some(format!("removed {:.1?} of idle time", removed)))
+    }
+}
+
+struct AttributeGc;
+
+impl Pass for AttributeGc {
+    fn name(&self) -> &'static str {
+        "attribute-gc"
+    }
+
+    fn run(&self, profile: &mut Profile<'_>) -> io::Result<Option<String>> {
+        let moved = profile.attribute_gc()?;
+        Ok(Some(format!("charged {} gc samples to javascript", moved)))
+    }
+}
+
+struct RemapScripts {
+    table: ScriptTable,
+}
+
+impl Pass for RemapScripts {
+    fn name(&self) -> &'static str {
+        "remap-scripts"
+    }
+
+    fn run(&self, profile: &mut Profile<'_>) -> io::Result<Option<String>> {
+        let renamed = profile.remap_scripts(&self.table)?;
+        Ok(Some(format!("renamed the scripts of {} nodes", renamed)))
+    }
+}
+
+struct GroupAnonymous;
+
+impl Pass for GroupAnonymous {
+    fn name(&self) -> &'static str {
+        "group-anonymous"
+    }
+
+    fn run(&self, profile: &mut Profile<'_>) -> io::Result<Option<String>> {
+        let grouped = profile.group_anonymous(&AnonymousGroups::default())?;
+        Ok(Some(format!("grouped {} nodes", grouped)))
+    }
+}
+
+struct Prune {
+    threshold: f64,
+}
+
+impl Pass for Prune {
+    fn name(&self) -> &'static str {
+        "prune"
+    }
+
+    fn run(&self, profile: &mut Profile<'_>) -> io::Result<Option<String>> {
+        let before = profile.nodes.len();
+        *profile = profile.drill_down(self.threshold);
+        Ok(Some(format!(
+            "kept {} of {} nodes",
+            profile.nodes.len(),
+            before
+        )))
+    }
+}
+
+struct Summary;
+
+impl Pass for Summary {
+    fn name(&self) -> &'static str {
+        "summary"
+    }
+
+    fn run(&self, profile: &mut Profile<'_>) -> io::Result<Option<String>> {
+        Ok(Some(profile.summary()))
+    }
+}
+
+struct Export<'r> {
+    format: &'r dyn Format,
+    out: PathBuf,
+}
+
+impl Pass for Export<'_> {
+    fn name(&self) -> &'static str {
+        "export"
+    }
+
+    fn run(&self, profile: &mut Profile<'_>) -> io::Result<Option<String>> {
+        if self.out == Path::new("-") {
+            let stdout = io::stdout();
+            let mut writer = stdout.lock();
+            self.format.write(profile, &mut writer)?;
+            writer.flush()?;
+            return Ok(None);
+        }
+        let mut writer = BufWriter::new(File::create(&self.out)?);
+        self.format.write(profile, &mut writer)?;
+        writer.flush()?;
+        Ok(Some(format!(
+            "wrote {} to {:?}",
+            self.format.name(),
+            self.out
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Pass, PassConfig, Pipeline, PipelineConfig};
+    use crate::format::Registry;
+    use crate::ser::tests::PROFILE;
+    use crate::Profile;
+    use alloc::string::String;
+    use alloc::vec;
+    use std::io;
+    use std::path::PathBuf;
+
+    struct Count;
+
+    impl Pass for Count {
+        fn name(&self) -> &'static str {
+            "count"
+        }
+
+        fn run(&self, profile: &mut Profile<'_>) -> io::Result<Option<String>> {
+            Ok(Some(alloc::format!("{}", profile.nodes.len())))
+        }
+    }
+
+    #[test]
+    fn pipeline() {
+        let config: PipelineConfig = serde_json::from_str(
+            r#"{"pass": [
+                {"name": "strip-idle"},
+                {"name": "remap-scripts", "table": "scripts.json"},
+                {"name": "group-anonymous"},
+                {"name": "prune", "threshold": 0.05},
+                {"name": "summary"},
+                {"name": "export", "format": "cpuprofile", "out": "out.cpuprofile"}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.pass,
+            vec![
+                PassConfig::StripIdle,
+                PassConfig::RemapScripts {
+                    table: PathBuf::from("scripts.json"),
+                },
+                PassConfig::GroupAnonymous,
+                PassConfig::Prune { threshold: 0.05 },
+                PassConfig::Summary,
+                PassConfig::Export {
+                    format: String::from("cpuprofile"),
+                    out: PathBuf::from("out.cpuprofile"),
+                },
+            ]
+        );
+        let invalid = |json: &str| serde_json::from_str::<PipelineConfig>(json).is_err();
+        assert!(invalid(r#"{"pass": [{"name": "remap"}]}"#));
+        assert!(invalid(r#"{"pass": [{"name": "prune"}]}"#));
+        assert!(invalid(r#"{"pass": [{"name": "remap-scripts"}]}"#));
+        assert!(invalid(
+            r#"{"pass": [{"name": "summary", "threshold": 1}]}"#
+        ));
+
+        let registry = Registry::default();
+        let unwritable = PipelineConfig {
+            pass: vec![PassConfig::Export {
+                format: String::from("nope"),
+                out: PathBuf::from("-"),
+            }],
+        };
+        assert!(Pipeline::from_config(&unwritable, &registry).is_err());
+
+        let mut profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        let nodes = profile.nodes.len();
+        let config = PipelineConfig {
+            pass: vec![PassConfig::Prune { threshold: 0.05 }],
+        };
+        let mut pipeline = Pipeline::from_config(&config, &registry).unwrap();
+        pipeline.push(Count);
+        let reports = pipeline.run(&mut profile).unwrap();
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].0, "prune");
+        assert_eq!(
+            reports[1],
+            ("count", alloc::format!("{}", profile.nodes.len()))
+        );
+        assert!(profile.nodes.len() < nodes);
+    }
+}
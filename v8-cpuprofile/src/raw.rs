@@ -0,0 +1,107 @@
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::string::String;
+use serde::{Serialize, Serializer};
+use serde_json::value::RawValue;
+
+/// A json value kept as it was written, such as a node's call frame, so a
+/// profile writes back what it read without parsing every field.
+///
+/// It borrows from the input or owns a copy. The text is checked to be valid
+/// json once when it is made, whichever parser produced it, and written as
+/// is when it is serialized.
+#[derive(Debug, Clone)]
+pub struct RawJson<'raw>(Cow<'raw, RawValue>);
+
+impl<'raw> RawJson<'raw> {
+    /// Borrows `json` after checking it is a single json value.
+    ///
+    /// # Errors
+    ///
+    /// If `json` is not valid json.
+    pub fn new(json: &'raw str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str::<&RawValue>(json).map(Self::from)
+    }
+
+    /// Takes `json` after checking it is a single json value.
+    ///
+    /// # Errors
+    ///
+    /// If `json` is not valid json.
+    pub fn from_string(json: String) -> Result<RawJson<'static>, serde_json::Error> {
+        RawValue::from_string(json).map(RawJson::from)
+    }
+
+    /// The json text.
+    #[must_use]
+    pub fn get(&self) -> &str {
+        self.0.get()
+    }
+
+    /// The json text if it is borrowed for `'raw`, such as from the input.
+    pub(crate) fn borrowed(&self) -> Option<&'raw str> {
+        match self.0 {
+            Cow::Borrowed(raw) => Some(raw.get()),
+            Cow::Owned(_) => None,
+        }
+    }
+
+    /// A copy borrowing from this one.
+    #[must_use]
+    pub fn reborrow(&self) -> RawJson<'_> {
+        RawJson(Cow::Borrowed(&*self.0))
+    }
+
+    #[must_use]
+    pub fn into_owned(self) -> RawJson<'static> {
+        RawJson(Cow::Owned(self.0.into_owned()))
+    }
+}
+
+impl<'raw> From<&'raw RawValue> for RawJson<'raw> {
+    fn from(raw: &'raw RawValue) -> Self {
+        RawJson(Cow::Borrowed(raw))
+    }
+}
+
+impl From<Box<RawValue>> for RawJson<'static> {
+    fn from(raw: Box<RawValue>) -> Self {
+        RawJson(Cow::Owned(raw))
+    }
+}
+
+impl PartialEq for RawJson<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.get() == other.get()
+    }
+}
+
+impl Eq for RawJson<'_> {}
+
+impl Serialize for RawJson<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RawJson;
+    use alloc::string::String;
+
+    #[test]
+    fn raw_json() {
+        let borrowed = RawJson::new(r#"{"a":[1,2]}"#).unwrap();
+        assert!(RawJson::new("{").is_err());
+        assert!(RawJson::from_string(String::from("1 2")).is_err());
+
+        let owned = borrowed.clone().into_owned();
+        assert_eq!(owned, borrowed);
+        assert_eq!(borrowed.borrowed(), Some(r#"{"a":[1,2]}"#));
+        assert_eq!(owned.borrowed(), None);
+        assert_eq!(serde_json::to_string(&owned).unwrap(), r#"{"a":[1,2]}"#);
+    }
+}
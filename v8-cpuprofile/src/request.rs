@@ -0,0 +1,215 @@
+//! Profiles of the labeled spans of a long capture, like the requests of an
+//! access log, see [`Profile::request_profiles`].
+
+use crate::aggregate::ProfileSet;
+use crate::join::copy;
+use crate::{Micros, Profile};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::time::Duration;
+use hashbrown::HashSet;
+
+/// A labeled span of time relative to the profile's `start_time`, the end
+/// excluded.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Interval {
+    pub label: String,
+    pub start: Duration,
+    pub end: Duration,
+}
+
+impl<'raw> Profile<'raw> {
+    /// The samples from `start` up to `end`, relative to the profile's
+    /// `start_time`, with the nodes on their stacks. The times of the
+    /// profile are those of the span, clamped to the capture.
+    #[must_use]
+    pub fn slice(&self, start: Duration, end: Duration) -> Profile<'raw> {
+        let duration = self.end_time.saturating_sub(self.start_time);
+        let start = Micros::from(start).min(duration);
+        let end = Micros::from(end).clamp(start, duration);
+        // samples kept in recorded order may go back in time
+        let samples: Vec<_> = self
+            .samples
+            .iter()
+            .filter(|sample| sample.ts >= start && sample.ts < end)
+            .map(|sample| {
+                let mut sample = *sample;
+                sample.ts = sample.ts.saturating_sub(start);
+                sample
+            })
+            .collect();
+
+        // the roots even without samples, so the profile has a tree
+        let mut included: HashSet<u64> = self
+            .nodes
+            .iter()
+            .filter(|node| node.parent_id.is_none())
+            .map(|node| node.id)
+            .collect();
+        for sample in &samples {
+            if !self.node_index.contains_key(&sample.node_id) {
+                continue;
+            }
+            if included.insert(sample.node_id) {
+                for parent_id in self.parent_ids_iter(sample.node_id) {
+                    if !included.insert(parent_id) {
+                        break;
+                    }
+                }
+            }
+        }
+        let mut nodes = Vec::with_capacity(included.len());
+        for node in self.nodes.iter().filter(|node| included.contains(&node.id)) {
+            let mut node = copy(node);
+            if let Some(children) = &mut node.children {
+                children.retain(|child_id| included.contains(child_id));
+            }
+            // hit counts of the span's samples
+            node.hit_count = 0;
+            nodes.push(node);
+        }
+        let node_index = nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (node.id, index))
+            .collect();
+        let mut profile = Profile {
+            nodes,
+            start_time: self.start_time + start,
+            end_time: self.start_time + end,
+            samples,
+            metadata: self.metadata.clone(),
+            sampling_interval: self.sampling_interval,
+            extra_fields: self.extra_fields.clone(),
+            exact_times: (None, None),
+            node_index,
+            weighting: self.weighting,
+        };
+        for index in 0..profile.samples.len() {
+            if let Some(&node_index) = profile.node_index.get(&profile.samples[index].node_id) {
+                let node = &mut profile.nodes[node_index];
+                node.hit_count = node.hit_count.saturating_add(1);
+            }
+        }
+        profile
+    }
+
+    /// A profile of each interval, in the order given. Intervals may
+    /// overlap, like concurrent requests, and then share samples.
+    #[must_use]
+    pub fn request_profiles(&self, intervals: &[Interval]) -> Vec<Profile<'raw>> {
+        intervals
+            .iter()
+            .map(|interval| self.slice(interval.start, interval.end))
+            .collect()
+    }
+
+    /// The profiles of the intervals of each label aggregated, one run per
+    /// interval, the labels in the order they first appear.
+    ///
+    /// # Errors
+    ///
+    /// If a node has an invalid call frame.
+    pub fn request_aggregates<'i>(
+        &self,
+        intervals: &'i [Interval],
+    ) -> Result<Vec<(&'i str, ProfileSet<'raw>)>, serde_json::Error> {
+        let mut labels: Vec<(&str, Vec<Profile<'raw>>)> = Vec::new();
+        for interval in intervals {
+            let profile = self.slice(interval.start, interval.end);
+            match labels
+                .iter_mut()
+                .find(|(label, _)| *label == interval.label)
+            {
+                Some((_, profiles)) => profiles.push(profile),
+                None => labels.push((&interval.label, alloc::vec![profile])),
+            }
+        }
+        labels
+            .into_iter()
+            .map(|(label, profiles)| Ok((label, ProfileSet::aggregate(&profiles)?)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Interval;
+    use crate::ser::tests::PROFILE;
+    use crate::{ParseOptions, Profile, TimeDeltaPolicy};
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use core::time::Duration;
+
+    #[test]
+    fn request_profiles() {
+        let profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        let half = profile.samples[profile.samples.len() / 2].ts.as_duration();
+        let interval = |label: &str, start, end| Interval {
+            label: String::from(label),
+            start,
+            end,
+        };
+        let intervals = [
+            interval("first", Duration::default(), half),
+            interval("second", half, profile.duration() * 2),
+            interval("first", Duration::default(), half / 2),
+        ];
+
+        let parts = profile.request_profiles(&intervals);
+        assert_eq!(parts.len(), 3);
+        for part in &parts {
+            part.roundtrip_check().unwrap();
+            let hits: u32 = part.nodes.iter().map(|node| node.hit_count).sum();
+            assert_eq!(hits as usize, part.samples.len());
+            assert!(part.root().is_some());
+        }
+        assert_eq!(
+            parts[0].samples.len() + parts[1].samples.len(),
+            profile.samples.len()
+        );
+        assert_eq!(parts[0].duration(), half);
+        assert_eq!(parts[1].end_time, profile.end_time);
+        assert_eq!(parts[1].samples[0].ts.as_duration(), Duration::default());
+        assert!(parts[1].nodes.len() < profile.nodes.len());
+        let total: Duration = parts[..2]
+            .iter()
+            .map(|part| part.self_times().values().sum::<Duration>())
+            .sum();
+        assert_eq!(total, profile.self_times().values().sum());
+
+        let aggregates = profile.request_aggregates(&intervals).unwrap();
+        let labels: Vec<_> = aggregates.iter().map(|(label, _)| *label).collect();
+        assert_eq!(labels, ["first", "second"]);
+        assert_eq!(aggregates[0].1.runs, 2);
+        assert_eq!(aggregates[1].1.runs, 1);
+
+        let empty = profile.slice(profile.duration() * 2, profile.duration() * 3);
+        assert!(empty.samples.is_empty());
+        assert_eq!(empty.duration(), Duration::default());
+        empty.roundtrip_check().unwrap();
+    }
+
+    #[test]
+    fn unordered_samples() {
+        const PROFILE: &str = r#"{"nodes":[
+            {"id":1,"callFrame":{},"hitCount":0,"children":[2]},
+            {"id":2,"callFrame":{},"hitCount":2}
+        ],"startTime":0,"endTime":40,"samples":[2,2,1,2],"timeDeltas":[30,-20,5,20]}"#;
+        let options = ParseOptions {
+            time_deltas: TimeDeltaPolicy::KeepSigned,
+            preserve_sample_order: true,
+            ..ParseOptions::default()
+        };
+        let profile = Profile::parse_with(PROFILE.as_bytes(), &options).unwrap();
+        let ts: Vec<_> = profile.samples.iter().map(|sample| sample.ts.0).collect();
+        assert_eq!(ts, [30, 10, 15, 35]);
+
+        let part = profile.slice(Duration::from_micros(5), Duration::from_micros(32));
+        let ts: Vec<_> = part.samples.iter().map(|sample| sample.ts.0).collect();
+        assert_eq!(ts, [25, 5, 10]);
+        assert_eq!(part.nodes.len(), 2);
+        assert_eq!(part.nodes[0].hit_count, 1);
+        assert_eq!(part.nodes[1].hit_count, 2);
+    }
+}
@@ -0,0 +1,91 @@
+use crate::Profile;
+use alloc::vec::Vec;
+use core::time::Duration;
+use hashbrown::HashMap;
+
+/// A profile with the self and subtree time of every node computed up front,
+/// see [`Profile::with_rollups`].
+#[derive(Debug)]
+pub struct Rollups<'p, 'raw> {
+    profile: &'p Profile<'raw>,
+    times: HashMap<u64, (Duration, Duration)>,
+}
+
+impl<'raw> Profile<'raw> {
+    /// Computes the self and total time of every node in one pass over the
+    /// tree, so repeated queries while exploring a profile don't walk it
+    /// again.
+    #[must_use]
+    pub fn with_rollups(&self) -> Rollups<'_, 'raw> {
+        let self_times = self.self_times();
+        let mut times: HashMap<u64, (Duration, Duration)> =
+            HashMap::with_capacity(self.nodes.len());
+        if let Some(root) = self.root() {
+            let mut order: Vec<u64> = Vec::with_capacity(self.nodes.len());
+            order.push(root.id);
+            order.extend(self.bfs(root.id));
+            // children come after their parent breadth first, so in reverse
+            // every child is done before its parent
+            for &node_id in order.iter().rev() {
+                let self_time = self_times.get(&node_id).copied().unwrap_or_default();
+                let children: Duration = self
+                    .child_ids(node_id)
+                    .filter_map(|child_id| times.get(&child_id))
+                    .map(|&(_, total_time)| total_time)
+                    .sum();
+                times.insert(node_id, (self_time, self_time + children));
+            }
+        }
+        Rollups {
+            profile: self,
+            times,
+        }
+    }
+}
+
+impl<'p, 'raw> Rollups<'p, 'raw> {
+    #[must_use]
+    pub fn profile(&self) -> &'p Profile<'raw> {
+        self.profile
+    }
+
+    #[must_use]
+    pub fn self_time(&self, node_id: u64) -> Duration {
+        self.times
+            .get(&node_id)
+            .map_or_else(Duration::default, |&(self_time, _)| self_time)
+    }
+
+    /// Time of the node and everything below it.
+    #[must_use]
+    pub fn total_time(&self, node_id: u64) -> Duration {
+        self.times
+            .get(&node_id)
+            .map_or_else(Duration::default, |&(_, total_time)| total_time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ser::tests::PROFILE;
+    use crate::Profile;
+
+    #[test]
+    fn rollups_match_total_times() {
+        let profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        let rollups = profile.with_rollups();
+        let total_times = profile.total_times();
+
+        for node in &rollups.profile().nodes {
+            assert_eq!(
+                rollups.total_time(node.id),
+                total_times.get(&node.id).copied().unwrap_or_default()
+            );
+        }
+        let root = profile.root().unwrap().id;
+        assert_eq!(
+            rollups.total_time(root),
+            profile.samples_with_durations().map(|(_, d)| d).sum()
+        );
+    }
+}
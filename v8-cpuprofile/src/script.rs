@@ -0,0 +1,311 @@
+//! The scripts seen in a profile, see [`Profile::scripts`], and frames
+//! renamed by script id, see [`Profile::remap_scripts`].
+
+use crate::{CallFrame, Profile, RawJson};
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::time::Duration;
+use hashbrown::HashMap;
+
+/// A script with the nodes and time of its functions.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Script<'raw> {
+    pub script_id: Cow<'raw, str>,
+    pub url: Cow<'raw, str>,
+    /// Nodes with a call frame in the script.
+    pub nodes: usize,
+    /// Samples of a function of the script.
+    pub samples: usize,
+    pub self_time: Duration,
+    /// Time with a function of the script anywhere on the stack, counted
+    /// once per sample.
+    pub total_time: Duration,
+}
+
+impl<'raw> Profile<'raw> {
+    /// Each distinct script id and url of the call frames, the most self
+    /// time first. Frames without a script, script id `0` like `(root)` and
+    /// `(program)`, are left out.
+    ///
+    /// # Errors
+    ///
+    /// If a node has an invalid call frame.
+    pub fn scripts(&self) -> Result<Vec<Script<'raw>>, serde_json::Error> {
+        let mut scripts: Vec<Script<'raw>> = Vec::new();
+        let mut by_key: HashMap<(Cow<'raw, str>, Cow<'raw, str>), usize> = HashMap::new();
+        // index into scripts of each node, by the node's index
+        let mut node_scripts = Vec::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            let frame = node.frame()?;
+            if frame.script_id == "0" {
+                node_scripts.push(None);
+                continue;
+            }
+            let index = *by_key
+                .entry((frame.script_id.clone(), frame.url.clone()))
+                .or_insert_with(|| {
+                    scripts.push(Script {
+                        script_id: frame.script_id,
+                        url: frame.url,
+                        ..Script::default()
+                    });
+                    scripts.len() - 1
+                });
+            scripts[index].nodes += 1;
+            node_scripts.push(Some(index));
+        }
+        let script = |node_id: u64| node_scripts[self.node_index[&node_id]];
+
+        for sample in &self.samples {
+            if let Some(index) = script(sample.node_id) {
+                scripts[index].samples += 1;
+            }
+        }
+        let mut on_stack = Vec::new();
+        for (node_id, time) in self.self_times() {
+            if let Some(index) = script(node_id) {
+                scripts[index].self_time += time;
+            }
+            on_stack.clear();
+            on_stack.extend(
+                core::iter::once(node_id)
+                    .chain(self.parent_ids_iter(node_id))
+                    .filter_map(script),
+            );
+            on_stack.sort_unstable();
+            on_stack.dedup();
+            for &index in &on_stack {
+                scripts[index].total_time += time;
+            }
+        }
+
+        scripts.sort_by(|a, b| {
+            b.self_time
+                .cmp(&a.self_time)
+                .then_with(|| a.url.cmp(&b.url))
+                .then_with(|| a.script_id.cmp(&b.script_id))
+        });
+        Ok(scripts)
+    }
+}
+
+/// Urls by script id, like the scripts of CDP `Debugger.scriptParsed`
+/// events recorded with the profile.
+///
+/// It deserializes from a json array of the events' params or of the whole
+/// CDP messages, messages other than `Debugger.scriptParsed` are skipped.
+/// A script without a url, like an eval'd one, is named `eval:<hash>` and a
+/// `data:` url script `data:<hash>` with the hash of its source, which stay
+/// the same across runs.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ScriptTable {
+    urls: HashMap<String, String>,
+}
+
+impl ScriptTable {
+    #[must_use]
+    pub fn new() -> Self {
+        ScriptTable::default()
+    }
+
+    /// Parses a json array of events, or events one after another like a
+    /// log with one message per line.
+    ///
+    /// # Errors
+    ///
+    /// If the json or an event is invalid.
+    pub fn parse(json: &str) -> Result<Self, serde_json::Error> {
+        if json.trim_start().starts_with('[') {
+            return serde_json::from_str(json);
+        }
+        let mut table = ScriptTable::new();
+        for event in serde_json::Deserializer::from_str(json).into_iter::<ScriptParsed>() {
+            table.add(event?);
+        }
+        Ok(table)
+    }
+
+    pub fn insert(&mut self, script_id: String, url: String) {
+        self.urls.insert(script_id, url);
+    }
+
+    #[must_use]
+    pub fn get(&self, script_id: &str) -> Option<&str> {
+        self.urls.get(script_id).map(String::as_str)
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.urls.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.urls.is_empty()
+    }
+
+    pub(crate) fn add(&mut self, event: ScriptParsed) {
+        let ScriptParsed {
+            script_id,
+            url,
+            hash,
+        } = event;
+        let Some(script_id) = script_id else {
+            return;
+        };
+        let url = match hash {
+            Some(hash) if url.is_empty() => alloc::format!("eval:{}", hash),
+            Some(hash) if url.starts_with("data:") => alloc::format!("data:{}", hash),
+            _ if url.is_empty() => return,
+            _ => url,
+        };
+        self.insert(script_id, url);
+    }
+}
+
+/// The params of a `Debugger.scriptParsed` event, no script id for other
+/// events.
+#[derive(Debug, Default)]
+pub(crate) struct ScriptParsed {
+    pub(crate) script_id: Option<String>,
+    pub(crate) url: String,
+    pub(crate) hash: Option<String>,
+}
+
+impl Profile<'_> {
+    /// Sets the url of the frames of each script in `table`, returns the
+    /// number of nodes changed.
+    ///
+    /// # Errors
+    ///
+    /// If a node has an invalid call frame.
+    pub fn remap_scripts(&mut self, table: &ScriptTable) -> Result<usize, serde_json::Error> {
+        self.remap_urls(|frame| table.get(&frame.script_id).map(String::from))
+    }
+
+    /// Sets the url of each frame `url` returns one for, returns the number
+    /// of nodes changed.
+    ///
+    /// # Errors
+    ///
+    /// If a node has an invalid call frame.
+    pub fn remap_urls(
+        &mut self,
+        mut url: impl FnMut(&CallFrame<'_>) -> Option<String>,
+    ) -> Result<usize, serde_json::Error> {
+        let mut changed = 0;
+        for node in &mut self.nodes {
+            let mut frame = node.frame()?;
+            match url(&frame) {
+                Some(url) if url != frame.url => frame.url = Cow::Owned(url),
+                _ => continue,
+            }
+            node.call_frame = RawJson::from(serde_json::value::to_raw_value(&frame)?);
+            changed += 1;
+        }
+        Ok(changed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ScriptTable;
+    use crate::ser::tests::PROFILE;
+    use crate::Profile;
+    use alloc::string::{String, ToString};
+    use core::time::Duration;
+    use hashbrown::HashSet;
+
+    #[test]
+    fn scripts() {
+        let profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        let scripts = profile.scripts().unwrap();
+
+        let mut keys = HashSet::new();
+        for node in &profile.nodes {
+            let frame = node.frame().unwrap();
+            if frame.script_id != "0" {
+                keys.insert((frame.script_id, frame.url));
+            }
+        }
+        assert_eq!(scripts.len(), keys.len());
+        assert!(scripts.iter().all(|script| script.script_id != "0"));
+        assert!(scripts
+            .windows(2)
+            .all(|pair| pair[0].self_time >= pair[1].self_time));
+        let frame_times = profile.frame_times().unwrap();
+        for script in &scripts {
+            let self_time: Duration = frame_times
+                .iter()
+                .filter(|(frame, _)| frame.url == script.url)
+                .map(|(_, times)| times.self_time)
+                .sum();
+            assert!(script.self_time <= self_time, "{}", script.url);
+            assert!(script.self_time <= script.total_time, "{}", script.url);
+            assert!(script.nodes > 0);
+        }
+
+        let total: Duration = profile.self_times().values().sum();
+        let loader = scripts
+            .iter()
+            .find(|script| script.url == "node:internal/modules/cjs/loader")
+            .unwrap();
+        assert!(loader.total_time > loader.self_time);
+        assert!(loader.total_time <= total);
+    }
+
+    #[test]
+    fn remap_scripts() {
+        let mut profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        let tty = profile
+            .scripts()
+            .unwrap()
+            .into_iter()
+            .find(|script| script.url == "node:tty")
+            .unwrap();
+        let events = alloc::format!(
+            r#"{{"method":"Debugger.scriptParsed","params":{{"scriptId":"{}","url":"","hash":"abc","startLine":0}}}}
+{{"method":"Runtime.consoleAPICalled","params":{{"type":"log"}}}}
+{{"method":"Debugger.scriptParsed","params":{{"scriptId":"1000","url":"data:text/javascript,f()","hash":"def"}}}}
+{{"method":"Debugger.scriptParsed","params":{{"scriptId":"1001","url":""}}}}"#,
+            tty.script_id
+        );
+        let table = ScriptTable::parse(&events).unwrap();
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.get(&tty.script_id), Some("eval:abc"));
+        assert_eq!(table.get("1000"), Some("data:def"));
+
+        assert_eq!(profile.remap_scripts(&table).unwrap(), tty.nodes);
+        profile.roundtrip_check().unwrap();
+        let scripts = profile.scripts().unwrap();
+        assert!(scripts.iter().all(|script| script.url != "node:tty"));
+        let eval = scripts
+            .iter()
+            .find(|script| script.url == "eval:abc")
+            .unwrap();
+        assert_eq!(eval.self_time, tty.self_time);
+        assert_eq!(profile.remap_scripts(&table).unwrap(), 0);
+
+        let params: ScriptTable =
+            serde_json::from_str(r#"[{"scriptId":"7","url":"file:///a.js","isModule":true}]"#)
+                .unwrap();
+        assert_eq!(
+            ScriptTable::parse(r#"[{"scriptId":"7","url":"file:///a.js"}]"#).unwrap(),
+            params
+        );
+        let mut table = ScriptTable::new();
+        table.insert(String::from("7"), String::from("file:///a.js"));
+        assert_eq!(params, table);
+        assert!(serde_json::from_str::<ScriptTable>(r#"[{"scriptId":7}]"#).is_err());
+    }
+    #[test]
+    fn unknown_sample_node() {
+        const PROFILE: &str = r#"{"nodes":[{"id":1,"callFrame":{"functionName":"(root)","scriptId":"0","url":"","lineNumber":-1,"columnNumber":-1},"hitCount":0}],"startTime":0,"endTime":10,"samples":[99],"timeDeltas":[1]}"#;
+        let error = serde_json::from_str::<Profile>(PROFILE).unwrap_err();
+        assert!(error.to_string().contains("unknown sample node id 99"));
+        let valid = PROFILE.replace("[99]", "[1]");
+        let profile: Profile = serde_json::from_str(&valid).unwrap();
+        assert!(profile.scripts().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,101 @@
+//! Nodes found by a regex with the path that leads to them, see
+//! [`Profile::search`].
+
+use crate::{FrameKey, Profile};
+use alloc::vec::Vec;
+use core::time::Duration;
+use regex::Regex;
+
+/// A node whose function name or url matched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchMatch<'raw> {
+    pub node_id: u64,
+    /// Frames from the root down to the node, the node's own last.
+    pub path: Vec<FrameKey<'raw>>,
+    pub self_time: Duration,
+    /// Time of the node and everything below it.
+    pub total_time: Duration,
+}
+
+impl<'raw> Profile<'raw> {
+    /// The nodes whose function name or script url matches `pattern`, the
+    /// most total time first.
+    ///
+    /// # Errors
+    ///
+    /// If a node has an invalid call frame.
+    pub fn search(&self, pattern: &Regex) -> Result<Vec<SearchMatch<'raw>>, serde_json::Error> {
+        let mut keys = Vec::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            keys.push(FrameKey::from(node.frame()?));
+        }
+        let key = |node_id: u64| keys[self.node_index[&node_id]].clone();
+
+        let rollups = self.with_rollups();
+        let mut matches: Vec<_> = self
+            .nodes
+            .iter()
+            .zip(&keys)
+            .filter(|(_, frame)| {
+                pattern.is_match(&frame.function_name) || pattern.is_match(&frame.url)
+            })
+            .map(|(node, frame)| {
+                let mut path: Vec<_> = self.parent_ids_iter(node.id).map(key).collect();
+                path.reverse();
+                path.push(frame.clone());
+                SearchMatch {
+                    node_id: node.id,
+                    path,
+                    self_time: rollups.self_time(node.id),
+                    total_time: rollups.total_time(node.id),
+                }
+            })
+            .collect();
+        // stable, so equal times stay in profile order
+        matches.sort_by_key(|found| core::cmp::Reverse(found.total_time));
+        Ok(matches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ser::tests::PROFILE;
+    use crate::Profile;
+    use regex::Regex;
+
+    #[test]
+    fn search() {
+        let profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        let matches = profile.search(&Regex::new("^compileFor").unwrap()).unwrap();
+
+        let nodes = profile
+            .find_nodes(
+                "compileForInternalLoader",
+                "node:internal/bootstrap/loaders",
+            )
+            .unwrap();
+        assert_eq!(matches.len(), nodes.len());
+        assert!(matches
+            .windows(2)
+            .all(|pair| pair[0].total_time >= pair[1].total_time));
+        for found in &matches {
+            assert_eq!(found.path[0].function_name, "(root)");
+            assert_eq!(
+                found.path.last().unwrap().function_name,
+                "compileForInternalLoader"
+            );
+            assert_eq!(
+                found.path.len(),
+                profile.parent_ids_iter(found.node_id).count() + 1
+            );
+            assert!(found.self_time <= found.total_time);
+        }
+
+        let by_url = profile.search(&Regex::new("^node:tty$").unwrap()).unwrap();
+        assert!(!by_url.is_empty());
+        assert!(profile
+            .search(&Regex::new("no such").unwrap())
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,96 @@
+use crate::aggregate::{FrameStats, ProfileSet, Stats};
+use crate::series::{FunctionSeries, TimeSeries};
+use alloc::vec::Vec;
+use core::time::Duration;
+use serde::ser::SerializeMap;
+use serde::Serialize;
+use serde::Serializer;
+
+impl Serialize for ProfileSet<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("runs", &self.runs)?;
+        if let Some(interval) = self.sampling_interval {
+            map.serialize_entry("samplingInterval", &interval.as_micros())?;
+        }
+        map.serialize_entry("frames", &self.frames)?;
+        map.end()
+    }
+}
+
+impl Serialize for FrameStats<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("callFrame", &self.frame)?;
+        map.serialize_entry("selfTime", &self.self_time)?;
+        map.serialize_entry("totalTime", &self.total_time)?;
+        map.serialize_entry("selfTimes", &MicrosSeq(&self.self_times))?;
+        map.end()
+    }
+}
+
+impl Serialize for TimeSeries<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("runs", &self.runs)?;
+        map.serialize_entry("functions", &self.functions)?;
+        map.end()
+    }
+}
+
+/// Times in microseconds, a point per run.
+impl Serialize for FunctionSeries<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let self_times: Vec<Duration> = self.times.iter().map(|times| times.self_time).collect();
+        let total_times: Vec<Duration> = self.times.iter().map(|times| times.total_time).collect();
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("callFrame", &self.frame)?;
+        map.serialize_entry("selfTime", &MicrosSeq(&self_times))?;
+        map.serialize_entry("totalTime", &MicrosSeq(&total_times))?;
+        map.end()
+    }
+}
+
+struct MicrosSeq<'a>(&'a [Duration]);
+
+impl Serialize for MicrosSeq<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(self.0.iter().map(Duration::as_micros))
+    }
+}
+
+/// All times in microseconds like the cpuprofile itself.
+impl Serialize for Stats {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("mean", &self.mean.as_micros())?;
+        map.serialize_entry("stddev", &self.stddev.as_micros())?;
+        map.serialize_entry("min", &self.min.as_micros())?;
+        map.serialize_entry("p50", &self.p50.as_micros())?;
+        map.serialize_entry("p90", &self.p90.as_micros())?;
+        map.serialize_entry("p99", &self.p99.as_micros())?;
+        map.serialize_entry("max", &self.max.as_micros())?;
+        if let Some(margin) = self.margin {
+            map.serialize_entry("margin", &margin.as_micros())?;
+        }
+        map.end()
+    }
+}
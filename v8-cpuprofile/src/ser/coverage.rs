@@ -0,0 +1,82 @@
+use crate::coverage::{Coverage, CoverageRange, FunctionCoverage, ScriptCoverage};
+use serde::ser::SerializeMap;
+use serde::Serialize;
+use serde::Serializer;
+
+impl Serialize for Coverage<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("result", &self.result)?;
+        map.end()
+    }
+}
+
+impl Serialize for ScriptCoverage<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("scriptId", &self.script_id)?;
+        map.serialize_entry("url", &self.url)?;
+        map.serialize_entry("functions", &self.functions)?;
+        map.end()
+    }
+}
+
+impl Serialize for FunctionCoverage<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("functionName", &self.function_name)?;
+        map.serialize_entry("ranges", &self.ranges)?;
+        map.serialize_entry("isBlockCoverage", &self.is_block_coverage)?;
+        map.end()
+    }
+}
+
+impl Serialize for CoverageRange {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("startOffset", &self.start_offset)?;
+        map.serialize_entry("endOffset", &self.end_offset)?;
+        map.serialize_entry("count", &self.count)?;
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::coverage::Coverage;
+    use crate::ser::tests::PROFILE;
+
+    #[test]
+    fn hot_functions() {
+        const COVERAGE: &str = concat!(
+            r#"{"result":[{"scriptId":"96","url":"node:tty","functions":["#,
+            r#"{"functionName":"WriteStream","ranges":[{"startOffset":2000,"endOffset":3000,"count":1},{"startOffset":2100,"endOffset":2200,"count":0}],"isBlockCoverage":true},"#,
+            r#"{"functionName":"isatty","ranges":[{"startOffset":100,"endOffset":200,"count":4}],"isBlockCoverage":false}"#,
+            r#"]}]}"#
+        );
+
+        let profile: crate::Profile<'_> = serde_json::from_str(PROFILE).unwrap();
+        let coverage: Coverage<'_> = serde_json::from_str(COVERAGE).unwrap();
+
+        assert_eq!(serde_json::to_string(&coverage).unwrap(), COVERAGE);
+
+        let hot = coverage.hot_functions(&profile).unwrap();
+
+        assert_eq!(hot.len(), 1);
+        assert_eq!(hot[0].function.function_name, "WriteStream");
+        assert!(hot[0].is_rarely_covered(1));
+        assert!(hot[0].has_dead_blocks());
+    }
+}
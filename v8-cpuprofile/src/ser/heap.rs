@@ -0,0 +1,160 @@
+use super::MakeIter;
+use super::Time;
+use super::{serialize_profile, ProfileHeader};
+use crate::heap::{AllocationTrace, TraceFunction, TraceNode};
+use crate::Micros;
+use crate::Sample;
+use core::fmt::Display;
+use serde::ser::SerializeMap;
+use serde::Serialize;
+use serde::Serializer;
+
+/// Serializes as a cpuprofile without samples, dev tools falls back to the
+/// `hitCount` of each node which is the allocation count.
+impl Serialize for AllocationTrace<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let nodes =
+            MakeIter::from(move || self.nodes().map(move |node| TraceProfileNode(self, node)));
+        let samples: &[Sample] = &[];
+        let header = ProfileHeader {
+            start_time: Time(Micros::ZERO, None),
+            end_time: Time(Micros::ZERO, None),
+            sampling_interval: None,
+            metadata: None,
+            extra_fields: &[],
+        };
+        serialize_profile(serializer, &nodes, samples, &header)
+    }
+}
+
+struct TraceProfileNode<'a, 'raw>(&'a AllocationTrace<'raw>, &'a TraceNode);
+
+impl Serialize for TraceProfileNode<'_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let TraceProfileNode(trace, node) = self;
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("id", &node.id)?;
+        map.serialize_entry("callFrame", &TraceCallFrame(trace.function(node)))?;
+        map.serialize_entry("hitCount", &node.count)?;
+        if !node.children.is_empty() {
+            let children = MakeIter::from(move || node.children.iter().map(|child| child.id));
+            map.serialize_entry("children", &children)?;
+        }
+        map.end()
+    }
+}
+
+struct TraceCallFrame<'a, 'raw>(&'a TraceFunction<'raw>);
+
+impl Serialize for TraceCallFrame<'_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let function = self.0;
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("functionName", &function.name)?;
+        map.serialize_entry("scriptId", &AsStr(function.script_id))?;
+        map.serialize_entry("url", &function.script_name)?;
+        // trace positions are 1-based with 0 for unknown, call frames are
+        // 0-based with -1 for unknown
+        map.serialize_entry("lineNumber", &(i64::from(function.line) - 1))?;
+        map.serialize_entry("columnNumber", &(i64::from(function.column) - 1))?;
+        map.end()
+    }
+}
+
+struct AsStr<T>(T);
+
+impl<T: Display> Serialize for AsStr<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::heap::AllocationTrace;
+    use alloc::string::ToString;
+
+    #[test]
+    fn allocation_trace_as_cpuprofile() {
+        const SNAPSHOT: &str = r#"{
+            "snapshot": {"meta": {}, "node_count": 0, "edge_count": 0, "trace_function_count": 2},
+            "nodes": [0, 1, 2],
+            "edges": [],
+            "trace_function_infos": [0, 2, 1, 0, 0, 0, 1, 3, 4, 7, 10, 5],
+            "trace_tree": [1, 0, 0, 0, [2, 1, 3, 96, []]],
+            "samples": [],
+            "locations": [],
+            "strings": ["<dummy>", "", "(root)", "makeThing", "app\u002ejs"]
+        }"#;
+
+        let trace: AllocationTrace<'_> = serde_json::from_str(SNAPSHOT).unwrap();
+
+        assert_eq!(trace.functions.len(), 2);
+        assert_eq!(trace.root.children[0].size, 96);
+        assert_eq!(
+            trace.function(&trace.root.children[0]).script_name,
+            "app.js"
+        );
+
+        assert_eq!(
+            serde_json::to_string(&trace).unwrap(),
+            concat!(
+                r#"{"nodes":["#,
+                r#"{"id":1,"callFrame":{"functionName":"(root)","scriptId":"0","url":"","lineNumber":-1,"columnNumber":-1},"hitCount":0,"children":[2]},"#,
+                r#"{"id":2,"callFrame":{"functionName":"makeThing","scriptId":"7","url":"app.js","lineNumber":9,"columnNumber":4},"hitCount":3}"#,
+                r#"],"startTime":0,"endTime":0,"samples":[],"timeDeltas":[]}"#
+            )
+        );
+    }
+
+    #[test]
+    fn trace_fields_from_meta() {
+        const SNAPSHOT: &str = r#"{
+            "snapshot": {"meta": {
+                "trace_function_info_fields": ["name", "function_id", "script_id", "script_name", "line", "column", "flags"],
+                "trace_node_fields": ["id", "function_info_index", "count", "size", "live_count", "live_size", "children"]
+            }},
+            "trace_function_infos": [2, 0, 0, 1, 0, 0, 9, 3, 1, 7, 4, 10, 5, 9],
+            "trace_tree": [1, 0, 0, 0, 0, 0, [2, 1, 3, 96, 1, 32, []]],
+            "strings": ["<dummy>", "", "(root)", "makeThing", "app.js"]
+        }"#;
+        let trace: AllocationTrace<'_> = serde_json::from_str(SNAPSHOT).unwrap();
+        let node = &trace.root.children[0];
+        assert_eq!((node.id, node.count, node.size), (2, 3, 96));
+        let function = trace.function(node);
+        assert_eq!(function.name, "makeThing");
+        assert_eq!(function.script_name, "app.js");
+        assert_eq!(
+            (function.function_id, function.script_id, function.line),
+            (1, 7, 10)
+        );
+
+        // the tree can't be read before its layout is known
+        const LATE_META: &str = r#"{
+            "trace_tree": [1, 0, 0, 0, []],
+            "snapshot": {"meta": {"trace_node_fields": ["id", "function_info_index", "count", "size", "live_count", "live_size", "children"]}}
+        }"#;
+        let error = serde_json::from_str::<AllocationTrace<'_>>(LATE_META).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("snapshot meta after trace_tree"));
+    }
+
+    #[test]
+    fn missing_allocation_trace() {
+        let result = serde_json::from_str::<AllocationTrace<'_>>(r#"{"nodes":[],"trace_tree":[]}"#);
+        assert!(result.is_err());
+    }
+}
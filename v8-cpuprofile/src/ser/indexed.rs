@@ -0,0 +1,15 @@
+use super::serialize_profile;
+use crate::indexed::Header;
+use crate::Sample;
+use serde::Serialize;
+use serde::Serializer;
+
+impl Serialize for Header<'_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let samples: &[Sample] = &[];
+        serialize_profile(serializer, &self.0.nodes, samples, &self.0.header())
+    }
+}
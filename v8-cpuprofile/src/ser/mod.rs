@@ -1,84 +1,195 @@
+mod aggregate;
+mod coverage;
+mod heap;
+#[cfg(feature = "std")]
+mod indexed;
+mod options;
+#[cfg(feature = "std")]
+mod sidecar;
+mod trace;
 mod util;
 
+use crate::CallFrame;
 use crate::FilteredNode;
+use crate::FrameKey;
+use crate::Micros;
 use crate::Node;
 use crate::Profile;
 use crate::ProfileChunk;
+use crate::RawJson;
 use crate::Sample;
-use core::time::Duration;
+use alloc::borrow::Cow;
+pub use options::SerializeOptions;
 use serde::ser::SerializeMap;
 use serde::Serialize;
 use serde::Serializer;
-use serde_json::value::RawValue;
 pub(crate) use util::MakeIter;
+use util::Time;
 
 impl Serialize for Node<'_> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        serialize_node(
-            serializer,
-            self.id,
-            self.call_frame,
-            self.hit_count,
-            self.children.as_ref(),
-            self.deopt_reason,
-            self.position_ticks,
-        )
+        serialize_node(serializer, self, self.children.as_ref())
     }
 }
 
+/// Serializes as the json v8 writes.
+impl Serialize for CallFrame<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(5))?;
+        map.serialize_entry("functionName", &self.function_name)?;
+        map.serialize_entry("scriptId", &self.script_id)?;
+        map.serialize_entry("url", &self.url)?;
+        map.serialize_entry("lineNumber", &self.line_number)?;
+        map.serialize_entry("columnNumber", &self.column_number)?;
+        map.end()
+    }
+}
+
+/// Serializes like a call frame without the script id.
+impl Serialize for FrameKey<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("functionName", &self.function_name)?;
+        map.serialize_entry("url", &self.url)?;
+        map.serialize_entry("lineNumber", &self.line_number)?;
+        map.serialize_entry("columnNumber", &self.column_number)?;
+        map.end()
+    }
+}
+
+impl Profile<'_> {
+    /// Converts to parsed json in the cpuprofile format.
+    ///
+    /// # Errors
+    ///
+    /// If a node's raw json is invalid.
+    pub fn to_value(&self) -> Result<serde_json::Value, serde_json::Error> {
+        serde_json::to_value(self)
+    }
+
+    /// Serializes the profile, parses it back with the default options and
+    /// checks nothing changed, for fuzzers and tests of code that builds or
+    /// edits profiles.
+    ///
+    /// # Errors
+    ///
+    /// If serializing or parsing fails, or with the first field that
+    /// differs.
+    pub fn roundtrip_check(&self) -> Result<(), serde_json::Error> {
+        let json = serde_json::to_vec(self)?;
+        let parsed: Profile = serde_json::from_slice(&json)?;
+        let differs = |what: &str| {
+            Err(serde::ser::Error::custom(alloc::format!(
+                "{} differs",
+                what
+            )))
+        };
+        if parsed.nodes.len() != self.nodes.len() {
+            return differs("number of nodes");
+        }
+        for (a, b) in self.nodes.iter().zip(&parsed.nodes) {
+            if a.id != b.id
+                || a.parent_id != b.parent_id
+                || a.call_frame != b.call_frame
+                || a.hit_count != b.hit_count
+                || a.children != b.children
+                || a.deopt_reason != b.deopt_reason
+                || a.position_ticks != b.position_ticks
+                || a.extra_fields.len() != b.extra_fields.len()
+            {
+                return differs(&alloc::format!("node {}", a.id));
+            }
+        }
+        if parsed.start_time != self.start_time || parsed.end_time != self.end_time {
+            return differs("time range");
+        }
+        if parsed.samples != self.samples {
+            return differs("samples");
+        }
+        if parsed.metadata != self.metadata {
+            return differs("metadata");
+        }
+        if parsed.sampling_interval != self.sampling_interval {
+            return differs("sampling interval");
+        }
+        Ok(())
+    }
+}
+
+impl<'raw> Profile<'raw> {
+    fn header(&self) -> ProfileHeader<'_, 'raw> {
+        ProfileHeader {
+            start_time: Time(self.start_time, self.exact_times.0),
+            end_time: Time(self.end_time, self.exact_times.1),
+            sampling_interval: self.sampling_interval,
+            metadata: self.metadata.as_ref(),
+            extra_fields: &self.extra_fields,
+        }
+    }
+}
+
+/// The fields of a profile besides its nodes and samples.
+struct ProfileHeader<'a, 'raw> {
+    start_time: Time,
+    end_time: Time,
+    sampling_interval: Option<Micros>,
+    metadata: Option<&'a RawJson<'raw>>,
+    extra_fields: &'a [(Cow<'raw, str>, RawJson<'raw>)],
+}
+
 impl Serialize for Profile<'_> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        serialize_profile(
-            serializer,
-            &self.nodes,
-            &self.start_time,
-            &self.end_time,
-            &self.samples,
-        )
+        let _span = span!(
+            "serialize_profile",
+            nodes = self.nodes.len(),
+            samples = self.samples.len(),
+        );
+        serialize_profile(serializer, &self.nodes, &self.samples, &self.header())
     }
 }
 
-fn serialize_node<'raw, S, C>(
-    serializer: S,
-    id: u64,
-    call_frame: &'raw RawValue,
-    hit_count: u32,
-    children: Option<&C>,
-    deopt_reason: Option<&'raw RawValue>,
-    position_ticks: Option<&'raw RawValue>,
-) -> Result<S::Ok, S::Error>
+/// Serializes `node` with `children` in place of its own.
+fn serialize_node<S, C>(serializer: S, node: &Node, children: Option<&C>) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
     C: Serialize,
 {
     let mut map = serializer.serialize_map(None)?;
-    map.serialize_entry(&"id", &id)?;
-    map.serialize_entry(&"callFrame", call_frame)?;
-    map.serialize_entry(&"hitCount", &hit_count)?;
+    map.serialize_entry(&"id", &node.id)?;
+    map.serialize_entry(&"callFrame", &node.call_frame)?;
+    map.serialize_entry(&"hitCount", &node.hit_count)?;
     if let Some(ref children) = children {
         map.serialize_entry(&"children", children)?;
     }
-    if let Some(deopt_reason) = deopt_reason {
+    if let Some(ref deopt_reason) = node.deopt_reason {
         map.serialize_entry(&"deoptReason", deopt_reason)?;
     }
-    if let Some(position_ticks) = position_ticks {
+    if let Some(ref position_ticks) = node.position_ticks {
         map.serialize_entry(&"positionTicks", position_ticks)?;
     }
+    for (key, value) in &node.extra_fields {
+        map.serialize_entry(key, value)?;
+    }
     map.end()
 }
 
-fn serialize_profile<'raw, 'iter, S, N, I>(
+fn serialize_profile<'iter, S, N, I>(
     serializer: S,
     nodes: &N,
-    start_time: &Duration,
-    end_time: &Duration,
     samples: I,
+    header: &ProfileHeader,
 ) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
@@ -87,21 +198,30 @@ where
 {
     let mut map = serializer.serialize_map(None)?;
     map.serialize_entry("nodes", &nodes)?;
-    map.serialize_entry("startTime", &start_time.as_micros())?;
-    map.serialize_entry("endTime", &end_time.as_micros())?;
+    map.serialize_entry("startTime", &header.start_time)?;
+    map.serialize_entry("endTime", &header.end_time)?;
     let sample_node_ids: MakeIter<_> = (|| samples.into_iter().map(|s| s.node_id)).into();
     map.serialize_entry("samples", &sample_node_ids)?;
     let sample_time_deltas: MakeIter<_> = (|| {
-        let mut last = 0;
+        // signed, samples kept in recorded order may go back in time
+        let mut last = Micros::ZERO;
         samples.into_iter().map(move |sample| {
-            let ts = sample.ts.as_micros();
-            let delta = ts - last;
-            last = ts;
+            let delta = sample.ts.delta_since(last);
+            last = sample.ts;
             delta
         })
     })
     .into();
     map.serialize_entry("timeDeltas", &sample_time_deltas)?;
+    if let Some(metadata) = header.metadata {
+        map.serialize_entry("metadata", metadata)?;
+    }
+    if let Some(sampling_interval) = header.sampling_interval {
+        map.serialize_entry("samplingInterval", &sampling_interval.0)?;
+    }
+    for (key, value) in header.extra_fields {
+        map.serialize_entry(key, value)?;
+    }
     map.end()
 }
 
@@ -110,13 +230,7 @@ impl Serialize for ProfileChunk<'_, '_> {
     where
         S: Serializer,
     {
-        serialize_profile(
-            serializer,
-            &self.nodes(),
-            &self.profile.start_time,
-            &self.profile.end_time,
-            self.samples,
-        )
+        options::ChunkWithOptions(self, SerializeOptions::default()).serialize(serializer)
     }
 }
 
@@ -125,27 +239,20 @@ impl Serialize for FilteredNode<'_, '_, '_> {
     where
         S: Serializer,
     {
-        serialize_node(
-            serializer,
-            self.0.id,
-            self.0.call_frame,
-            self.0.hit_count,
-            self.children().as_ref(),
-            self.0.deopt_reason,
-            self.0.position_ticks,
-        )
+        serialize_node(serializer, self.0, self.filtered_children().as_ref())
     }
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
+    /// The profile most tests read, shared by the tests of every module.
+    pub(crate) const PROFILE: &str = core::include_str!("../../tests/fixture.cpuprofile");
     use alloc::vec::Vec;
 
     #[test]
     fn round_trip_serialization() {
-        const PROFILE: &str = core::include_str!("../../tests/fixture.cpuprofile");
-
         let profile: crate::Profile<'_> = serde_json::from_str(PROFILE).unwrap();
+        profile.roundtrip_check().unwrap();
 
         let json = serde_json::to_string(&profile).unwrap();
 
@@ -161,4 +268,179 @@ mod tests {
 
         assert_eq!(json, PROFILE);
     }
+
+    #[test]
+    fn owned_round_trip() {
+        let borrowed: crate::Profile<'_> = serde_json::from_slice(PROFILE.as_bytes()).unwrap();
+        assert_eq!(serde_json::to_string(&borrowed).unwrap(), PROFILE);
+
+        // a Value can't lend raw json
+        let value: serde_json::Value = serde_json::from_str(PROFILE).unwrap();
+        let owned = crate::Profile::from_value(&value).unwrap();
+        assert_eq!(owned.to_value().unwrap(), value);
+        assert_eq!(owned.nodes.len(), borrowed.nodes.len());
+        assert_eq!(
+            owned.nodes[1].frame().unwrap(),
+            borrowed.nodes[1].frame().unwrap()
+        );
+        assert_eq!(
+            owned.deopts().unwrap().total,
+            borrowed.deopts().unwrap().total
+        );
+    }
+
+    #[test]
+    fn traversal() {
+        let profile: crate::Profile<'_> = serde_json::from_str(PROFILE).unwrap();
+        let root = profile.root().unwrap().id;
+        assert_eq!(root, 1);
+
+        let dfs: Vec<_> = profile.dfs(root).collect();
+        let mut bfs: Vec<_> = profile.bfs(root).collect();
+        assert_eq!(dfs.len(), profile.nodes.len() - 1);
+        // every node comes after its parent
+        for order in &[&dfs, &bfs] {
+            for (index, &node_id) in order.iter().enumerate() {
+                let parent_id = profile[node_id].parent_id.unwrap();
+                assert!(parent_id == root || order[..index].contains(&parent_id));
+            }
+        }
+        let depth = |node_id| profile.parent_ids_iter(node_id).count();
+        assert!(bfs.windows(2).all(|w| depth(w[0]) <= depth(w[1])));
+        bfs.sort_unstable();
+        let mut sorted = dfs.clone();
+        sorted.sort_unstable();
+        assert_eq!(bfs, sorted);
+    }
+
+    #[test]
+    fn filtered_node_accessors() {
+        let profile: crate::Profile<'_> = serde_json::from_str(PROFILE).unwrap();
+        let chunk = profile.chunks(2).next().unwrap();
+
+        for node in chunk.nodes() {
+            let json = serde_json::to_value(&node).unwrap();
+            assert_eq!(json["id"], node.id());
+            assert_eq!(json["hitCount"], node.hit_count());
+            assert_eq!(
+                json["callFrame"]["functionName"],
+                *node.frame().unwrap().function_name
+            );
+            let children: Vec<_> = node.children().collect();
+            match json.get("children") {
+                Some(expected) => assert_eq!(*expected, serde_json::json!(children)),
+                None => assert!(children.is_empty()),
+            }
+        }
+    }
+
+    #[test]
+    fn chunk_time_ranges() {
+        let profile: crate::Profile<'_> = serde_json::from_str(PROFILE).unwrap();
+        let ranges: Vec<_> = profile.chunks(3).map(|chunk| chunk.time_range()).collect();
+        assert_eq!(ranges.len(), 3);
+        assert_eq!(ranges[0].start, profile.samples[0].ts);
+        assert!(ranges.windows(2).all(|pair| pair[0].end == pair[1].start));
+        assert_eq!(ranges[2].end.as_duration(), profile.duration());
+    }
+
+    #[test]
+    fn shared_nodes() {
+        let profile: crate::Profile<'_> = serde_json::from_str(PROFILE).unwrap();
+        let table = serde_json::to_string(&crate::ProfileChunk::node_table(&profile)).unwrap();
+        let mut jsons = alloc::vec![table];
+        for mut chunk in profile.chunks(3) {
+            chunk.set_shared_nodes(true);
+            assert!(chunk.nodes().into_iter().count() > 0);
+            let json = serde_json::to_string(&chunk).unwrap();
+            assert!(json.starts_with(r#"{"nodes":[],"#));
+            jsons.push(json);
+        }
+        let parts: Vec<crate::Profile<'_>> = jsons
+            .iter()
+            .map(|json| serde_json::from_str(json).unwrap())
+            .collect();
+        assert_eq!(parts[0].nodes.len(), profile.nodes.len());
+        assert!(parts[0].samples.is_empty());
+
+        let joined = crate::Profile::join(&parts).unwrap();
+        assert_eq!(serde_json::to_string(&joined).unwrap(), PROFILE);
+    }
+
+    #[test]
+    fn metadata_round_trip() {
+        const PROFILE: &str = r#"{"nodes":[{"id":1,"callFrame":{"functionName":"(root)","scriptId":"0","url":"","lineNumber":-1,"columnNumber":-1},"hitCount":1,"children":[]}],"startTime":0,"endTime":10,"samples":[1],"timeDeltas":[5],"metadata":{"tool":"test","nested":[1,2]}}"#;
+        let profile: crate::Profile<'_> = serde_json::from_str(PROFILE).unwrap();
+        assert_eq!(
+            profile.metadata.as_ref().unwrap().get(),
+            r#"{"tool":"test","nested":[1,2]}"#
+        );
+        assert_eq!(serde_json::to_string(&profile).unwrap(), PROFILE);
+
+        let mut chunk = crate::ProfileChunk::new(&profile, &profile.samples);
+        assert_eq!(serde_json::to_string(&chunk).unwrap(), PROFILE);
+        chunk.set_metadata(None);
+        assert!(!serde_json::to_string(&chunk).unwrap().contains("metadata"));
+    }
+
+    #[test]
+    fn duplicate_timestamps_round_trip() {
+        const PROFILE: &str = r#"{"nodes":[{"id":1,"callFrame":{"functionName":"(root)","scriptId":"0","url":"","lineNumber":-1,"columnNumber":-1},"hitCount":0,"children":[2,3]},{"id":2,"callFrame":{"functionName":"a","scriptId":"1","url":"","lineNumber":0,"columnNumber":0},"hitCount":2,"children":[]},{"id":3,"callFrame":{"functionName":"b","scriptId":"1","url":"","lineNumber":1,"columnNumber":0},"hitCount":2,"children":[]}],"startTime":0,"endTime":30,"samples":[3,2,2,3],"timeDeltas":[5,0,10,0]}"#;
+        let profile: crate::Profile<'_> = serde_json::from_str(PROFILE).unwrap();
+        let node_ids: Vec<_> = profile.samples.iter().map(|s| s.node_id).collect();
+        assert_eq!(node_ids, [3, 2, 2, 3]);
+        assert_eq!(serde_json::to_string(&profile).unwrap(), PROFILE);
+
+        let chunks: Vec<_> = profile.chunks(4).map(|chunk| chunk.time_range()).collect();
+        assert_eq!(
+            chunks
+                .iter()
+                .map(|range| (range.start.0, range.end.0))
+                .collect::<Vec<_>>(),
+            [(5, 5), (5, 15), (15, 15), (15, 30)]
+        );
+    }
+
+    #[test]
+    fn fractional_times() {
+        const PROFILE: &str =
+            r#"{"nodes":[],"startTime":1000.25,"endTime":1010.75,"samples":[1],"timeDeltas":[5]}"#;
+        let profile: crate::Profile<'_> = serde_json::from_str(PROFILE).unwrap();
+        assert_eq!(profile.start_time, crate::Micros(1000));
+        assert_eq!(profile.end_time, crate::Micros(1011));
+        assert_eq!(serde_json::to_string(&profile).unwrap(), PROFILE);
+
+        let mut moved: crate::Profile<'_> = serde_json::from_str(PROFILE).unwrap();
+        moved.start_time = crate::Micros(900);
+        assert!(serde_json::to_string(&moved)
+            .unwrap()
+            .contains(r#""startTime":900,"endTime":1010.75"#));
+    }
+
+    #[test]
+    fn without_samples() {
+        const EMPTY: &str =
+            r#"{"nodes":[],"startTime":5,"endTime":5,"samples":[],"timeDeltas":[]}"#;
+        let profile: crate::Profile<'_> =
+            serde_json::from_str(r#"{"nodes":[],"startTime":5,"endTime":5}"#).unwrap();
+        assert!(profile.samples.is_empty());
+        assert_eq!(serde_json::to_string(&profile).unwrap(), EMPTY);
+        assert_eq!(profile.chunks(4).count(), 0);
+
+        let only_deltas = r#"{"nodes":[],"startTime":5,"endTime":5,"timeDeltas":[1]}"#;
+        assert!(serde_json::from_str::<crate::Profile<'_>>(only_deltas).is_err());
+    }
+
+    #[test]
+    fn samples_with_durations() {
+        let profile: crate::Profile<'_> = serde_json::from_str(
+            r#"{"nodes":[],"startTime":100,"endTime":200,"samples":[1,2,3],"timeDeltas":[10,30,20]}"#,
+        )
+        .unwrap();
+        let durations: Vec<_> = profile
+            .samples_with_durations()
+            .map(|(sample, duration)| (sample.node_id, duration.as_micros()))
+            .collect();
+        assert_eq!(durations, [(1, 30), (2, 20), (3, 40)]);
+    }
 }
@@ -0,0 +1,268 @@
+use super::{serialize_node, serialize_profile, MakeIter, ProfileHeader};
+use crate::{FilteredNode, Node, Profile, ProfileChunk};
+use alloc::string::String;
+use serde::Serialize;
+use serde::Serializer;
+
+/// How [`Profile::write_json`] formats a profile or
+/// [`ProfileChunk::write_json`] a chunk.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct SerializeOptions {
+    /// Indents nested values with two spaces.
+    pub pretty: bool,
+    /// Sorts the keys of every object, including call frames, so equal
+    /// profiles serialize to equal bytes.
+    pub sort_keys: bool,
+    /// Leaves out `children` on nodes without any.
+    pub omit_empty_children: bool,
+}
+
+impl Profile<'_> {
+    /// Serializes the profile to a json string formatted by `options`.
+    ///
+    /// # Errors
+    ///
+    /// If a node's raw json is invalid.
+    pub fn to_json(&self, options: SerializeOptions) -> Result<String, serde_json::Error> {
+        let profile = WithOptions(self, options);
+        let json = match (options.sort_keys, options.pretty) {
+            (true, true) => serde_json::to_string_pretty(&serde_json::to_value(&profile)?),
+            (true, false) => serde_json::to_string(&serde_json::to_value(&profile)?),
+            (false, true) => serde_json::to_string_pretty(&profile),
+            (false, false) => serde_json::to_string(&profile),
+        }?;
+        event!("serialized profile", bytes = json.len());
+        Ok(json)
+    }
+
+    /// Writes the profile as json formatted by `options`.
+    ///
+    /// # Errors
+    ///
+    /// If writing fails or a node's raw json is invalid.
+    #[cfg(feature = "std")]
+    pub fn write_json<W>(
+        &self,
+        writer: W,
+        options: SerializeOptions,
+    ) -> Result<(), serde_json::Error>
+    where
+        W: std::io::Write,
+    {
+        let profile = WithOptions(self, options);
+        if options.sort_keys {
+            // objects in a Value are ordered by key
+            write(writer, &serde_json::to_value(&profile)?, options.pretty)
+        } else {
+            write(writer, &profile, options.pretty)
+        }
+    }
+}
+
+impl ProfileChunk<'_, '_> {
+    /// Writes the chunk as json formatted by `options`, like
+    /// [`Profile::write_json`].
+    ///
+    /// # Errors
+    ///
+    /// If writing fails or a node's raw json is invalid.
+    #[cfg(feature = "std")]
+    pub fn write_json<W>(
+        &self,
+        writer: W,
+        options: SerializeOptions,
+    ) -> Result<(), serde_json::Error>
+    where
+        W: std::io::Write,
+    {
+        let chunk = ChunkWithOptions(self, options);
+        if options.sort_keys {
+            write(writer, &serde_json::to_value(&chunk)?, options.pretty)
+        } else {
+            write(writer, &chunk, options.pretty)
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+fn write<W, T>(writer: W, value: &T, pretty: bool) -> Result<(), serde_json::Error>
+where
+    W: std::io::Write,
+    T: Serialize,
+{
+    if pretty {
+        serde_json::to_writer_pretty(writer, value)
+    } else {
+        serde_json::to_writer(writer, value)
+    }
+}
+
+struct WithOptions<'p, 'raw>(&'p Profile<'raw>, SerializeOptions);
+
+impl Serialize for WithOptions<'_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let WithOptions(profile, options) = *self;
+        let nodes: MakeIter<_> = (move || {
+            profile
+                .nodes
+                .iter()
+                .map(move |node| NodeWithOptions(node, options))
+        })
+        .into();
+        serialize_profile(serializer, &nodes, &profile.samples, &profile.header())
+    }
+}
+
+pub(super) struct ChunkWithOptions<'c, 'p, 'raw>(
+    pub(super) &'c ProfileChunk<'p, 'raw>,
+    pub(super) SerializeOptions,
+);
+
+impl Serialize for ChunkWithOptions<'_, '_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let ChunkWithOptions(chunk, options) = *self;
+        let _span = span!(
+            "serialize_chunk",
+            nodes = chunk.included.len(),
+            samples = chunk.samples.len(),
+        );
+        let header = ProfileHeader {
+            metadata: chunk.metadata.as_ref(),
+            ..chunk.profile.header()
+        };
+        if chunk.shared_nodes {
+            return serialize_profile(serializer, &[(); 0], chunk.samples, &header);
+        }
+        let nodes: MakeIter<_> = (move || {
+            chunk
+                .nodes()
+                .into_iter()
+                .map(move |node| FilteredNodeWithOptions(node, options))
+        })
+        .into();
+        serialize_profile(serializer, &nodes, chunk.samples, &header)
+    }
+}
+
+struct FilteredNodeWithOptions<'p, 'raw, 's>(FilteredNode<'p, 'raw, 's>, SerializeOptions);
+
+impl Serialize for FilteredNodeWithOptions<'_, '_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let node = &self.0;
+        if self.1.omit_empty_children && node.children().next().is_none() {
+            serialize_node(serializer, node.node(), None::<&()>)
+        } else {
+            node.serialize(serializer)
+        }
+    }
+}
+
+struct NodeWithOptions<'p, 'raw>(&'p Node<'raw>, SerializeOptions);
+
+impl Serialize for NodeWithOptions<'_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let node = self.0;
+        let children = node
+            .children
+            .as_ref()
+            .filter(|children| !(self.1.omit_empty_children && children.is_empty()));
+        serialize_node(serializer, node, children)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SerializeOptions;
+    use crate::ser::tests::PROFILE;
+    use crate::Profile;
+
+    #[test]
+    fn to_json() {
+        let profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        let to_json = |options| profile.to_json(options).unwrap();
+
+        assert_eq!(to_json(SerializeOptions::default()), PROFILE);
+
+        let pretty = to_json(SerializeOptions {
+            pretty: true,
+            ..SerializeOptions::default()
+        });
+        assert!(pretty.contains("\n  \"nodes\": ["));
+
+        let canonical = to_json(SerializeOptions {
+            sort_keys: true,
+            ..SerializeOptions::default()
+        });
+        assert!(canonical.starts_with(r#"{"endTime":"#));
+        assert!(canonical.contains(r#""callFrame":{"columnNumber":"#));
+        let reparsed: Profile = serde_json::from_str(&canonical).unwrap();
+        assert_eq!(reparsed.nodes.len(), profile.nodes.len());
+
+        let leaf: Profile = serde_json::from_str(
+            r#"{"nodes":[{"id":1,"callFrame":{},"hitCount":0,"children":[]}],"startTime":0,"endTime":0,"samples":[],"timeDeltas":[]}"#,
+        )
+        .unwrap();
+        let options = SerializeOptions {
+            omit_empty_children: true,
+            ..SerializeOptions::default()
+        };
+        assert!(!leaf.to_json(options).unwrap().contains("children"));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn write_json() {
+        let profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        let options = SerializeOptions {
+            pretty: true,
+            sort_keys: true,
+            omit_empty_children: true,
+        };
+        let mut out = alloc::vec::Vec::new();
+        profile.write_json(&mut out, options).unwrap();
+        assert_eq!(out, profile.to_json(options).unwrap().into_bytes());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn write_chunk_json() {
+        let profile: Profile = serde_json::from_str(
+            r#"{"nodes":[{"id":1,"callFrame":{},"hitCount":0,"children":[2]},{"id":2,"callFrame":{},"hitCount":1,"children":[3]},{"id":3,"callFrame":{},"hitCount":1}],"startTime":0,"endTime":20,"samples":[2,3],"timeDeltas":[5,10]}"#,
+        )
+        .unwrap();
+        let chunk = profile.chunks(2).next().unwrap();
+        let write = |options| {
+            let mut out = alloc::vec::Vec::new();
+            chunk.write_json(&mut out, options).unwrap();
+            alloc::string::String::from_utf8(out).unwrap()
+        };
+        let plain = write(SerializeOptions::default());
+        assert_eq!(plain, serde_json::to_string(&chunk).unwrap());
+        // node 3 is left out of the chunk
+        assert!(plain.contains(r#""children":[]"#));
+
+        let options = SerializeOptions {
+            pretty: true,
+            sort_keys: true,
+            omit_empty_children: true,
+        };
+        let json = write(options);
+        assert!(json.starts_with("{\n  \"endTime\": "));
+        assert!(!json.contains("\"children\": []"));
+        let read: Profile = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.samples.len(), chunk.samples().len());
+        assert_eq!(read.nodes.len(), chunk.nodes().into_iter().count());
+    }
+}
@@ -0,0 +1,27 @@
+use crate::sidecar::Environment;
+use serde::ser::SerializeMap;
+use serde::Serialize;
+use serde::Serializer;
+
+/// Leaves out what isn't known.
+impl Serialize for Environment {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+        let fields = [
+            ("hostname", &self.hostname),
+            ("nodeVersion", &self.node_version),
+            ("v8Version", &self.v8_version),
+            ("cpuModel", &self.cpu_model),
+            ("gitRevision", &self.git_revision),
+        ];
+        for (key, value) in fields {
+            if let Some(value) = value {
+                map.serialize_entry(key, value)?;
+            }
+        }
+        map.end()
+    }
+}
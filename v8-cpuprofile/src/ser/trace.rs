@@ -0,0 +1,166 @@
+use crate::trace::{Trace, Track};
+use serde::ser::{Error, SerializeMap, SerializeSeq};
+use serde::Serialize;
+use serde::Serializer;
+
+impl Serialize for Trace<'_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("traceEvents", &Events(self))?;
+        map.serialize_entry("displayTimeUnit", "ms")?;
+        map.end()
+    }
+}
+
+struct Events<'t, 'a, 'raw>(&'t Trace<'a, 'raw>);
+
+impl Serialize for Events<'_, '_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(None)?;
+        for track in &self.0.tracks {
+            seq.serialize_element(&ThreadName(track))?;
+            let profile = track.profile;
+            for slice in profile.slices() {
+                let frame = profile[slice.node_id].frame().map_err(S::Error::custom)?;
+                // idle time would be drawn as a bar over everything else
+                if frame.function_name == "(idle)" {
+                    continue;
+                }
+                let name = if frame.function_name.is_empty() {
+                    "(anonymous)"
+                } else {
+                    &frame.function_name
+                };
+                seq.serialize_element(&Complete {
+                    track,
+                    name,
+                    ts: (profile.start_time.as_duration() + slice.start).as_micros(),
+                    dur: slice.duration.as_micros(),
+                    url: &frame.url,
+                    line_number: frame.line_number,
+                    column_number: frame.column_number,
+                })?;
+            }
+        }
+        seq.end()
+    }
+}
+
+struct ThreadName<'t, 'a, 'raw>(&'t Track<'a, 'raw>);
+
+impl Serialize for ThreadName<'_, '_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("name", "thread_name")?;
+        map.serialize_entry("ph", "M")?;
+        map.serialize_entry("pid", &self.0.pid)?;
+        map.serialize_entry("tid", &self.0.tid)?;
+        map.serialize_entry("args", &Args::Name(&self.0.name))?;
+        map.end()
+    }
+}
+
+/// A complete event, a slice with a duration.
+struct Complete<'t, 'a, 'raw> {
+    track: &'t Track<'a, 'raw>,
+    name: &'t str,
+    ts: u128,
+    dur: u128,
+    url: &'t str,
+    line_number: i32,
+    column_number: i32,
+}
+
+impl Serialize for Complete<'_, '_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("name", self.name)?;
+        map.serialize_entry("cat", "js")?;
+        map.serialize_entry("ph", "X")?;
+        map.serialize_entry("ts", &self.ts)?;
+        map.serialize_entry("dur", &self.dur)?;
+        map.serialize_entry("pid", &self.track.pid)?;
+        map.serialize_entry("tid", &self.track.tid)?;
+        map.serialize_entry(
+            "args",
+            &Args::Location(self.url, self.line_number, self.column_number),
+        )?;
+        map.end()
+    }
+}
+
+enum Args<'t> {
+    Name(&'t str),
+    Location(&'t str, i32, i32),
+}
+
+impl Serialize for Args<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+        match *self {
+            Args::Name(name) => map.serialize_entry("name", name)?,
+            Args::Location(url, line_number, column_number) => {
+                map.serialize_entry("url", url)?;
+                map.serialize_entry("lineNumber", &line_number)?;
+                map.serialize_entry("columnNumber", &column_number)?;
+            }
+        }
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ser::tests::PROFILE;
+    use crate::trace::{Trace, Track};
+    use crate::Profile;
+    use alloc::string::ToString;
+
+    #[test]
+    fn serialize_trace() {
+        let profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        let trace = Trace {
+            tracks: alloc::vec![
+                Track {
+                    pid: 7,
+                    tid: 0,
+                    name: "main".to_string(),
+                    profile: &profile,
+                },
+                Track {
+                    pid: 7,
+                    tid: 1,
+                    name: "worker 1".to_string(),
+                    profile: &profile,
+                },
+            ],
+        };
+        let json: serde_json::Value =
+            serde_json::from_str(&serde_json::to_string(&trace).unwrap()).unwrap();
+        let events = json["traceEvents"].as_array().unwrap();
+
+        assert_eq!(events[0]["ph"], "M");
+        assert_eq!(events[0]["args"]["name"], "main");
+        let per_track = events.iter().filter(|e| e["tid"] == 1).count();
+        assert_eq!(per_track * 2, events.len());
+        assert!(events
+            .iter()
+            .filter(|e| e["ph"] == "X")
+            .all(|e| e["pid"] == 7 && e["name"] != "(idle)"));
+    }
+}
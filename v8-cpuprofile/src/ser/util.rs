@@ -1,5 +1,25 @@
+use crate::de::round_micros;
+use crate::Micros;
 use serde::ser::{Serialize, Serializer};
 
+/// A profile time in microseconds, written as the float it was read as
+/// while it still rounds to the time.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Time(pub(crate) Micros, pub(crate) Option<f64>);
+
+impl Serialize for Time {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let Time(micros, exact) = *self;
+        match exact {
+            Some(exact) if round_micros(exact) == micros => serializer.serialize_f64(exact),
+            _ => micros.0.serialize(serializer),
+        }
+    }
+}
+
 /// Turns a `fn() -> Iterator` into an `IntoIterator`
 #[derive(Debug)]
 pub(crate) struct MakeIter<F>(F);
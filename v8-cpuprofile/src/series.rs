@@ -0,0 +1,147 @@
+//! How the time of functions changes over an ordered set of profiles, such
+//! as nightly captures, to plot trends.
+
+use crate::{FrameKey, FrameTimes, Profile};
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+use core::time::Duration;
+use hashbrown::HashMap;
+#[cfg(feature = "std")]
+use std::io::{self, Write};
+
+/// The times of each selected function in every run, see
+/// [`TimeSeries::new`].
+#[derive(Debug)]
+pub struct TimeSeries<'raw> {
+    pub runs: usize,
+    /// By self time summed over the runs, heaviest first.
+    pub functions: Vec<FunctionSeries<'raw>>,
+}
+
+#[derive(Debug)]
+pub struct FunctionSeries<'raw> {
+    pub frame: FrameKey<'raw>,
+    /// A point per run in the order the profiles were given, zero for a run
+    /// without the function.
+    pub times: Vec<FrameTimes>,
+}
+
+impl<'raw> TimeSeries<'raw> {
+    /// The self and total time per run of the functions `select` accepts.
+    ///
+    /// # Errors
+    ///
+    /// If a node of any profile has an invalid call frame.
+    pub fn new<F>(profiles: &[Profile<'raw>], mut select: F) -> Result<Self, serde_json::Error>
+    where
+        F: FnMut(&FrameKey) -> bool,
+    {
+        let runs = profiles.len();
+        let mut times: HashMap<FrameKey<'raw>, Vec<FrameTimes>> = HashMap::new();
+        for (run, profile) in profiles.iter().enumerate() {
+            for (frame, frame_times) in profile.frame_times()? {
+                if select(&frame) {
+                    times
+                        .entry(frame)
+                        .or_insert_with(|| alloc::vec![FrameTimes::default(); runs])[run] =
+                        frame_times;
+                }
+            }
+        }
+        let mut functions: Vec<_> = times
+            .into_iter()
+            .map(|(frame, times)| FunctionSeries { frame, times })
+            .collect();
+        functions.sort_by(|a, b| {
+            Reverse(a.self_time())
+                .cmp(&Reverse(b.self_time()))
+                .then_with(|| a.frame.cmp(&b.frame))
+        });
+        Ok(TimeSeries { runs, functions })
+    }
+
+    /// Writes a row per run and function, with `labels` naming the runs,
+    /// times in microseconds. Lines and columns are 1-based.
+    ///
+    /// # Errors
+    ///
+    /// If writing fails.
+    ///
+    /// # Panics
+    ///
+    /// If there isn't a label for each run.
+    #[cfg(feature = "std")]
+    pub fn write_csv<W: Write>(&self, labels: &[&str], mut writer: W) -> io::Result<()> {
+        assert_eq!(labels.len(), self.runs, "a label per run");
+        writeln!(writer, "run,function,url,line,column,self_us,total_us")?;
+        for (run, label) in labels.iter().enumerate() {
+            for function in &self.functions {
+                let frame = &function.frame;
+                writeln!(
+                    writer,
+                    "{},{},{},{},{},{},{}",
+                    crate::format::quote(label),
+                    crate::format::quote(&frame.function_name),
+                    crate::format::quote(&frame.url),
+                    crate::format::position(frame.line_number),
+                    crate::format::position(frame.column_number),
+                    function.times[run].self_time.as_micros(),
+                    function.times[run].total_time.as_micros()
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FunctionSeries<'_> {
+    /// Self time summed over the runs.
+    #[must_use]
+    pub fn self_time(&self) -> Duration {
+        self.times.iter().map(|times| times.self_time).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TimeSeries;
+    use crate::ser::tests::PROFILE;
+    use crate::Profile;
+    #[cfg(feature = "std")]
+    use alloc::{string::String, vec::Vec};
+
+    fn series() -> TimeSeries<'static> {
+        let profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        let empty = Profile::synthesize(&crate::synth::SynthOptions {
+            nodes: 1,
+            samples: 0,
+            ..crate::synth::SynthOptions::default()
+        });
+        TimeSeries::new(&[profile, empty], |frame| {
+            frame.function_name == "WriteStream" || frame.function_name == "(root)"
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn time_series() {
+        let series = series();
+        assert_eq!(series.runs, 2);
+        assert_eq!(series.functions.len(), 2);
+        assert_eq!(series.functions[0].frame.function_name, "WriteStream");
+        assert_eq!(series.functions[0].times[1], crate::FrameTimes::default());
+        assert!(series.functions[0].times[0].self_time > core::time::Duration::default());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn csv() {
+        let mut csv = Vec::new();
+        series().write_csv(&["a", "b,c"], &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert!(lines[1].starts_with("a,WriteStream,node:tty,84,21,"));
+        assert!(lines[3].starts_with("\"b,c\",WriteStream,"));
+    }
+}
@@ -0,0 +1,131 @@
+//! Where a profile was captured, kept next to it in
+//! `<name>.cpuprofile.meta.json` so profiles can later be grouped by
+//! machine, node version or revision.
+
+use alloc::string::String;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The environment of a capture, each part `None` if it couldn't be found
+/// out.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Environment {
+    pub hostname: Option<String>,
+    /// Like `v14.15.0`.
+    pub node_version: Option<String>,
+    pub v8_version: Option<String>,
+    pub cpu_model: Option<String>,
+    /// The commit checked out where the profiled code came from.
+    pub git_revision: Option<String>,
+}
+
+/// The sidecar of a profile, its path with `.meta.json` appended.
+#[must_use]
+pub fn sidecar_path(cpu_profile: &Path) -> PathBuf {
+    let mut path = OsString::from(cpu_profile.as_os_str());
+    path.push(".meta.json");
+    PathBuf::from(path)
+}
+
+impl Environment {
+    /// Reads the sidecar of a profile, `None` if it has none. Fields this
+    /// version doesn't know are skipped.
+    ///
+    /// # Errors
+    ///
+    /// If the sidecar can't be read or isn't a json object.
+    pub fn read(cpu_profile: &Path) -> io::Result<Option<Self>> {
+        let json = match fs::read(sidecar_path(cpu_profile)) {
+            Ok(json) => json,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error),
+        };
+        Ok(Some(serde_json::from_slice(&json)?))
+    }
+
+    /// Writes the sidecar of a profile, replacing any there is.
+    ///
+    /// # Errors
+    ///
+    /// If the sidecar can't be written.
+    pub fn write(&self, cpu_profile: &Path) -> io::Result<()> {
+        let mut json = serde_json::to_vec_pretty(self)?;
+        json.push(b'\n');
+        fs::write(sidecar_path(cpu_profile), json)
+    }
+
+    /// A short description for grouping, like
+    /// `ci-7 node v14.15.0 Intel(R) Xeon(R) CPU @ 2.20GHz at 1a2b3c4`,
+    /// leaving out what isn't known.
+    #[must_use]
+    pub fn label(&self) -> String {
+        let mut parts = alloc::vec::Vec::new();
+        if let Some(hostname) = &self.hostname {
+            parts.push(hostname.clone());
+        }
+        if let Some(node_version) = &self.node_version {
+            parts.push(alloc::format!("node {}", node_version));
+        }
+        if let Some(cpu_model) = &self.cpu_model {
+            parts.push(cpu_model.clone());
+        }
+        if let Some(git_revision) = &self.git_revision {
+            let short = git_revision.get(..7).unwrap_or(git_revision);
+            parts.push(alloc::format!("at {}", short));
+        }
+        if parts.is_empty() {
+            String::from("unknown environment")
+        } else {
+            parts.join(" ")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sidecar_path, Environment};
+    use alloc::string::String;
+    use std::path::Path;
+
+    #[test]
+    fn sidecar() {
+        assert_eq!(
+            sidecar_path(Path::new("out/CPU.1.cpuprofile")),
+            Path::new("out/CPU.1.cpuprofile.meta.json")
+        );
+
+        let environment = Environment {
+            hostname: Some(String::from("ci-7")),
+            node_version: Some(String::from("v14.15.0")),
+            v8_version: Some(String::from("8.4.371.19-node.17")),
+            cpu_model: None,
+            git_revision: Some(String::from("1a2b3c4d5e6f")),
+        };
+        let json = serde_json::to_string(&environment).unwrap();
+        assert!(!json.contains("cpuModel"));
+        assert_eq!(
+            serde_json::from_str::<Environment>(&json).unwrap(),
+            environment
+        );
+        let newer = r#"{"hostname":"ci-7","arch":"arm64"}"#;
+        assert_eq!(
+            serde_json::from_str::<Environment>(newer)
+                .unwrap()
+                .hostname
+                .as_deref(),
+            Some("ci-7")
+        );
+        assert_eq!(environment.label(), "ci-7 node v14.15.0 at 1a2b3c4");
+        assert_eq!(Environment::default().label(), "unknown environment");
+
+        let dir = std::env::temp_dir().join(alloc::format!("sidecar-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let cpu_profile = dir.join("a.cpuprofile");
+        assert_eq!(Environment::read(&cpu_profile).unwrap(), None);
+        environment.write(&cpu_profile).unwrap();
+        assert_eq!(Environment::read(&cpu_profile).unwrap(), Some(environment));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,89 @@
+use crate::{FrameKey, Profile};
+use alloc::vec::Vec;
+use core::time::Duration;
+use hashbrown::HashMap;
+
+/// Self time of one distinct stack, see [`Profile::subtract`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackTime<'raw> {
+    /// Frames from the root down to the sampled function.
+    pub stack: Vec<FrameKey<'raw>>,
+    pub self_time: Duration,
+}
+
+impl<'raw> Profile<'raw> {
+    /// Sums the self time of each distinct stack of frames, so nodes of
+    /// different profiles can be matched.
+    ///
+    /// # Errors
+    ///
+    /// If a node has an invalid call frame.
+    pub fn stack_times(&self) -> Result<HashMap<Vec<FrameKey<'raw>>, Duration>, serde_json::Error> {
+        let mut stack_times: HashMap<Vec<FrameKey<'raw>>, Duration> = HashMap::new();
+        for (node_id, self_time) in self.self_times() {
+            let mut stack = Vec::new();
+            stack.push(FrameKey::from(self[node_id].frame()?));
+            for parent_id in self.parent_ids_iter(node_id) {
+                stack.push(FrameKey::from(self[parent_id].frame()?));
+            }
+            stack.reverse();
+            *stack_times.entry(stack).or_default() += self_time;
+        }
+        Ok(stack_times)
+    }
+
+    /// The time of each stack minus its time in `baseline`, clamped at
+    /// zero, to isolate what a change costs by comparing captures with and
+    /// without it. Stacks that didn't get slower are left out, the rest are
+    /// ordered by the remaining time.
+    ///
+    /// # Errors
+    ///
+    /// If a node of either profile has an invalid call frame.
+    pub fn subtract(&self, baseline: &Profile) -> Result<Vec<StackTime<'raw>>, serde_json::Error> {
+        let baseline = baseline.stack_times()?;
+        let mut stacks: Vec<StackTime<'raw>> = self
+            .stack_times()?
+            .into_iter()
+            .filter_map(|(stack, self_time)| {
+                let self_time =
+                    self_time.saturating_sub(baseline.get(&stack).copied().unwrap_or_default());
+                if self_time > Duration::default() {
+                    Some(StackTime { stack, self_time })
+                } else {
+                    None
+                }
+            })
+            .collect();
+        stacks.sort_by(|a, b| {
+            b.self_time
+                .cmp(&a.self_time)
+                .then_with(|| a.stack.cmp(&b.stack))
+        });
+        Ok(stacks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ser::tests::PROFILE;
+    use crate::Profile;
+    use core::time::Duration;
+
+    #[test]
+    fn subtract_self_is_empty() {
+        let profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        assert_eq!(profile.subtract(&profile).unwrap(), []);
+
+        let empty: Profile = serde_json::from_str(
+            r#"{"nodes":[],"startTime":0,"endTime":0,"samples":[],"timeDeltas":[]}"#,
+        )
+        .unwrap();
+        let stacks = profile.subtract(&empty).unwrap();
+        let total: Duration = stacks.iter().map(|stack| stack.self_time).sum();
+        assert_eq!(total, profile.self_times().values().sum());
+        assert!(stacks
+            .windows(2)
+            .all(|pair| pair[0].self_time >= pair[1].self_time));
+    }
+}
@@ -0,0 +1,93 @@
+//! Reading and writing profiles with `std::io`, for callers that don't need
+//! to borrow from a mapped file.
+//!
+//! Errors are [`io::Error`]s, or `serde_json::Error`s from parsing, which
+//! already implement [`std::error::Error`]. The crate's own error types,
+//! [`FilterError`](crate::filter::FilterError) and
+//! [`Failure`](crate::conformance::Failure), implement it under the `std`
+//! feature too.
+
+use crate::{Profile, SerializeOptions};
+use alloc::vec::Vec;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+
+impl Profile<'static> {
+    /// Reads `reader` to the end and parses a profile that owns its raw
+    /// json, see [`Profile::deserialize_owned`].
+    ///
+    /// # Errors
+    ///
+    /// If reading fails or the input is not a valid profile.
+    pub fn from_reader<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        event!("read profile", bytes = bytes.len());
+        let mut deserializer = serde_json::Deserializer::from_slice(&bytes);
+        let profile = Self::deserialize_owned(&mut deserializer)?;
+        deserializer.end()?;
+        Ok(profile)
+    }
+
+    /// Reads and parses the profile in a file, see [`Profile::from_reader`].
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be read or is not a valid profile.
+    pub fn from_path(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_reader(File::open(path)?)
+    }
+}
+
+impl Profile<'_> {
+    /// Creates or truncates a file and writes the profile to it as json
+    /// formatted by `options`.
+    ///
+    /// # Errors
+    ///
+    /// If writing fails or a node's raw json is invalid.
+    pub fn write_to_path(
+        &self,
+        path: impl AsRef<Path>,
+        options: SerializeOptions,
+    ) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_json(&mut writer, options)?;
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ser::tests::PROFILE;
+    use crate::{Profile, SerializeOptions};
+
+    #[test]
+    fn round_trip() {
+        let profile = Profile::from_reader(PROFILE.as_bytes()).unwrap();
+        let path = std::env::temp_dir().join(alloc::format!(
+            "v8-cpuprofile-std-io-{}.cpuprofile",
+            std::process::id()
+        ));
+        profile
+            .write_to_path(&path, SerializeOptions::default())
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), PROFILE);
+
+        let read = Profile::from_path(&path).unwrap();
+        assert_eq!(read.samples, profile.samples);
+        std::fs::remove_file(&path).unwrap();
+
+        let error = Profile::from_reader(&b"{}"[..]).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn errors() {
+        fn is_error<E: std::error::Error + Send + Sync + 'static>() {}
+        is_error::<crate::filter::FilterError>();
+        is_error::<crate::conformance::Failure>();
+        is_error::<serde_json::Error>();
+    }
+}
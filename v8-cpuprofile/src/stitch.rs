@@ -0,0 +1,119 @@
+use crate::{FrameKey, FrameTimes, Profile};
+use alloc::vec::Vec;
+use hashbrown::HashMap;
+
+/// Functions that resume async functions after an `await`, a continuation
+/// is sampled with one of these as its parent instead of its caller.
+pub const ASYNC_BOUNDARIES: &[&str] = &["processTicksAndRejections", "runMicrotasks"];
+
+impl<'raw> Profile<'raw> {
+    /// Maps each continuation, a node whose parent is an async boundary, to
+    /// the node where the same function was called synchronously.
+    ///
+    /// The profile doesn't record which call a continuation belongs to, so a
+    /// continuation is only stitched when its function has exactly one
+    /// synchronous call site.
+    ///
+    /// # Errors
+    ///
+    /// If a node has an invalid call frame.
+    pub fn async_callers(&self) -> Result<HashMap<u64, u64>, serde_json::Error> {
+        let mut keys = Vec::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            keys.push(FrameKey::from(node.frame()?));
+        }
+        let key = |node_id: u64| &keys[self.node_index[&node_id]];
+        let is_boundary =
+            |node_id: u64| ASYNC_BOUNDARIES.contains(&key(node_id).function_name.as_ref());
+
+        let mut sync_calls: HashMap<&FrameKey, Vec<u64>> = HashMap::new();
+        let mut continuations = Vec::new();
+        for node in &self.nodes {
+            if is_boundary(node.id) {
+                continue;
+            }
+            match node.parent_id {
+                Some(parent_id) if is_boundary(parent_id) => continuations.push(node.id),
+                _ => {
+                    if !self.parent_ids_iter(node.id).any(is_boundary) {
+                        sync_calls.entry(key(node.id)).or_default().push(node.id);
+                    }
+                }
+            }
+        }
+
+        let mut callers = HashMap::new();
+        for node_id in continuations {
+            if let Some(&[caller]) = sync_calls.get(key(node_id)).map(Vec::as_slice) {
+                callers.insert(node_id, caller);
+            }
+        }
+        Ok(callers)
+    }
+
+    /// Like [`Profile::frame_times`] but the time of a continuation also
+    /// counts toward the callers of its synchronous call, see
+    /// [`Profile::async_callers`].
+    ///
+    /// # Errors
+    ///
+    /// If a node has an invalid call frame.
+    pub fn stitched_frame_times(
+        &self,
+    ) -> Result<HashMap<FrameKey<'raw>, FrameTimes>, serde_json::Error> {
+        let callers = self.async_callers()?;
+        self.frame_times_by(|node_id| match callers.get(&node_id) {
+            Some(&caller) => self[caller].parent_id,
+            None => self[node_id].parent_id,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{FrameKey, Profile};
+    use alloc::format;
+
+    #[test]
+    fn stitch_continuation() {
+        let frame = |name: &str| {
+            format!(
+                r#"{{"functionName":"{}","scriptId":"1","url":"app.js","lineNumber":0,"columnNumber":0}}"#,
+                name
+            )
+        };
+        let node = |id: u64, name: &str, children: &[u64]| {
+            format!(
+                r#"{{"id":{},"callFrame":{},"hitCount":0,"children":{:?}}}"#,
+                id,
+                frame(name),
+                children
+            )
+        };
+        // main calls load synchronously, load resumes after an await
+        let json = format!(
+            r#"{{"nodes":[{},{},{},{},{}],"startTime":0,"endTime":400,"samples":[3,3,5,5],"timeDeltas":[0,100,100,100]}}"#,
+            node(1, "(root)", &[2, 4]),
+            node(2, "main", &[3]),
+            node(3, "load", &[]),
+            node(4, "processTicksAndRejections", &[5]),
+            node(5, "load", &[]),
+        );
+        let profile: Profile = serde_json::from_str(&json).unwrap();
+
+        let callers = profile.async_callers().unwrap();
+        assert_eq!(callers.get(&5), Some(&3));
+
+        let main = |times: &hashbrown::HashMap<FrameKey, crate::FrameTimes>| {
+            times
+                .iter()
+                .find(|(key, _)| key.function_name == "main")
+                .unwrap()
+                .1
+                .total_time
+                .as_micros()
+        };
+        assert_eq!(main(&profile.frame_times().unwrap()), 200);
+        assert_eq!(main(&profile.stitched_frame_times().unwrap()), 400);
+    }
+}
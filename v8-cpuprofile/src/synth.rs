@@ -0,0 +1,235 @@
+//! Synthetic profiles of a configurable shape, for benchmarks and for
+//! fixtures larger than real captures that can be checked in.
+
+use crate::{Micros, Node, Profile, RawJson, Sample, Weighting};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::time::Duration;
+use hashbrown::HashMap;
+
+/// The shape of a profile made by [`Profile::synthesize`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SynthOptions {
+    /// Nodes including the root, at least 1.
+    pub nodes: usize,
+    /// Deepest a node may be below the root, at least 1.
+    pub max_depth: usize,
+    pub samples: usize,
+    /// Time between samples.
+    pub interval: Duration,
+    pub hotspots: Hotspots,
+    /// The same seed and options make the same profile.
+    pub seed: u64,
+}
+
+impl Default for SynthOptions {
+    fn default() -> Self {
+        SynthOptions {
+            nodes: 100,
+            max_depth: 16,
+            samples: 1000,
+            interval: Duration::from_millis(1),
+            hotspots: Hotspots::Uniform,
+            seed: 0,
+        }
+    }
+}
+
+/// How samples are spread over the nodes.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Hotspots {
+    /// Every node below the root is as likely.
+    Uniform,
+    /// The nth hottest node is sampled in proportion to `1 / n^s`, so a
+    /// higher exponent concentrates samples in fewer nodes.
+    Zipf(f64),
+}
+
+impl Profile<'static> {
+    /// Generates a random tree of `options.nodes` nodes and samples it at a
+    /// fixed interval starting at time 0.
+    #[must_use]
+    pub fn synthesize(options: &SynthOptions) -> Self {
+        let mut rng = Rng::new(options.seed);
+        let len = options.nodes.max(1);
+        let max_depth = options.max_depth.max(1);
+
+        let mut nodes: Vec<Node<'static>> = Vec::with_capacity(len);
+        let mut depths = Vec::with_capacity(len);
+        // nodes that can still have children
+        let mut parents = Vec::new();
+        for index in 0..len {
+            let id = index as u64 + 1;
+            let parent = if index == 0 {
+                None
+            } else {
+                Some(parents[rng.below(parents.len())])
+            };
+            let depth = parent.map_or(0, |parent: usize| depths[parent] + 1);
+            if let Some(parent) = parent {
+                if let Some(ref mut children) = nodes[parent].children {
+                    children.push(id);
+                }
+            }
+            if depth < max_depth {
+                parents.push(index);
+            }
+            depths.push(depth);
+            nodes.push(Node {
+                id,
+                parent_id: parent.map(|parent| nodes[parent].id),
+                call_frame: call_frame(index),
+                hit_count: 0,
+                children: Some(Vec::new()),
+                deopt_reason: None,
+                position_ticks: None,
+                extra_fields: Vec::new(),
+            });
+        }
+
+        let weights = weights(options.hotspots, len - 1, &mut rng);
+        let total: f64 = weights.iter().sum();
+        let cumulative: Vec<f64> = weights
+            .iter()
+            .scan(0.0, |sum, weight| {
+                *sum += weight;
+                Some(*sum)
+            })
+            .collect();
+        let mut samples = Vec::with_capacity(options.samples);
+        let interval = Micros::from(options.interval);
+        let mut ts = Micros::ZERO;
+        for _ in 0..options.samples {
+            // the root alone is sampled when it has no children
+            let index = if cumulative.is_empty() {
+                0
+            } else {
+                let target = rng.unit() * total;
+                1 + cumulative
+                    .partition_point(|&sum| sum <= target)
+                    .min(cumulative.len() - 1)
+            };
+            nodes[index].hit_count += 1;
+            samples.push(Sample {
+                node_id: nodes[index].id,
+                ts,
+            });
+            ts += interval;
+        }
+
+        let node_index: HashMap<u64, usize> = nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (node.id, index))
+            .collect();
+        Profile {
+            nodes,
+            start_time: Micros::ZERO,
+            end_time: ts,
+            samples,
+            metadata: None,
+            sampling_interval: Some(interval),
+            extra_fields: Vec::new(),
+            exact_times: (None, None),
+            node_index,
+            weighting: Weighting::default(),
+        }
+    }
+}
+
+fn call_frame(index: usize) -> RawJson<'static> {
+    let json = if index == 0 {
+        String::from(
+            r#"{"functionName":"(root)","scriptId":"0","url":"","lineNumber":-1,"columnNumber":-1}"#,
+        )
+    } else {
+        format!(
+            r#"{{"functionName":"fn{}","scriptId":"1","url":"synth.js","lineNumber":{},"columnNumber":0}}"#,
+            index, index
+        )
+    };
+    RawJson::from_string(json).expect("a call frame is valid json")
+}
+
+/// The weight of each node below the root.
+fn weights(hotspots: Hotspots, len: usize, rng: &mut Rng) -> Vec<f64> {
+    match hotspots {
+        Hotspots::Uniform => alloc::vec![1.0; len],
+        Hotspots::Zipf(exponent) => {
+            // ranks in a random order so the hottest nodes aren't all near
+            // the root
+            let mut ranks: Vec<usize> = (1..=len).collect();
+            for index in (1..len).rev() {
+                ranks.swap(index, rng.below(index + 1));
+            }
+            #[allow(clippy::cast_precision_loss)]
+            ranks
+                .into_iter()
+                .map(|rank| 1.0 / libm::pow(rank as f64, exponent))
+                .collect()
+        }
+    }
+}
+
+/// xorshift64*, small and deterministic across platforms.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng((seed ^ 0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+
+    /// In `0.0..1.0`.
+    #[allow(clippy::cast_precision_loss)]
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1_u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Hotspots, SynthOptions};
+    use crate::{Micros, Profile};
+
+    #[test]
+    fn synthesize() {
+        let options = SynthOptions {
+            nodes: 200,
+            max_depth: 4,
+            samples: 5000,
+            hotspots: Hotspots::Zipf(1.5),
+            ..SynthOptions::default()
+        };
+        let profile = Profile::synthesize(&options);
+        profile.roundtrip_check().unwrap();
+        assert_eq!(profile.nodes.len(), 200);
+        assert_eq!(profile.samples.len(), 5000);
+        assert_eq!(profile.end_time, Micros(5_000_000));
+        assert!(profile
+            .nodes
+            .iter()
+            .all(|node| profile.parent_ids_iter(node.id).count() <= 4));
+
+        // most samples land in a few nodes
+        let mut hits: alloc::vec::Vec<_> =
+            profile.nodes.iter().map(|node| node.hit_count).collect();
+        hits.sort_unstable_by(|a, b| b.cmp(a));
+        assert!(hits[..10].iter().sum::<u32>() > 2500);
+
+        let again = Profile::synthesize(&options);
+        assert_eq!(again.samples, profile.samples);
+    }
+}
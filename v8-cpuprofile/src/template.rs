@@ -0,0 +1,135 @@
+//! `{name}` placeholders in file names and urls, shared by the names of
+//! split chunks and [`SourceLinks`](crate::links::SourceLinks).
+//!
+//! A placeholder can be padded to a width like `{num:3}` or with zeros like
+//! `{num:03}`, `{{` and `}}` are literal braces.
+
+use alloc::string::String;
+use core::fmt::{self, Write};
+
+/// Why a template didn't render.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateError {
+    /// A `{` without a `}`.
+    Unclosed,
+    /// A `}` without a `{`, write `}}` for a brace.
+    Unmatched,
+    /// The name of a placeholder that isn't known.
+    Unknown(String),
+    /// The placeholder with a width that isn't a number.
+    Width(String),
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::Unclosed => f.write_str("unclosed placeholder"),
+            TemplateError::Unmatched => f.write_str("unmatched }"),
+            TemplateError::Unknown(name) => write!(f, "unknown placeholder {{{}}}", name),
+            TemplateError::Width(placeholder) => write!(f, "invalid width in {{{}}}", placeholder),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TemplateError {}
+
+/// Replaces each placeholder of `template` with what `value` writes for its
+/// name, `value` returns false for names it doesn't know.
+///
+/// # Errors
+///
+/// If a brace is unmatched, `value` doesn't know a name or a width is
+/// invalid.
+pub fn render<F>(template: &str, mut value: F) -> Result<String, TemplateError>
+where
+    F: FnMut(&str, &mut String) -> bool,
+{
+    let mut out = String::with_capacity(template.len());
+    let mut filled = String::new();
+    let mut rest = template;
+    while let Some(brace) = rest.find(&['{', '}'][..]) {
+        out.push_str(&rest[..brace]);
+        rest = &rest[brace..];
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            out.push_str(&rest[..1]);
+            rest = &rest[2..];
+            continue;
+        }
+        if rest.starts_with('}') {
+            return Err(TemplateError::Unmatched);
+        }
+        let close = rest.find('}').ok_or(TemplateError::Unclosed)?;
+        let placeholder = &rest[1..close];
+        let (name, spec) = placeholder.split_once(':').unwrap_or((placeholder, ""));
+        let width: usize = if spec.is_empty() {
+            0
+        } else {
+            spec.parse()
+                .map_err(|_| TemplateError::Width(placeholder.into()))?
+        };
+        filled.clear();
+        if !value(name, &mut filled) {
+            return Err(TemplateError::Unknown(name.into()));
+        }
+        let _ = if spec.starts_with('0') {
+            write!(out, "{:0>width$}", filled, width = width)
+        } else {
+            write!(out, "{:>width$}", filled, width = width)
+        };
+        rest = &rest[close + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render, TemplateError};
+    use alloc::string::{String, ToString};
+
+    fn fill(template: &str) -> Result<String, TemplateError> {
+        render(template, |name, value| {
+            match name {
+                "stem" => value.push_str("trace"),
+                "num" => value.push('7'),
+                "empty" => {}
+                _ => return false,
+            }
+            true
+        })
+    }
+
+    #[test]
+    fn placeholders() {
+        assert_eq!(fill("{stem}.part{num}").unwrap(), "trace.part7");
+        assert_eq!(fill("{num:03}-{num:3}-{empty}.").unwrap(), "007-  7-.");
+        assert_eq!(fill("no placeholders").unwrap(), "no placeholders");
+        assert_eq!(fill("").unwrap(), "");
+    }
+
+    #[test]
+    fn unknown_placeholders() {
+        assert_eq!(
+            fill("{stem}{x}"),
+            Err(TemplateError::Unknown(String::from("x")))
+        );
+        assert_eq!(
+            fill("{num:x}"),
+            Err(TemplateError::Width(String::from("num:x")))
+        );
+        assert_eq!(fill("{num"), Err(TemplateError::Unclosed));
+        assert_eq!(fill("num}"), Err(TemplateError::Unmatched));
+        assert_eq!(
+            fill("{x}").unwrap_err().to_string(),
+            "unknown placeholder {x}"
+        );
+    }
+
+    #[test]
+    fn escaping() {
+        assert_eq!(fill("{{num}}").unwrap(), "{num}");
+        assert_eq!(fill("{{{num}}}").unwrap(), "{7}");
+        assert_eq!(fill("a}}b{{").unwrap(), "a}b{");
+    }
+}
@@ -0,0 +1,193 @@
+use crate::{CallFrame, Profile};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+/// A run of consecutive samples that all have the node on their stack, one
+/// bar of a flame chart.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Slice {
+    pub node_id: u64,
+    /// 0 for the children of the root.
+    pub depth: usize,
+    /// Relative to the profile's `start_time` like sample timestamps.
+    pub start: Duration,
+    pub duration: Duration,
+}
+
+/// A call reconstructed from the samples, see [`Profile::reconstruct_spans`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span<'raw> {
+    pub frame: CallFrame<'raw>,
+    pub node_id: u64,
+    /// 0 for the children of the root.
+    pub depth: usize,
+    /// Relative to the profile's `start_time` like sample timestamps.
+    pub start: Duration,
+    pub end: Duration,
+}
+
+impl Span<'_> {
+    /// How long the call lasted, as far as the samples tell.
+    #[must_use]
+    pub fn duration(&self) -> Duration {
+        self.end.saturating_sub(self.start)
+    }
+}
+
+/// Profiles of several threads, or processes, merged into one Chrome trace
+/// with a track each, their `start_time` is from the same monotonic clock so
+/// they line up.
+///
+/// It serializes as the Chrome trace event format, which both the Chrome
+/// performance panel and the Firefox profiler load.
+#[derive(Debug)]
+pub struct Trace<'a, 'raw> {
+    pub tracks: Vec<Track<'a, 'raw>>,
+}
+
+#[derive(Debug)]
+pub struct Track<'a, 'raw> {
+    pub pid: u32,
+    pub tid: u32,
+    pub name: String,
+    pub profile: &'a Profile<'raw>,
+}
+
+impl Profile<'_> {
+    /// The flame chart of the samples, ordered by start and then depth. The
+    /// root node is left out.
+    #[must_use]
+    pub fn slices(&self) -> Vec<Slice> {
+        let mut slices = Vec::new();
+        // node ids from below the root up to the sampled node, with the
+        // timestamp of the first sample each was seen in
+        let mut open: Vec<(u64, Duration)> = Vec::new();
+        let mut stack = Vec::new();
+
+        for sample in &self.samples {
+            stack.clear();
+            stack.push(sample.node_id);
+            stack.extend(self.parent_ids_iter(sample.node_id));
+            stack.pop();
+            stack.reverse();
+
+            let common = open
+                .iter()
+                .zip(&stack)
+                .take_while(|((open_id, _), node_id)| open_id == *node_id)
+                .count();
+            let ts = sample.ts.as_duration();
+            close(&mut open, common, ts, &mut slices);
+            open.extend(stack[common..].iter().map(|&node_id| (node_id, ts)));
+        }
+        close(&mut open, 0, self.duration(), &mut slices);
+
+        slices.sort_by_key(|slice| (slice.start, slice.depth));
+        slices
+    }
+}
+
+impl<'raw> Profile<'raw> {
+    /// The calls of the profile, each run of consecutive samples with a
+    /// node on their stack, lasting at least `min_duration`, ordered like
+    /// [`Profile::slices`]. A call sampled twice with a sample of another
+    /// stack in between is two spans.
+    ///
+    /// # Errors
+    ///
+    /// If a node has an invalid call frame.
+    pub fn reconstruct_spans(
+        &self,
+        min_duration: Duration,
+    ) -> Result<Vec<Span<'raw>>, serde_json::Error> {
+        self.slices()
+            .into_iter()
+            .filter(|slice| slice.duration >= min_duration)
+            .map(|slice| {
+                Ok(Span {
+                    frame: self[slice.node_id].frame()?,
+                    node_id: slice.node_id,
+                    depth: slice.depth,
+                    start: slice.start,
+                    end: slice.start + slice.duration,
+                })
+            })
+            .collect()
+    }
+}
+
+fn close(open: &mut Vec<(u64, Duration)>, keep: usize, end: Duration, slices: &mut Vec<Slice>) {
+    while open.len() > keep {
+        if let Some((node_id, start)) = open.pop() {
+            slices.push(Slice {
+                node_id,
+                depth: open.len(),
+                start,
+                duration: end.saturating_sub(start),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ser::tests::PROFILE;
+    use crate::Profile;
+    use core::time::Duration;
+
+    #[test]
+    fn slices_nest() {
+        let profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        let slices = profile.slices();
+
+        let top_level: core::time::Duration = slices
+            .iter()
+            .filter(|slice| slice.depth == 0)
+            .map(|slice| slice.duration)
+            .sum();
+        let sampled: core::time::Duration = profile.samples_with_durations().map(|(_, d)| d).sum();
+        assert_eq!(top_level, sampled);
+
+        for slice in &slices {
+            let parent_id = profile[slice.node_id].parent_id.unwrap();
+            if slice.depth > 0 {
+                assert!(slices.iter().any(|parent| parent.node_id == parent_id
+                    && parent.depth + 1 == slice.depth
+                    && parent.start <= slice.start
+                    && parent.start + parent.duration >= slice.start + slice.duration));
+            }
+        }
+    }
+
+    #[test]
+    fn reconstruct_spans() {
+        let profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        let slices = profile.slices();
+        let spans = profile.reconstruct_spans(Duration::default()).unwrap();
+        assert_eq!(spans.len(), slices.len());
+        for (span, slice) in spans.iter().zip(&slices) {
+            assert_eq!(span.node_id, slice.node_id);
+            assert_eq!(span.duration(), slice.duration);
+            assert_eq!(span.frame, profile[span.node_id].frame().unwrap());
+        }
+
+        let min = Duration::from_millis(1);
+        let long = profile.reconstruct_spans(min).unwrap();
+        assert!(!long.is_empty() && long.len() < spans.len());
+        assert!(long.iter().all(|span| span.duration() >= min));
+        // a parent lasts at least as long as its children, so it's kept
+        for span in long.iter().filter(|span| span.depth > 0) {
+            let parent_id = profile[span.node_id].parent_id.unwrap();
+            assert!(long.iter().any(|parent| parent.node_id == parent_id
+                && parent.start <= span.start
+                && parent.end >= span.end));
+        }
+        let eval = long
+            .iter()
+            .find(|span| span.frame.url == "node:internal/main/eval_string")
+            .unwrap();
+        assert_eq!(eval.depth, 0);
+        assert!(eval.duration() > Duration::from_millis(20));
+    }
+}
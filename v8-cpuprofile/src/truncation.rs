@@ -0,0 +1,205 @@
+//! Signs that v8 cut sampled stacks short, which skews bottom-up numbers,
+//! see [`Profile::truncation`].
+
+use crate::Profile;
+use alloc::vec::Vec;
+use hashbrown::{HashMap, HashSet};
+
+/// Frames v8 walks per sample at most, the outermost are dropped beyond it.
+pub const MAX_FRAMES: usize = 255;
+
+/// Counts of samples whose stack looks truncated. The kinds don't overlap,
+/// so they add up to [`Truncation::affected`].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Truncation {
+    pub samples: usize,
+    /// A function of a script sampled right below the root, so v8 lost the
+    /// stack between it and whatever entered the script. A script's top
+    /// level code is an entry point and isn't counted.
+    pub root_leaves: usize,
+    /// A function of a script sampled at less than an eighth of the median
+    /// depth of such samples, and deeper than right below the root.
+    pub shallow: usize,
+    /// Stacks of [`MAX_FRAMES`] or more, which v8 may have cut off.
+    pub at_depth_limit: usize,
+}
+
+impl Truncation {
+    #[must_use]
+    pub fn affected(&self) -> usize {
+        self.root_leaves + self.shallow + self.at_depth_limit
+    }
+
+    /// The share of samples that look truncated, 0 without samples.
+    #[must_use]
+    pub fn fraction(&self) -> f64 {
+        self.share(self.affected())
+    }
+
+    /// `count` as a share of the samples, 0 without samples.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn share(&self, count: usize) -> f64 {
+        if self.samples == 0 {
+            0.0
+        } else {
+            count as f64 / self.samples as f64
+        }
+    }
+}
+
+impl Profile<'_> {
+    /// Counts the samples with stacks that look cut short. Pseudo frames
+    /// like `(idle)` and `(program)` and builtins without a script are
+    /// expected right below the root and aren't counted.
+    ///
+    /// # Errors
+    ///
+    /// If a sampled node has an invalid call frame.
+    pub fn truncation(&self) -> Result<Truncation, serde_json::Error> {
+        // depth of each sampled node with a script, None for the others
+        let mut depths: HashMap<u64, Option<usize>> = HashMap::new();
+        let mut entry_points = HashSet::new();
+        for sample in &self.samples {
+            if !depths.contains_key(&sample.node_id) {
+                let node = &self[sample.node_id];
+                let frame = node.frame()?;
+                let depth = if frame.url.is_empty() {
+                    None
+                } else {
+                    Some(self.parent_ids_iter(node.id).count())
+                };
+                if frame.line_number == 0 && frame.column_number == 0 {
+                    entry_points.insert(node.id);
+                }
+                depths.insert(node.id, depth);
+            }
+        }
+
+        let mut script_depths: Vec<usize> = self
+            .samples
+            .iter()
+            .filter_map(|sample| depths[&sample.node_id])
+            .collect();
+        let median = if script_depths.is_empty() {
+            0
+        } else {
+            let middle = script_depths.len() / 2;
+            *script_depths.select_nth_unstable(middle).1
+        };
+
+        let mut truncation = Truncation {
+            samples: self.samples.len(),
+            ..Truncation::default()
+        };
+        for sample in &self.samples {
+            let depth = match depths[&sample.node_id] {
+                Some(depth) => depth,
+                // a builtin's depth still counts against the limit
+                None => self.parent_ids_iter(sample.node_id).count(),
+            };
+            let script = depths[&sample.node_id].is_some();
+            if depth >= MAX_FRAMES {
+                truncation.at_depth_limit += 1;
+            } else if script && depth == 1 {
+                if !entry_points.contains(&sample.node_id) {
+                    truncation.root_leaves += 1;
+                }
+            } else if script && depth * 8 < median {
+                truncation.shallow += 1;
+            }
+        }
+        Ok(truncation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MAX_FRAMES;
+    use crate::ser::tests::PROFILE;
+    use crate::synth::SynthOptions;
+    use crate::Profile;
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
+    use core::convert::TryFrom;
+    use serde_json::{json, Value};
+
+    fn node(id: u64, function_name: &str, line: i64, children: &[u64]) -> Value {
+        let url = if line < 0 { "" } else { "a.js" };
+        json!({
+            "id": id,
+            "callFrame": {
+                "functionName": function_name,
+                "scriptId": "1",
+                "url": url,
+                "lineNumber": line,
+                "columnNumber": line.min(0),
+            },
+            "hitCount": 0,
+            "children": children,
+        })
+    }
+
+    /// A chain of `depth` functions below an entry point sampled at the
+    /// given depths, and a function right below the root sampled once.
+    fn chain(depth: u64, sampled_depths: &[u64]) -> String {
+        let stray = depth + 2;
+        let mut nodes = alloc::vec![node(1, "(root)", -1, &[2, stray])];
+        for id in 2..=depth + 1 {
+            let children: &[u64] = if id <= depth { &[id + 1] } else { &[] };
+            // the entry point at the top of a.js, then a function per line
+            let line = if id == 2 {
+                0
+            } else {
+                i64::try_from(id).unwrap()
+            };
+            nodes.push(node(id, "f", line, children));
+        }
+        nodes.push(node(stray, "stray", 5, &[]));
+        let mut samples: Vec<u64> = sampled_depths.iter().map(|depth| depth + 1).collect();
+        samples.push(stray);
+        json!({
+            "nodes": nodes,
+            "startTime": 0,
+            "endTime": samples.len(),
+            "timeDeltas": alloc::vec![1; samples.len()],
+            "samples": samples,
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn truncation() {
+        let profile: Profile = serde_json::from_str(PROFILE).unwrap();
+        let truncation = profile.truncation().unwrap();
+        assert_eq!(truncation.samples, profile.samples.len());
+        assert_eq!(truncation.affected(), 0);
+        assert!(truncation.fraction().abs() < f64::EPSILON);
+
+        // every function of a flat profile is right below the root
+        let flat = Profile::synthesize(&SynthOptions {
+            nodes: 10,
+            max_depth: 1,
+            samples: 100,
+            ..SynthOptions::default()
+        });
+        let truncation = flat.truncation().unwrap();
+        assert_eq!(truncation.root_leaves, 100);
+        assert!((truncation.fraction() - 1.0).abs() < f64::EPSILON);
+
+        let json = chain(20, &[20, 20, 20, 20, 2]);
+        let profile: Profile = serde_json::from_str(&json).unwrap();
+        let truncation = profile.truncation().unwrap();
+        assert_eq!(truncation.samples, 6);
+        assert_eq!(truncation.root_leaves, 1);
+        assert_eq!(truncation.shallow, 1);
+        assert_eq!(truncation.at_depth_limit, 0);
+
+        let json = chain(MAX_FRAMES as u64, &[MAX_FRAMES as u64, 1]);
+        let profile: Profile = serde_json::from_str(&json).unwrap();
+        let truncation = profile.truncation().unwrap();
+        assert_eq!(truncation.at_depth_limit, 1);
+        // the stray function, the entry point doesn't count
+        assert_eq!(truncation.affected(), 2);
+    }
+}
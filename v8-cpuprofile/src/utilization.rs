@@ -0,0 +1,209 @@
+use crate::Profile;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::time::Duration;
+use hashbrown::HashMap;
+use serde::de::Error;
+
+/// The most buckets [`Profile::utilization_series`] makes.
+pub const MAX_BUCKETS: usize = 1 << 24;
+
+/// How the capture window was spent, see [`Profile::utilization`].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Utilization {
+    /// Samples in javascript or the garbage collector.
+    pub busy: Duration,
+    /// Samples in `(program)`, native code outside of javascript.
+    pub program: Duration,
+    /// Samples in `(idle)`.
+    pub idle: Duration,
+    /// Time not covered by samples, before the first sample and where the
+    /// sampler stalled.
+    pub gaps: Duration,
+}
+
+impl Utilization {
+    #[must_use]
+    pub fn total(&self) -> Duration {
+        self.busy + self.program + self.idle + self.gaps
+    }
+
+    /// Share of the total spent busy or in `(program)`.
+    #[must_use]
+    pub fn busy_fraction(&self) -> f64 {
+        let total = self.total().as_secs_f64();
+        if total > 0.0 {
+            (self.busy + self.program).as_secs_f64() / total
+        } else {
+            0.0
+        }
+    }
+
+    fn add(&mut self, kind: Kind, duration: Duration) {
+        match kind {
+            Kind::Busy => self.busy += duration,
+            Kind::Program => self.program += duration,
+            Kind::Idle => self.idle += duration,
+            Kind::Gap => self.gaps += duration,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Kind {
+    Busy,
+    Program,
+    Idle,
+    Gap,
+}
+
+/// A sample lasting longer than this many times the median interval is
+/// counted as a gap after the median interval.
+const GAP_FACTOR: u32 = 4;
+
+impl Profile<'_> {
+    /// Busy vs idle time over the whole capture window.
+    ///
+    /// # Errors
+    ///
+    /// If a sampled node has an invalid call frame.
+    pub fn utilization(&self) -> Result<Utilization, serde_json::Error> {
+        let mut utilization = Utilization::default();
+        for (start, end, kind) in self.intervals()? {
+            utilization.add(kind, end.saturating_sub(start));
+        }
+        Ok(utilization)
+    }
+
+    /// Utilization of consecutive buckets of the capture window for plotting.
+    ///
+    /// # Errors
+    ///
+    /// If a sampled node has an invalid call frame or the capture window
+    /// needs more than [`MAX_BUCKETS`] buckets.
+    ///
+    /// # Panics
+    ///
+    /// If the bucket is zero.
+    pub fn utilization_series(
+        &self,
+        bucket: Duration,
+    ) -> Result<Vec<Utilization>, serde_json::Error> {
+        assert!(bucket > Duration::default(), "bucket must not be zero");
+        let too_many = || serde_json::Error::custom("too many buckets for the capture window");
+        let bucket_nanos = bucket.as_nanos();
+        let intervals = self.intervals()?;
+        let window = intervals.iter().map(|&(_, end, _)| end).max();
+        if let Some(window) = window {
+            let buckets = window.as_nanos().div_ceil(bucket_nanos);
+            if buckets > MAX_BUCKETS as u128 {
+                return Err(too_many());
+            }
+        }
+        let mut series: Vec<Utilization> = Vec::new();
+        for (start, end, kind) in intervals {
+            let mut start = start;
+            while start < end {
+                let index = usize::try_from(start.as_nanos() / bucket_nanos)
+                    .ok()
+                    .filter(|&index| index < MAX_BUCKETS)
+                    .ok_or_else(too_many)?;
+                // index + 1 fits, the bucket count is capped
+                let bucket_end = (index as u128 + 1)
+                    .checked_mul(bucket_nanos)
+                    .and_then(nanos)
+                    .unwrap_or(end);
+                let split = bucket_end.min(end);
+                if series.len() <= index {
+                    series.resize(index + 1, Utilization::default());
+                }
+                series[index].add(kind, split.saturating_sub(start));
+                start = split;
+            }
+        }
+        Ok(series)
+    }
+
+    /// Classified `(start, end, kind)` intervals relative to `start_time`.
+    fn intervals(&self) -> Result<Vec<(Duration, Duration, Kind)>, serde_json::Error> {
+        let mut kinds: HashMap<u64, Kind> = HashMap::new();
+        for sample in &self.samples {
+            if !kinds.contains_key(&sample.node_id) {
+                let frame = self[sample.node_id].frame()?;
+                let kind = match &*frame.function_name {
+                    "(idle)" => Kind::Idle,
+                    "(program)" => Kind::Program,
+                    _ => Kind::Busy,
+                };
+                kinds.insert(sample.node_id, kind);
+            }
+        }
+
+        let mut durations: Vec<Duration> = self
+            .samples_with_durations()
+            .map(|(_, duration)| duration)
+            .collect();
+        durations.sort_unstable();
+        let threshold = durations
+            .get(durations.len() / 2)
+            .map(|median| *median * GAP_FACTOR)
+            .filter(|threshold| *threshold > Duration::default());
+
+        let mut intervals = Vec::with_capacity(self.samples.len() + 1);
+        if let Some(first) = self.samples.first() {
+            intervals.push((Duration::default(), first.ts.as_duration(), Kind::Gap));
+        }
+        for (sample, duration) in self.samples_with_durations() {
+            let kind = kinds[&sample.node_id];
+            let start = sample.ts.as_duration();
+            let end = start + duration;
+            match threshold {
+                Some(threshold) if duration > threshold => {
+                    let stall = start + threshold;
+                    intervals.push((start, stall, kind));
+                    intervals.push((stall, end, Kind::Gap));
+                }
+                _ => intervals.push((start, end, kind)),
+            }
+        }
+        Ok(intervals)
+    }
+}
+
+/// `nanos` as a duration, `None` if it doesn't fit.
+fn nanos(nanos: u128) -> Option<Duration> {
+    let secs = u64::try_from(nanos / 1_000_000_000).ok()?;
+    let subsec = u32::try_from(nanos % 1_000_000_000).ok()?;
+    Some(Duration::new(secs, subsec))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Utilization;
+    use crate::ser::tests::PROFILE;
+    use core::time::Duration;
+
+    #[test]
+    fn utilization() {
+        let profile: crate::Profile<'_> = serde_json::from_str(PROFILE).unwrap();
+        let utilization = profile.utilization().unwrap();
+
+        assert_eq!(utilization.total(), profile.duration());
+        assert_eq!(utilization.gaps, Duration::from_micros(1573));
+        assert_eq!(utilization.idle, Duration::default());
+
+        let series = profile
+            .utilization_series(Duration::from_millis(10))
+            .unwrap();
+        assert_eq!(series.len(), 4);
+        assert_eq!(series[0].total(), Duration::from_millis(10));
+        let total: Duration = series.iter().map(Utilization::total).sum();
+        assert_eq!(total, utilization.total());
+
+        // 34ms in 1ns buckets
+        assert!(profile.utilization_series(Duration::from_nanos(1)).is_err());
+        let series = profile.utilization_series(Duration::MAX).unwrap();
+        assert_eq!(series.len(), 1);
+        assert_eq!(series[0].total(), utilization.total());
+    }
+}
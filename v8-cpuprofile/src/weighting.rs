@@ -0,0 +1,195 @@
+//! How samples are turned into time.
+
+use crate::{Micros, Profile, Sample};
+use core::fmt;
+use core::str::FromStr;
+use core::time::Duration;
+
+/// The time each sample stands for in [`Profile::self_times`] and every
+/// analysis built on it. They differ in how time is split around gaps, like
+/// a pause of the sampling thread.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Weighting {
+    /// Every sample is as long as
+    /// [`Profile::nominal_sampling_interval`], so time is in proportion to
+    /// hit counts and gaps aren't counted.
+    HitCount,
+    /// A sample lasts until the next one and the last until `end_time`, so
+    /// a gap is charged to the sample before it. The default, like Chrome's
+    /// performance panel.
+    #[default]
+    DeltaToNext,
+    /// A sample lasts from halfway since the previous one to halfway to the
+    /// next, so a gap is split between the samples around it.
+    Midpoint,
+}
+
+impl Weighting {
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Weighting::HitCount => "hit-count",
+            Weighting::DeltaToNext => "delta-to-next",
+            Weighting::Midpoint => "midpoint",
+        }
+    }
+}
+
+impl fmt::Display for Weighting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Weighting {
+    type Err = alloc::string::String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        [
+            Weighting::HitCount,
+            Weighting::DeltaToNext,
+            Weighting::Midpoint,
+        ]
+        .iter()
+        .copied()
+        .find(|weighting| weighting.name() == name)
+        .ok_or_else(|| alloc::format!("unknown weighting {}", name))
+    }
+}
+
+impl Profile<'_> {
+    #[must_use]
+    pub fn weighting(&self) -> Weighting {
+        self.weighting
+    }
+
+    /// Changes how samples are turned into time for every analysis of the
+    /// profile.
+    pub fn set_weighting(&mut self, weighting: Weighting) {
+        self.weighting = weighting;
+    }
+
+    /// The interval the profiler sampled at, its `samplingInterval` if it
+    /// was written or else the median time between samples. `None` for
+    /// fewer than two samples without a `samplingInterval`.
+    #[must_use]
+    pub fn nominal_sampling_interval(&self) -> Option<Duration> {
+        if let Some(interval) = self.sampling_interval {
+            return Some(interval.as_duration());
+        }
+        let mut deltas: alloc::vec::Vec<Micros> = self
+            .samples
+            .windows(2)
+            .map(|pair| pair[1].ts.saturating_sub(pair[0].ts))
+            .collect();
+        if deltas.is_empty() {
+            return None;
+        }
+        let middle = deltas.len() / 2;
+        let (_, median, _) = deltas.select_nth_unstable(middle);
+        Some(median.as_duration())
+    }
+
+    /// Pairs each sample with the time it stands for under the profile's
+    /// [`Weighting`]. Unlike [`Profile::samples_with_durations`] the times
+    /// don't follow the timeline.
+    pub fn samples_with_weights(&self) -> impl Iterator<Item = (Sample, Duration)> + '_ {
+        let end = self.end_time.saturating_sub(self.start_time);
+        let nominal = if self.weighting == Weighting::HitCount {
+            self.nominal_sampling_interval().unwrap_or_else(|| {
+                // the one sample lasts until the end
+                self.samples.first().map_or(Duration::default(), |first| {
+                    end.saturating_sub(first.ts).as_duration()
+                })
+            })
+        } else {
+            Duration::default()
+        };
+        let weighting = self.weighting;
+        self.samples
+            .iter()
+            .enumerate()
+            .map(move |(index, &sample)| {
+                let next = self.samples.get(index + 1).map_or(end, |next| next.ts);
+                let duration = match weighting {
+                    Weighting::HitCount => nominal,
+                    Weighting::DeltaToNext => next.saturating_sub(sample.ts).as_duration(),
+                    Weighting::Midpoint => {
+                        let start = match index.checked_sub(1) {
+                            Some(previous) => midpoint(self.samples[previous].ts, sample.ts),
+                            None => sample.ts,
+                        };
+                        let end = if index + 1 < self.samples.len() {
+                            midpoint(sample.ts, next)
+                        } else {
+                            end
+                        };
+                        end.saturating_sub(start).as_duration()
+                    }
+                };
+                (sample, duration)
+            })
+    }
+}
+
+/// Halfway between two samples in time order.
+fn midpoint(earlier: Micros, later: Micros) -> Micros {
+    Micros(earlier.0 + later.0.saturating_sub(earlier.0) / 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Weighting;
+    use crate::Profile;
+    use alloc::vec::Vec;
+    use core::time::Duration;
+
+    #[test]
+    fn weightings() {
+        // a 100us gap after the second sample
+        let mut profile: Profile = serde_json::from_str(
+            r#"{"nodes":[
+                {"id":1,"callFrame":{"functionName":"(root)","scriptId":"0","url":"","lineNumber":-1,"columnNumber":-1},"hitCount":0,"children":[2,3]},
+                {"id":2,"callFrame":{"functionName":"a","scriptId":"1","url":"","lineNumber":0,"columnNumber":0},"hitCount":2},
+                {"id":3,"callFrame":{"functionName":"b","scriptId":"1","url":"","lineNumber":1,"columnNumber":0},"hitCount":2}
+            ],"startTime":0,"endTime":140,"samples":[2,2,3,3],"timeDeltas":[0,10,100,10]}"#,
+        )
+        .unwrap();
+        let weights = |profile: &Profile| -> Vec<u128> {
+            profile
+                .samples_with_weights()
+                .map(|(_, duration)| duration.as_micros())
+                .collect()
+        };
+        assert_eq!(profile.weighting(), Weighting::DeltaToNext);
+        assert_eq!(weights(&profile), [10, 100, 10, 20]);
+
+        profile.set_weighting(Weighting::Midpoint);
+        assert_eq!(weights(&profile), [5, 55, 55, 25]);
+        assert_eq!(profile.self_times()[&3], Duration::from_micros(80));
+
+        profile.set_weighting("hit-count".parse().unwrap());
+        assert_eq!(weights(&profile), [10, 10, 10, 10]);
+        assert_eq!(profile.self_times()[&2], profile.self_times()[&3],);
+        // the timeline doesn't change
+        assert_eq!(
+            profile
+                .samples_with_durations()
+                .map(|(_, duration)| duration.as_micros())
+                .collect::<Vec<_>>(),
+            [10, 100, 10, 20]
+        );
+
+        assert_eq!(
+            profile.nominal_sampling_interval(),
+            Some(Duration::from_micros(10))
+        );
+        profile.sampling_interval = Some(crate::Micros(25));
+        assert_eq!(weights(&profile), [25, 25, 25, 25]);
+        let json = serde_json::to_string(&profile).unwrap();
+        assert!(json.contains(r#""samplingInterval":25"#));
+        profile.roundtrip_check().unwrap();
+        let parsed: Profile = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.sampling_interval, Some(crate::Micros(25)));
+    }
+}